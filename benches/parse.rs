@@ -0,0 +1,102 @@
+// Compares frame parsing throughput of serde_json and, with `--features simd`, simd-json.
+//
+//   cargo bench --bench parse --features simd
+//   GEMINI_CORPUS=capture.jsonl.gz cargo bench --bench parse --features simd
+//
+// Without GEMINI_CORPUS a synthetic corpus of busy-period update frames is used.
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use order_book::capture::CaptureReader;
+use order_book::models::MarketMessage;
+
+const MIN_RUN: Duration = Duration::from_secs(2);
+
+fn load(path: &Path) -> Vec<Vec<u8>> {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime");
+    runtime.block_on(async {
+        let mut reader = CaptureReader::open(path, 0.).await.expect("open corpus");
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next().await.expect("read corpus") {
+            frames.push(frame.frame.into_bytes());
+        }
+        frames
+    })
+}
+
+// Updates carrying a few trades and a run of book changes, like a volatile minute
+fn synthetic() -> Vec<Vec<u8>> {
+    (0..5_000u64).map(|i| {
+        let mut events = Vec::new();
+        for t in 0..i % 4 {
+            events.push(format!(
+                r#"{{"type":"trade","tid":{},"price":"{}.{:02}","amount":"0.{:08}","makerSide":"bid"}}"#,
+                i * 10 + t, 64_000 + i % 50, t * 7, 1_234_567 + i
+            ));
+        }
+        for c in 0..(5 + i % 30) {
+            let side = if c % 2 == 0 { "bid" } else { "ask" };
+            events.push(format!(
+                r#"{{"type":"change","side":"{}","price":"{}.{:02}","remaining":"{}.{:08}","delta":"-0.{:08}","reason":"place"}}"#,
+                side, 64_000 + c, c * 3 % 100, c % 5, 98_765_432 - c, 1_000 + c
+            ));
+        }
+        format!(
+            r#"{{"type":"update","eventId":{},"timestamp":{},"timestampms":{},"socket_sequence":{},"events":[{}]}}"#,
+            1_000_000 + i, 1_700_000_000 + i / 10, 1_700_000_000_000 + i * 100, i, events.join(",")
+        ).into_bytes()
+    }).collect()
+}
+
+// Runs `parse` over the whole corpus until MIN_RUN has passed, returns the time per pass
+fn measure(frames: &[Vec<u8>], mut parse: impl FnMut(&[u8])) -> Duration {
+    for frame in frames {
+        parse(frame);
+    }
+    let start = Instant::now();
+    let mut passes = 0;
+    while start.elapsed() < MIN_RUN {
+        for frame in frames {
+            parse(frame);
+        }
+        passes += 1;
+    }
+    start.elapsed() / passes
+}
+
+fn report(name: &str, pass: Duration, frames: &[Vec<u8>], baseline: Option<Duration>) {
+    let bytes: usize = frames.iter().map(Vec::len).sum();
+    let speedup = baseline.map(|b| format!("  {:.2}x", b.as_secs_f64() / pass.as_secs_f64())).unwrap_or_default();
+    println!(
+        "{:<12} {:>8.1} MB/s {:>8} ns/frame{}",
+        name,
+        bytes as f64 / pass.as_secs_f64() / 1e6,
+        pass.as_nanos() / frames.len() as u128,
+        speedup
+    );
+}
+
+fn main() {
+    let (frames, source) = match std::env::var_os("GEMINI_CORPUS") {
+        Some(path) => (load(Path::new(&path)), path.to_string_lossy().into_owned()),
+        None => (synthetic(), String::from("synthetic")),
+    };
+    let bytes: usize = frames.iter().map(Vec::len).sum();
+    println!("corpus: {} frames, {:.1} MB ({})", frames.len(), bytes as f64 / 1e6, source);
+
+    let serde = measure(&frames, |frame| {
+        let _ = black_box(serde_json::from_slice::<MarketMessage>(frame));
+    });
+    report("serde_json", serde, &frames, None);
+
+    #[cfg(feature = "simd")]
+    {
+        let simd = measure(&frames, |frame| {
+            let _ = black_box(simd_json::serde::from_slice::<MarketMessage>(&mut frame.to_vec()));
+        });
+        report("simd-json", simd, &frames, Some(serde));
+    }
+    #[cfg(not(feature = "simd"))]
+    println!("simd-json    not built, run with --features simd");
+}
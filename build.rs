@@ -0,0 +1,28 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=proto");
+    #[cfg(feature = "grpc")]
+    {
+        // Use the vendored protoc so building doesn't need one installed
+        if std::env::var_os("PROTOC").is_none() {
+            std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+        }
+        tonic_build::configure()
+            .build_client(false)
+            .compile_protos(&["proto/market_data.proto"], &["proto"])?;
+    }
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR")?;
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))?;
+        // Only the one file, reading the whole crate would run cargo metadata
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{}/src/ffi.rs", crate_dir))
+            .generate()?
+            .write_to_file(format!("{}/include/order_book.h", crate_dir));
+    }
+    Ok(())
+}
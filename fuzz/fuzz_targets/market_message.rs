@@ -0,0 +1,17 @@
+// Any frame either parses or is rejected, and whatever parses serializes and parses
+// back to the same events.
+//
+//   cargo +nightly fuzz run market_message tests/fixtures
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use order_book::models::MarketMessage;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(message) = MarketMessage::from_slice(data) else {
+        return;
+    };
+    let json = serde_json::to_vec(&message).expect("serialize a parsed message");
+    let again = MarketMessage::from_slice(&json).expect("reparse a serialized message");
+    assert_eq!(format!("{:?}", message.events), format!("{:?}", again.events));
+});
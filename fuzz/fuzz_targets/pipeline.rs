@@ -0,0 +1,37 @@
+// Feeds newline separated frames through a pipeline with every analytics stage on,
+// looking for panics in the book, candles, trade statistics, indicators, volatility,
+// quote dwell times and anomaly checks.
+//
+//   cargo +nightly fuzz run pipeline tests/fixtures
+#![no_main]
+
+use std::time::Duration;
+
+use libfuzzer_sys::fuzz_target;
+use order_book::analytics::anomaly::AnomalyOptions;
+use order_book::pipeline::Pipeline;
+use order_book::queue::QueueOptions;
+use rust_decimal::Decimal;
+
+fuzz_target!(|data: &[u8]| {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let anomalies = AnomalyOptions {
+        trade_pct: Some(Decimal::ONE),
+        spread_multiple: Some(Decimal::from(3)),
+    };
+    runtime.block_on(async {
+        let mut pipeline = Pipeline::new(QueueOptions::default())
+            .top_of_book(false)
+            .with_candles(Duration::from_secs(60))
+            .with_trade_stats(vec![Duration::from_secs(60)], Duration::from_secs(1))
+            .with_indicators(vec![Decimal::new(-5, 1), Decimal::new(5, 1)])
+            .with_volatility(Duration::from_secs(1), vec![Duration::from_secs(60)])
+            .with_dwell(Duration::from_secs(60), Duration::from_millis(100), Duration::from_secs(1))
+            .with_anomalies(anomalies, Duration::from_secs(60));
+        for (i, frame) in data.split(|b| *b == b'\n').enumerate() {
+            let _ = pipeline.handle_frame("btcusd", frame, 1_547_760_000_000 + i as u64 * 100).await;
+        }
+        let _ = pipeline.flush().await;
+        let _ = pipeline.summaries();
+    });
+});
@@ -0,0 +1,122 @@
+#ifndef ORDER_BOOK_H
+#define ORDER_BOOK_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum GeminiEventKind {
+  GEMINI_EVENT_KIND_TRADE,
+  GEMINI_EVENT_KIND_BLOCK_TRADE,
+  GEMINI_EVENT_KIND_QUOTE,
+  GEMINI_EVENT_KIND_AUCTION_OPEN,
+  GEMINI_EVENT_KIND_AUCTION_INDICATIVE,
+  GEMINI_EVENT_KIND_AUCTION_RESULT,
+  GEMINI_EVENT_KIND_UNKNOWN,
+} GeminiEventKind;
+
+typedef enum GeminiSide {
+  GEMINI_SIDE_UNKNOWN,
+  GEMINI_SIDE_BID,
+  GEMINI_SIDE_ASK,
+} GeminiSide;
+
+/**
+ * Why a quote changed, `Initial` marks the book snapshot after connecting
+ */
+typedef enum GeminiReason {
+  GEMINI_REASON_NONE,
+  GEMINI_REASON_INITIAL,
+  GEMINI_REASON_PLACE,
+  GEMINI_REASON_CANCEL,
+  GEMINI_REASON_TRADE,
+  GEMINI_REASON_OTHER,
+} GeminiReason;
+
+/**
+ * One event of a frame. Numbers that do not apply to the kind are NaN or 0.
+ */
+typedef struct GeminiEvent {
+  enum GeminiEventKind kind;
+  /**
+   * The maker's side for trades, the book side for quotes
+   */
+  enum GeminiSide side;
+  enum GeminiReason reason;
+  /**
+   * Trade or quote price, the indicative or final price of an auction
+   */
+  double price;
+  /**
+   * Trade amount, remaining size at a quote's price, auction quantity
+   */
+  double amount;
+  /**
+   * Block trade id or auction event id
+   */
+  uint64_t id;
+} GeminiEvent;
+
+/**
+ * A parsed frame, free it with gemini_message_free
+ */
+typedef struct GeminiMessage {
+  uint64_t event_id;
+  uint32_t socket_sequence;
+  /**
+   * -1 when the frame has no timestamp, as the initial book does
+   */
+  int64_t timestampms;
+  struct GeminiEvent *events;
+  size_t events_len;
+} GeminiMessage;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Parses one market data frame. Returns NULL when it is malformed, see
+ * gemini_last_error for why.
+ *
+ * # Safety
+ * `data` must point to `len` readable bytes.
+ */
+struct GeminiMessage *gemini_parse(const uint8_t *data, size_t len);
+
+/**
+ * # Safety
+ * `message` must come from gemini_parse and not have been freed, or be NULL.
+ */
+void gemini_message_free(struct GeminiMessage *message);
+
+/**
+ * Parses one market data frame and returns it as normalized JSON with prices as
+ * strings, nothing lost to floating point. NULL when it is malformed.
+ *
+ * # Safety
+ * `data` must point to `len` readable bytes.
+ */
+char *gemini_parse_json(const uint8_t *data, size_t len);
+
+/**
+ * # Safety
+ * `json` must come from gemini_parse_json and not have been freed, or be NULL.
+ */
+void gemini_string_free(char *json);
+
+/**
+ * Why the last parse on this thread failed, NULL if none has. Valid until the next
+ * failing call on the same thread.
+ */
+const char *gemini_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ORDER_BOOK_H */
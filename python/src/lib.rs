@@ -0,0 +1,104 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures_util::{Stream, StreamExt};
+use order_book::client::Endpoint;
+use order_book::models::MarketMessage;
+use order_book::{Client, GeminiError};
+use pyo3::exceptions::{PyStopAsyncIteration, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+type MessageStream = Pin<Box<dyn Stream<Item = Result<MarketMessage, GeminiError>> + Send>>;
+
+fn to_py_err(e: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+// Prices and amounts stay strings, as in the JSONL output, so no precision is lost
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_pyobject(py)?.to_owned().into_any().unbind(),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => u.into_pyobject(py)?.into_any().unbind(),
+            (None, Some(i)) => i.into_pyobject(py)?.into_any().unbind(),
+            _ => n.as_f64().unwrap_or(f64::NAN).into_pyobject(py)?.into_any().unbind(),
+        },
+        Value::String(s) => s.into_pyobject(py)?.into_any().unbind(),
+        Value::Array(items) => {
+            let items = items.iter().map(|item| to_python(py, item)).collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_any().unbind()
+        },
+        Value::Object(fields) => {
+            let dict = PyDict::new(py);
+            for (key, field) in fields {
+                dict.set_item(key, to_python(py, field)?)?;
+            }
+            dict.into_any().unbind()
+        },
+    })
+}
+
+fn message_to_python(py: Python<'_>, message: &MarketMessage) -> PyResult<PyObject> {
+    to_python(py, &serde_json::to_value(message).map_err(to_py_err)?)
+}
+
+// Parses one raw market data frame into a dict with the same normalization as the collector
+#[pyfunction]
+fn parse_market_message(py: Python<'_>, data: &[u8]) -> PyResult<PyObject> {
+    let message = MarketMessage::from_slice(data).map_err(to_py_err)?;
+    message_to_python(py, &message)
+}
+
+// Market data of one symbol as an async iterator of parsed messages:
+//
+//     async for message in gemini_websocket.Client("btcusd"):
+//         ...
+//
+// Reconnects like the collector does; the iterator ends once it gives up.
+#[pyclass(name = "Client")]
+struct PyClient {
+    stream: Arc<Mutex<MessageStream>>,
+}
+
+#[pymethods]
+impl PyClient {
+    #[new]
+    #[pyo3(signature = (symbol, endpoint = None))]
+    fn new(symbol: &str, endpoint: Option<&str>) -> PyResult<Self> {
+        let endpoint = match endpoint {
+            Some(url) => Endpoint::custom(url).map_err(to_py_err)?,
+            None => Endpoint::production(),
+        };
+        let stream = Client::new(symbol).endpoint(endpoint).stream();
+        Ok(Self {
+            stream: Arc::new(Mutex::new(Box::pin(stream))),
+        })
+    }
+
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let stream = self.stream.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let next = stream.lock().await.next().await;
+            match next {
+                Some(Ok(message)) => Python::with_gil(|py| message_to_python(py, &message)),
+                Some(Err(e)) => Err(to_py_err(e)),
+                None => Err(PyStopAsyncIteration::new_err(())),
+            }
+        })
+    }
+}
+
+#[pymodule]
+fn gemini_websocket(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse_market_message, m)?)?;
+    m.add_class::<PyClient>()?;
+    Ok(())
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::analytics::anomaly::{AnomalyEvent, AnomalyKind};
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{BestBidOffer, MarketSide, Trade};
+use crate::output::EventContext;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertField {
+    Last,
+    Bid,
+    Ask,
+    Mid,
+    Spread,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Above,
+    AtOrAbove,
+    Below,
+    AtOrBelow,
+}
+
+impl Comparison {
+    fn holds(&self, value: Decimal, threshold: Decimal) -> bool {
+        match self {
+            Comparison::Above => value > threshold,
+            Comparison::AtOrAbove => value >= threshold,
+            Comparison::Below => value < threshold,
+            Comparison::AtOrBelow => value <= threshold,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Comparison::Above => ">",
+            Comparison::AtOrAbove => ">=",
+            Comparison::Below => "<",
+            Comparison::AtOrBelow => "<=",
+        }
+    }
+}
+
+// `SYMBOL[.FIELD] OP VALUE`, e.g. "btcusd > 70000" or "ethusd.spread >= 2.5"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlertRule {
+    pub symbol: String,
+    pub field: AlertField,
+    pub comparison: Comparison,
+    pub threshold: Decimal,
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = match self.field {
+            AlertField::Last => "",
+            AlertField::Bid => ".bid",
+            AlertField::Ask => ".ask",
+            AlertField::Mid => ".mid",
+            AlertField::Spread => ".spread",
+        };
+        write!(f, "{}{} {} {}", self.symbol, field, self.comparison.as_str(), self.threshold)
+    }
+}
+
+impl FromStr for AlertRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let [target, op, value] = parts[..] else {
+            return Err(format!("expected `SYMBOL[.FIELD] OP VALUE`, got `{}`", s));
+        };
+        let (symbol, field) = match target.split_once('.') {
+            Some((symbol, field)) => (symbol, field),
+            None => (target, "last"),
+        };
+        let field = match field {
+            "last" => AlertField::Last,
+            "bid" => AlertField::Bid,
+            "ask" | "offer" => AlertField::Ask,
+            "mid" => AlertField::Mid,
+            "spread" => AlertField::Spread,
+            other => return Err(format!("unknown alert field `{}`", other)),
+        };
+        let comparison = match op {
+            ">" => Comparison::Above,
+            ">=" => Comparison::AtOrAbove,
+            "<" => Comparison::Below,
+            "<=" => Comparison::AtOrBelow,
+            other => return Err(format!("unknown comparison `{}`", other)),
+        };
+        let threshold = Decimal::from_str(value).map_err(|e| format!("bad threshold `{}`: {}", value, e))?;
+        Ok(Self {
+            symbol: symbol.to_lowercase(),
+            field,
+            comparison,
+            threshold,
+        })
+    }
+}
+
+const TELEGRAM_API: &str = "https://api.telegram.org";
+
+// Chat message text with `{rule}`, `{symbol}`, `{field}`, `{value}`, `{threshold}`,
+// `{price}` and `{size}` placeholders, e.g. "{symbol} traded {size} at {price}"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlertTemplate(String);
+
+impl Default for AlertTemplate {
+    fn default() -> Self {
+        Self(String::from("{rule} triggered: {symbol} {field} is {value}"))
+    }
+}
+
+impl FromStr for AlertTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else {
+                return Err(format!("unclosed `{{` in `{}`", s));
+            };
+            let name = &rest[start + 1..start + end];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(format!("unknown placeholder `{{{}}}`, use {}", name, PLACEHOLDERS.join(", ")));
+            }
+            rest = &rest[start + end + 1..];
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+const PLACEHOLDERS: &[&str] = &["rule", "symbol", "field", "value", "threshold", "price", "size"];
+
+impl AlertTemplate {
+    // Values the event does not have, like the size of a spread, render as `-`
+    pub fn render(&self, event: &AlertEvent) -> String {
+        let optional = |value: Option<Decimal>| value.map(|v| v.to_string()).unwrap_or_else(|| String::from("-"));
+        let field = serde_json::to_value(event.field).ok().and_then(|v| v.as_str().map(String::from)).unwrap_or_default();
+        self.0
+            .replace("{rule}", &event.rule)
+            .replace("{symbol}", &event.symbol)
+            .replace("{field}", &field)
+            .replace("{value}", &event.value.to_string())
+            .replace("{threshold}", &event.threshold.to_string())
+            .replace("{price}", &optional(event.price))
+            .replace("{size}", &optional(event.size))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatTarget {
+    Slack { webhook: String },
+    Discord { webhook: String },
+    Telegram { bot_token: String, chat_id: String },
+}
+
+// A chat that receives every triggered alert as a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatChannel {
+    pub target: ChatTarget,
+    pub template: AlertTemplate,
+}
+
+impl ChatChannel {
+    fn name(&self) -> &'static str {
+        match self.target {
+            ChatTarget::Slack { .. } => "slack",
+            ChatTarget::Discord { .. } => "discord",
+            ChatTarget::Telegram { .. } => "telegram",
+        }
+    }
+
+    fn request(&self, http: &reqwest::Client, text: String) -> reqwest::RequestBuilder {
+        match &self.target {
+            ChatTarget::Slack { webhook } => http.post(webhook).json(&serde_json::json!({ "text": text })),
+            ChatTarget::Discord { webhook } => http.post(webhook).json(&serde_json::json!({ "content": text })),
+            ChatTarget::Telegram { bot_token, chat_id } => http
+                .post(format!("{}/bot{}/sendMessage", TELEGRAM_API, bot_token))
+                .json(&serde_json::json!({ "chat_id": chat_id, "text": text })),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AlertActions {
+    pub webhook: Option<String>,
+    pub command: Option<String>,
+    pub chats: Vec<ChatChannel>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AlertEvent {
+    pub rule: String,
+    pub symbol: String,
+    pub field: AlertField,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub value: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub threshold: Decimal,
+    // Trade price, or the mid for BBO fields
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub price: Option<Decimal>,
+    // Trade amount, or the size at the best bid or offer
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub size: Option<Decimal>,
+    pub triggered_ms: u64,
+}
+
+// What a rule compares plus the context a chat message can show
+struct Reading {
+    value: Decimal,
+    price: Option<Decimal>,
+    size: Option<Decimal>,
+}
+
+struct ArmedRule {
+    rule: AlertRule,
+    last_fired_ms: Option<u64>,
+}
+
+// Evaluates rules against trades and BBO updates and fires the configured actions
+pub struct AlertSink {
+    rules: Vec<ArmedRule>,
+    // Last firing per symbol and kind when anomalies fire the actions too
+    anomalies: Option<HashMap<(String, AnomalyKind), u64>>,
+    actions: AlertActions,
+    cooldown_ms: u64,
+    http: reqwest::Client,
+}
+
+impl AlertSink {
+    pub fn new(rules: Vec<AlertRule>, actions: AlertActions, cooldown: Duration) -> Self {
+        Self {
+            rules: rules.into_iter().map(|rule| ArmedRule { rule, last_fired_ms: None }).collect(),
+            anomalies: None,
+            actions,
+            cooldown_ms: cooldown.as_millis() as u64,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    // Fire the actions for anomaly events as well, under the same cooldown
+    pub fn with_anomalies(mut self, anomalies: bool) -> Self {
+        self.anomalies = anomalies.then(HashMap::new);
+        self
+    }
+
+    fn evaluate(&mut self, symbol: &str, ctx: &EventContext, reading_of: impl Fn(AlertField) -> Option<Reading>) {
+        let now = ctx.timestampms.unwrap_or(ctx.received_ms);
+        let mut fired = Vec::new();
+        for armed in self.rules.iter_mut().filter(|r| r.rule.symbol == symbol) {
+            let Some(reading) = reading_of(armed.rule.field) else {
+                continue;
+            };
+            if !armed.rule.comparison.holds(reading.value, armed.rule.threshold) {
+                continue;
+            }
+            if armed.last_fired_ms.is_some_and(|last| now.saturating_sub(last) < self.cooldown_ms) {
+                continue;
+            }
+            armed.last_fired_ms = Some(now);
+            fired.push(AlertEvent {
+                rule: armed.rule.to_string(),
+                symbol: symbol.to_string(),
+                field: armed.rule.field,
+                value: reading.value,
+                threshold: armed.rule.threshold,
+                price: reading.price,
+                size: reading.size,
+                triggered_ms: now,
+            });
+        }
+        for event in fired {
+            self.fire(event);
+        }
+    }
+
+    fn fire(&self, event: AlertEvent) {
+        warn!(rule = %event.rule, symbol = %event.symbol, value = %event.value, "alert");
+        if let Some(url) = self.actions.webhook.clone() {
+            let request = self.http.post(url).json(&event);
+            tokio::spawn(async move {
+                match request.send().await.and_then(|r| r.error_for_status()) {
+                    Ok(_) => {},
+                    Err(e) => warn!(error = %e, "alert webhook failed"),
+                }
+            });
+        }
+        for chat in &self.actions.chats {
+            let request = chat.request(&self.http, chat.template.render(&event));
+            let name = chat.name();
+            tokio::spawn(async move {
+                match request.send().await.and_then(|r| r.error_for_status()) {
+                    Ok(_) => {},
+                    Err(e) => warn!(chat = name, error = %e, "alert message failed"),
+                }
+            });
+        }
+        if let Some(command) = self.actions.command.clone() {
+            let event = event.clone();
+            tokio::spawn(async move {
+                let status = tokio::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(&command)
+                    .env("ALERT_RULE", &event.rule)
+                    .env("ALERT_SYMBOL", &event.symbol)
+                    .env("ALERT_VALUE", event.value.to_string())
+                    .env("ALERT_THRESHOLD", event.threshold.to_string())
+                    .status()
+                    .await;
+                match status {
+                    Ok(status) if !status.success() => warn!(%status, "alert command failed"),
+                    Err(e) => warn!(error = %e, "alert command failed"),
+                    Ok(_) => {},
+                }
+            });
+        }
+    }
+}
+
+impl EventHandler for AlertSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.evaluate(symbol, ctx, |field| match field {
+            AlertField::Last => Some(Reading { value: trade.price, price: Some(trade.price), size: Some(trade.amount) }),
+            _ => None,
+        });
+        Ok(())
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        // Until both sides are known there is nothing meaningful to compare
+        if bbo.best_bid.is_zero() || bbo.best_offer.is_zero() {
+            return Ok(());
+        }
+        let mid = (bbo.best_bid + bbo.best_offer) / Decimal::TWO;
+        self.evaluate(symbol, ctx, |field| match field {
+            AlertField::Last => None,
+            AlertField::Bid => Some(Reading { value: bbo.best_bid, price: Some(bbo.best_bid), size: Some(bbo.bid_amount_remaining) }),
+            AlertField::Ask => Some(Reading { value: bbo.best_offer, price: Some(bbo.best_offer), size: Some(bbo.ask_amount_remaining) }),
+            AlertField::Mid => Some(Reading { value: mid, price: Some(mid), size: None }),
+            AlertField::Spread => Some(Reading { value: bbo.best_offer - bbo.best_bid, price: Some(mid), size: None }),
+        });
+        Ok(())
+    }
+
+    fn on_anomaly(&mut self, symbol: &str, ctx: &EventContext, anomaly: &AnomalyEvent) -> Result<(), GeminiError> {
+        let Some(fired) = self.anomalies.as_mut() else {
+            return Ok(());
+        };
+        let now = ctx.timestampms.unwrap_or(ctx.received_ms);
+        let key = (symbol.to_string(), anomaly.anomaly);
+        if fired.get(&key).is_some_and(|last| now.saturating_sub(*last) < self.cooldown_ms) {
+            return Ok(());
+        }
+        fired.insert(key, now);
+        let (rule, field, price, size) = match anomaly.anomaly {
+            AnomalyKind::TradeAwayFromMid => (
+                format!("{} trade more than {}% from the mid", symbol, anomaly.threshold),
+                AlertField::Last, Some(anomaly.value), anomaly.amount,
+            ),
+            AnomalyKind::SpreadBlowout => (
+                format!("{}.spread more than {}x the average", symbol, anomaly.threshold),
+                AlertField::Spread, Some(anomaly.reference), None,
+            ),
+            AnomalyKind::StaleQuotes => {
+                let field = match anomaly.side {
+                    Some(MarketSide::Ask) => AlertField::Ask,
+                    _ => AlertField::Bid,
+                };
+                let side = anomaly.side.unwrap_or_default().as_str();
+                (format!("{}.{} unchanged for more than {}s", symbol, side, anomaly.threshold), field, Some(anomaly.reference), None)
+            },
+        };
+        self.fire(AlertEvent {
+            rule,
+            symbol: symbol.to_string(),
+            field,
+            value: anomaly.value,
+            threshold: anomaly.threshold,
+            price,
+            size,
+            triggered_ms: now,
+        });
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "alerts"
+    }
+}
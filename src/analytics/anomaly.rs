@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::models::{BestBidOffer, MarketSide};
+
+// Book updates the rolling window needs before a trade or spread is judged against it
+const MIN_SAMPLES: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnomalyOptions {
+    // Flag trades more than this many percent away from the rolling mid
+    pub trade_pct: Option<Decimal>,
+    // Flag spreads wider than this multiple of the rolling average spread
+    pub spread_multiple: Option<Decimal>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    TradeAwayFromMid,
+    SpreadBlowout,
+    // A side of the book that stopped changing, see `QuoteClock`
+    StaleQuotes,
+}
+
+impl AnomalyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyKind::TradeAwayFromMid => "trade_away_from_mid",
+            AnomalyKind::SpreadBlowout => "spread_blowout",
+            AnomalyKind::StaleQuotes => "stale_quotes",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AnomalyEvent {
+    pub anomaly: AnomalyKind,
+    // Trade price, current spread or seconds since the side last changed
+    #[serde(with = "rust_decimal::serde::str")]
+    pub value: Decimal,
+    // Rolling mid or rolling average spread it was compared with, the best price of a stale side
+    #[serde(with = "rust_decimal::serde::str")]
+    pub reference: Decimal,
+    // Percent away from the mid, or the multiple of the average spread or of the stale window
+    #[serde(with = "rust_decimal::serde::str")]
+    pub deviation: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub threshold: Decimal,
+    // Amount of the trade
+    #[serde(skip_serializing_if = "Option::is_none", with = "rust_decimal::serde::str_option")]
+    pub amount: Option<Decimal>,
+    // The side that went stale
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub side: Option<MarketSide>,
+}
+
+// Rolling mid and spread of one symbol and the checks of trades and BBOs against them
+#[derive(Debug, Clone)]
+pub struct AnomalyDetector {
+    options: AnomalyOptions,
+    window_ms: u64,
+    samples: VecDeque<(u64, Decimal, Decimal)>,
+    mid_sum: Decimal,
+    spread_sum: Decimal,
+    // Spread blowouts are reported once, when the spread first widens past the threshold
+    blown_out: bool,
+}
+
+impl AnomalyDetector {
+    pub fn new(options: AnomalyOptions, window: Duration) -> Self {
+        Self {
+            options,
+            window_ms: window.as_millis() as u64,
+            samples: VecDeque::new(),
+            mid_sum: Decimal::ZERO,
+            spread_sum: Decimal::ZERO,
+            blown_out: false,
+        }
+    }
+
+    fn evict(&mut self, now_ms: u64) {
+        let cutoff = now_ms.saturating_sub(self.window_ms);
+        while let Some(&(ts, mid, spread)) = self.samples.front() {
+            if ts > cutoff {
+                break;
+            }
+            self.samples.pop_front();
+            self.mid_sum -= mid;
+            self.spread_sum -= spread;
+        }
+    }
+
+    // Averages over the window, None while it holds too few updates
+    fn averages(&self) -> Option<(Decimal, Decimal)> {
+        let n = self.samples.len();
+        (n >= MIN_SAMPLES).then(|| (self.mid_sum / Decimal::from(n), self.spread_sum / Decimal::from(n)))
+    }
+
+    // Judges a trade at `ts_ms` against the rolling mid
+    pub fn trade(&mut self, ts_ms: u64, price: Decimal, amount: Decimal) -> Option<AnomalyEvent> {
+        let threshold = self.options.trade_pct?;
+        self.evict(ts_ms);
+        let (mid, _) = self.averages()?;
+        let deviation = (price - mid).checked_div(mid)?.checked_mul(Decimal::ONE_HUNDRED)?.round_dp(4);
+        (deviation.abs() > threshold).then_some(AnomalyEvent {
+            anomaly: AnomalyKind::TradeAwayFromMid,
+            value: price,
+            reference: mid.round_dp(price.scale() + 2),
+            deviation,
+            threshold,
+            amount: Some(amount),
+            side: None,
+        })
+    }
+
+    // Judges the spread of a new BBO against the window before adding it
+    pub fn bbo(&mut self, ts_ms: u64, bbo: &BestBidOffer) -> Option<AnomalyEvent> {
+        if bbo.best_bid.is_zero() || bbo.best_offer.is_zero() {
+            return None;
+        }
+        self.evict(ts_ms);
+        let spread = bbo.best_offer - bbo.best_bid;
+        let anomaly = match (self.options.spread_multiple, self.averages()) {
+            (Some(threshold), Some((_, average))) if average > Decimal::ZERO => {
+                let multiple = spread.checked_div(average).unwrap_or(Decimal::MAX).round_dp(2);
+                let blown_out = multiple > threshold;
+                let entered = blown_out && !self.blown_out;
+                self.blown_out = blown_out;
+                entered.then_some(AnomalyEvent {
+                    anomaly: AnomalyKind::SpreadBlowout,
+                    value: spread,
+                    reference: average.round_dp(spread.scale() + 2),
+                    deviation: multiple,
+                    threshold,
+                    amount: None,
+                    side: None,
+                })
+            },
+            _ => None,
+        };
+        let mid = (bbo.best_bid + bbo.best_offer) / Decimal::TWO;
+        self.samples.push_back((ts_ms, mid, spread));
+        self.mid_sum += mid;
+        self.spread_sum += spread;
+        anomaly
+    }
+
+    // A rebuilt book after a reconnect is compared with fresh averages
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.mid_sum = Decimal::ZERO;
+        self.spread_sum = Decimal::ZERO;
+        self.blown_out = false;
+    }
+}
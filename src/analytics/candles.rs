@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::analytics::Interruption;
+use crate::models::Trade;
+
+// Flat bars filled in for one silent stretch at most, a clock jump of years would
+// otherwise allocate a bar for every interval in between
+const MAX_GAP_BARS: usize = 10_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub start_ms: u64,
+    pub interval: String,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub open: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub high: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub low: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub close: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub volume: Decimal,
+    pub trades: u64,
+    // The feed was interrupted during the bar, trades may be missing from it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tainted: bool,
+    // Computed by the exchange, which does not count the trades
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exchange: bool,
+}
+
+// Builds fixed-interval OHLCV bars from the trade stream, aligned to the epoch
+#[derive(Debug, Clone)]
+pub struct CandleAggregator {
+    interval_ms: u64,
+    label: String,
+    current: Option<Candle>,
+    last_close: Option<Decimal>,
+    // Start of the bar after one closed ahead of the next trade, silence from there still gets flat bars
+    flat_from: Option<u64>,
+    interruption: Option<Interruption>,
+}
+
+impl CandleAggregator {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval_ms: (interval.as_millis() as u64).max(1),
+            label: humantime::format_duration(interval).to_string(),
+            current: None,
+            last_close: None,
+            flat_from: None,
+            interruption: None,
+        }
+    }
+
+    // Bars overlapping it are marked tainted as they complete
+    pub fn interrupt(&mut self, interruption: Interruption) {
+        self.interruption = Some(interruption);
+    }
+
+    fn bucket(&self, ts_ms: u64) -> u64 {
+        ts_ms - ts_ms % self.interval_ms
+    }
+
+    // Closes every bar whose interval ended before `ts_ms`. Intervals without trades
+    // produce flat zero-volume bars at the previous close, up to MAX_GAP_BARS of them.
+    pub fn advance(&mut self, ts_ms: u64) -> Vec<Candle> {
+        let bucket = self.bucket(ts_ms);
+        let mut completed = Vec::new();
+        if self.current.is_none() {
+            match (self.flat_from.take(), self.last_close) {
+                (Some(start), Some(close)) if start < bucket => self.current = Some(self.flat(start, close)),
+                (start, _) => self.flat_from = start,
+            }
+        }
+        while let Some(mut current) = self.current.take() {
+            if current.start_ms >= bucket {
+                self.current = Some(current);
+                break;
+            }
+            let next_start = current.start_ms + self.interval_ms;
+            current.tainted |= self.interruption.is_some_and(|i| i.overlaps(current.start_ms, next_start - 1));
+            self.last_close = Some(current.close);
+            completed.push(current);
+            if next_start < bucket && completed.len() < MAX_GAP_BARS {
+                self.current = self.last_close.map(|close| self.flat(next_start, close));
+            } else if next_start >= bucket {
+                self.flat_from = Some(next_start);
+            }
+        }
+        completed
+    }
+
+    pub fn record(&mut self, ts_ms: u64, trade: &Trade) -> Vec<Candle> {
+        let completed = self.advance(ts_ms);
+        let start_ms = self.bucket(ts_ms);
+        match self.current.as_mut() {
+            Some(candle) => {
+                candle.high = candle.high.max(trade.price);
+                candle.low = candle.low.min(trade.price);
+                candle.close = trade.price;
+                candle.volume += trade.amount;
+                candle.trades += 1;
+            },
+            None => {
+                self.flat_from = None;
+                let mut candle = self.flat(start_ms, trade.price);
+                candle.volume = trade.amount;
+                candle.trades = 1;
+                self.current = Some(candle);
+            },
+        }
+        completed
+    }
+
+    pub fn current(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+
+    pub fn last_close(&self) -> Option<Decimal> {
+        self.last_close
+    }
+
+    // Picks up a bar saved by an earlier run, dropped if the interval has changed since
+    pub fn restore(&mut self, current: Option<Candle>, last_close: Option<Decimal>) {
+        self.current = current.filter(|c| c.interval == self.label);
+        self.last_close = last_close;
+        self.flat_from = None;
+    }
+
+    fn flat(&self, start_ms: u64, price: Decimal) -> Candle {
+        Candle {
+            start_ms,
+            interval: self.label.clone(),
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: Decimal::ZERO,
+            trades: 0,
+            tainted: false,
+            exchange: false,
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::models::BestBidOffer;
+
+// `TARGET=NUMERATOR/DENOMINATOR`, e.g. "ethbtc=ethusd/btcusd": the ethbtc mid is
+// compared with the ethusd mid divided by the btcusd mid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossRule {
+    pub target: String,
+    pub numerator: String,
+    pub denominator: String,
+}
+
+impl CrossRule {
+    pub fn symbols(&self) -> [&str; 3] {
+        [&self.target, &self.numerator, &self.denominator]
+    }
+}
+
+impl fmt::Display for CrossRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}/{}", self.target, self.numerator, self.denominator)
+    }
+}
+
+impl FromStr for CrossRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = s.split_once('=').and_then(|(target, legs)| {
+            let (numerator, denominator) = legs.split_once('/')?;
+            Some((target.trim(), numerator.trim(), denominator.trim()))
+        });
+        match parsed {
+            Some((target, numerator, denominator)) if ![target, numerator, denominator].contains(&"") => Ok(Self {
+                target: target.to_lowercase(),
+                numerator: numerator.to_lowercase(),
+                denominator: denominator.to_lowercase(),
+            }),
+            _ => Err(format!("expected `TARGET=NUMERATOR/DENOMINATOR`, got `{}`", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CrossDivergence {
+    pub cross: String,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub actual: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub implied: Decimal,
+    // (actual - implied) / implied in basis points
+    #[serde(with = "rust_decimal::serde::str")]
+    pub divergence_bps: Decimal,
+    // Whether |divergence_bps| is above the threshold
+    pub diverged: bool,
+    // Whether `diverged` flipped with this update
+    #[serde(skip)]
+    pub changed: bool,
+}
+
+struct WatchedCross {
+    rule: CrossRule,
+    diverged: bool,
+}
+
+// Compares synthetic crosses built from two legs with the directly quoted pair
+pub struct CrossMonitor {
+    crosses: Vec<WatchedCross>,
+    threshold_bps: Decimal,
+    mids: HashMap<String, Decimal>,
+}
+
+impl CrossMonitor {
+    pub fn new(rules: Vec<CrossRule>, threshold_bps: Decimal) -> Self {
+        Self {
+            crosses: rules.into_iter().map(|rule| WatchedCross { rule, diverged: false }).collect(),
+            threshold_bps,
+            mids: HashMap::new(),
+        }
+    }
+
+    // Recomputes every cross involving `symbol`, once all three mids are known
+    pub fn update(&mut self, symbol: &str, bbo: &BestBidOffer) -> Vec<CrossDivergence> {
+        if bbo.best_bid.is_zero() || bbo.best_offer.is_zero() {
+            self.mids.remove(symbol);
+            return Vec::new();
+        }
+        self.mids.insert(symbol.to_string(), (bbo.best_bid + bbo.best_offer) / Decimal::TWO);
+
+        let mut divergences = Vec::new();
+        for cross in self.crosses.iter_mut().filter(|c| c.rule.symbols().contains(&symbol)) {
+            let mid = |s: &str| self.mids.get(s).copied();
+            let (Some(actual), Some(numerator), Some(denominator)) =
+                (mid(&cross.rule.target), mid(&cross.rule.numerator), mid(&cross.rule.denominator)) else {
+                continue;
+            };
+            let implied = numerator / denominator;
+            let divergence_bps = ((actual - implied) / implied * Decimal::from(10_000)).round_dp(2);
+            let diverged = divergence_bps.abs() > self.threshold_bps;
+            divergences.push(CrossDivergence {
+                cross: cross.rule.to_string(),
+                actual,
+                implied: implied.round_dp(actual.scale().max(8)),
+                divergence_bps,
+                diverged,
+                changed: diverged != cross.diverged,
+            });
+            cross.diverged = diverged;
+        }
+        divergences
+    }
+}
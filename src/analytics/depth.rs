@@ -0,0 +1,123 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::book::OrderBook;
+
+// What the depth event aggregates: the best `levels` per side, and the prices at which
+// each of `sizes` would fill
+#[derive(Debug, Clone, Default)]
+pub struct DepthOptions {
+    pub levels: Option<usize>,
+    pub sizes: Vec<Decimal>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DepthSide {
+    // Fewer than asked for when the book is thinner
+    pub levels: usize,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
+    // Average price of the levels weighted by their size
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
+}
+
+// The average prices an order of `amount` fills at, sweeping the book from the top.
+// A side holding less than the amount has no price.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SizePrice {
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub buy: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub sell: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub mid: Option<Decimal>,
+    // Buy less sell price in basis points of the mid, the round trip cost at the size
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub spread_bps: Option<Decimal>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DepthSnapshot {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bid: Option<DepthSide>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ask: Option<DepthSide>,
+    // The aggregated prices weighted by the opposite side's amount, the microprice over
+    // the levels
+    #[serde(skip_serializing_if = "Option::is_none", with = "rust_decimal::serde::str_option")]
+    pub weighted_mid: Option<Decimal>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sizes: Vec<SizePrice>,
+    // Messages were lost since the book was last rebuilt from a snapshot
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub tainted: bool,
+}
+
+impl DepthSnapshot {
+    // None until both sides of the book have a level
+    pub fn from_book(book: &OrderBook, options: &DepthOptions) -> Option<Self> {
+        let (bid, ask) = (book.best_bid()?.0, book.best_ask()?.0);
+        // Averages get up to two places beyond the prices, like the microprice
+        let scale = bid.scale().max(ask.scale()) + 2;
+        let (bids, asks) = match options.levels {
+            Some(levels) => (aggregate(book.bids().take(levels), scale), aggregate(book.asks().take(levels), scale)),
+            None => (None, None),
+        };
+        let weighted_mid = match (&bids, &asks) {
+            (Some(bid), Some(ask)) => Some(
+                ((bid.price * ask.amount + ask.price * bid.amount) / (bid.amount + ask.amount)).round_dp(scale).normalize(),
+            ),
+            _ => None,
+        };
+        let sizes = options.sizes.iter().map(|&amount| {
+            let buy = sweep(book.asks(), amount).map(|p| p.round_dp(scale).normalize());
+            let sell = sweep(book.bids(), amount).map(|p| p.round_dp(scale).normalize());
+            let mid = buy.zip(sell).map(|(buy, sell)| ((buy + sell) / Decimal::TWO).round_dp(scale).normalize());
+            SizePrice {
+                amount,
+                buy,
+                sell,
+                mid,
+                spread_bps: buy.zip(sell).zip(mid).map(|((buy, sell), mid)| ((buy - sell) / mid * Decimal::from(10_000)).round_dp(2)),
+            }
+        }).collect();
+        Some(Self {
+            bid: bids,
+            ask: asks,
+            weighted_mid,
+            sizes,
+            tainted: false,
+        })
+    }
+}
+
+fn aggregate<'a>(levels: impl Iterator<Item = (&'a Decimal, &'a Decimal)>, scale: u32) -> Option<DepthSide> {
+    let (count, amount, notional) = levels.fold((0, Decimal::ZERO, Decimal::ZERO), |(count, amount, notional), (price, size)| {
+        (count + 1, amount + size, notional + price * size)
+    });
+    if amount.is_zero() {
+        return None;
+    }
+    Some(DepthSide {
+        levels: count,
+        amount,
+        price: (notional / amount).round_dp(scale).normalize(),
+    })
+}
+
+// Average price of filling `amount` off `levels`, best first
+fn sweep<'a>(levels: impl Iterator<Item = (&'a Decimal, &'a Decimal)>, amount: Decimal) -> Option<Decimal> {
+    let (mut left, mut notional) = (amount, Decimal::ZERO);
+    for (price, size) in levels {
+        let take = left.min(*size);
+        notional += price * take;
+        left -= take;
+        if left.is_zero() {
+            return Some(notional / amount);
+        }
+    }
+    None
+}
@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::book::LevelDwell;
+use crate::models::MarketSide;
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SideDwell {
+    // Best prices replaced within the window
+    pub levels: usize,
+    pub mean_ms: u64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+    // Levels replaced before the flicker threshold, and their share of all levels
+    pub flickers: usize,
+    pub flicker_rate: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DwellSnapshot {
+    pub window: String,
+    pub flicker_threshold: String,
+    pub bid: SideDwell,
+    pub ask: SideDwell,
+}
+
+// Rolling distribution of how long best bid and offer prices stood before being replaced
+#[derive(Debug, Clone)]
+pub struct DwellStats {
+    window: Duration,
+    flicker: Duration,
+    // Dwell times by side and the time the level was replaced
+    dwells: VecDeque<(u64, MarketSide, u64)>,
+}
+
+impl DwellStats {
+    pub fn new(window: Duration, flicker: Duration) -> Self {
+        Self {
+            window,
+            flicker,
+            dwells: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, dwell: &LevelDwell) {
+        self.dwells.push_back((dwell.since_ms + dwell.dwell_ms, dwell.side, dwell.dwell_ms));
+    }
+
+    // Nearest-rank statistics of the levels replaced in the window ending at `now_ms`
+    pub fn snapshot(&mut self, now_ms: u64) -> DwellSnapshot {
+        let cutoff = now_ms.saturating_sub(self.window.as_millis() as u64);
+        while self.dwells.front().is_some_and(|&(ended, _, _)| ended <= cutoff) {
+            self.dwells.pop_front();
+        }
+        DwellSnapshot {
+            window: humantime::format_duration(self.window).to_string(),
+            flicker_threshold: humantime::format_duration(self.flicker).to_string(),
+            bid: self.side(MarketSide::Bid),
+            ask: self.side(MarketSide::Ask),
+        }
+    }
+
+    fn side(&self, side: MarketSide) -> SideDwell {
+        let mut dwells: Vec<u64> = self.dwells.iter().filter(|(_, s, _)| *s == side).map(|&(_, _, d)| d).collect();
+        if dwells.is_empty() {
+            return SideDwell::default();
+        }
+        dwells.sort_unstable();
+        let rank = |p: usize| dwells[(dwells.len() * p).div_ceil(100).max(1) - 1];
+        let flicker_ms = self.flicker.as_millis() as u64;
+        let flickers = dwells.iter().filter(|d| **d < flicker_ms).count();
+        SideDwell {
+            levels: dwells.len(),
+            mean_ms: dwells.iter().sum::<u64>() / dwells.len() as u64,
+            p50_ms: rank(50),
+            p90_ms: rank(90),
+            p99_ms: rank(99),
+            max_ms: dwells[dwells.len() - 1],
+            flickers,
+            flicker_rate: (flickers as f64 / dwells.len() as f64 * 10_000.).round() / 10_000.,
+        }
+    }
+}
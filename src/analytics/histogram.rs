@@ -0,0 +1,181 @@
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// Levels a profile keeps before it doubles up on its bucket width, whatever the range
+// the price travels over a session
+const MAX_PROFILE_LEVELS: usize = 500;
+
+// Share of the volume around the point of control making up the value area
+const VALUE_AREA_PERCENT: u32 = 70;
+
+// The power of ten at or below `value`, which must be positive
+fn decade(value: Decimal) -> Decimal {
+    let mut exponent = value.to_f64().map_or(0, |v| v.log10().floor() as i32).clamp(-28, 27);
+    let power = |e: i32| match e {
+        e if e < 0 => Decimal::new(1, e.unsigned_abs()),
+        e => Decimal::from(10u128.pow(e as u32)),
+    };
+    // Floating point may land one decade off right at the edges
+    while exponent > -28 && power(exponent) > value {
+        exponent -= 1;
+    }
+    while exponent < 27 && power(exponent + 1) <= value {
+        exponent += 1;
+    }
+    power(exponent)
+}
+
+// The 1-2-5 step at or below `value`, such as 0.2 for 0.37 or 50 for 72
+fn step_below(value: Decimal) -> Decimal {
+    let decade = decade(value);
+    [Decimal::from(5), Decimal::from(2)].into_iter()
+        .map(|m| m * decade)
+        .find(|step| *step <= value)
+        .unwrap_or(decade)
+}
+
+// The 1-2-5 step after `value`, itself one of them
+fn step_after(value: Decimal) -> Decimal {
+    let decade = decade(value);
+    match value / decade {
+        m if m < Decimal::from(2) => decade * Decimal::from(2),
+        m if m < Decimal::from(5) => decade * Decimal::from(5),
+        _ => decade * Decimal::TEN,
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SizeBucket {
+    // Sizes from here up to the next bucket
+    #[serde(with = "rust_decimal::serde::str")]
+    pub from: Decimal,
+    pub trades: u64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub volume: Decimal,
+}
+
+// Trade sizes in logarithmic 1-2-5 buckets, a handful per decade, so any mix of sizes
+// takes a few dozen buckets at most
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct SizeHistogram {
+    buckets: Vec<SizeBucket>,
+}
+
+impl SizeHistogram {
+    pub fn record(&mut self, amount: Decimal) {
+        if amount <= Decimal::ZERO {
+            return;
+        }
+        let from = step_below(amount);
+        let at = self.buckets.partition_point(|b| b.from < from);
+        match self.buckets.get_mut(at).filter(|b| b.from == from) {
+            Some(bucket) => {
+                bucket.trades += 1;
+                bucket.volume += amount;
+            },
+            None => self.buckets.insert(at, SizeBucket { from, trades: 1, volume: amount }),
+        }
+    }
+
+    // Smallest sizes first
+    pub fn buckets(&self) -> &[SizeBucket] {
+        &self.buckets
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProfileLevel {
+    // Trades from this price up to the next level
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub volume: Decimal,
+    pub trades: u64,
+}
+
+// Volume traded at each price. Levels start one tick of the first price apart and widen
+// in 1-2-5 steps whenever there would be more than MAX_PROFILE_LEVELS of them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeProfile {
+    #[serde(with = "rust_decimal::serde::str")]
+    pub width: Decimal,
+    pub levels: Vec<ProfileLevel>,
+}
+
+impl VolumeProfile {
+    pub fn record(&mut self, price: Decimal, amount: Decimal) {
+        if self.width.is_zero() {
+            self.width = Decimal::new(1, price.scale());
+        }
+        self.add(ProfileLevel { price, volume: amount, trades: 1 });
+        if self.levels.len() > MAX_PROFILE_LEVELS {
+            self.widen(MAX_PROFILE_LEVELS);
+        }
+    }
+
+    fn add(&mut self, level: ProfileLevel) {
+        let price = (level.price / self.width).floor() * self.width;
+        let at = self.levels.partition_point(|l| l.price < price);
+        match self.levels.get_mut(at).filter(|l| l.price == price) {
+            Some(existing) => {
+                existing.volume += level.volume;
+                existing.trades += level.trades;
+            },
+            None => self.levels.insert(at, ProfileLevel { price, ..level }),
+        }
+    }
+
+    // Merges neighbouring levels until at most `max` are left
+    fn widen(&mut self, max: usize) {
+        while self.levels.len() > max.max(1) {
+            self.width = step_after(self.width);
+            for level in std::mem::take(&mut self.levels) {
+                self.add(level);
+            }
+        }
+    }
+
+    // The same profile in at most `max` levels, for display
+    pub fn coarsened(&self, max: usize) -> VolumeProfile {
+        let mut profile = self.clone();
+        profile.widen(max);
+        profile
+    }
+
+    // The level with the most volume
+    pub fn point_of_control(&self) -> Option<&ProfileLevel> {
+        self.levels.iter().max_by_key(|l| l.volume)
+    }
+
+    // The levels around the point of control holding VALUE_AREA_PERCENT of the volume,
+    // grown towards the busier neighbour each step, as the prices of its lowest and highest level
+    pub fn value_area(&self) -> Option<(Decimal, Decimal)> {
+        let (poc, _) = self.levels.iter().enumerate().max_by_key(|(_, l)| l.volume)?;
+        let total: Decimal = self.levels.iter().map(|l| l.volume).sum();
+        let target = total * Decimal::from(VALUE_AREA_PERCENT) / Decimal::ONE_HUNDRED;
+        let (mut low, mut high) = (poc, poc);
+        let mut volume = self.levels[poc].volume;
+        while volume < target && (low > 0 || high + 1 < self.levels.len()) {
+            let below = low.checked_sub(1).map(|i| self.levels[i].volume);
+            let above = self.levels.get(high + 1).map(|l| l.volume);
+            if above.is_some() && above >= below {
+                high += 1;
+                volume += self.levels[high].volume;
+            } else {
+                low -= 1;
+                volume += self.levels[low].volume;
+            }
+        }
+        Some((self.levels[low].price, self.levels[high].price))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+}
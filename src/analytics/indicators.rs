@@ -0,0 +1,43 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::models::BestBidOffer;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BookIndicators {
+    // (bid size - offer size) / (bid size + offer size), from -1 with only offers to 1 with only bids
+    #[serde(with = "rust_decimal::serde::str")]
+    pub imbalance: Decimal,
+    // Mid weighted by the opposite side's size, so it leans towards the thinner side
+    #[serde(with = "rust_decimal::serde::str")]
+    pub microprice: Decimal,
+    // Index of the imbalance band, when bands are configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub band: Option<usize>,
+    // Messages were lost since the book was last rebuilt from a snapshot
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub tainted: bool,
+}
+
+impl BookIndicators {
+    // None until both sides of the book have size
+    pub fn from_bbo(bbo: &BestBidOffer) -> Option<Self> {
+        let (bid_size, ask_size) = (bbo.bid_amount_remaining, bbo.ask_amount_remaining);
+        if bid_size.is_zero() || ask_size.is_zero() {
+            return None;
+        }
+        let total = bid_size + ask_size;
+        let scale = bbo.best_bid.scale().max(bbo.best_offer.scale()) + 2;
+        Some(Self {
+            imbalance: ((bid_size - ask_size) / total).round_dp(4),
+            microprice: ((bbo.best_bid * ask_size + bbo.best_offer * bid_size) / total).round_dp(scale),
+            band: None,
+            tainted: false,
+        })
+    }
+}
+
+// Which of the bands split by the sorted `edges` the imbalance falls in, 0 being below the first edge
+pub fn imbalance_band(edges: &[Decimal], imbalance: Decimal) -> usize {
+    edges.iter().filter(|edge| imbalance >= **edge).count()
+}
@@ -0,0 +1,64 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serde::Serialize;
+
+// Rolling window of feed delays, `received_ms - timestampms` per message.
+// Delays can be negative when the local clock runs behind the exchange.
+#[derive(Debug, Clone)]
+pub struct LatencyTracker {
+    window_ms: u64,
+    samples: VecDeque<(u64, i64)>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySnapshot {
+    pub samples: usize,
+    pub p50_ms: i64,
+    pub p95_ms: i64,
+    pub p99_ms: i64,
+    pub max_ms: i64,
+    // The fastest message, the least network and exchange delay on top of any clock skew
+    pub min_ms: i64,
+}
+
+impl LatencyTracker {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window_ms: window.as_millis() as u64,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, received_ms: u64, timestampms: u64) {
+        let delay = received_ms as i64 - timestampms as i64;
+        self.samples.push_back((received_ms, delay));
+        self.evict(received_ms);
+    }
+
+    fn evict(&mut self, now_ms: u64) {
+        let cutoff = now_ms.saturating_sub(self.window_ms);
+        while self.samples.front().is_some_and(|&(ts, _)| ts <= cutoff) {
+            self.samples.pop_front();
+        }
+    }
+
+    // Nearest-rank percentiles over the window ending at `now_ms`, None without samples
+    pub fn snapshot(&mut self, now_ms: u64) -> Option<LatencySnapshot> {
+        self.evict(now_ms);
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut delays: Vec<i64> = self.samples.iter().map(|&(_, d)| d).collect();
+        delays.sort_unstable();
+        let rank = |p: usize| delays[(delays.len() * p).div_ceil(100).max(1) - 1];
+        Some(LatencySnapshot {
+            samples: delays.len(),
+            p50_ms: rank(50),
+            p95_ms: rank(95),
+            p99_ms: rank(99),
+            max_ms: delays[delays.len() - 1],
+            min_ms: delays[0],
+        })
+    }
+}
@@ -0,0 +1,41 @@
+pub mod anomaly;
+pub mod candles;
+pub mod cross;
+pub mod depth;
+pub mod dwell;
+pub mod histogram;
+pub mod indicators;
+pub mod latency;
+pub mod ofi;
+pub mod spread;
+pub mod stale;
+pub mod technical;
+pub mod volatility;
+pub mod vwap;
+
+use std::time::Duration;
+
+// The latest stretch the feed of a symbol was interrupted: a sequence gap lost what
+// arrived between two messages, a disconnect lasts until the next message comes in.
+// Aggregates over any part of it are incomplete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interruption {
+    pub from_ms: u64,
+    // None while still interrupted
+    pub to_ms: Option<u64>,
+}
+
+impl Interruption {
+    pub fn overlaps(&self, start_ms: u64, end_ms: u64) -> bool {
+        self.from_ms <= end_ms && self.to_ms.is_none_or(|to| to >= start_ms)
+    }
+}
+
+// Accepts humantime durations like "1m", "5m" or "1h"
+pub fn parse_window(s: &str) -> Result<Duration, String> {
+    let window = humantime::parse_duration(s).map_err(|e| e.to_string())?;
+    if window.is_zero() {
+        return Err(String::from("window must be longer than zero"));
+    }
+    Ok(window)
+}
@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::analytics::Interruption;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OfiWindow {
+    pub window: String,
+    // Net size added at the best bid less that added at the best ask, in base currency
+    #[serde(with = "rust_decimal::serde::str")]
+    pub ofi: Decimal,
+    // Book changes the sum is over
+    pub changes: usize,
+    // The feed was interrupted within the window, changes may be missing from it
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub tainted: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OfiSnapshot {
+    pub windows: Vec<OfiWindow>,
+}
+
+// Order flow imbalance of every book change, summed over several rolling windows
+#[derive(Debug, Clone)]
+pub struct OrderFlow {
+    windows: Vec<Duration>,
+    flows: VecDeque<(u64, Decimal)>,
+    interruption: Option<Interruption>,
+}
+
+impl OrderFlow {
+    pub fn new(windows: &[Duration]) -> Self {
+        Self {
+            windows: windows.to_vec(),
+            flows: VecDeque::new(),
+            interruption: None,
+        }
+    }
+
+    // Windows reaching back into it are tainted until it has slid out of them
+    pub fn interrupt(&mut self, interruption: Interruption) {
+        self.interruption = Some(interruption);
+    }
+
+    pub fn record(&mut self, ts_ms: u64, flow: Decimal) {
+        self.flows.push_back((ts_ms, flow));
+    }
+
+    pub fn snapshot(&mut self, now_ms: u64) -> OfiSnapshot {
+        let longest = self.windows.iter().max().map_or(0, |w| w.as_millis() as u64);
+        let cutoff = now_ms.saturating_sub(longest);
+        while self.flows.front().is_some_and(|&(ts, _)| ts <= cutoff) {
+            self.flows.pop_front();
+        }
+        let windows = self.windows.iter().map(|window| {
+            let start_ms = now_ms.saturating_sub(window.as_millis() as u64);
+            let (changes, ofi) = self.flows.iter().rev()
+                .take_while(|&&(ts, _)| ts > start_ms)
+                .fold((0, Decimal::ZERO), |(changes, sum), &(_, flow)| (changes + 1, sum + flow));
+            OfiWindow {
+                window: humantime::format_duration(*window).to_string(),
+                ofi,
+                changes,
+                tainted: self.interruption.is_some_and(|i| i.overlaps(start_ms, now_ms)),
+            }
+        }).collect();
+        OfiSnapshot { windows }
+    }
+}
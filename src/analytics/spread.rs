@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::BestBidOffer;
+use crate::output::EventContext;
+
+// Spreads reported as wider than these many basis points of the mid unless configured
+pub const DEFAULT_THRESHOLDS_BPS: [u32; 3] = [1, 5, 10];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SpreadThreshold {
+    #[serde(with = "rust_decimal::serde::str")]
+    pub bps: Decimal,
+    // Share of the observed time the spread was wider, 0 to 100
+    #[serde(with = "rust_decimal::serde::str")]
+    pub percent_above: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SpreadSnapshot {
+    // Time with a two sided book, the weight of everything below
+    pub observed_ms: u64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub twas: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub twas_bps: Decimal,
+    // The spread in place half of the observed time or less
+    #[serde(with = "rust_decimal::serde::str")]
+    pub median: Decimal,
+    pub thresholds: Vec<SpreadThreshold>,
+}
+
+// One spread, standing from `since_ms` until the next BBO
+#[derive(Debug, Clone, Copy)]
+struct Standing {
+    since_ms: u64,
+    spread: Decimal,
+    bps: Decimal,
+}
+
+// Time-weighted spread statistics of one symbol. Every spread counts for as long as it
+// stood, so a burst of updates weighs no more than a quiet hour.
+#[derive(Debug, Clone, Default)]
+pub struct SpreadStats {
+    thresholds_bps: Vec<Decimal>,
+    current: Option<Standing>,
+    observed_ms: u64,
+    // Sums of spread times milliseconds
+    weighted: Decimal,
+    weighted_bps: Decimal,
+    // Milliseconds per spread, spreads are tick multiples so this stays small
+    durations: BTreeMap<Decimal, u64>,
+    above_ms: Vec<u64>,
+}
+
+impl SpreadStats {
+    pub fn new(thresholds_bps: &[Decimal]) -> Self {
+        Self {
+            thresholds_bps: thresholds_bps.to_vec(),
+            above_ms: vec![0; thresholds_bps.len()],
+            ..Self::default()
+        }
+    }
+
+    // Closes the standing spread at `ts_ms` and starts the one of `bbo`, none for a one
+    // sided book
+    pub fn record(&mut self, ts_ms: u64, bbo: &BestBidOffer) {
+        self.close(ts_ms);
+        if bbo.best_bid.is_zero() || bbo.best_offer.is_zero() {
+            return;
+        }
+        let spread = bbo.best_offer - bbo.best_bid;
+        let mid = (bbo.best_bid + bbo.best_offer) / Decimal::TWO;
+        let bps = spread.checked_div(mid).unwrap_or_default() * Decimal::from(10_000);
+        self.current = Some(Standing { since_ms: ts_ms, spread, bps });
+    }
+
+    // Nothing is known about the spread while disconnected, so the one standing at the
+    // disconnect counts for nothing
+    pub fn interrupt(&mut self) {
+        self.current = None;
+    }
+
+    fn close(&mut self, ts_ms: u64) {
+        let Some(standing) = self.current.take() else {
+            return;
+        };
+        let ms = ts_ms.saturating_sub(standing.since_ms);
+        if ms == 0 {
+            return;
+        }
+        self.observed_ms += ms;
+        self.weighted += standing.spread * Decimal::from(ms);
+        self.weighted_bps += standing.bps * Decimal::from(ms);
+        *self.durations.entry(standing.spread).or_default() += ms;
+        for (threshold, above) in self.thresholds_bps.iter().zip(self.above_ms.iter_mut()) {
+            if standing.bps > *threshold {
+                *above += ms;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<SpreadSnapshot> {
+        if self.observed_ms == 0 {
+            return None;
+        }
+        let observed = Decimal::from(self.observed_ms);
+        let mut seen = 0;
+        let median = self.durations.iter()
+            .find(|(_, ms)| {
+                seen += **ms;
+                seen * 2 >= self.observed_ms
+            })
+            .map(|(spread, _)| *spread)
+            .unwrap_or_default();
+        let thresholds = self.thresholds_bps.iter().zip(&self.above_ms)
+            .map(|(bps, above)| SpreadThreshold {
+                bps: *bps,
+                percent_above: (Decimal::from(*above) * Decimal::ONE_HUNDRED / observed).round_dp(2),
+            })
+            .collect();
+        Some(SpreadSnapshot {
+            observed_ms: self.observed_ms,
+            twas: (self.weighted / observed).round_dp(8).normalize(),
+            twas_bps: (self.weighted_bps / observed).round_dp(4).normalize(),
+            median,
+            thresholds,
+        })
+    }
+}
+
+// Spread statistics of every symbol, fed as a handler and read by the status server and
+// the summaries
+#[derive(Debug, Clone, Default)]
+pub struct SpreadTracker {
+    thresholds_bps: Arc<Mutex<Vec<Decimal>>>,
+    stats: Arc<Mutex<HashMap<String, SpreadStats>>>,
+}
+
+impl SpreadTracker {
+    pub fn new(thresholds_bps: Vec<Decimal>) -> Self {
+        Self {
+            thresholds_bps: Arc::new(Mutex::new(thresholds_bps)),
+            stats: Arc::default(),
+        }
+    }
+
+    // Applies to symbols seen from now on
+    pub fn set_thresholds(&self, thresholds_bps: Vec<Decimal>) {
+        *self.thresholds_bps.lock().unwrap() = thresholds_bps;
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<SpreadSnapshot> {
+        self.stats.lock().unwrap().get(symbol)?.snapshot()
+    }
+}
+
+impl EventHandler for SpreadTracker {
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        let mut stats = self.stats.lock().unwrap();
+        let stats = stats.entry(symbol.to_string())
+            .or_insert_with(|| SpreadStats::new(&self.thresholds_bps.lock().unwrap()));
+        stats.record(ctx.timestampms.unwrap_or(ctx.received_ms), bbo);
+        Ok(())
+    }
+
+    fn on_disconnect(&mut self, symbol: &str, _reason: &str) -> Result<(), GeminiError> {
+        if let Some(stats) = self.stats.lock().unwrap().get_mut(symbol) {
+            stats.interrupt();
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "spread"
+    }
+}
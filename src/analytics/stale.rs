@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+
+use crate::analytics::anomaly::{AnomalyEvent, AnomalyKind};
+use crate::models::MarketSide;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct SideClock {
+    updated_ms: Option<u64>,
+    // Reported as stale since the last change
+    stale: bool,
+}
+
+// When each side of a symbol's book last changed, in local time. A silent side is either a
+// quiet market or a subscription that stopped delivering, which the clock cannot tell
+// apart, so the window wants to be longer than the market is ever quiet.
+#[derive(Debug, Clone, Default)]
+pub struct QuoteClock {
+    bid: SideClock,
+    ask: SideClock,
+}
+
+impl QuoteClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn side_mut(&mut self, side: MarketSide) -> Option<&mut SideClock> {
+        match side {
+            MarketSide::Bid => Some(&mut self.bid),
+            MarketSide::Ask => Some(&mut self.ask),
+            MarketSide::Unknown => None,
+        }
+    }
+
+    // A change of `side` at `now_ms`, true when the side was stale until now
+    pub fn update(&mut self, side: MarketSide, now_ms: u64) -> bool {
+        let Some(clock) = self.side_mut(side) else {
+            return false;
+        };
+        clock.updated_ms = Some(now_ms);
+        std::mem::take(&mut clock.stale)
+    }
+
+    // Time since each side last changed, None before its first change
+    pub fn ages(&self, now_ms: u64) -> [(MarketSide, Option<u64>); 2] {
+        [(MarketSide::Bid, self.bid), (MarketSide::Ask, self.ask)]
+            .map(|(side, clock)| (side, clock.updated_ms.map(|ms| now_ms.saturating_sub(ms))))
+    }
+
+    pub fn is_stale(&self, side: MarketSide) -> bool {
+        match side {
+            MarketSide::Bid => self.bid.stale,
+            MarketSide::Ask => self.ask.stale,
+            MarketSide::Unknown => false,
+        }
+    }
+
+    // The sides that have not changed for longer than `window`, each reported once until it
+    // changes again. `best` is the price of the side the book still shows.
+    pub fn check(&mut self, now_ms: u64, window: Duration, best: impl Fn(MarketSide) -> Decimal) -> Vec<AnomalyEvent> {
+        let window_ms = window.as_millis() as u64;
+        let mut stale = Vec::new();
+        for side in [MarketSide::Bid, MarketSide::Ask] {
+            let Some(clock) = self.side_mut(side) else {
+                continue;
+            };
+            let Some(age_ms) = clock.updated_ms.map(|ms| now_ms.saturating_sub(ms)) else {
+                continue;
+            };
+            if clock.stale || age_ms <= window_ms {
+                continue;
+            }
+            clock.stale = true;
+            stale.push(AnomalyEvent {
+                anomaly: AnomalyKind::StaleQuotes,
+                value: Decimal::new(age_ms as i64, 3).normalize(),
+                reference: best(side),
+                deviation: Decimal::from(age_ms).checked_div(Decimal::from(window_ms.max(1))).unwrap_or_default().round_dp(2),
+                threshold: Decimal::new(window_ms as i64, 3).normalize(),
+                amount: None,
+                side: Some(side),
+            });
+        }
+        stale
+    }
+}
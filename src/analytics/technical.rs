@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::analytics::candles::Candle;
+
+const DECIMALS: u32 = 8;
+
+// `KIND:PERIOD`, or `bollinger:PERIOD:WIDTH` with WIDTH standard deviations, e.g. "ema:20"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndicatorSpec {
+    Sma(usize),
+    Ema(usize),
+    Rsi(usize),
+    Bollinger(usize, Decimal),
+}
+
+impl FromStr for IndicatorSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split(':').collect();
+        let period = |p: &str| match p.parse::<usize>() {
+            Ok(period) if period > 0 => Ok(period),
+            _ => Err(format!("period `{}` is not a positive number in `{}`", p, s)),
+        };
+        match parts.as_slice() {
+            ["sma", p] => Ok(IndicatorSpec::Sma(period(p)?)),
+            ["ema", p] => Ok(IndicatorSpec::Ema(period(p)?)),
+            ["rsi", p] => Ok(IndicatorSpec::Rsi(period(p)?)),
+            ["bollinger", p] => Ok(IndicatorSpec::Bollinger(period(p)?, Decimal::TWO)),
+            ["bollinger", p, w] => match w.parse::<Decimal>() {
+                Ok(width) if width > Decimal::ZERO => Ok(IndicatorSpec::Bollinger(period(p)?, width)),
+                _ => Err(format!("width `{}` is not a positive number in `{}`", w, s)),
+            },
+            _ => Err(format!("expected sma:N, ema:N, rsi:N or bollinger:N[:WIDTH], got `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for IndicatorSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndicatorSpec::Sma(n) => write!(f, "sma_{}", n),
+            IndicatorSpec::Ema(n) => write!(f, "ema_{}", n),
+            IndicatorSpec::Rsi(n) => write!(f, "rsi_{}", n),
+            IndicatorSpec::Bollinger(n, _) => write!(f, "bb_{}", n),
+        }
+    }
+}
+
+// Indicator values after a candle closed, keyed like `ema_20` or `bb_20_upper`.
+// An indicator still warming up is left out.
+#[derive(Serialize, Debug, Clone)]
+pub struct TechnicalSnapshot {
+    pub interval: String,
+    pub start_ms: u64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub close: Decimal,
+    pub values: BTreeMap<String, Decimal>,
+}
+
+// The last `period` closes with their running sum
+#[derive(Debug, Clone)]
+struct Closes {
+    period: usize,
+    closes: VecDeque<Decimal>,
+    sum: Decimal,
+}
+
+impl Closes {
+    fn new(period: usize) -> Self {
+        Self { period, closes: VecDeque::with_capacity(period + 1), sum: Decimal::ZERO }
+    }
+
+    fn push(&mut self, close: Decimal) {
+        self.closes.push_back(close);
+        self.sum += close;
+        if self.closes.len() > self.period {
+            self.sum -= self.closes.pop_front().unwrap_or_default();
+        }
+    }
+
+    fn mean(&self) -> Option<Decimal> {
+        (self.closes.len() == self.period).then(|| self.sum / Decimal::from(self.period as u64))
+    }
+
+    // Population standard deviation, computed in floating point for the square root
+    fn std_dev(&self, mean: Decimal) -> Option<Decimal> {
+        let variance = self.closes.iter().map(|c| (c - mean).to_f64().unwrap_or(0.).powi(2)).sum::<f64>() / self.period as f64;
+        Decimal::from_f64(variance.sqrt())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum State {
+    Sma(Closes),
+    // Seeded with the SMA of the first `period` closes
+    Ema { seed: Closes, value: Option<Decimal> },
+    // Wilder's smoothing of gains and losses, seeded with their plain average
+    Rsi { period: usize, last: Option<Decimal>, seen: usize, gain: Decimal, loss: Decimal },
+    Bollinger { closes: Closes, width: Decimal },
+}
+
+impl State {
+    fn new(spec: &IndicatorSpec) -> Self {
+        match spec {
+            IndicatorSpec::Sma(n) => State::Sma(Closes::new(*n)),
+            IndicatorSpec::Ema(n) => State::Ema { seed: Closes::new(*n), value: None },
+            IndicatorSpec::Rsi(n) => State::Rsi { period: *n, last: None, seen: 0, gain: Decimal::ZERO, loss: Decimal::ZERO },
+            IndicatorSpec::Bollinger(n, width) => State::Bollinger { closes: Closes::new(*n), width: *width },
+        }
+    }
+
+    fn update(&mut self, name: &str, close: Decimal, values: &mut BTreeMap<String, Decimal>) {
+        match self {
+            State::Sma(closes) => {
+                closes.push(close);
+                if let Some(mean) = closes.mean() {
+                    values.insert(name.to_string(), mean.round_dp(DECIMALS));
+                }
+            },
+            State::Ema { seed, value } => {
+                let next = match *value {
+                    Some(ema) => {
+                        let alpha = Decimal::TWO / Decimal::from(seed.period as u64 + 1);
+                        Some(alpha * close + (Decimal::ONE - alpha) * ema)
+                    },
+                    None => {
+                        seed.push(close);
+                        seed.mean()
+                    },
+                };
+                *value = next.map(|v| v.round_dp(DECIMALS + 4));
+                if let Some(ema) = *value {
+                    values.insert(name.to_string(), ema.round_dp(DECIMALS));
+                }
+            },
+            State::Rsi { period, last, seen, gain, loss } => {
+                let Some(previous) = last.replace(close) else {
+                    return;
+                };
+                let change = close - previous;
+                let (up, down) = (change.max(Decimal::ZERO), (-change).max(Decimal::ZERO));
+                let n = Decimal::from(*period as u64);
+                *seen += 1;
+                if *seen <= *period {
+                    *gain += up / n;
+                    *loss += down / n;
+                    if *seen < *period {
+                        return;
+                    }
+                } else {
+                    *gain = (*gain * (n - Decimal::ONE) + up) / n;
+                    *loss = (*loss * (n - Decimal::ONE) + down) / n;
+                }
+                let rsi = match loss.is_zero() {
+                    true => Decimal::ONE_HUNDRED,
+                    false => Decimal::ONE_HUNDRED - Decimal::ONE_HUNDRED / (Decimal::ONE + *gain / *loss),
+                };
+                values.insert(name.to_string(), rsi.round_dp(2));
+            },
+            State::Bollinger { closes, width } => {
+                closes.push(close);
+                let Some(mean) = closes.mean() else {
+                    return;
+                };
+                let band = closes.std_dev(mean).unwrap_or_default() * *width;
+                values.insert(format!("{}_upper", name), (mean + band).round_dp(DECIMALS));
+                values.insert(format!("{}_middle", name), mean.round_dp(DECIMALS));
+                values.insert(format!("{}_lower", name), (mean - band).round_dp(DECIMALS));
+            },
+        }
+    }
+}
+
+// Runs the configured indicators over the closes of completed candles
+#[derive(Debug, Clone)]
+pub struct TechnicalIndicators {
+    indicators: Vec<(String, State)>,
+}
+
+impl TechnicalIndicators {
+    pub fn new(specs: &[IndicatorSpec]) -> Self {
+        Self {
+            indicators: specs.iter().map(|spec| (spec.to_string(), State::new(spec))).collect(),
+        }
+    }
+
+    pub fn update(&mut self, candle: &Candle) -> TechnicalSnapshot {
+        let mut values = BTreeMap::new();
+        for (name, state) in self.indicators.iter_mut() {
+            state.update(name, candle.close, &mut values);
+        }
+        TechnicalSnapshot {
+            interval: candle.interval.clone(),
+            start_ms: candle.start_ms,
+            close: candle.close,
+            values,
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+const SECONDS_PER_YEAR: f64 = 365. * 24. * 3600.;
+
+// Samples without a book update in between are filled with zero returns, up to this many
+const MAX_GAP_SAMPLES: u64 = 100_000;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WindowVolatility {
+    pub window: String,
+    // Returns in the window, fewer than it holds while it is filling up
+    pub samples: usize,
+    // Log return of the mid over the window in basis points
+    pub return_bps: f64,
+    // Square root of the summed squared log returns, not scaled
+    pub realized_vol: f64,
+    // Realized volatility scaled to a year of continuous trading
+    pub annualized_vol: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VolatilitySnapshot {
+    pub sample: String,
+    pub windows: Vec<WindowVolatility>,
+}
+
+// Rolling realized volatility of mid-price log returns taken once per `sample`
+#[derive(Debug, Clone)]
+pub struct VolatilityEstimator {
+    sample: Duration,
+    sample_ms: u64,
+    windows: Vec<Duration>,
+    // Sample period of the latest mid and that mid
+    period: Option<u64>,
+    mid: f64,
+    // Mid at the end of the last completed sample
+    close: Option<f64>,
+    // Log returns by the end of their sample in ms
+    returns: VecDeque<(u64, f64)>,
+}
+
+impl VolatilityEstimator {
+    pub fn new(sample: Duration, windows: &[Duration]) -> Self {
+        Self {
+            sample,
+            sample_ms: (sample.as_millis() as u64).max(1),
+            windows: windows.to_vec(),
+            period: None,
+            mid: 0.,
+            close: None,
+            returns: VecDeque::new(),
+        }
+    }
+
+    // Records the mid at `ts_ms`, returns a snapshot whenever a sample completed
+    pub fn record(&mut self, ts_ms: u64, mid: Decimal) -> Option<VolatilitySnapshot> {
+        let mid = mid.to_f64().filter(|m| *m > 0.)?;
+        let period = ts_ms / self.sample_ms;
+        let Some(current) = self.period else {
+            self.period = Some(period);
+            self.mid = mid;
+            return None;
+        };
+        // Late updates count towards the current sample
+        if period <= current {
+            self.mid = mid;
+            return None;
+        }
+        let completed = match self.close {
+            Some(close) => {
+                self.returns.push_back(((current + 1) * self.sample_ms, (self.mid / close).ln()));
+                // The mid stood still through samples without updates
+                for skipped in (current + 1..period).take(MAX_GAP_SAMPLES as usize) {
+                    self.returns.push_back(((skipped + 1) * self.sample_ms, 0.));
+                }
+                true
+            },
+            None => false,
+        };
+        self.close = Some(self.mid);
+        self.period = Some(period);
+        self.mid = mid;
+        if !completed {
+            return None;
+        }
+        let now_ms = period * self.sample_ms;
+        let longest = self.windows.iter().max().map(|w| w.as_millis() as u64).unwrap_or(0);
+        while self.returns.front().is_some_and(|(end, _)| *end + longest <= now_ms) {
+            self.returns.pop_front();
+        }
+        Some(self.snapshot(now_ms))
+    }
+
+    fn snapshot(&self, now_ms: u64) -> VolatilitySnapshot {
+        let windows = self.windows.iter().map(|window| {
+            let cutoff = now_ms.saturating_sub(window.as_millis() as u64);
+            let (mut samples, mut total, mut squares) = (0, 0., 0.);
+            for (_, r) in self.returns.iter().rev().take_while(|(end, _)| *end > cutoff) {
+                samples += 1;
+                total += r;
+                squares += r * r;
+            }
+            let covered_secs = samples as f64 * self.sample.as_secs_f64();
+            WindowVolatility {
+                window: humantime::format_duration(*window).to_string(),
+                samples,
+                return_bps: round(total * 10_000., 4),
+                realized_vol: round(squares.sqrt(), 8),
+                annualized_vol: match samples {
+                    0 => 0.,
+                    _ => round((squares / covered_secs * SECONDS_PER_YEAR).sqrt(), 6),
+                },
+            }
+        }).collect();
+        VolatilitySnapshot {
+            sample: humantime::format_duration(self.sample).to_string(),
+            windows,
+        }
+    }
+}
+
+fn round(value: f64, digits: i32) -> f64 {
+    let scale = 10f64.powi(digits);
+    (value * scale).round() / scale
+}
@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::analytics::Interruption;
+use crate::models::{OrderSide, Trade};
+
+// Trades inside a sliding time window with running sums
+#[derive(Debug, Clone)]
+pub struct RollingWindow {
+    window_ms: u64,
+    trades: VecDeque<(u64, Decimal, Decimal, Option<OrderSide>)>,
+    volume: Decimal,
+    notional: Decimal,
+    // Volume of buyer and of seller initiated trades, unclassified ones count in neither
+    buy_volume: Decimal,
+    sell_volume: Decimal,
+}
+
+impl RollingWindow {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window_ms: window.as_millis() as u64,
+            trades: VecDeque::new(),
+            volume: Decimal::ZERO,
+            notional: Decimal::ZERO,
+            buy_volume: Decimal::ZERO,
+            sell_volume: Decimal::ZERO,
+        }
+    }
+
+    pub fn push(&mut self, ts_ms: u64, price: Decimal, amount: Decimal, aggressor: Option<OrderSide>) {
+        self.trades.push_back((ts_ms, price, amount, aggressor));
+        self.volume += amount;
+        self.notional += price * amount;
+        match aggressor {
+            Some(OrderSide::Buy) => self.buy_volume += amount,
+            Some(OrderSide::Sell) => self.sell_volume += amount,
+            None => {},
+        }
+        self.evict(ts_ms);
+    }
+
+    pub fn evict(&mut self, now_ms: u64) {
+        let cutoff = now_ms.saturating_sub(self.window_ms);
+        while let Some(&(ts, price, amount, aggressor)) = self.trades.front() {
+            if ts > cutoff {
+                break;
+            }
+            self.trades.pop_front();
+            self.volume -= amount;
+            self.notional -= price * amount;
+            match aggressor {
+                Some(OrderSide::Buy) => self.buy_volume -= amount,
+                Some(OrderSide::Sell) => self.sell_volume -= amount,
+                None => {},
+            }
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn volume(&self) -> Decimal {
+        self.volume
+    }
+
+    pub fn notional(&self) -> Decimal {
+        self.notional
+    }
+
+    pub fn buy_volume(&self) -> Decimal {
+        self.buy_volume
+    }
+
+    pub fn sell_volume(&self) -> Decimal {
+        self.sell_volume
+    }
+
+    pub fn vwap(&self) -> Option<Decimal> {
+        match self.volume.is_zero() {
+            true => None,
+            false => Some((self.notional / self.volume).round_dp(8)),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WindowStats {
+    pub window: String,
+    pub trades: usize,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub volume: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub notional: Decimal,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub vwap: Option<Decimal>,
+    // Volume of trades whose taker bought and of those whose taker sold
+    #[serde(with = "rust_decimal::serde::str")]
+    pub buy_volume: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub sell_volume: Decimal,
+    // The feed was interrupted within the window, trades may be missing from it
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub tainted: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TradeStatsSnapshot {
+    pub windows: Vec<WindowStats>,
+    // Buy minus sell volume since the stats started
+    #[serde(with = "rust_decimal::serde::str")]
+    pub cumulative_delta: Decimal,
+}
+
+// Rolling VWAP, count and notional over several windows at once
+#[derive(Debug, Clone)]
+pub struct TradeStats {
+    windows: Vec<(Duration, RollingWindow)>,
+    interruption: Option<Interruption>,
+    cumulative_delta: Decimal,
+}
+
+impl TradeStats {
+    pub fn new(windows: &[Duration]) -> Self {
+        Self {
+            windows: windows.iter().map(|w| (*w, RollingWindow::new(*w))).collect(),
+            interruption: None,
+            cumulative_delta: Decimal::ZERO,
+        }
+    }
+
+    // Windows reaching back into it are tainted until it has slid out of them
+    pub fn interrupt(&mut self, interruption: Interruption) {
+        self.interruption = Some(interruption);
+    }
+
+    pub fn record(&mut self, ts_ms: u64, trade: &Trade) {
+        let aggressor = trade.aggressor();
+        for (_, window) in self.windows.iter_mut() {
+            window.push(ts_ms, trade.price, trade.amount, aggressor);
+        }
+        match aggressor {
+            Some(OrderSide::Buy) => self.cumulative_delta += trade.amount,
+            Some(OrderSide::Sell) => self.cumulative_delta -= trade.amount,
+            None => {},
+        }
+    }
+
+    pub fn snapshot(&mut self, now_ms: u64) -> TradeStatsSnapshot {
+        let interruption = self.interruption;
+        let windows = self.windows.iter_mut().map(|(duration, window)| {
+            window.evict(now_ms);
+            let start_ms = now_ms.saturating_sub(duration.as_millis() as u64);
+            WindowStats {
+                window: humantime::format_duration(*duration).to_string(),
+                trades: window.count(),
+                volume: window.volume(),
+                notional: window.notional(),
+                vwap: window.vwap(),
+                buy_volume: window.buy_volume(),
+                sell_volume: window.sell_volume(),
+                tainted: interruption.is_some_and(|i| i.overlaps(start_ms, now_ms)),
+            }
+        }).collect();
+        TradeStatsSnapshot {
+            windows,
+            cumulative_delta: self.cumulative_delta,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha384;
+
+use crate::client::now_ms;
+use crate::config::Credentials;
+use crate::error::GeminiError;
+
+// Every private request needs a nonce larger than the last one seen for the key.
+// Milliseconds since the epoch keep that true across restarts, two requests in the
+// same millisecond get bumped by one.
+#[derive(Debug, Default)]
+pub struct NonceSource {
+    last: AtomicU64,
+}
+
+impl NonceSource {
+    pub fn next(&self) -> u64 {
+        let now = now_ms();
+        let mut last = self.last.load(Ordering::Relaxed);
+        loop {
+            let next = now.max(last + 1);
+            match self.last.compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => return next,
+                Err(actual) => last = actual,
+            }
+        }
+    }
+}
+
+// Service name the API key and secret are stored under in the OS keyring, as the
+// entries `api_key` and `api_secret`
+pub const KEYRING_SERVICE: &str = "gemini";
+
+// Resolves the API key, the first complete pair wins: GEMINI_API_KEY and
+// GEMINI_API_SECRET, then the OS keyring when `keyring` is set, then `[credentials]`
+// from the config file
+pub fn load_credentials(config: Credentials, keyring: bool) -> Result<Credentials, GeminiError> {
+    if let (Ok(api_key), Ok(api_secret)) = (std::env::var("GEMINI_API_KEY"), std::env::var("GEMINI_API_SECRET")) {
+        return Ok(Credentials {
+            api_key: Some(api_key),
+            api_secret: Some(api_secret),
+        });
+    }
+    if keyring {
+        if let Some(credentials) = from_keyring()? {
+            return Ok(credentials);
+        }
+    }
+    Ok(config)
+}
+
+#[cfg(feature = "keyring")]
+fn from_keyring() -> Result<Option<Credentials>, GeminiError> {
+    let read = |user: &str| -> Result<Option<String>, GeminiError> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, user)
+            .map_err(|e| GeminiError::Auth(format!("keyring: {}", e)))?;
+        match entry.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(GeminiError::Auth(format!("keyring {}: {}", user, e))),
+        }
+    };
+    match (read("api_key")?, read("api_secret")?) {
+        (Some(api_key), Some(api_secret)) => Ok(Some(Credentials {
+            api_key: Some(api_key),
+            api_secret: Some(api_secret),
+        })),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+fn from_keyring() -> Result<Option<Credentials>, GeminiError> {
+    Err(GeminiError::Config(String::from("--keyring needs a build with the keyring feature")))
+}
+
+// The three X-GEMINI-* header values of one private request
+pub struct SignedRequest {
+    pub api_key: String,
+    pub payload: String,
+    pub signature: String,
+}
+
+// Signs private API payloads with an API key's secret. Clones share the nonce counter,
+// so the tasks using the same key never send a nonce the exchange already saw.
+#[derive(Clone)]
+pub struct Signer {
+    api_key: String,
+    secret: String,
+    nonce: Arc<NonceSource>,
+}
+
+impl Signer {
+    pub fn new(credentials: &Credentials) -> Result<Self, GeminiError> {
+        match (&credentials.api_key, &credentials.api_secret) {
+            (Some(api_key), Some(secret)) => Ok(Self {
+                api_key: api_key.clone(),
+                secret: secret.clone(),
+                nonce: Arc::default(),
+            }),
+            _ => Err(GeminiError::Auth(String::from(
+                "no API key, set GEMINI_API_KEY and GEMINI_API_SECRET, api_key and api_secret under [credentials] or store them in the keyring with --keyring",
+            ))),
+        }
+    }
+
+    // `request` is the API path, e.g. "/v1/order/new", and `params` must serialize to
+    // a JSON object whose fields are merged into the payload
+    pub fn sign<P: Serialize>(&self, request: &str, params: &P) -> Result<SignedRequest, GeminiError> {
+        let mut payload = match serde_json::to_value(params) {
+            Ok(Value::Object(fields)) => fields,
+            Ok(Value::Null) => serde_json::Map::new(),
+            Ok(other) => return Err(GeminiError::Protocol(format!("request parameters must be an object, got {}", other))),
+            Err(e) => return Err(GeminiError::Protocol(format!("cannot encode request parameters: {}", e))),
+        };
+        payload.insert(String::from("request"), Value::from(request));
+        payload.insert(String::from("nonce"), Value::from(self.nonce.next()));
+        let payload = BASE64.encode(Value::Object(payload).to_string());
+
+        let mut mac = Hmac::<Sha384>::new_from_slice(self.secret.as_bytes())
+            .map_err(|e| GeminiError::Auth(format!("unusable API secret: {}", e)))?;
+        mac.update(payload.as_bytes());
+        Ok(SignedRequest {
+            api_key: self.api_key.clone(),
+            payload,
+            signature: hex::encode(mac.finalize().into_bytes()),
+        })
+    }
+}
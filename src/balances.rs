@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+use crate::auth::Signer;
+use crate::market_state::MarketState;
+use crate::error::GeminiError;
+use crate::models::Balance;
+use crate::rest::RestClient;
+
+// Holdings are valued in this currency through the `<currency>usd` books
+pub const VALUATION_CURRENCY: &str = "usd";
+
+pub struct Holding {
+    pub currency: String,
+    pub amount: Decimal,
+    // None when no `<currency>usd` book is subscribed or it has no two-sided quote yet
+    pub value: Option<Decimal>,
+}
+
+// Values every non-zero balance at the mid of its live book
+pub fn value(balances: &[Balance], bbo: &MarketState) -> Vec<Holding> {
+    balances.iter()
+        .filter(|b| !b.amount.is_zero())
+        .map(|b| {
+            let currency = b.currency.to_lowercase();
+            let value = match currency == VALUATION_CURRENCY {
+                true => Some(b.amount),
+                false => bbo.get(&format!("{}{}", currency, VALUATION_CURRENCY))
+                    .filter(|bbo| !bbo.best_bid.is_zero() && !bbo.best_offer.is_zero())
+                    .map(|bbo| b.amount * (bbo.best_bid + bbo.best_offer) / Decimal::TWO),
+            };
+            Holding {
+                currency,
+                amount: b.amount,
+                value,
+            }
+        })
+        .collect()
+}
+
+pub fn report(holdings: &[Holding]) -> String {
+    let mut text = format!("Balances in {}\n", VALUATION_CURRENCY);
+    let mut total = Decimal::ZERO;
+    for holding in holdings {
+        let value = match holding.value {
+            Some(value) => {
+                total += value;
+                value.round_dp(2).to_string()
+            },
+            None => String::from("-"),
+        };
+        text.push_str(&format!("  {} {} = {}\n", holding.currency, holding.amount.normalize(), value));
+    }
+    let unpriced = holdings.iter().filter(|h| h.value.is_none()).count();
+    text.push_str(&format!("  total {}", total.round_dp(2)));
+    if unpriced > 0 {
+        text.push_str(&format!(" ({} without a live {} book)", unpriced, VALUATION_CURRENCY));
+    }
+    text.push('\n');
+    text
+}
+
+// Logs the account's holdings every `interval` until shutdown. A rejected key stops
+// the polling, anything else is retried at the next tick.
+pub async fn poll(rest: RestClient, signer: Signer, interval: Duration, bbo: MarketState, shutdown: CancellationToken) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = ticker.tick() => {},
+        }
+        match rest.balances(&signer).await {
+            Ok(balances) => eprint!("{}", report(&value(&balances, &bbo))),
+            Err(e @ GeminiError::Auth(_)) => {
+                error!(error = %e, "balances: giving up");
+                return;
+            },
+            Err(e) => warn!(error = %e, "balances: poll failed"),
+        }
+    }
+}
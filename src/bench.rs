@@ -0,0 +1,105 @@
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::capture::{CaptureReader, CapturedFrame};
+use crate::error::GeminiError;
+use crate::models::MarketMessage;
+use crate::pipeline::Pipeline;
+
+// Time spent in one stage over the whole capture
+#[derive(Serialize, Debug, Clone)]
+pub struct Stage {
+    pub name: &'static str,
+    pub total_ms: f64,
+    pub per_frame_us: f64,
+}
+
+// Throughput of one pass of a capture through the pipeline. The capture is read into
+// memory first, so reading and decompressing do not count against the other stages.
+#[derive(Serialize, Debug, Clone)]
+pub struct BenchReport {
+    pub frames: u64,
+    pub events: u64,
+    pub bytes: u64,
+    // Parse, pipeline and drain together
+    pub elapsed_ms: f64,
+    pub frames_per_sec: f64,
+    pub events_per_sec: f64,
+    pub stages: Vec<Stage>,
+}
+
+impl BenchReport {
+    fn new(frames: u64, events: u64, bytes: u64, stages: [(&'static str, Duration); 4]) -> Self {
+        let elapsed: Duration = stages[1..].iter().map(|(_, d)| *d).sum();
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        Self {
+            frames,
+            events,
+            bytes,
+            elapsed_ms: secs * 1e3,
+            frames_per_sec: frames as f64 / secs,
+            events_per_sec: events as f64 / secs,
+            stages: stages.into_iter().map(|(name, total)| Stage {
+                name,
+                total_ms: total.as_secs_f64() * 1e3,
+                per_frame_us: total.as_secs_f64() * 1e6 / frames.max(1) as f64,
+            }).collect(),
+        }
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "bench: {} frames, {} events, {:.1} MB in {:.1}ms",
+            self.frames, self.events, self.bytes as f64 / 1e6, self.elapsed_ms,
+        )?;
+        writeln!(f, "  throughput {:.0} frames/s, {:.0} events/s", self.frames_per_sec, self.events_per_sec)?;
+        for stage in &self.stages {
+            writeln!(f, "  {:<9} {:>10.1}ms {:>9.2}us/frame", stage.name, stage.total_ms, stage.per_frame_us)?;
+        }
+        Ok(())
+    }
+}
+
+// Feeds the capture at `path` through `pipeline` as fast as it goes and times each
+// stage: reading the capture, parsing the frames, the pipeline's book, analytics and
+// dispatch, and the outputs draining what was dispatched. Frames of other symbols than
+// `symbols` are skipped unless it is empty. Flushes the pipeline.
+pub async fn run(path: &Path, symbols: &[String], pipeline: &mut Pipeline, shutdown: &CancellationToken) -> Result<BenchReport, GeminiError> {
+    let started = Instant::now();
+    let mut reader = CaptureReader::open(path, 0.).await?;
+    let mut captured: Vec<CapturedFrame> = Vec::new();
+    while let Some(frame) = reader.next().await? {
+        if symbols.is_empty() || symbols.contains(&frame.symbol) {
+            captured.push(frame);
+        }
+    }
+    let read = started.elapsed();
+
+    let (mut parse, mut handle) = (Duration::ZERO, Duration::ZERO);
+    let (mut frames, mut events, mut bytes) = (0, 0, 0);
+    for frame in &captured {
+        if shutdown.is_cancelled() {
+            break;
+        }
+        let started = Instant::now();
+        let parsed = MarketMessage::from_slice(frame.frame.as_bytes()).map_err(|e| e.to_string());
+        parse += started.elapsed();
+        frames += 1;
+        bytes += frame.frame.len() as u64;
+        events += parsed.as_ref().map_or(0, |m| m.events.len() as u64);
+        let started = Instant::now();
+        pipeline.handle_message(&frame.symbol, parsed, frame.received_ms).await?;
+        handle += started.elapsed();
+    }
+    let started = Instant::now();
+    pipeline.flush().await?;
+    let drain = started.elapsed();
+    Ok(BenchReport::new(frames, events, bytes, [("read", read), ("parse", parse), ("pipeline", handle), ("drain", drain)]))
+}
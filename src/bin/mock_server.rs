@@ -0,0 +1,89 @@
+// Local stand-in for Gemini's market data WebSocket, for trying the client, its
+// reconnects and the parser without the exchange:
+//
+//   cargo run --features mock-server --bin mock-server -- --disconnect-after 50 --malformed-every 20
+//   cargo run -- --endpoint ws://127.0.0.1:8765 btcusd
+use std::io::IsTerminal;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::Parser;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use order_book::logging::{self, LogFormat};
+use order_book::mock::{self, Faults, Scenario};
+use order_book::GeminiError;
+
+#[derive(Parser)]
+struct Args {
+    /// Address to serve /v1/marketdata/SYMBOL and /v2/marketdata on
+    #[arg(long, default_value = "127.0.0.1:8765")]
+    listen: SocketAddr,
+    /// Serve the frames of this capture instead of a synthetic feed
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+    /// Replay speed multiplier, 0 sends the capture as fast as possible
+    #[arg(long, default_value_t = 1.0, requires = "replay")]
+    speed: f64,
+    /// Synthetic updates per connection after the initial book
+    #[arg(long, default_value_t = 1000, conflicts_with = "replay")]
+    frames: usize,
+    /// Time between synthetic updates
+    #[arg(long, default_value = "100ms", value_parser = humantime::parse_duration, conflicts_with = "replay")]
+    interval: Duration,
+    /// Drop connections without a close frame after this many frames
+    #[arg(long, value_name = "N")]
+    disconnect_after: Option<usize>,
+    /// How many connections per feed --disconnect-after drops
+    #[arg(long, default_value_t = 1, requires = "disconnect_after")]
+    disconnects: u32,
+    /// Skip a socket_sequence number before every N-th frame
+    #[arg(long, value_name = "N")]
+    gap_every: Option<NonZeroUsize>,
+    /// Send every N-th frame cut in half
+    #[arg(long, value_name = "N")]
+    malformed_every: Option<NonZeroUsize>,
+    /// Level of the logs on stderr, or filter directives
+    #[arg(long, value_name = "FILTER", default_value = "info")]
+    log_level: String,
+    /// Write the logs on stderr as text or as one JSON object per line
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+}
+
+async fn run(args: Args) -> Result<(), GeminiError> {
+    logging::init(&args.log_level, args.log_format, std::io::stderr().is_terminal())?;
+    let scenario = match &args.replay {
+        Some(path) => Scenario::from_capture(path, args.speed).await?,
+        None => Scenario::synthetic(args.frames, args.interval),
+    };
+    let scenario = scenario.with_faults(Faults {
+        disconnect_after: args.disconnect_after,
+        disconnects: args.disconnects,
+        gap_every: args.gap_every,
+        malformed_every: args.malformed_every,
+    });
+    let shutdown = CancellationToken::new();
+    let (addr, server) = mock::bind(args.listen, scenario, shutdown.clone()).await?;
+    info!(%addr, "serving mock market data");
+    let server = tokio::spawn(server);
+    tokio::signal::ctrl_c().await?;
+    shutdown.cancel();
+    let _ = server.await;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Args::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::error::GeminiError;
+use crate::order_events::Fill;
+
+pub const BLOTTER_HEADER: &str = "time,symbol,side,price,quantity,fee,fee_currency,order_id,client_order_id,trade_id,liquidity";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlotterFormat {
+    #[default]
+    Csv,
+    // A file per run in a directory, Parquet files cannot be appended to
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+enum Writer {
+    Csv(File),
+    #[cfg(feature = "parquet")]
+    Parquet(Box<crate::sinks::parquet::ParquetBlotter>),
+}
+
+// Every fill of the account, one row each, in a shape accounting and tax tools import
+// as is. CSV rows are written through as they come, a crash loses none of them.
+pub struct Blotter {
+    writer: Writer,
+}
+
+impl Blotter {
+    // A CSV blotter is appended to across runs, the header goes into new files only
+    pub fn open(path: &Path, format: BlotterFormat) -> Result<Self, GeminiError> {
+        let writer = match format {
+            BlotterFormat::Csv => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)
+                    .map_err(|e| GeminiError::Sink(format!("blotter: cannot open {}: {}", path.display(), e)))?;
+                if file.metadata()?.len() == 0 {
+                    writeln!(file, "{}", BLOTTER_HEADER)?;
+                }
+                Writer::Csv(file)
+            },
+            #[cfg(feature = "parquet")]
+            BlotterFormat::Parquet => Writer::Parquet(Box::new(crate::sinks::parquet::ParquetBlotter::create(path)?)),
+        };
+        Ok(Self { writer })
+    }
+
+    pub fn write(&mut self, fill: &Fill) -> Result<(), GeminiError> {
+        match &mut self.writer {
+            Writer::Csv(file) => {
+                file.write_all(csv_row(fill).as_bytes())?;
+                file.flush()?;
+                Ok(())
+            },
+            #[cfg(feature = "parquet")]
+            Writer::Parquet(blotter) => blotter.write(fill),
+        }
+    }
+
+    // Finishes a Parquet file, CSV rows are already written
+    pub fn close(&mut self) -> Result<(), GeminiError> {
+        match &mut self.writer {
+            Writer::Csv(_) => Ok(()),
+            #[cfg(feature = "parquet")]
+            Writer::Parquet(blotter) => blotter.close(),
+        }
+    }
+}
+
+// Order ids are numbers, client order ids are free text and may need quoting
+fn csv_row(fill: &Fill) -> String {
+    let time = humantime::format_rfc3339_millis(UNIX_EPOCH + Duration::from_millis(fill.timestampms));
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{}\n",
+        time, fill.symbol, fill.side, fill.price, fill.amount, fill.fee, fill.fee_currency,
+        fill.order_id, quoted(fill.client_order_id.as_deref().unwrap_or_default()), fill.trade_id, fill.liquidity,
+    )
+}
+
+fn quoted(field: &str) -> String {
+    match field.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
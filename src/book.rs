@@ -0,0 +1,210 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use rust_decimal::Decimal;
+
+use crate::models::{BestBidOffer, BookSnapshot, MarketSide, Quote};
+
+// Price levels per side, keyed by price with the remaining size as value
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_snapshot(snapshot: &BookSnapshot) -> Self {
+        Self {
+            bids: snapshot.bids.iter().map(|l| (l.price, l.amount)).collect(),
+            asks: snapshot.asks.iter().map(|l| (l.price, l.amount)).collect(),
+        }
+    }
+
+    fn side_mut(&mut self, side: MarketSide) -> Option<&mut BTreeMap<Decimal, Decimal>> {
+        match side {
+            MarketSide::Bid => Some(&mut self.bids),
+            MarketSide::Ask => Some(&mut self.asks),
+            MarketSide::Unknown => None,
+        }
+    }
+
+    // Full depth feed: `remaining` is the new size of the level, zero removes it
+    pub fn apply(&mut self, quote: &Quote) {
+        let Some(levels) = self.side_mut(quote.side) else {
+            return;
+        };
+        if quote.remaining.is_zero() {
+            levels.remove(&quote.price);
+        } else {
+            levels.insert(quote.price, quote.remaining);
+        }
+    }
+
+    // Top of book feed: every change describes the whole visible side
+    pub fn replace_top(&mut self, quote: &Quote) {
+        if let Some(levels) = self.side_mut(quote.side) {
+            levels.clear();
+        }
+        self.apply(quote);
+    }
+
+    // Drops the levels beyond the best `levels` of each side, returning how many went. The
+    // book past that depth is incomplete from then on, levels coming back are added again.
+    pub fn truncate(&mut self, levels: usize) -> usize {
+        let mut evicted = 0;
+        while self.bids.len() > levels {
+            self.bids.pop_first();
+            evicted += 1;
+        }
+        while self.asks.len() > levels {
+            self.asks.pop_last();
+            evicted += 1;
+        }
+        evicted
+    }
+
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    pub fn best_bid(&self) -> Option<(Decimal, Decimal)> {
+        self.bids.iter().next_back().map(|(p, s)| (*p, *s))
+    }
+
+    pub fn best_ask(&self) -> Option<(Decimal, Decimal)> {
+        self.asks.iter().next().map(|(p, s)| (*p, *s))
+    }
+
+    // Bids from best (highest) to worst
+    pub fn bids(&self) -> impl Iterator<Item = (&Decimal, &Decimal)> {
+        self.bids.iter().rev()
+    }
+
+    // Asks from best (lowest) to worst
+    pub fn asks(&self) -> impl Iterator<Item = (&Decimal, &Decimal)> {
+        self.asks.iter()
+    }
+
+    pub fn depth(&self) -> (usize, usize) {
+        (self.bids.len(), self.asks.len())
+    }
+
+    // Price levels among the best `levels` per side that only one of the books has at that size
+    pub fn differing_levels(&self, other: &OrderBook, levels: usize) -> usize {
+        let differing = |ours: Vec<(&Decimal, &Decimal)>, theirs: Vec<(&Decimal, &Decimal)>| {
+            ours.iter().filter(|l| !theirs.contains(l)).count() + theirs.iter().filter(|l| !ours.contains(l)).count()
+        };
+        differing(self.bids().take(levels).collect(), other.bids().take(levels).collect())
+            + differing(self.asks().take(levels).collect(), other.asks().take(levels).collect())
+    }
+
+    pub fn bbo(&self) -> BestBidOffer {
+        let (best_bid, bid_amount_remaining) = self.best_bid().unwrap_or_default();
+        let (best_offer, ask_amount_remaining) = self.best_ask().unwrap_or_default();
+        BestBidOffer {
+            best_bid,
+            best_offer,
+            bid_amount_remaining,
+            ask_amount_remaining,
+        }
+    }
+}
+
+// A best price that was replaced, with the time it took over and how long it stood
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelDwell {
+    pub side: MarketSide,
+    pub price: Decimal,
+    pub since_ms: u64,
+    pub dwell_ms: u64,
+}
+
+// When each side's best price took over, so its dwell is known once it is replaced.
+// Size changes at the same price keep the level alive.
+#[derive(Debug, Clone, Default)]
+pub struct TopOfBookClock {
+    bid: Option<(Decimal, u64)>,
+    ask: Option<(Decimal, u64)>,
+}
+
+impl TopOfBookClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Timestamps the BBO at `ts_ms` and returns the levels it replaced
+    pub fn update(&mut self, ts_ms: u64, bbo: &BestBidOffer) -> Vec<LevelDwell> {
+        let mut replaced = Vec::new();
+        for (side, top, price) in [(MarketSide::Bid, &mut self.bid, bbo.best_bid), (MarketSide::Ask, &mut self.ask, bbo.best_offer)] {
+            match *top {
+                Some((current, _)) if current == price => continue,
+                // An empty side is no level, so it has no dwell of its own
+                Some((current, since_ms)) if !current.is_zero() => replaced.push(LevelDwell {
+                    side,
+                    price: current,
+                    since_ms,
+                    dwell_ms: ts_ms.saturating_sub(since_ms),
+                }),
+                _ => {},
+            }
+            *top = Some((price, ts_ms));
+        }
+        replaced
+    }
+
+    // Forgets the levels, a rebuilt book after a reconnect starts new ones
+    pub fn reset(&mut self) {
+        self.bid = None;
+        self.ask = None;
+    }
+}
+
+// The best level of each side as of the last change, so the next change can be judged
+// against it for order flow imbalance
+#[derive(Debug, Clone, Default)]
+pub struct TopOfBook {
+    bid: Option<(Decimal, Decimal)>,
+    ask: Option<(Decimal, Decimal)>,
+}
+
+impl TopOfBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Order flow of the book moving from the previous top to its current one, after Cont,
+    // Kukanov and Stoikov: size joining the bid or leaving the ask counts as buying,
+    // positive, the reverse as selling. None until both tops are known for a side.
+    pub fn update(&mut self, book: &OrderBook) -> Option<Decimal> {
+        let bid = std::mem::replace(&mut self.bid, book.best_bid());
+        let ask = std::mem::replace(&mut self.ask, book.best_ask());
+        let bid_flow = side_flow(bid, self.bid, Ordering::Greater);
+        let ask_flow = side_flow(ask, self.ask, Ordering::Less);
+        match (bid_flow, ask_flow) {
+            (None, None) => None,
+            (bid, ask) => Some(bid.unwrap_or_default() - ask.unwrap_or_default()),
+        }
+    }
+
+    // Forgets both sides, the next update only sets the new top
+    pub fn reset(&mut self) {
+        self.bid = None;
+        self.ask = None;
+    }
+}
+
+// Size added at the top of one side: all of it when the price improved, the change at an
+// unchanged price, and minus the old size when the level was taken out
+fn side_flow(previous: Option<(Decimal, Decimal)>, current: Option<(Decimal, Decimal)>, improves: Ordering) -> Option<Decimal> {
+    let ((old_price, old_size), (price, size)) = (previous?, current?);
+    Some(match price.cmp(&old_price) {
+        Ordering::Equal => size - old_size,
+        ordering if ordering == improves => size,
+        _ => -old_size,
+    })
+}
@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+use crate::book::OrderBook;
+use crate::error::GeminiError;
+use crate::metrics;
+use crate::models::BookSnapshot;
+use crate::rest::RestClient;
+
+// Outcome of comparing a local book with a REST snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Matches,
+    // Differs once, which a change arriving while the snapshot was taken explains. Check again.
+    Suspect,
+    // Differs on two checks in a row, so the local book missed or misapplied a change
+    Diverged(usize),
+}
+
+// Compares the maintained books against REST snapshots of their best `levels` per side
+pub struct BookChecker {
+    rest: RestClient,
+    levels: usize,
+    suspect: HashSet<String>,
+    fetches: JoinSet<(String, Result<BookSnapshot, GeminiError>)>,
+}
+
+impl BookChecker {
+    pub fn new(rest: RestClient, levels: usize) -> Self {
+        Self {
+            rest,
+            levels,
+            suspect: HashSet::new(),
+            fetches: JoinSet::new(),
+        }
+    }
+
+    // Starts fetching a snapshot of `symbol`, it comes back from `next`
+    pub fn check(&mut self, symbol: &str) {
+        let (rest, symbol, levels) = (self.rest.clone(), symbol.to_string(), self.levels);
+        self.fetches.spawn(async move {
+            let snapshot = rest.book(&symbol, levels).await;
+            (symbol, snapshot)
+        });
+    }
+
+    // The next fetched snapshot, waits forever while none is in flight so it fits a select loop
+    pub async fn next(&mut self) -> (String, BookSnapshot) {
+        loop {
+            match self.fetches.join_next().await {
+                Some(Ok((symbol, Ok(snapshot)))) => return (symbol, snapshot),
+                Some(Ok((symbol, Err(e)))) => warn!(symbol, error = %e, "book check: could not fetch the snapshot"),
+                Some(Err(e)) => warn!(error = %e, "book check: fetch task failed"),
+                None => std::future::pending().await,
+            }
+        }
+    }
+
+    pub fn compare(&mut self, symbol: &str, book: &OrderBook, snapshot: &BookSnapshot) -> Verdict {
+        let differing = book.differing_levels(&OrderBook::from_snapshot(snapshot), self.levels);
+        if differing == 0 {
+            self.suspect.remove(symbol);
+            return Verdict::Matches;
+        }
+        if self.suspect.insert(symbol.to_string()) {
+            debug!(symbol, differing, "book check: book differs from the snapshot, checking again");
+            return Verdict::Suspect;
+        }
+        self.suspect.remove(symbol);
+        metrics::global().book_divergences.with_label_values(&[symbol]).inc();
+        warn!(symbol, differing, levels = self.levels, "book check: order book diverged from the REST snapshot");
+        Verdict::Diverged(differing)
+    }
+
+    // Drops the state of a symbol that was unsubscribed or reconnected
+    pub fn forget(&mut self, symbol: &str) {
+        self.suspect.remove(symbol);
+    }
+}
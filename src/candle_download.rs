@@ -0,0 +1,85 @@
+use std::io::Write;
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::analytics::candles::Candle;
+use crate::error::GeminiError;
+use crate::exchange_candles;
+use crate::precision::Precision;
+use crate::rest::RestClient;
+
+pub const CANDLE_CSV_HEADER: &str = "symbol,start_ms,interval,open,high,low,close,volume";
+
+#[derive(Serialize)]
+struct SymbolCandle<'a> {
+    symbol: &'a str,
+    #[serde(flatten)]
+    candle: &'a Candle,
+}
+
+// The exchange's completed `interval` bars of each symbol over REST, oldest first per
+// symbol, those starting from `since_ms` up to before `until_ms`. The exchange only keeps
+// so much history and takes no time range, so there is nothing to page through. A
+// warning says when the history does not reach back to `since_ms`.
+pub async fn download(
+    rest: &RestClient,
+    symbols: &[String],
+    interval: Duration,
+    since_ms: Option<u64>,
+    until_ms: Option<u64>,
+) -> Result<Vec<(String, Candle)>, GeminiError> {
+    let time_frame = exchange_candles::time_frame(interval)?;
+    let label = humantime::format_duration(interval).to_string();
+    let mut downloaded = Vec::new();
+    for symbol in symbols {
+        let mut candles = rest.candles(symbol, time_frame).await?.into_iter()
+            .map(|row| exchange_candles::candle(row, &label))
+            .collect::<Result<Vec<Candle>, GeminiError>>()?;
+        candles.sort_by_key(|c| c.start_ms);
+        // The newest bar is still forming
+        candles.pop();
+        if let (Some(since_ms), Some(oldest)) = (since_ms, candles.first()) {
+            if oldest.start_ms > since_ms {
+                let oldest = humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_millis(oldest.start_ms));
+                warn!(symbol, %oldest, "the exchange keeps no older candles");
+            }
+        }
+        candles.retain(|c| since_ms.is_none_or(|since| c.start_ms >= since) && until_ms.is_none_or(|until| c.start_ms < until));
+        info!(symbol, candles = candles.len(), "downloaded candles");
+        downloaded.extend(candles.into_iter().map(|candle| (symbol.clone(), candle)));
+    }
+    Ok(downloaded)
+}
+
+pub fn write_csv(out: &mut impl Write, candles: &[(String, Candle)], precision: &Precision) -> Result<(), GeminiError> {
+    let p = precision;
+    writeln!(out, "{}", CANDLE_CSV_HEADER)?;
+    for (symbol, c) in candles {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{}",
+            symbol, c.start_ms, c.interval, p.price(c.open), p.price(c.high), p.price(c.low), p.price(c.close), p.amount(c.volume),
+        )?;
+    }
+    Ok(())
+}
+
+// One object per bar, the candle fields of the live output plus the symbol
+pub fn write_jsonl(out: &mut impl Write, candles: &[(String, Candle)], precision: &Precision) -> Result<(), GeminiError> {
+    let p = precision;
+    for (symbol, c) in candles {
+        let candle = Candle {
+            open: p.price(c.open),
+            high: p.price(c.high),
+            low: p.price(c.low),
+            close: p.price(c.close),
+            volume: p.amount(c.volume),
+            ..c.clone()
+        };
+        // Serializing a struct of strings and numbers cannot fail
+        writeln!(out, "{}", serde_json::to_string(&SymbolCandle { symbol, candle: &candle }).unwrap_or_default())?;
+    }
+    Ok(())
+}
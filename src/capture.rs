@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, Lines};
+use tracing::info;
+
+use crate::error::GeminiError;
+use crate::files::PathTemplate;
+use crate::wire::{self, WireFormat};
+
+// One line of a JSON capture file, or one length prefixed record of a binary one: the
+// raw frame exactly as received
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CapturedFrame {
+    pub received_ms: u64,
+    pub symbol: String,
+    pub frame: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn extension(self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+        }
+    }
+
+    // Replays go by the file name
+    fn of_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CaptureOptions {
+    pub format: WireFormat,
+    pub compression: Compression,
+    // Start a new file whenever the wall clock enters the next multiple of this
+    pub rotate_every: Option<Duration>,
+    // Start a new file before one would exceed this many uncompressed bytes
+    pub rotate_size: Option<u64>,
+}
+
+// Accepts sizes like "500MB", "2GiB" or a plain byte count
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid size `{}`", s))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1_000,
+        "m" | "mb" => 1_000_000,
+        "g" | "gb" => 1_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        other => return Err(format!("unknown size unit `{}`", other)),
+    };
+    match (number * multiplier as f64) as u64 {
+        0 => Err(String::from("size must be larger than zero")),
+        bytes => Ok(bytes),
+    }
+}
+
+type CaptureSink = Box<dyn AsyncWrite + Send + Unpin>;
+
+pub struct CaptureWriter {
+    path: PathBuf,
+    options: CaptureOptions,
+    out: Option<CaptureSink>,
+    // Which `rotate_every` period the open file belongs to
+    period: Option<u64>,
+    written: u64,
+    sequence: u32,
+}
+
+impl CaptureWriter {
+    // Without rotation everything goes to `path`, plus the compression's extension. With
+    // rotation each file is named `<stem>-<UTC open time>-<sequence>.<extension>`, e.g.
+    // capture-20240601T143000Z-0001.jsonl.gz, so the names sort in recording order.
+    pub async fn create(path: &Path, options: CaptureOptions) -> Result<Self, GeminiError> {
+        let mut writer = Self {
+            path: path.to_path_buf(),
+            options,
+            out: None,
+            period: None,
+            written: 0,
+            sequence: 0,
+        };
+        // Opened right away so an unwritable path fails at startup
+        writer.open(SystemTime::now()).await?;
+        Ok(writer)
+    }
+
+    fn rotating(&self) -> bool {
+        self.options.rotate_every.is_some() || self.options.rotate_size.is_some()
+    }
+
+    fn file_name(&self, now: SystemTime) -> PathBuf {
+        let compression = self.options.compression.extension();
+        if !self.rotating() {
+            let mut name = self.path.clone().into_os_string();
+            if !name.to_string_lossy().ends_with(compression) {
+                name.push(compression);
+            }
+            return PathBuf::from(name);
+        }
+        let stem = self.path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let extension = self.path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        let opened: String = humantime::format_rfc3339_seconds(now).to_string().chars().filter(|c| !matches!(c, '-' | ':')).collect();
+        let name = format!("{}-{}-{:04}{}{}", stem, opened, self.sequence, extension, compression);
+        self.path.with_file_name(name)
+    }
+
+    async fn open(&mut self, now: SystemTime) -> Result<(), GeminiError> {
+        self.sequence += 1;
+        let path = self.file_name(now);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let file = BufWriter::new(File::create(&path).await?);
+        self.out = Some(match self.options.compression {
+            Compression::None => Box::new(file),
+            Compression::Gzip => Box::new(GzipEncoder::new(file)),
+            Compression::Zstd => Box::new(ZstdEncoder::new(file)),
+        });
+        self.period = self.options.rotate_every.map(|every| period(now, every));
+        self.written = 0;
+        // Each rotated file gets its own header, so any of them can be replayed alone
+        if let (WireFormat::Bincode, Some(out)) = (self.options.format, self.out.as_mut()) {
+            out.write_all(&wire::header()).await?;
+        }
+        if self.rotating() {
+            info!(path = %path.display(), "recording");
+        }
+        Ok(())
+    }
+
+    fn needs_rotation(&self, now: SystemTime, len: u64) -> bool {
+        let period_over = match (self.options.rotate_every, self.period) {
+            (Some(every), Some(current)) => period(now, every) != current,
+            _ => false,
+        };
+        let full = self.options.rotate_size.is_some_and(|max| self.written > 0 && self.written + len > max);
+        period_over || full
+    }
+
+    pub async fn write(&mut self, frame: &CapturedFrame) -> Result<(), GeminiError> {
+        let line = match self.options.format {
+            WireFormat::Json => {
+                let mut line = serde_json::to_vec(frame).map_err(|e| GeminiError::Protocol(e.to_string()))?;
+                line.push(b'\n');
+                line
+            },
+            WireFormat::Bincode => {
+                let record = wire::encode(frame)?;
+                let mut line = (record.len() as u32).to_le_bytes().to_vec();
+                line.extend(record);
+                line
+            },
+        };
+        let now = SystemTime::now();
+        if self.needs_rotation(now, line.len() as u64) {
+            self.close().await?;
+            self.open(now).await?;
+        }
+        let Some(out) = self.out.as_mut() else {
+            return Ok(());
+        };
+        out.write_all(&line).await?;
+        // A flush per frame would wreck the compression ratio, compressed files are
+        // completed when they rotate or the recording finishes
+        if self.options.compression == Compression::None {
+            out.flush().await?;
+        }
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), GeminiError> {
+        if let Some(mut out) = self.out.take() {
+            // Writes the compression trailer and flushes the file
+            out.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    // Must be called for a compressed capture to be readable to the end
+    pub async fn finish(mut self) -> Result<(), GeminiError> {
+        self.close().await
+    }
+}
+
+// Captures split over the files a path template names, e.g. data/{symbol}/{date}.jsonl.
+// Each file is a CaptureWriter of its own, finished when the date or hour in its name
+// has passed. A path without placeholders is a single capture as before.
+pub struct CaptureFiles {
+    template: PathTemplate,
+    options: CaptureOptions,
+    writers: HashMap<PathBuf, CaptureWriter>,
+    period: Option<u64>,
+}
+
+impl CaptureFiles {
+    pub async fn create(path: &Path, options: CaptureOptions) -> Result<Self, GeminiError> {
+        let template = PathTemplate::new(path)?;
+        if template.uses("{kind}") {
+            return Err(GeminiError::Config(format!("{}: captures hold raw frames, {{kind}} does not apply", template)));
+        }
+        let mut writers = HashMap::new();
+        if template.is_static() {
+            writers.insert(path.to_path_buf(), CaptureWriter::create(path, options.clone()).await?);
+        }
+        Ok(Self {
+            template,
+            options,
+            writers,
+            period: None,
+        })
+    }
+
+    pub async fn write(&mut self, frame: &CapturedFrame) -> Result<(), GeminiError> {
+        let period = self.template.period(frame.received_ms);
+        if period > self.period {
+            for (_, writer) in self.writers.drain() {
+                writer.finish().await?;
+            }
+            self.period = period;
+        }
+        let path = self.template.render(&frame.symbol, "", frame.received_ms);
+        let writer = match self.writers.get_mut(&path) {
+            Some(writer) => writer,
+            None => {
+                let writer = CaptureWriter::create(&path, self.options.clone()).await?;
+                info!(path = %path.display(), "recording");
+                self.writers.entry(path).or_insert(writer)
+            },
+        };
+        writer.write(frame).await
+    }
+
+    // Must be called for compressed captures to be readable to the end
+    pub async fn finish(self) -> Result<(), GeminiError> {
+        for (_, writer) in self.writers {
+            writer.finish().await?;
+        }
+        Ok(())
+    }
+}
+
+fn period(now: SystemTime, every: Duration) -> u64 {
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+    since_epoch / (every.as_millis() as u64).max(1)
+}
+
+type CaptureSource = Box<dyn AsyncRead + Send + Unpin>;
+
+enum Records {
+    Json(Lines<BufReader<CaptureSource>>),
+    Bincode(BufReader<CaptureSource>),
+}
+
+pub struct CaptureReader {
+    records: Records,
+    speed: f64,
+    last_received_ms: Option<u64>,
+}
+
+impl CaptureReader {
+    // A speed of 0 replays as fast as possible. Files ending in .gz or .zst are
+    // decompressed on the fly, binary captures are told apart by their header.
+    pub async fn open(path: &Path, speed: f64) -> Result<Self, GeminiError> {
+        let file = BufReader::new(File::open(path).await?);
+        let source: CaptureSource = match Compression::of_path(path) {
+            Compression::None => Box::new(file),
+            Compression::Gzip => {
+                let mut decoder = GzipDecoder::new(file);
+                decoder.multiple_members(true);
+                Box::new(decoder)
+            },
+            Compression::Zstd => Box::new(ZstdDecoder::new(file)),
+        };
+        let mut source = BufReader::new(source);
+        let records = match source.fill_buf().await?.first() {
+            Some(&first) if first == wire::MAGIC[0] => {
+                let mut header = [0; wire::HEADER_LEN];
+                source.read_exact(&mut header).await?;
+                wire::check_header(&header, wire::MIN_CAPTURE_VERSION)?;
+                Records::Bincode(source)
+            },
+            _ => Records::Json(source.lines()),
+        };
+        Ok(Self {
+            records,
+            speed,
+            last_received_ms: None,
+        })
+    }
+
+    // Returns the next frame after sleeping for the recorded inter-arrival gap
+    pub async fn next(&mut self) -> Result<Option<CapturedFrame>, GeminiError> {
+        let frame = match &mut self.records {
+            Records::Json(lines) => {
+                let line = loop {
+                    match lines.next_line().await? {
+                        Some(line) if line.trim().is_empty() => continue,
+                        Some(line) => break line,
+                        None => return Ok(None),
+                    }
+                };
+                serde_json::from_str(&line)
+                    .map_err(|e| GeminiError::Protocol(format!("bad capture line: {}", e)))?
+            },
+            Records::Bincode(source) => {
+                let mut len = [0; 4];
+                match source.read_exact(&mut len).await {
+                    Ok(_) => {},
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(e) => return Err(e.into()),
+                }
+                let mut record = vec![0; u32::from_le_bytes(len) as usize];
+                source.read_exact(&mut record).await?;
+                wire::decode::<CapturedFrame>(&record)?
+            },
+        };
+
+        if let Some(last) = self.last_received_ms {
+            if self.speed > 0. && frame.received_ms > last {
+                let gap = (frame.received_ms - last) as f64 / self.speed;
+                tokio::time::sleep(Duration::from_secs_f64(gap / 1000.)).await;
+            }
+        }
+        self.last_received_ms = Some(frame.received_ms);
+        Ok(Some(frame))
+    }
+}
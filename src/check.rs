@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::client::{self, Endpoint, FeedOptions};
+use crate::error::GeminiError;
+use crate::exchange::{self, Exchange};
+use crate::rest::RestClient;
+use crate::symbols;
+
+// The outcome of checking one symbol, serialized as one JSON line
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckResult {
+    pub symbol: String,
+    pub exchange: Exchange,
+    pub ok: bool,
+    // None when the symbol list could not be fetched or the venue has none
+    pub symbol_known: Option<bool>,
+    // From starting the connection until the handshake and any subscribe messages are done
+    pub handshake_ms: Option<u64>,
+    // From the end of the handshake until the first message arrived
+    pub first_message_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    fn new(symbol: &str, exchange: Exchange) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            exchange,
+            ok: false,
+            symbol_known: None,
+            handshake_ms: None,
+            first_message_ms: None,
+            error: None,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // Serializing a struct of strings, numbers and bools cannot fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+// Checks that every symbol is listed, connects to it and waits up to `timeout` for its
+// first message. Failures end up in the results rather than an error, so every symbol
+// gets one.
+pub async fn run(
+    default: Exchange,
+    endpoint: &Endpoint,
+    symbols: &[String],
+    feed: &FeedOptions,
+    timeout: Duration,
+) -> Vec<CheckResult> {
+    let known = match symbols.iter().any(|s| exchange::route(default, s).0 == Exchange::Gemini) {
+        true => match RestClient::new(endpoint) {
+            Ok(rest) => rest.symbols().await.ok(),
+            // A client that cannot be built fails every symbol the same way
+            Err(e) => return symbols.iter().map(|symbol| {
+                let mut result = CheckResult::new(symbol, exchange::route(default, symbol).0);
+                result.error = Some(e.to_string());
+                result
+            }).collect(),
+        },
+        false => None,
+    };
+    let mut results = Vec::new();
+    for symbol in symbols {
+        let (exchange, pair) = exchange::route(default, symbol);
+        let mut result = CheckResult::new(symbol, exchange);
+        if let (Exchange::Gemini, Some(known)) = (exchange, &known) {
+            let listed = symbols::validate(&[pair.to_string()], known);
+            result.symbol_known = Some(listed.is_ok());
+            if let Err(e) = listed {
+                result.error = Some(e.to_string());
+                results.push(result);
+                continue;
+            }
+        }
+        match tokio::time::timeout(timeout, probe(exchange, endpoint, pair, feed, &mut result)).await {
+            Ok(Ok(())) => result.ok = true,
+            Ok(Err(e)) => result.error = Some(e.to_string()),
+            Err(_) => result.error = Some(format!("nothing received within {}", humantime::format_duration(timeout))),
+        }
+        results.push(result);
+    }
+    results
+}
+
+async fn probe(
+    exchange: Exchange,
+    endpoint: &Endpoint,
+    pair: &str,
+    feed: &FeedOptions,
+    result: &mut CheckResult,
+) -> Result<(), GeminiError> {
+    let started = Instant::now();
+    let mut ws_stream = client::open(exchange.feed(endpoint).as_ref(), endpoint, pair, feed).await?;
+    let connected = Instant::now();
+    result.handshake_ms = Some(connected.duration_since(started).as_millis() as u64);
+    while let Some(message) = ws_stream.next().await {
+        match message? {
+            Message::Text(_) | Message::Binary(_) => {
+                result.first_message_ms = Some(connected.elapsed().as_millis() as u64);
+                let _ = ws_stream.close(None).await;
+                return Ok(());
+            },
+            Message::Close(frame) => {
+                let reason = frame.map(|f| f.reason.to_string()).unwrap_or_default();
+                return Err(GeminiError::Protocol(format!("closed before the first message {}", reason).trim_end().to_string()));
+            },
+            _ => {},
+        }
+    }
+    Err(GeminiError::Protocol(String::from("closed before the first message")))
+}
@@ -0,0 +1,713 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures_util::stream::{self, Stream};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::Message;
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+use tokio_tungstenite::{client_async_tls_with_config, connect_async_tls_with_config};
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+use tokio_tungstenite::{client_async_with_config, connect_async_with_config};
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::book::OrderBook;
+use crate::close::Close;
+use crate::error::GeminiError;
+use crate::exchange::{self, Exchange, ExchangeFeed, Normalizer};
+use crate::failover::Endpoints;
+use crate::history::{History, Timed};
+use crate::metrics;
+use crate::models::{BestBidOffer, Event, MarketMessage, Trade};
+use crate::proxy;
+use crate::queue::QueueSender;
+use crate::tls::TlsOptions;
+
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// A raw frame tagged with the symbol it arrived for and its local receive time
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub symbol: String,
+    pub data: Vec<u8>,
+    pub received_ms: u64,
+    pub received_ns: u64,
+}
+
+// A frame with its message, parsed on a shard worker. Errors are not `Clone`, so a
+// frame that does not parse carries the error as text.
+#[derive(Debug, Clone)]
+pub struct ParsedFrame {
+    pub frame: Frame,
+    pub message: Result<MarketMessage, String>,
+}
+
+// What the reader tasks hand to the consumer
+#[derive(Debug, Clone)]
+pub enum FeedEvent {
+    Frame(Frame),
+    Parsed(ParsedFrame),
+    // The connection for `symbol` dropped, a reconnect may follow
+    Disconnected { symbol: String, reason: String },
+    // A ping on the connection for `symbol` came back after this long
+    RoundTrip { symbol: String, rtt_ms: u64 },
+}
+
+pub const PRODUCTION_HOST: &str = "api.gemini.com";
+pub const SANDBOX_HOST: &str = "api.sandbox.gemini.com";
+
+// How often a live connection is pinged to measure its round trip
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+// Where to reach the exchange: the WebSocket base for market data and the REST base
+// for everything else, optionally through an egress proxy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub ws_base: Url,
+    pub rest_base: Url,
+    pub proxy: Option<Url>,
+    pub tls: TlsOptions,
+}
+
+impl Endpoint {
+    fn for_host(host: &str) -> Self {
+        Self {
+            ws_base: Url::parse(&format!("wss://{}/", host)).expect("static url"),
+            rest_base: Url::parse(&format!("https://{}/", host)).expect("static url"),
+            proxy: None,
+            tls: TlsOptions::default(),
+        }
+    }
+
+    pub fn production() -> Self {
+        Self::for_host(PRODUCTION_HOST)
+    }
+
+    pub fn sandbox() -> Self {
+        Self::for_host(SANDBOX_HOST)
+    }
+
+    // A custom ws:// or wss:// base, the REST base is the same host over http(s)
+    pub fn custom(ws_base: &str) -> Result<Self, GeminiError> {
+        let mut ws_base = Url::parse(ws_base)?;
+        let rest_scheme = match ws_base.scheme() {
+            "wss" => "https",
+            "ws" => "http",
+            other => return Err(GeminiError::Config(format!("endpoint scheme must be ws or wss, got {}", other))),
+        };
+        if !ws_base.path().ends_with('/') {
+            let path = format!("{}/", ws_base.path());
+            ws_base.set_path(&path);
+        }
+        let mut rest_base = ws_base.clone();
+        // Switching between the special schemes ws/wss and http/https is always allowed
+        let _ = rest_base.set_scheme(rest_scheme);
+        Ok(Self {
+            ws_base,
+            rest_base,
+            proxy: None,
+            tls: TlsOptions::default(),
+        })
+    }
+
+    // See `proxy::parse` for the schemes understood
+    pub fn with_proxy(mut self, proxy: Option<Url>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    pub fn with_tls(mut self, tls: TlsOptions) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    pub fn market_data_url(&self, symbol: &str, feed: &FeedOptions) -> Result<Url, GeminiError> {
+        let mut url = self.ws_base.join(&format!("v1/marketdata/{}", symbol))?;
+        url.set_query(Some(&feed.query()));
+        Ok(url)
+    }
+}
+
+impl Default for Endpoint {
+    fn default() -> Self {
+        Self::production()
+    }
+}
+
+// Shape of the v1 market data feed, mapped onto its query parameters. Full depth with
+// trades by default, which the depth, dwell and book check analytics need; top of book
+// is the lighter feed of best bid and offer changes.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeedOptions {
+    pub top_of_book: bool,
+    pub bids: bool,
+    pub offers: bool,
+    pub trades: bool,
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        Self {
+            top_of_book: false,
+            bids: true,
+            offers: true,
+            trades: true,
+        }
+    }
+}
+
+impl FeedOptions {
+    pub fn query(&self) -> String {
+        format!(
+            "top_of_book={}&bids={}&offers={}&trades={}",
+            self.top_of_book, self.bids, self.offers, self.trades,
+        )
+    }
+}
+
+pub async fn connect(endpoint: &Endpoint, symbol: &str, feed: &FeedOptions) -> Result<WsStream, GeminiError> {
+    let url = endpoint.market_data_url(symbol, feed)?;
+    connect_url(endpoint, url).await
+}
+
+// Opens `url` with the endpoint's proxy and TLS settings
+pub async fn connect_url(endpoint: &Endpoint, url: Url) -> Result<WsStream, GeminiError> {
+    connect_request(endpoint, url.as_str().into_client_request()?).await
+}
+
+// Like `connect_url` for a handshake request carrying headers of its own
+pub async fn connect_request(endpoint: &Endpoint, request: Request) -> Result<WsStream, GeminiError> {
+    let connector = endpoint.tls.connector()?;
+    let Some(proxy) = &endpoint.proxy else {
+        return handshake(request, None, connector).await;
+    };
+    let host = request.uri().host().unwrap_or_default().to_string();
+    let port = request.uri().port_u16().unwrap_or(match request.uri().scheme_str() {
+        Some("ws") => 80,
+        _ => 443,
+    });
+    let stream = proxy::tunnel(proxy, &host, port).await?;
+    handshake(request, Some(stream), connector).await
+}
+
+// Over `stream` when a proxy tunnels the connection, straight to the host otherwise
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+async fn handshake(request: Request, stream: Option<TcpStream>, connector: Option<Connector>) -> Result<WsStream, GeminiError> {
+    let (ws_stream, _) = match stream {
+        Some(stream) => client_async_tls_with_config(request, stream, None, connector).await?,
+        None => connect_async_tls_with_config(request, None, false, connector).await?,
+    };
+    Ok(ws_stream)
+}
+
+// Without a TLS backend only ws:// endpoints can be reached
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+async fn handshake(request: Request, stream: Option<TcpStream>, _connector: Option<Connector>) -> Result<WsStream, GeminiError> {
+    if request.uri().scheme_str() != Some("ws") {
+        return Err(GeminiError::Config(format!("{}: wss:// needs a build with the native-tls or rustls feature", request.uri())));
+    }
+    let (ws_stream, _) = match stream {
+        Some(stream) => client_async_with_config(request, MaybeTlsStream::Plain(stream), None).await?,
+        None => connect_async_with_config(request, None, false).await?,
+    };
+    Ok(ws_stream)
+}
+
+// Connects to `exchange` for `symbol` and sends whatever subscribes to its data
+pub async fn open(exchange: &dyn ExchangeFeed, endpoint: &Endpoint, symbol: &str, feed: &FeedOptions) -> Result<WsStream, GeminiError> {
+    let mut ws_stream = connect_url(endpoint, exchange.url(symbol, feed)?).await?;
+    for message in exchange.subscribe(symbol, feed) {
+        ws_stream.send(Message::Text(message)).await?;
+    }
+    Ok(ws_stream)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectPolicy {
+    pub enabled: bool,
+    #[serde(with = "humantime_serde")]
+    pub initial_delay: Duration,
+    #[serde(with = "humantime_serde")]
+    pub max_delay: Duration,
+    // Consecutive failed attempts before giving up, unlimited when unset
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+
+    // Exponential backoff, doubling from `initial_delay` up to `max_delay`
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.min(16));
+        self.initial_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    // Gemini connections fail over along these, the other venues have a single feed
+    pub endpoints: Endpoints,
+    pub feed: FeedOptions,
+    pub reconnect: ReconnectPolicy,
+    // Venue of symbols without an `exchange:` prefix
+    pub exchange: Exchange,
+}
+
+impl ConnectOptions {
+    // The endpoint for a connection to `exchange`, with its index among `endpoints` when
+    // it takes part in failover
+    fn select(&self, exchange: Exchange) -> (Option<usize>, Endpoint) {
+        match exchange {
+            Exchange::Gemini => {
+                let (index, endpoint) = self.endpoints.select();
+                (Some(index), endpoint)
+            },
+            _ => (None, self.endpoints.primary().clone()),
+        }
+    }
+}
+
+enum SessionEnd {
+    // Shutdown was requested or the consumer went away
+    Finished,
+    // The server hung up without an error
+    Closed(Close),
+}
+
+// Streams frames for one symbol into `tx` until `shutdown` fires, reconnecting per `policy`.
+// A close frame is sent on shutdown. Everything logged meanwhile is inside a `connection` span.
+#[tracing::instrument(name = "connection", skip_all, fields(symbol = %symbol))]
+pub async fn stream_frames(
+    symbol: String,
+    tx: QueueSender<FeedEvent>,
+    shutdown: CancellationToken,
+    options: ConnectOptions,
+) -> Result<(), GeminiError> {
+    let policy = &options.reconnect;
+    let mut attempt = 0;
+    loop {
+        let mut received = false;
+        let (error, close) = match session(&options, &symbol, &tx, &shutdown, &mut received).await {
+            Ok(SessionEnd::Finished) => return Ok(()),
+            Ok(SessionEnd::Closed(close)) => {
+                metrics::global().closes.with_label_values(&[&symbol, close.cause.as_str()]).inc();
+                (GeminiError::Protocol(format!("{} connection closed by server: {}", symbol, close)), Some(close))
+            },
+            Err(e) => (e, None),
+        };
+        let disconnected = FeedEvent::Disconnected {
+            symbol: symbol.clone(),
+            reason: error.to_string(),
+        };
+        if tx.send(disconnected).await.is_err() {
+            return Ok(());
+        }
+        if !policy.enabled {
+            return Err(error);
+        }
+        // Such as a wss:// endpoint in a build without TLS, the next attempt would fail the same way
+        if let GeminiError::Config(_) = &error {
+            error!(error = %error, "not reconnecting, the configuration has to change");
+            return Err(error);
+        }
+        if close.as_ref().is_some_and(|close| !close.retries()) {
+            error!(error = %error, "not reconnecting, the server would refuse again");
+            return Err(error);
+        }
+        // A session that delivered data resets the backoff
+        if received {
+            attempt = 0;
+        }
+        // Closes the server asks to come back after neither count nor give up
+        let failed = close.as_ref().is_none_or(|close| close.is_failure());
+        if failed && policy.max_attempts.is_some_and(|max| attempt >= max) {
+            return Err(error);
+        }
+        let delay = match &close {
+            Some(close) => close.delay(policy, attempt),
+            None => policy.delay(attempt),
+        };
+        warn!(error = %error, delay = ?delay, attempt = attempt + 1, "reconnecting");
+        if failed {
+            attempt += 1;
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            _ = tokio::time::sleep(delay) => {},
+        }
+        metrics::global().reconnects.with_label_values(&[&symbol]).inc();
+    }
+}
+
+async fn session(
+    options: &ConnectOptions,
+    symbol: &str,
+    tx: &QueueSender<FeedEvent>,
+    shutdown: &CancellationToken,
+    received: &mut bool,
+) -> Result<SessionEnd, GeminiError> {
+    let (exchange, pair) = exchange::route(options.exchange, symbol);
+    let (index, endpoint) = options.select(exchange);
+    let exchange = exchange.feed(&endpoint);
+    let ws_stream = tokio::select! {
+        _ = shutdown.cancelled() => return Ok(SessionEnd::Finished),
+        ws_stream = open(exchange.as_ref(), &endpoint, pair, &options.feed) => ws_stream,
+    };
+    let ws_stream = match (ws_stream, index) {
+        (Ok(ws_stream), _) => ws_stream,
+        (Err(e), Some(index)) => {
+            options.endpoints.failed(index);
+            return Err(e);
+        },
+        (Err(e), None) => return Err(e),
+    };
+    info!(exchange = exchange.name(), endpoint = %endpoint.ws_base, "connected");
+    let mut normalizer = exchange.normalizer(pair);
+    let Some(index) = index else {
+        return read_frames(ws_stream, normalizer.as_mut(), symbol, tx, shutdown, received, None).await;
+    };
+    options.endpoints.activate(symbol, index);
+    let result = read_frames(ws_stream, normalizer.as_mut(), symbol, tx, shutdown, received, Some((&options.endpoints, index))).await;
+    // Hung up or broke before delivering anything
+    if !*received && !matches!(result, Ok(SessionEnd::Finished)) {
+        options.endpoints.failed(index);
+    }
+    result
+}
+
+// Reads the connection until it ends. The first data to arrive counts for the endpoint
+// it came through, when that takes part in failover.
+async fn read_frames(
+    ws_stream: WsStream,
+    normalizer: &mut dyn Normalizer,
+    symbol: &str,
+    tx: &QueueSender<FeedEvent>,
+    shutdown: &CancellationToken,
+    received: &mut bool,
+    endpoint: Option<(&Endpoints, usize)>,
+) -> Result<SessionEnd, GeminiError> {
+    let (mut write, mut read) = ws_stream.split();
+    let mut ping_timer = tokio::time::interval(PING_INTERVAL);
+    loop {
+        let message = tokio::select! {
+            _ = shutdown.cancelled() => {
+                write.send(Message::Close(None)).await?;
+                return Ok(SessionEnd::Finished);
+            },
+            _ = ping_timer.tick() => {
+                write.send(Message::Ping(now_ms().to_be_bytes().to_vec())).await?;
+                continue;
+            },
+            message = read.next() => match message {
+                Some(message) => message?,
+                None => return Ok(SessionEnd::Closed(Close::abnormal())),
+            },
+        };
+        let received_ns = now_ns();
+        let received_ms = received_ns / 1_000_000;
+        if let Message::Close(frame) = &message {
+            return Ok(SessionEnd::Closed(Close::from_frame(frame.as_ref())));
+        }
+        // Our pings carry the time they were sent
+        if let Message::Pong(payload) = &message {
+            if let Ok(sent) = <[u8; 8]>::try_from(payload.as_slice()) {
+                let rtt_ms = received_ms.saturating_sub(u64::from_be_bytes(sent));
+                if tx.send(FeedEvent::RoundTrip { symbol: symbol.to_string(), rtt_ms }).await.is_err() {
+                    return Ok(SessionEnd::Finished);
+                }
+            }
+            continue;
+        }
+        // The server's pings are answered by tungstenite, neither they nor raw frames are market data
+        if let Message::Ping(_) | Message::Frame(_) = &message {
+            continue;
+        }
+        if message.is_empty() {
+            continue;
+        }
+        if let (false, Some((endpoints, index))) = (*received, endpoint) {
+            endpoints.succeeded(index);
+        }
+        *received = true;
+        let Some(data) = normalizer.normalize(message.into_data(), received_ms)? else {
+            continue;
+        };
+        let frame = Frame {
+            symbol: symbol.to_string(),
+            data,
+            received_ms,
+            received_ns,
+        };
+        if tx.send(FeedEvent::Frame(frame)).await.is_err() {
+            return Ok(SessionEnd::Finished);
+        }
+    }
+}
+
+// Market data for one symbol as a plain `Stream` of parsed messages, for applications
+// that drive their own runtime instead of the binary's main loop
+#[derive(Debug, Clone)]
+pub struct Client {
+    symbol: String,
+    options: ConnectOptions,
+    // Shared with the streams, which record into it
+    history: Option<History>,
+}
+
+impl Client {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            options: ConnectOptions::default(),
+            history: None,
+        }
+    }
+
+    pub fn options(mut self, options: ConnectOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.options.endpoints = endpoint.into();
+        self
+    }
+
+    // The primary endpoint first, connections fail over along the rest in order
+    pub fn endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.options.endpoints = endpoints;
+        self
+    }
+
+    pub fn feed(mut self, feed: FeedOptions) -> Self {
+        self.options.feed = feed;
+        self
+    }
+
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.options.reconnect = policy;
+        self
+    }
+
+    // Keep the trades and BBO changes of the last `window` seen by the streams, see
+    // `recent_trades` and `bbo_history`
+    pub fn with_history(mut self, window: Duration) -> Self {
+        self.history = Some(History::new(window));
+        self
+    }
+
+    // Trades of the history window, oldest first, empty without `with_history`
+    pub fn recent_trades(&self) -> Vec<Timed<Trade>> {
+        self.history.as_ref().map(|h| h.recent_trades(&self.symbol)).unwrap_or_default()
+    }
+
+    // BBO changes of the history window, oldest first, empty without `with_history`
+    pub fn bbo_history(&self) -> Vec<Timed<BestBidOffer>> {
+        self.history.as_ref().map(|h| h.bbo_history(&self.symbol)).unwrap_or_default()
+    }
+
+    // Connects lazily on first poll and reconnects per the policy. Malformed messages are
+    // yielded as errors without ending the stream; the stream ends after a connection
+    // error the policy does not retry.
+    pub fn stream(&self) -> impl Stream<Item = Result<MarketMessage, GeminiError>> + Send + 'static {
+        let state = StreamState {
+            client: self.clone(),
+            ws: None,
+            normalizer: None,
+            attempt: 0,
+            done: false,
+            endpoint: None,
+            book: OrderBook::new(),
+            last_bbo: None,
+        };
+        stream::unfold(state, |mut state| async move {
+            let item = state.next().await?;
+            Some((item, state))
+        })
+    }
+}
+
+struct StreamState {
+    client: Client,
+    ws: Option<WsStream>,
+    // Translates the frames of the current connection
+    normalizer: Option<Box<dyn Normalizer>>,
+    attempt: u32,
+    done: bool,
+    // Index of the endpoint the current connection goes through until it delivers data
+    endpoint: Option<usize>,
+    // Only maintained for the history
+    book: OrderBook,
+    last_bbo: Option<BestBidOffer>,
+}
+
+impl StreamState {
+    async fn next(&mut self) -> Option<Result<MarketMessage, GeminiError>> {
+        loop {
+            if self.done {
+                return None;
+            }
+            if self.ws.is_none() {
+                let Client { symbol, options, .. } = &self.client;
+                let (exchange, pair) = exchange::route(options.exchange, symbol);
+                let (index, endpoint) = options.select(exchange);
+                let exchange = exchange.feed(&endpoint);
+                match open(exchange.as_ref(), &endpoint, pair, &options.feed).await {
+                    Ok(ws) => {
+                        self.ws = Some(ws);
+                        self.normalizer = Some(exchange.normalizer(pair));
+                        self.endpoint = index;
+                    },
+                    Err(e) => match self.fail(index, e).await {
+                        Some(e) => return Some(Err(e)),
+                        None => continue,
+                    },
+                }
+            }
+            let Some(ws) = self.ws.as_mut() else {
+                continue;
+            };
+            let symbol = &self.client.symbol;
+            let mut close = None;
+            let error = match ws.next().await {
+                Some(Ok(Message::Close(frame))) => {
+                    let closed = Close::from_frame(frame.as_ref());
+                    let error = GeminiError::Protocol(format!("{} connection closed by server: {}", symbol, closed));
+                    close = Some(closed);
+                    error
+                },
+                // Control frames and raw frames are not market data
+                Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => continue,
+                Some(Ok(message)) if message.is_empty() => continue,
+                Some(Ok(message)) => {
+                    self.attempt = 0;
+                    if let Some(index) = self.endpoint.take() {
+                        self.client.options.endpoints.succeeded(index);
+                    }
+                    let received_ns = now_ns();
+                    let normalized = match self.normalizer.as_mut() {
+                        Some(normalizer) => normalizer.normalize(message.into_data(), received_ns / 1_000_000),
+                        None => Ok(Some(message.into_data())),
+                    };
+                    match normalized {
+                        Ok(Some(data)) => {
+                            let mut parsed = MarketMessage::from_slice(&data);
+                            if let Ok(message) = &mut parsed {
+                                message.received_at_ns = Some(received_ns);
+                                self.record(message);
+                                message.emitted_at_ns = Some(now_ns());
+                            }
+                            return Some(parsed.map_err(GeminiError::from));
+                        },
+                        Ok(None) => continue,
+                        Err(e) => e,
+                    }
+                },
+                Some(Err(e)) => e.into(),
+                None => {
+                    close = Some(Close::abnormal());
+                    GeminiError::Protocol(format!("{} connection closed by server: {}", symbol, Close::abnormal()))
+                },
+            };
+            self.ws = None;
+            let endpoint = self.endpoint.take();
+            if let Some(close) = &close {
+                metrics::global().closes.with_label_values(&[symbol, close.cause.as_str()]).inc();
+            }
+            if let Some(index) = endpoint {
+                self.client.options.endpoints.failed(index);
+            }
+            if let Some(e) = self.retry(error, close).await {
+                return Some(Err(e));
+            }
+        }
+    }
+
+    fn record(&mut self, message: &MarketMessage) {
+        let Some(history) = &self.client.history else {
+            return;
+        };
+        let symbol = &self.client.symbol;
+        let ts = message.timestampms.unwrap_or_else(now_ms);
+        // The first message of a connection carries the whole book
+        let initial = !message.events.is_empty() && message.events.iter()
+            .all(|e| matches!(e, Event::Quote(q) if q.reason == "initial"));
+        if initial {
+            self.book.clear();
+        }
+        for event in &message.events {
+            match event {
+                Event::Trade(t) => history.record_trade(symbol, ts, t),
+                Event::Quote(q) if self.client.options.feed.top_of_book => self.book.replace_top(q),
+                Event::Quote(q) => self.book.apply(q),
+                _ => {},
+            }
+        }
+        let bbo = self.book.bbo();
+        if self.last_bbo.as_ref() != Some(&bbo) {
+            history.record_bbo(symbol, ts, &bbo);
+            self.last_bbo = Some(bbo);
+        }
+    }
+
+    // A connection through endpoint `index` failed before delivering anything
+    async fn fail(&mut self, index: Option<usize>, error: GeminiError) -> Option<GeminiError> {
+        if let Some(index) = index {
+            self.client.options.endpoints.failed(index);
+        }
+        self.retry(error, None).await
+    }
+
+    // Waits out the backoff and returns None, or hands back the error when giving up.
+    // `close` is how the server ended the connection, if it did.
+    async fn retry(&mut self, error: GeminiError, close: Option<Close>) -> Option<GeminiError> {
+        let policy = &self.client.options.reconnect;
+        let failed = close.as_ref().is_none_or(|close| close.is_failure());
+        let refused = close.as_ref().is_some_and(|close| !close.retries());
+        if !policy.enabled || refused || (failed && policy.max_attempts.is_some_and(|max| self.attempt >= max)) {
+            self.done = true;
+            return Some(error);
+        }
+        let delay = match &close {
+            Some(close) => close.delay(policy, self.attempt),
+            None => policy.delay(self.attempt),
+        };
+        tokio::time::sleep(delay).await;
+        if failed {
+            self.attempt += 1;
+        }
+        metrics::global().reconnects.with_label_values(&[&self.client.symbol]).inc();
+        None
+    }
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+pub fn now_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
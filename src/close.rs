@@ -0,0 +1,106 @@
+use std::fmt;
+use std::time::Duration;
+
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+use crate::client::ReconnectPolicy;
+
+// Why the server ended a connection, going by its close frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseCause {
+    // 1000 or 1001, the server rotated or shed the connection
+    Normal,
+    // 1012 service restart, 1013 try again later, or a reason that mentions maintenance
+    Maintenance,
+    // 1008, the server refuses what the client does, such as too many connections
+    PolicyViolation,
+    // 1011 and the codes for broken frames, on either side
+    Error,
+    // The connection ended without a close frame, or with a code of no known meaning
+    Abnormal,
+}
+
+impl CloseCause {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CloseCause::Normal => "normal",
+            CloseCause::Maintenance => "maintenance",
+            CloseCause::PolicyViolation => "policy_violation",
+            CloseCause::Error => "error",
+            CloseCause::Abnormal => "abnormal",
+        }
+    }
+}
+
+// How a connection ended on the server's side, with the code and reason it gave
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Close {
+    pub cause: CloseCause,
+    pub code: Option<u16>,
+    pub reason: String,
+}
+
+impl Close {
+    pub fn from_frame(frame: Option<&CloseFrame<'_>>) -> Self {
+        let Some(frame) = frame else {
+            return Self::abnormal();
+        };
+        let reason = frame.reason.to_string();
+        let cause = match frame.code {
+            _ if reason.to_lowercase().contains("maintenance") => CloseCause::Maintenance,
+            CloseCode::Restart | CloseCode::Again => CloseCause::Maintenance,
+            CloseCode::Normal | CloseCode::Away => CloseCause::Normal,
+            CloseCode::Policy => CloseCause::PolicyViolation,
+            CloseCode::Error | CloseCode::Protocol | CloseCode::Unsupported | CloseCode::Invalid
+                | CloseCode::Size | CloseCode::Extension => CloseCause::Error,
+            _ => CloseCause::Abnormal,
+        };
+        Self {
+            cause,
+            code: Some(frame.code.into()),
+            reason,
+        }
+    }
+
+    // The stream ended without a close frame
+    pub fn abnormal() -> Self {
+        Self {
+            cause: CloseCause::Abnormal,
+            code: None,
+            reason: String::new(),
+        }
+    }
+
+    // Whether to reconnect at all. A policy violation is repeated by every new connection.
+    pub fn retries(&self) -> bool {
+        self.cause != CloseCause::PolicyViolation
+    }
+
+    // Whether the reconnect counts against the policy's attempts and backoff. The server
+    // asked to come back after a normal close or maintenance, which is no failure.
+    pub fn is_failure(&self) -> bool {
+        matches!(self.cause, CloseCause::Error | CloseCause::Abnormal)
+    }
+
+    // The wait before the reconnect after `attempt` failed ones: the shortest after a
+    // normal close, the longest during maintenance
+    pub fn delay(&self, policy: &ReconnectPolicy, attempt: u32) -> Duration {
+        match self.cause {
+            CloseCause::Normal => policy.initial_delay,
+            CloseCause::Maintenance => policy.max_delay,
+            _ => policy.delay(attempt),
+        }
+    }
+}
+
+impl fmt::Display for Close {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.cause.as_str())?;
+        match (self.code, self.reason.is_empty()) {
+            (Some(code), true) => write!(f, " ({})", code),
+            (Some(code), false) => write!(f, " ({}: {})", code, self.reason),
+            (None, _) => write!(f, ", no close frame"),
+        }
+    }
+}
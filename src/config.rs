@@ -0,0 +1,281 @@
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+
+use crate::analytics;
+use crate::blotter::BlotterFormat;
+use crate::capture::Compression;
+use crate::client::{FeedOptions, ReconnectPolicy};
+use crate::error::GeminiError;
+use crate::exchange::Exchange;
+use crate::exchange_candles::CandleSource;
+use crate::logging::LogFormat;
+use crate::output::OutputFormat;
+use crate::queue::BackpressurePolicy;
+use crate::routing::RouteConfig;
+use crate::summary::ReportFormat;
+use crate::wire::WireFormat;
+
+// Contents of a `--config` TOML file. Every field is optional, command line flags win.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub symbols: Vec<String>,
+    pub sandbox: bool,
+    pub endpoint: Option<String>,
+    // As --failover takes them
+    pub failover: Vec<String>,
+    pub exchange: Option<Exchange>,
+    pub proxy: Option<String>,
+    pub ca_file: Option<PathBuf>,
+    pub insecure: bool,
+    pub output: Option<OutputFormat>,
+    // Time zone name as --tz takes it
+    pub tz: Option<String>,
+    // As --decimals takes them, e.g. ["price=2", "amount=8"]
+    pub decimals: Vec<String>,
+    pub thousands: bool,
+    // As --throttle and --sample take them, e.g. "10/s" and ["bbo:1s"]
+    pub throttle: Option<String>,
+    pub sample: Vec<String>,
+    #[serde(with = "humantime_serde")]
+    pub downsample: Option<Duration>,
+    // [[routes]] tables with a sink, its events and symbols, as --route takes them
+    pub routes: Vec<RouteConfig>,
+    pub output_file: Option<PathBuf>,
+    pub report_format: Option<ReportFormat>,
+    pub record: Option<PathBuf>,
+    pub capture_unknown: Option<PathBuf>,
+    #[serde(with = "humantime_serde")]
+    pub backfill: Option<Duration>,
+    pub gap_fill: bool,
+    #[serde(with = "humantime_serde")]
+    pub gap_fill_max: Option<Duration>,
+    pub state_file: Option<PathBuf>,
+    pub dump_dir: Option<PathBuf>,
+    // Rhai script with on_trade, on_quote and on_bbo callbacks, needs the scripting feature
+    pub script: Option<PathBuf>,
+    #[serde(with = "humantime_serde")]
+    pub rotate: Option<Duration>,
+    pub rotate_size: Option<String>,
+    pub compress: Option<Compression>,
+    pub record_format: Option<WireFormat>,
+    pub metrics_port: Option<u16>,
+    pub metrics_addr: Option<SocketAddr>,
+    pub status_port: Option<u16>,
+    pub status_bind: Option<IpAddr>,
+    #[serde(with = "humantime_serde")]
+    pub history: Option<Duration>,
+    pub history_max_entries: Option<usize>,
+    pub control_socket: Option<PathBuf>,
+    #[serde(with = "humantime_serde")]
+    pub stale_after: Option<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub check_book: Option<Duration>,
+    pub check_levels: Option<usize>,
+    pub resync: bool,
+    #[serde(with = "humantime_serde")]
+    pub funding_interval: Option<Duration>,
+    pub serve: Option<SocketAddr>,
+    pub serve_format: Option<WireFormat>,
+    pub grpc: Option<SocketAddr>,
+    #[serde(deserialize_with = "windows")]
+    pub vwap_windows: Vec<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub stats_interval: Option<Duration>,
+    // Indicators like "ema:20" or "bollinger:20:2" over the candles
+    pub technical: Vec<String>,
+    #[serde(deserialize_with = "windows")]
+    pub ofi_windows: Vec<Duration>,
+    #[serde(deserialize_with = "windows")]
+    pub volatility_windows: Vec<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub volatility_sample: Option<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub dwell_window: Option<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub flicker_threshold: Option<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub candles: Option<Duration>,
+    pub candle_source: Option<CandleSource>,
+    #[serde(with = "humantime_serde")]
+    pub latency_interval: Option<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub max_clock_skew: Option<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub balances: Option<Duration>,
+    pub blotter: Option<PathBuf>,
+    pub blotter_format: Option<BlotterFormat>,
+    pub positions: bool,
+    #[serde(with = "humantime_serde")]
+    pub duration: Option<Duration>,
+    #[serde(with = "humantime_serde")]
+    pub until: Option<SystemTime>,
+    #[serde(with = "humantime_serde")]
+    pub start_at: Option<SystemTime>,
+    pub indicators: bool,
+    pub imbalance_bands: Vec<Decimal>,
+    pub depth_levels: Option<u64>,
+    pub depth_sizes: Vec<Decimal>,
+    pub spread_thresholds: Vec<Decimal>,
+    pub min_trade_size: Option<Decimal>,
+    pub min_notional: Option<Decimal>,
+    pub log_level: Option<String>,
+    pub log_format: Option<LogFormat>,
+    pub daemon: bool,
+    pub pid_file: Option<PathBuf>,
+    // A lock file or redis:// URL, as --leader-lock takes it
+    pub leader_lock: Option<String>,
+    pub leader_key: Option<String>,
+    #[serde(with = "humantime_serde")]
+    pub leader_ttl: Option<Duration>,
+    pub backpressure: Option<BackpressurePolicy>,
+    pub queue_capacity: Option<usize>,
+    pub max_book_levels: Option<u64>,
+    pub workers: Option<u64>,
+    pub feed: FeedOptions,
+    pub reconnect: ReconnectPolicy,
+    pub sinks: SinksConfig,
+    pub alerts: AlertsConfig,
+    pub cross: CrossConfig,
+    pub anomaly: AnomalyConfig,
+    pub credentials: Credentials,
+    pub keyring: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SinksConfig {
+    pub sqlite: Option<PathBuf>,
+    pub kafka_brokers: Option<String>,
+    pub kafka_topic: Option<String>,
+    pub parquet: Option<PathBuf>,
+    pub postgres_url: Option<String>,
+    pub nats_url: Option<String>,
+    pub nats_subject_prefix: Option<String>,
+    pub nats_stream: Option<String>,
+    pub redis_url: Option<String>,
+    pub redis_prefix: Option<String>,
+    pub redis_stream_maxlen: Option<u64>,
+    pub zmq_bind: Option<String>,
+    pub zmq_format: Option<WireFormat>,
+    pub mqtt_url: Option<String>,
+    pub mqtt_topic_prefix: Option<String>,
+    pub mqtt_qos: Option<u8>,
+    pub fix: Option<String>,
+    pub fix_sender_comp_id: Option<String>,
+    pub fix_target_comp_id: Option<String>,
+    pub fix_heartbeat: Option<u64>,
+    pub influx_url: Option<String>,
+    pub influx_token: Option<String>,
+    pub influx_tags: Vec<String>,
+    pub influx_measurements: Vec<String>,
+    pub http_url: Option<String>,
+    pub http_batch_size: Option<u64>,
+    #[serde(with = "humantime_serde")]
+    pub http_batch_interval: Option<Duration>,
+    pub http_secret: Option<String>,
+    pub http_retries: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    pub rules: Vec<String>,
+    pub webhook: Option<String>,
+    pub command: Option<String>,
+    #[serde(with = "humantime_serde")]
+    pub cooldown: Option<Duration>,
+    pub notify_trades_above: Option<Decimal>,
+    // Default text of chat messages, see `AlertTemplate`
+    pub template: Option<String>,
+    pub slack: Option<WebhookChatConfig>,
+    pub discord: Option<WebhookChatConfig>,
+    pub telegram: Option<TelegramConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookChatConfig {
+    pub webhook: String,
+    pub template: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub chat_id: String,
+    pub template: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrossConfig {
+    pub rules: Vec<String>,
+    pub threshold_bps: Option<Decimal>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnomalyConfig {
+    pub trade_pct: Option<Decimal>,
+    pub spread_multiple: Option<Decimal>,
+    #[serde(with = "humantime_serde")]
+    pub window: Option<Duration>,
+    // As --stale-quotes takes it
+    #[serde(with = "humantime_serde")]
+    pub stale_quotes: Option<Duration>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Credentials {
+    pub api_key: Option<String>,
+    pub api_secret: Option<String>,
+}
+
+// Never print the secret, not even in debug output
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("api_key", &self.api_key)
+            .field("api_secret", &self.api_secret.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+pub fn load(path: &Path) -> Result<Config, GeminiError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| GeminiError::Config(format!("{}: {}", path.display(), e)))?;
+    toml::from_str(&text).map_err(|e| GeminiError::Config(format!("{}: {}", path.display(), e)))
+}
+
+// Sets `symbols` in the config file at `path`, creating it when missing. The rest of the
+// file, comments and layout included, stays as it is.
+pub fn save_symbols(path: &Path, symbols: &[String]) -> Result<(), GeminiError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(GeminiError::Config(format!("{}: {}", path.display(), e))),
+    };
+    let mut document = text.parse::<toml_edit::DocumentMut>()
+        .map_err(|e| GeminiError::Config(format!("{}: {}", path.display(), e)))?;
+    let mut value = toml_edit::Value::from(symbols.iter().collect::<toml_edit::Array>());
+    if let Some(old) = document.get("symbols").and_then(toml_edit::Item::as_value) {
+        *value.decor_mut() = old.decor().clone();
+    }
+    document["symbols"] = toml_edit::Item::Value(value);
+    std::fs::write(path, document.to_string()).map_err(|e| GeminiError::Config(format!("{}: {}", path.display(), e)))
+}
+
+fn windows<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Duration>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|w| analytics::parse_window(w).map_err(serde::de::Error::custom))
+        .collect()
+}
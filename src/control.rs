@@ -0,0 +1,254 @@
+use std::str::FromStr;
+
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::error::GeminiError;
+use crate::rest::RestClient;
+use crate::symbols;
+
+#[cfg(unix)]
+pub use self::unix::listen;
+
+pub const USAGE: &str = "commands: subscribe|sub SYMBOL, unsubscribe|unsub SYMBOL, depth LEVELS, snapshot SYMBOL, stats, dump, shutdown";
+
+// Commands for a running collector. Every line gets exactly one reply line:
+// `ok`, `error: ...` or a JSON document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlCommand {
+    Subscribe(String),
+    Unsubscribe(String),
+    Snapshot(String),
+    // Levels per side in snapshots, 0 for all of them
+    Depth(usize),
+    Stats,
+    // Writes the state of every symbol to a file, replies with its path
+    Dump,
+    Shutdown,
+}
+
+impl FromStr for ControlCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            ["subscribe" | "sub", symbol] => Ok(ControlCommand::Subscribe(symbol.to_lowercase())),
+            ["unsubscribe" | "unsub", symbol] => Ok(ControlCommand::Unsubscribe(symbol.to_lowercase())),
+            ["snapshot", symbol] => Ok(ControlCommand::Snapshot(symbol.to_lowercase())),
+            ["depth", levels] => levels.parse().map(ControlCommand::Depth).map_err(|_| format!("depth `{}` is not a number of levels", levels)),
+            ["stats"] => Ok(ControlCommand::Stats),
+            ["dump"] => Ok(ControlCommand::Dump),
+            ["shutdown"] => Ok(ControlCommand::Shutdown),
+            _ => Err(format!("unknown command `{}`, {}", s.trim(), USAGE)),
+        }
+    }
+}
+
+// A command on its way to the main loop, which owns the connections and the pipeline
+#[derive(Debug)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub reply: oneshot::Sender<String>,
+}
+
+// Hands `command` to the main loop and waits for its reply, None once the loop is gone
+async fn request(tx: &mpsc::Sender<ControlRequest>, command: ControlCommand) -> Option<String> {
+    let (reply, rx) = oneshot::channel();
+    tx.send(ControlRequest { command, reply }).await.ok()?;
+    rx.await.ok()
+}
+
+// Parses a command line, checking the symbol of a subscribe against the exchange
+async fn parse(line: &str, rest: &RestClient) -> Result<ControlCommand, GeminiError> {
+    match line.parse::<ControlCommand>() {
+        Ok(ControlCommand::Subscribe(symbol)) => check_symbol(rest, &symbol).await.map(|_| ControlCommand::Subscribe(symbol)),
+        Ok(command) => Ok(command),
+        Err(e) => Err(GeminiError::Config(e)),
+    }
+}
+
+// Same rule as at startup: a typo is refused, an unreachable symbol list is not
+async fn check_symbol(rest: &RestClient, symbol: &str) -> Result<(), GeminiError> {
+    match rest.symbols().await {
+        Ok(known) => symbols::validate(&[symbol.to_string()], &known),
+        Err(e) => {
+            warn!(error = %e, "could not fetch the symbol list, skipping validation");
+            Ok(())
+        },
+    }
+}
+
+fn error_reply(e: GeminiError) -> String {
+    match e {
+        GeminiError::Config(e) => format!("error: {}", e),
+        e => format!("error: {}", e),
+    }
+}
+
+// Gets every command line of a LocalControl with its reply
+pub type ReplyFn = Box<dyn FnMut(&str, String) + Send>;
+
+// Command lines from within the process, such as the keys of the dashboard
+pub struct LocalControl {
+    pub lines: mpsc::Receiver<String>,
+    pub reply: ReplyFn,
+}
+
+// Answers the lines of `local` until shutdown or until its senders are gone
+pub async fn serve_local(mut local: LocalControl, tx: mpsc::Sender<ControlRequest>, rest: RestClient, shutdown: CancellationToken) {
+    loop {
+        let line = tokio::select! {
+            _ = shutdown.cancelled() => return,
+            line = local.lines.recv() => match line {
+                Some(line) => line,
+                None => return,
+            },
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match parse(&line, &rest).await {
+            Ok(command) => {
+                info!(command = line.trim(), "control command");
+                match request(&tx, command).await {
+                    Some(reply) => reply,
+                    None => return,
+                }
+            },
+            Err(e) => error_reply(e),
+        };
+        (local.reply)(&line, reply);
+    }
+}
+
+// Takes commands typed into the terminal, replies go to stdout. Stdin is read on a
+// plain thread, a blocking read there does not hold up the runtime at exit.
+pub fn read_stdin(
+    tx: mpsc::Sender<ControlRequest>,
+    rest: RestClient,
+    shutdown: CancellationToken,
+) -> impl std::future::Future<Output = ()> {
+    let (lines_tx, lines) = mpsc::channel::<String>(16);
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    warn!(error = %e, "control: stdin failed");
+                    return;
+                },
+            };
+            if lines_tx.blocking_send(line).is_err() {
+                return;
+            }
+        }
+    });
+    let local = LocalControl {
+        lines,
+        reply: Box::new(|_, reply| println!("{}", reply)),
+    };
+    serve_local(local, tx, rest, shutdown)
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::Permissions;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::sync::mpsc;
+    use tokio_util::sync::CancellationToken;
+    use tracing::{info, warn};
+
+    use super::{error_reply, parse, request, ControlCommand, ControlRequest};
+    use crate::error::GeminiError;
+    use crate::rest::RestClient;
+
+    // Binds the socket at `path` right away so a clash fails at startup. A socket file
+    // left behind by a crashed run is replaced, one that still answers is an error.
+    // The socket is only accessible to the current user and removed on shutdown.
+    pub async fn listen(
+        path: &Path,
+        tx: mpsc::Sender<ControlRequest>,
+        rest: RestClient,
+        shutdown: CancellationToken,
+    ) -> Result<impl std::future::Future<Output = ()>, GeminiError> {
+        if path.exists() {
+            if UnixStream::connect(path).await.is_ok() {
+                return Err(GeminiError::Config(format!("control socket {} is in use", path.display())));
+            }
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, Permissions::from_mode(0o600))?;
+        info!(path = %path.display(), "accepting control commands");
+        let path = path.to_path_buf();
+        Ok(async move {
+            accept_loop(listener, tx, rest, shutdown).await;
+            let _ = std::fs::remove_file(&path);
+        })
+    }
+
+    async fn accept_loop(listener: UnixListener, tx: mpsc::Sender<ControlRequest>, rest: RestClient, shutdown: CancellationToken) {
+        loop {
+            let stream = tokio::select! {
+                _ = shutdown.cancelled() => return,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!(error = %e, "control: accept failed");
+                        continue;
+                    },
+                },
+            };
+            let (tx, rest, shutdown) = (tx.clone(), rest.clone(), shutdown.clone());
+            tokio::spawn(async move {
+                if let Err(e) = client(stream, tx, rest, shutdown).await {
+                    info!(error = %e, "control: client dropped");
+                }
+            });
+        }
+    }
+
+    async fn client(stream: UnixStream, tx: mpsc::Sender<ControlRequest>, rest: RestClient, shutdown: CancellationToken) -> Result<(), GeminiError> {
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        loop {
+            let line = tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                line = lines.next_line() => match line? {
+                    Some(line) => line,
+                    None => return Ok(()),
+                },
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let command = parse(&line, &rest).await;
+            // The process may exit right after a shutdown, so that reply goes out first
+            if let Ok(ControlCommand::Shutdown) = command {
+                info!(command = line.trim(), "control command");
+                write.write_all(b"ok\n").await?;
+                write.flush().await?;
+                request(&tx, ControlCommand::Shutdown).await;
+                return Ok(());
+            }
+            let reply = match command {
+                Ok(command) => {
+                    info!(command = line.trim(), "control command");
+                    match request(&tx, command).await {
+                        Some(reply) => reply,
+                        None => return Ok(()),
+                    }
+                },
+                Err(e) => error_reply(e),
+            };
+            write.write_all(format!("{}\n", reply).as_bytes()).await?;
+            write.flush().await?;
+        }
+    }
+}
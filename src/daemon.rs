@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use crate::error::GeminiError;
+
+// The process id in a file while running, removed again on drop. A file left behind by a
+// process that is gone is taken over, one naming a live process is an error.
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn create(path: &Path) -> Result<Self, GeminiError> {
+        if let Some(pid) = fs::read_to_string(path).ok().and_then(|s| s.trim().parse::<u32>().ok()) {
+            if pid != std::process::id() && is_running(pid) {
+                return Err(GeminiError::Config(format!("{}: already running as process {}", path.display(), pid)));
+            }
+        }
+        fs::write(path, format!("{}\n", std::process::id()))?;
+        Ok(Self { path: path.to_path_buf() })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!(path = %self.path.display(), error = %e, "could not remove the pid file");
+        }
+    }
+}
+
+// Without /proc there is no telling, so the process is assumed gone
+fn is_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+// Sends a state such as READY=1 to the service manager. Not running under systemd, or
+// not as a notify service, is not an error and nothing is sent.
+pub fn notify(state: &str) {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    match send(&socket.to_string_lossy(), state) {
+        Ok(()) => debug!(state, "notified the service manager"),
+        Err(e) => warn!(state, error = %e, "could not notify the service manager"),
+    }
+}
+
+#[cfg(unix)]
+fn send(socket: &str, state: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound()?;
+    // A leading @ names a socket in the abstract namespace
+    #[cfg(target_os = "linux")]
+    if let Some(name) = socket.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::SocketAddr;
+
+        let addr = SocketAddr::from_abstract_name(name)?;
+        return datagram.send_to_addr(state.as_bytes(), &addr).map(|_| ());
+    }
+    datagram.send_to(state.as_bytes(), socket).map(|_| ())
+}
+
+#[cfg(not(unix))]
+fn send(_socket: &str, _state: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "sd_notify needs a Unix system"))
+}
+
+// Half the watchdog timeout of the service, when it has one meant for this process
+pub fn watchdog_interval() -> Option<Duration> {
+    if let Some(pid) = std::env::var("WATCHDOG_PID").ok().and_then(|p| p.parse::<u32>().ok()) {
+        if pid != std::process::id() {
+            return None;
+        }
+    }
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec) / 2)
+}
+
+// Keeps the watchdog fed until `shutdown`. It runs on the runtime of the pipeline, so a
+// wedged runtime stops the pings and systemd restarts the service.
+pub async fn watchdog(interval: Duration, shutdown: CancellationToken) {
+    let mut timer = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = timer.tick() => notify("WATCHDOG=1"),
+        }
+    }
+}
+
+// SIGHUP, the signal systemd's `ExecReload=kill -HUP $MAINPID` sends. Never fires on
+// systems without it.
+pub struct Reloads {
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+}
+
+impl Reloads {
+    pub fn new() -> Result<Self, GeminiError> {
+        Ok(Self {
+            #[cfg(unix)]
+            hangup: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?,
+        })
+    }
+
+    pub async fn next(&mut self) {
+        #[cfg(unix)]
+        if self.hangup.recv().await.is_some() {
+            return;
+        }
+        std::future::pending::<()>().await
+    }
+}
+
+// SIGUSR1, asking for a dump of the current state. Never fires on systems without it.
+pub struct DumpRequests {
+    #[cfg(unix)]
+    user1: tokio::signal::unix::Signal,
+}
+
+impl DumpRequests {
+    pub fn new() -> Result<Self, GeminiError> {
+        Ok(Self {
+            #[cfg(unix)]
+            user1: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined1())?,
+        })
+    }
+
+    pub async fn next(&mut self) {
+        #[cfg(unix)]
+        if self.user1.recv().await.is_some() {
+            return;
+        }
+        std::future::pending::<()>().await
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::{HashSet, VecDeque};
+
+// Event ids remembered per symbol, far more than a reconnect sends again
+const WINDOW: usize = 4096;
+
+// Where an event id falls relative to the ones seen before
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventOrder {
+    // Above every id so far, the normal case
+    Next,
+    // Seen already, the frame was delivered twice
+    Duplicate,
+    // Below the highest id so far without having been seen
+    OutOfOrder { last: u64 },
+}
+
+// Event ids count up across connections, so a frame whose id came through before is a
+// repeat, typically from a feed resending after a reconnect
+#[derive(Debug, Clone, Default)]
+pub struct EventIds {
+    recent: VecDeque<u64>,
+    seen: HashSet<u64>,
+    last: Option<u64>,
+}
+
+impl EventIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Classifies `event_id` and remembers it
+    pub fn check(&mut self, event_id: u64) -> EventOrder {
+        if self.seen.contains(&event_id) {
+            return EventOrder::Duplicate;
+        }
+        self.seen.insert(event_id);
+        self.recent.push_back(event_id);
+        if self.recent.len() > WINDOW {
+            if let Some(oldest) = self.recent.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        match self.last {
+            Some(last) if event_id < last => EventOrder::OutOfOrder { last },
+            _ => {
+                self.last = Some(event_id);
+                EventOrder::Next
+            },
+        }
+    }
+}
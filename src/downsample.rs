@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::analytics::anomaly::AnomalyEvent;
+use crate::analytics::candles::Candle;
+use crate::analytics::cross::CrossDivergence;
+use crate::analytics::depth::DepthSnapshot;
+use crate::analytics::dwell::DwellSnapshot;
+use crate::analytics::indicators::BookIndicators;
+use crate::analytics::ofi::OfiSnapshot;
+use crate::analytics::technical::TechnicalSnapshot;
+use crate::analytics::volatility::VolatilitySnapshot;
+use crate::analytics::vwap::TradeStatsSnapshot;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{AuctionEvent, BestBidOffer, BlockTrade, FundingAmount, Quote, Trade};
+use crate::output::EventContext;
+
+// Wraps a sink so it stores aggregates instead of ticks: the last BBO of every bucket of
+// `bucket` aligned to the epoch, once the bucket is over, and the candles and other
+// periodic snapshots as they come. Trades, quotes, block trades and the per update book
+// indicators and depth are left out. A bucket without BBO changes stores nothing.
+// Buckets run on exchange time, so replays downsample the same way.
+pub struct Downsampled {
+    inner: Box<dyn EventHandler>,
+    bucket_ms: u64,
+    // The latest BBO per symbol with the bucket it fell in
+    pending: HashMap<String, (u64, EventContext, BestBidOffer)>,
+}
+
+impl Downsampled {
+    pub fn new(inner: Box<dyn EventHandler>, bucket: Duration) -> Self {
+        Self {
+            inner,
+            bucket_ms: (bucket.as_millis() as u64).max(1),
+            pending: HashMap::new(),
+        }
+    }
+
+    fn bucket(&self, ctx: &EventContext) -> u64 {
+        ctx.timestampms.unwrap_or(ctx.received_ms) / self.bucket_ms
+    }
+
+    // Stores the held BBO of `symbol` once `bucket` is past it, or right away with none
+    fn release(&mut self, symbol: &str, bucket: Option<u64>) -> Result<(), GeminiError> {
+        let due = self.pending.get(symbol).is_some_and(|(pending, _, _)| bucket.is_none_or(|bucket| bucket > *pending));
+        if !due {
+            return Ok(());
+        }
+        if let Some((_, ctx, bbo)) = self.pending.remove(symbol) {
+            self.inner.on_book_update(symbol, &ctx, &bbo)?;
+        }
+        Ok(())
+    }
+
+    fn pass(&mut self, symbol: &str, ctx: &EventContext) -> Result<&mut dyn EventHandler, GeminiError> {
+        self.release(symbol, Some(self.bucket(ctx)))?;
+        Ok(self.inner.as_mut())
+    }
+
+    // A left out event still closes the bucket before it
+    fn skip(&mut self, symbol: &str, ctx: &EventContext) -> Result<(), GeminiError> {
+        self.release(symbol, Some(self.bucket(ctx)))
+    }
+}
+
+impl EventHandler for Downsampled {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, _trade: &Trade) -> Result<(), GeminiError> {
+        self.skip(symbol, ctx)
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, _quote: &Quote) -> Result<(), GeminiError> {
+        self.skip(symbol, ctx)
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        let bucket = self.bucket(ctx);
+        self.release(symbol, Some(bucket))?;
+        self.pending.insert(symbol.to_string(), (bucket, *ctx, bbo.clone()));
+        Ok(())
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, _trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.skip(symbol, ctx)
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_auction(symbol, ctx, auction)
+    }
+
+    fn on_stats(&mut self, symbol: &str, ctx: &EventContext, stats: &TradeStatsSnapshot) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_stats(symbol, ctx, stats)
+    }
+
+    fn on_candle(&mut self, symbol: &str, ctx: &EventContext, candle: &Candle) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_candle(symbol, ctx, candle)
+    }
+
+    fn on_technical(&mut self, symbol: &str, ctx: &EventContext, technical: &TechnicalSnapshot) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_technical(symbol, ctx, technical)
+    }
+
+    fn on_indicators(&mut self, symbol: &str, ctx: &EventContext, _indicators: &BookIndicators) -> Result<(), GeminiError> {
+        self.skip(symbol, ctx)
+    }
+
+    fn on_depth(&mut self, symbol: &str, ctx: &EventContext, _depth: &DepthSnapshot) -> Result<(), GeminiError> {
+        self.skip(symbol, ctx)
+    }
+
+    fn on_volatility(&mut self, symbol: &str, ctx: &EventContext, volatility: &VolatilitySnapshot) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_volatility(symbol, ctx, volatility)
+    }
+
+    fn on_dwell(&mut self, symbol: &str, ctx: &EventContext, dwell: &DwellSnapshot) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_dwell(symbol, ctx, dwell)
+    }
+
+    fn on_ofi(&mut self, symbol: &str, ctx: &EventContext, ofi: &OfiSnapshot) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_ofi(symbol, ctx, ofi)
+    }
+
+    fn on_anomaly(&mut self, symbol: &str, ctx: &EventContext, anomaly: &AnomalyEvent) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_anomaly(symbol, ctx, anomaly)
+    }
+
+    fn on_cross(&mut self, symbol: &str, ctx: &EventContext, cross: &CrossDivergence) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_cross(symbol, ctx, cross)
+    }
+
+    fn on_funding(&mut self, symbol: &str, ctx: &EventContext, funding: &FundingAmount) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_funding(symbol, ctx, funding)
+    }
+
+    // The last BBO before the connection dropped is stored
+    fn on_disconnect(&mut self, symbol: &str, reason: &str) -> Result<(), GeminiError> {
+        self.release(symbol, None)?;
+        self.inner.on_disconnect(symbol, reason)
+    }
+
+    // The last BBO of a symbol gone quiet is stored once its bucket is over
+    fn on_tick(&mut self, now_ms: u64) -> Result<(), GeminiError> {
+        let symbols: Vec<String> = self.pending.keys().cloned().collect();
+        for symbol in symbols {
+            self.release(&symbol, Some(now_ms / self.bucket_ms))?;
+        }
+        self.inner.on_tick(now_ms)
+    }
+
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        let symbols: Vec<String> = self.pending.keys().cloned().collect();
+        for symbol in symbols {
+            self.release(&symbol, None)?;
+        }
+        self.inner.flush()
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
@@ -0,0 +1,35 @@
+use thiserror::Error;
+use tokio_tungstenite::tungstenite;
+
+use crate::models::ParseError;
+
+#[derive(Error, Debug)]
+pub enum GeminiError {
+    #[error("connection error: {0}")]
+    Connection(#[source] Box<tungstenite::Error>),
+    #[error("invalid endpoint: {0}")]
+    Url(#[from] url::ParseError),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("protocol error: {0}")]
+    Protocol(String),
+    #[error("authentication error: {0}")]
+    Auth(String),
+    #[error("config error: {0}")]
+    Config(String),
+    #[error("api error: {0}")]
+    Api(String),
+    #[error("rest error: {0}")]
+    Rest(#[from] reqwest::Error),
+    #[error("sink error: {0}")]
+    Sink(String),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+// tungstenite's error is large, keep it boxed so every Result stays small
+impl From<tungstenite::Error> for GeminiError {
+    fn from(e: tungstenite::Error) -> Self {
+        GeminiError::Connection(Box::new(e))
+    }
+}
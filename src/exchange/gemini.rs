@@ -0,0 +1,44 @@
+use url::Url;
+
+use crate::client::{Endpoint, FeedOptions};
+use crate::error::GeminiError;
+use crate::exchange::{ExchangeFeed, Normalizer};
+
+// The v1 market data feed, one connection per symbol selected by its URL
+#[derive(Debug, Clone)]
+pub struct GeminiFeed {
+    endpoint: Endpoint,
+}
+
+impl GeminiFeed {
+    pub fn new(endpoint: Endpoint) -> Self {
+        Self { endpoint }
+    }
+}
+
+impl ExchangeFeed for GeminiFeed {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn url(&self, symbol: &str, feed: &FeedOptions) -> Result<Url, GeminiError> {
+        self.endpoint.market_data_url(symbol, feed)
+    }
+
+    fn subscribe(&self, _symbol: &str, _feed: &FeedOptions) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn normalizer(&self, _symbol: &str) -> Box<dyn Normalizer> {
+        Box::new(Passthrough)
+    }
+}
+
+// Frames already are in the v1 shape
+struct Passthrough;
+
+impl Normalizer for Passthrough {
+    fn normalize(&mut self, data: Vec<u8>, _received_ms: u64) -> Result<Option<Vec<u8>>, GeminiError> {
+        Ok(Some(data))
+    }
+}
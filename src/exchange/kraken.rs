@@ -0,0 +1,220 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::UNIX_EPOCH;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::Number;
+use url::Url;
+
+use crate::client::FeedOptions;
+use crate::error::GeminiError;
+use crate::exchange::{split_pair, ExchangeFeed, Normalizer};
+use crate::models::{Event, MarketMessage, MarketSide, Quote, Trade};
+
+pub const KRAKEN_URL: &str = "wss://ws.kraken.com/v2";
+
+// Levels per side of the subscribed book, one of the depths Kraken offers
+const BOOK_DEPTH: u32 = 100;
+
+// Kraken's v2 public feed: one connection per symbol subscribing to its book and trades
+#[derive(Debug, Clone)]
+pub struct KrakenFeed {
+    url: Url,
+}
+
+impl KrakenFeed {
+    pub fn new() -> Self {
+        Self {
+            url: Url::parse(KRAKEN_URL).expect("static url"),
+        }
+    }
+}
+
+impl Default for KrakenFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// btcusd becomes BTC/USD
+fn kraken_symbol(symbol: &str) -> String {
+    match split_pair(symbol) {
+        Some((base, quote)) => format!("{}/{}", base, quote).to_uppercase(),
+        None => symbol.to_uppercase(),
+    }
+}
+
+impl ExchangeFeed for KrakenFeed {
+    fn name(&self) -> &'static str {
+        "kraken"
+    }
+
+    fn url(&self, _symbol: &str, _feed: &FeedOptions) -> Result<Url, GeminiError> {
+        Ok(self.url.clone())
+    }
+
+    fn subscribe(&self, symbol: &str, feed: &FeedOptions) -> Vec<String> {
+        let symbol = kraken_symbol(symbol);
+        let mut messages = Vec::new();
+        if feed.bids || feed.offers {
+            messages.push(serde_json::json!({
+                "method": "subscribe",
+                "params": { "channel": "book", "symbol": [symbol], "depth": BOOK_DEPTH },
+            }).to_string());
+        }
+        if feed.trades {
+            // The snapshot of recent trades would replay history as if it were live
+            messages.push(serde_json::json!({
+                "method": "subscribe",
+                "params": { "channel": "trade", "symbol": [symbol], "snapshot": false },
+            }).to_string());
+        }
+        messages
+    }
+
+    fn normalizer(&self, _symbol: &str) -> Box<dyn Normalizer> {
+        Box::new(KrakenNormalizer::default())
+    }
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    channel: Option<String>,
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    #[serde(default)]
+    data: Vec<serde_json::Value>,
+    // Set on replies to subscribe requests
+    #[serde(default)]
+    success: Option<bool>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Level {
+    price: Number,
+    qty: Number,
+}
+
+#[derive(Deserialize)]
+struct BookData {
+    #[serde(default)]
+    bids: Vec<Level>,
+    #[serde(default)]
+    asks: Vec<Level>,
+    #[serde(default)]
+    timestamp: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TradeData {
+    side: String,
+    price: Number,
+    qty: Number,
+    #[serde(default)]
+    timestamp: Option<String>,
+}
+
+// Numbers come as JSON floats, small quantities possibly in exponent form
+fn decimal(n: &Number) -> Result<Decimal, GeminiError> {
+    let s = n.to_string();
+    Decimal::from_str(&s)
+        .or_else(|_| Decimal::from_scientific(&s))
+        .map_err(|e| GeminiError::Protocol(format!("kraken: bad number {}: {}", s, e)))
+}
+
+fn timestamp_ms(timestamp: Option<&str>) -> Option<u64> {
+    let time = humantime::parse_rfc3339_weak(timestamp?).ok()?;
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_millis() as u64)
+}
+
+// Shared by every connection so event ids keep counting up across reconnects
+static EVENT_IDS: AtomicU64 = AtomicU64::new(0);
+
+// Numbers the frames the way Gemini does: socket_sequence counts up from the first
+// frame of a connection, event ids across all of them
+#[derive(Default)]
+struct KrakenNormalizer {
+    sequence: u32,
+}
+
+impl KrakenNormalizer {
+    fn message(&mut self, events: Vec<Event>, timestampms: Option<u64>) -> Result<Vec<u8>, GeminiError> {
+        let message = MarketMessage {
+            event_id: EVENT_IDS.fetch_add(1, Ordering::Relaxed) + 1,
+            events,
+            timestamp: timestampms.map(|ms| ms / 1000),
+            timestampms,
+            socket_sequence: self.sequence,
+            received_at_ns: None,
+            emitted_at_ns: None,
+        };
+        self.sequence = self.sequence.wrapping_add(1);
+        serde_json::to_vec(&message).map_err(|e| GeminiError::Protocol(format!("kraken: {}", e)))
+    }
+}
+
+impl Normalizer for KrakenNormalizer {
+    fn normalize(&mut self, data: Vec<u8>, received_ms: u64) -> Result<Option<Vec<u8>>, GeminiError> {
+        let envelope: Envelope = serde_json::from_slice(&data)
+            .map_err(|e| GeminiError::Protocol(format!("kraken: malformed message: {}", e)))?;
+        if envelope.success == Some(false) {
+            let error = envelope.error.unwrap_or_default();
+            return Err(GeminiError::Protocol(format!("kraken: subscription failed: {}", error)));
+        }
+        let mut events = Vec::new();
+        let mut timestampms = None;
+        match (envelope.channel.as_deref(), envelope.kind.as_deref()) {
+            (Some("book"), Some(kind @ ("snapshot" | "update"))) => {
+                // The first book message of a connection carries the whole book
+                let reason = if kind == "snapshot" { "initial" } else { "place" };
+                for value in envelope.data {
+                    let book: BookData = serde_json::from_value(value)
+                        .map_err(|e| GeminiError::Protocol(format!("kraken: malformed book: {}", e)))?;
+                    timestampms = timestampms.or(timestamp_ms(book.timestamp.as_deref()));
+                    let sides = [(MarketSide::Bid, book.bids), (MarketSide::Ask, book.asks)];
+                    for (side, levels) in sides {
+                        for level in levels {
+                            let remaining = decimal(&level.qty)?;
+                            let reason = if remaining.is_zero() && kind == "update" { "cancel" } else { reason };
+                            events.push(Event::Quote(Quote {
+                                price: decimal(&level.price)?,
+                                reason: reason.to_string(),
+                                remaining,
+                                side,
+                                delta: None,
+                            }));
+                        }
+                    }
+                }
+            },
+            (Some("trade"), Some("update")) => {
+                for value in envelope.data {
+                    let trade: TradeData = serde_json::from_value(value)
+                        .map_err(|e| GeminiError::Protocol(format!("kraken: malformed trade: {}", e)))?;
+                    timestampms = timestampms.or(timestamp_ms(trade.timestamp.as_deref()));
+                    // Kraken reports the taker's side, the resting order was on the other one
+                    let maker_side = match trade.side.as_str() {
+                        "buy" => MarketSide::Ask,
+                        "sell" => MarketSide::Bid,
+                        _ => MarketSide::Unknown,
+                    };
+                    events.push(Event::Trade(Trade {
+                        price: decimal(&trade.price)?,
+                        amount: decimal(&trade.qty)?,
+                        maker_side,
+                    }));
+                }
+            },
+            _ => return Ok(None),
+        }
+        if events.is_empty() {
+            return Ok(None);
+        }
+        // Snapshots carry no timestamp, the receive time stands in
+        self.message(events, Some(timestampms.unwrap_or(received_ms))).map(Some)
+    }
+}
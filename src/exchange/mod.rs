@@ -0,0 +1,88 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::client::{Endpoint, FeedOptions};
+use crate::error::GeminiError;
+
+pub mod gemini;
+pub mod kraken;
+
+// A venue's market data WebSocket. Every backend hands the pipeline frames in Gemini's
+// v1 market data shape, so the analytics, sinks and captures work the same for all.
+pub trait ExchangeFeed: fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Where to connect for `symbol`, which is given in Gemini's form such as btcusd
+    fn url(&self, symbol: &str, feed: &FeedOptions) -> Result<Url, GeminiError>;
+
+    // Text messages to send once connected, empty when the URL selects the data
+    fn subscribe(&self, symbol: &str, feed: &FeedOptions) -> Vec<String>;
+
+    // Translation state for one connection
+    fn normalizer(&self, symbol: &str) -> Box<dyn Normalizer>;
+}
+
+// Turns a venue's frames into v1 market data frames. None skips frames without market
+// data such as heartbeats and subscription acknowledgements.
+pub trait Normalizer: Send {
+    fn normalize(&mut self, data: Vec<u8>, received_ms: u64) -> Result<Option<Vec<u8>>, GeminiError>;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Exchange {
+    #[default]
+    Gemini,
+    Kraken,
+}
+
+impl Exchange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Exchange::Gemini => "gemini",
+            Exchange::Kraken => "kraken",
+        }
+    }
+
+    // Gemini connections go to `endpoint`, the other venues to their public feed
+    pub fn feed(&self, endpoint: &Endpoint) -> Box<dyn ExchangeFeed> {
+        match self {
+            Exchange::Gemini => Box::new(gemini::GeminiFeed::new(endpoint.clone())),
+            Exchange::Kraken => Box::new(kraken::KrakenFeed::new()),
+        }
+    }
+}
+
+impl fmt::Display for Exchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// A symbol may name its venue as `kraken:btcusd`, anything unprefixed goes to `default`.
+// The prefix stays part of the symbol the pipeline sees, so the same pair on two venues
+// is tracked side by side.
+pub fn route(default: Exchange, symbol: &str) -> (Exchange, &str) {
+    if let Some((prefix, pair)) = symbol.split_once(':') {
+        if let Ok(exchange) = Exchange::from_str(prefix, true) {
+            return (exchange, pair);
+        }
+    }
+    (default, symbol)
+}
+
+// Quote currencies, longest first so usdt is not read as usd
+const QUOTES: [&str; 14] = [
+    "usdt", "usdc", "gusd", "usd", "eur", "gbp", "sgd", "aud", "cad", "chf", "jpy", "btc", "eth", "dai",
+];
+
+// Splits a Gemini style pair such as btcusd into its base and quote currency
+pub fn split_pair(symbol: &str) -> Option<(&str, &str)> {
+    let symbol = symbol.strip_suffix("perp").unwrap_or(symbol);
+    QUOTES.iter()
+        .filter_map(|quote| symbol.strip_suffix(quote).map(|base| (base, &symbol[base.len()..])))
+        .find(|(base, _)| !base.is_empty())
+}
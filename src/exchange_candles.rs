@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use futures_util::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::Number;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::analytics::candles::Candle;
+use crate::client::{self, ReconnectPolicy};
+use crate::close::Close;
+use crate::error::GeminiError;
+use crate::failover::Endpoints;
+
+// The bar lengths Gemini's v2 feed computes, by the suffix of their channel
+pub const INTERVALS: [(&str, Duration); 7] = [
+    ("1m", Duration::from_secs(60)),
+    ("5m", Duration::from_secs(5 * 60)),
+    ("15m", Duration::from_secs(15 * 60)),
+    ("30m", Duration::from_secs(30 * 60)),
+    ("1h", Duration::from_secs(60 * 60)),
+    ("6h", Duration::from_secs(6 * 60 * 60)),
+    ("1d", Duration::from_secs(24 * 60 * 60)),
+];
+
+// Where bars come from: aggregated here from the trades, or as the exchange computes them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CandleSource {
+    #[default]
+    Local,
+    Exchange,
+}
+
+// `[time, open, high, low, close, volume]`, as both the v2 feed and REST API send bars
+pub type CandleRow = (u64, Number, Number, Number, Number, Number);
+
+// The v2 channel with bars of `interval`, such as candles_1m
+pub fn channel(interval: Duration) -> Result<String, GeminiError> {
+    Ok(format!("candles_{}", time_frame(interval)?))
+}
+
+// How the exchange names bars of `interval`, such as 1m
+pub fn time_frame(interval: Duration) -> Result<&'static str, GeminiError> {
+    match INTERVALS.iter().find(|(_, i)| *i == interval) {
+        Some((suffix, _)) => Ok(suffix),
+        None => {
+            let offered: Vec<&str> = INTERVALS.iter().map(|(suffix, _)| *suffix).collect();
+            Err(GeminiError::Config(format!(
+                "the exchange has no {} candles, it offers {}",
+                humantime::format_duration(interval),
+                offered.join(", "),
+            )))
+        },
+    }
+}
+
+// Rows newest first
+#[derive(Deserialize)]
+struct CandleUpdate {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    symbol: String,
+    #[serde(default)]
+    changes: Vec<CandleRow>,
+}
+
+// Prices come as JSON floats, small volumes possibly in exponent form
+fn decimal(n: &Number) -> Result<Decimal, GeminiError> {
+    let s = n.to_string();
+    Decimal::from_str(&s)
+        .or_else(|_| Decimal::from_scientific(&s))
+        .map_err(|e| GeminiError::Protocol(format!("candles: bad number {}: {}", s, e)))
+}
+
+pub fn candle(row: CandleRow, label: &str) -> Result<Candle, GeminiError> {
+    let (start_ms, open, high, low, close, volume) = row;
+    Ok(Candle {
+        start_ms,
+        interval: label.to_string(),
+        open: decimal(&open)?,
+        high: decimal(&high)?,
+        low: decimal(&low)?,
+        close: decimal(&close)?,
+        volume: decimal(&volume)?,
+        trades: 0,
+        tainted: false,
+        exchange: true,
+    })
+}
+
+// The bar of each symbol still forming. Updates repeat it until the next one starts,
+// which completes it.
+struct Bars {
+    label: String,
+    forming: HashMap<String, Candle>,
+}
+
+impl Bars {
+    // The bars `changes` complete, oldest first. The first update of a symbol carries
+    // its history, only the bar forming is kept of it. After a reconnect the bars
+    // completed meanwhile come out.
+    fn update(&mut self, symbol: &str, changes: Vec<CandleRow>) -> Result<Vec<Candle>, GeminiError> {
+        let mut changes = changes.into_iter()
+            .map(|row| candle(row, &self.label))
+            .collect::<Result<Vec<Candle>, GeminiError>>()?;
+        changes.sort_by_key(|c| c.start_ms);
+        let mut completed = Vec::new();
+        let Some(mut forming) = self.forming.remove(symbol) else {
+            if let Some(latest) = changes.pop() {
+                self.forming.insert(symbol.to_string(), latest);
+            }
+            return Ok(completed);
+        };
+        for candle in changes {
+            if candle.start_ms > forming.start_ms {
+                completed.push(std::mem::replace(&mut forming, candle));
+            } else if candle.start_ms == forming.start_ms {
+                forming = candle;
+            }
+        }
+        self.forming.insert(symbol.to_string(), forming);
+        Ok(completed)
+    }
+}
+
+// Subscribes `symbols` to the exchange's `interval` bars on one v2 connection and hands
+// every completed bar to `tx`, reconnecting per `policy` and failing over along
+// `endpoints` like the market data connections, until shutdown or until the receiver is gone
+pub async fn stream(
+    endpoints: Endpoints,
+    symbols: Vec<String>,
+    interval: Duration,
+    policy: ReconnectPolicy,
+    tx: mpsc::Sender<(String, Candle)>,
+    shutdown: CancellationToken,
+) {
+    let Ok(channel) = channel(interval) else {
+        return;
+    };
+    let mut bars = Bars {
+        label: humantime::format_duration(interval).to_string(),
+        forming: HashMap::new(),
+    };
+    let mut attempt = 0;
+    loop {
+        let mut received = false;
+        let (index, endpoint) = endpoints.select();
+        let (error, close) = match session(&endpoint, &symbols, &channel, &mut bars, &tx, &shutdown, &mut received).await {
+            Ok(None) => return,
+            Ok(Some(close)) => (GeminiError::Protocol(format!("{} connection closed by server: {}", channel, close)), Some(close)),
+            Err(e) => (e, None),
+        };
+        if received {
+            attempt = 0;
+            endpoints.succeeded(index);
+        } else {
+            endpoints.failed(index);
+        }
+        let failed = close.as_ref().is_none_or(|close| close.is_failure());
+        let refused = close.as_ref().is_some_and(|close| !close.retries());
+        if !policy.enabled || refused || (failed && policy.max_attempts.is_some_and(|max| attempt >= max)) {
+            warn!(channel, error = %error, "exchange candles stopped");
+            return;
+        }
+        let delay = match &close {
+            Some(close) => close.delay(&policy, attempt),
+            None => policy.delay(attempt),
+        };
+        warn!(channel, error = %error, delay = ?delay, "reconnecting to the exchange candles");
+        if failed {
+            attempt += 1;
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {},
+        }
+    }
+}
+
+// How the server closed the connection, None once shut down or nobody listens anymore
+async fn session(
+    endpoint: &client::Endpoint,
+    symbols: &[String],
+    channel: &str,
+    bars: &mut Bars,
+    tx: &mpsc::Sender<(String, Candle)>,
+    shutdown: &CancellationToken,
+    received: &mut bool,
+) -> Result<Option<Close>, GeminiError> {
+    let url = endpoint.ws_base.join("v2/marketdata")?;
+    let mut ws_stream = tokio::select! {
+        _ = shutdown.cancelled() => return Ok(None),
+        ws_stream = client::connect_url(endpoint, url) => ws_stream?,
+    };
+    let subscribe = serde_json::json!({
+        "type": "subscribe",
+        "subscriptions": [{ "name": channel, "symbols": symbols.iter().map(|s| s.to_uppercase()).collect::<Vec<_>>() }],
+    });
+    ws_stream.send(Message::Text(subscribe.to_string())).await?;
+    info!(channel, symbols = symbols.len(), "subscribed to the exchange candles");
+    let updates = format!("{}_updates", channel);
+    loop {
+        let message = tokio::select! {
+            _ = shutdown.cancelled() => {
+                ws_stream.send(Message::Close(None)).await?;
+                return Ok(None);
+            },
+            message = ws_stream.next() => match message {
+                Some(message) => message?,
+                None => return Ok(Some(Close::abnormal())),
+            },
+        };
+        if let Message::Close(frame) = &message {
+            return Ok(Some(Close::from_frame(frame.as_ref())));
+        }
+        if !message.is_text() {
+            continue;
+        }
+        let update: CandleUpdate = match serde_json::from_slice(&message.into_data()) {
+            Ok(update) => update,
+            Err(e) => {
+                warn!(channel, error = %e, "skipping malformed candle update");
+                continue;
+            },
+        };
+        // Heartbeats and the other channels of the v2 feed
+        if update.kind != updates {
+            debug!(channel, kind = update.kind, "ignoring v2 message");
+            continue;
+        }
+        *received = true;
+        let symbol = update.symbol.to_lowercase();
+        for candle in bars.update(&symbol, update.changes)? {
+            if tx.send((symbol.clone(), candle)).await.is_err() {
+                return Ok(None);
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::client::Endpoint;
+use crate::metrics;
+
+// Weight of the latest outcome in an endpoint's score
+const SCORE_WEIGHT: f64 = 0.5;
+
+// Scores below this take an endpoint out of rotation. From full health that is two
+// failures in a row, so one dropped connect does not move every symbol elsewhere.
+const HEALTHY_SCORE: f64 = 0.5;
+
+// How long an unhealthy endpoint sits out before connections try it again
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+struct Health {
+    // 1 after a run of successful sessions, towards 0 with every failure
+    score: f64,
+    benched_until: Option<Instant>,
+}
+
+// Endpoints in order of preference, with a health score each shared by every connection
+// made through them. A connection takes the first one not sitting out a cooldown, so
+// after the primary recovers new connections go back to it; live ones stay where they are.
+#[derive(Debug, Clone)]
+pub struct Endpoints {
+    endpoints: Arc<[Endpoint]>,
+    health: Arc<Mutex<Vec<Health>>>,
+}
+
+impl Endpoints {
+    // The first endpoint is the primary, `fallbacks` follow in order
+    pub fn new(primary: Endpoint, fallbacks: Vec<Endpoint>) -> Self {
+        let endpoints: Arc<[Endpoint]> = std::iter::once(primary).chain(fallbacks).collect();
+        let health = endpoints.iter().map(|_| Health { score: 1., benched_until: None }).collect();
+        let endpoints = Self {
+            endpoints,
+            health: Arc::new(Mutex::new(health)),
+        };
+        endpoints.publish(&endpoints.lock());
+        endpoints
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Health>> {
+        self.health.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn primary(&self) -> &Endpoint {
+        &self.endpoints[0]
+    }
+
+    // The endpoint a new connection should use, with its index for reporting back.
+    // When all of them sit out, the one whose cooldown ends first.
+    pub fn select(&self) -> (usize, Endpoint) {
+        let now = Instant::now();
+        let health = self.lock();
+        let index = health.iter()
+            .position(|h| h.benched_until.is_none_or(|until| until <= now))
+            .or_else(|| health.iter().enumerate().min_by_key(|(_, h)| h.benched_until).map(|(i, _)| i))
+            .unwrap_or(0);
+        (index, self.endpoints[index].clone())
+    }
+
+    // A session through `index` delivered data
+    pub fn succeeded(&self, index: usize) {
+        let mut health = self.lock();
+        let h = &mut health[index];
+        h.score = h.score * (1. - SCORE_WEIGHT) + SCORE_WEIGHT;
+        h.benched_until = None;
+        self.publish(&health);
+    }
+
+    // A session through `index` failed to connect or ended before any data
+    pub fn failed(&self, index: usize) {
+        let mut health = self.lock();
+        let h = &mut health[index];
+        h.score *= 1. - SCORE_WEIGHT;
+        if h.score < HEALTHY_SCORE && h.benched_until.is_none_or(|until| until <= Instant::now()) {
+            h.benched_until = Some(Instant::now() + COOLDOWN);
+            if self.endpoints.len() > 1 {
+                warn!(endpoint = %self.endpoints[index].ws_base, score = h.score, cooldown = ?COOLDOWN, "endpoint unhealthy, failing over");
+            }
+        }
+        self.publish(&health);
+    }
+
+    // Marks `index` as the endpoint the connection of `symbol` uses
+    pub fn activate(&self, symbol: &str, index: usize) {
+        for (i, endpoint) in self.endpoints.iter().enumerate() {
+            let active = if i == index { 1. } else { 0. };
+            metrics::global().active_endpoint.with_label_values(&[symbol, endpoint.ws_base.as_str()]).set(active);
+        }
+    }
+
+    fn publish(&self, health: &[Health]) {
+        for (endpoint, h) in self.endpoints.iter().zip(health) {
+            metrics::global().endpoint_health.with_label_values(&[endpoint.ws_base.as_str()]).set(h.score);
+        }
+    }
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self::new(Endpoint::production(), Vec::new())
+    }
+}
+
+impl From<Endpoint> for Endpoints {
+    fn from(endpoint: Endpoint) -> Self {
+        Self::new(endpoint, Vec::new())
+    }
+}
@@ -0,0 +1,222 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::ptr;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::models::{AuctionEvent, Event, MarketMessage, MarketSide};
+
+// The C interface: frames go in as bytes and come out either as a GeminiMessage with
+// the events in a flat array, or as the normalized JSON. Everything returned is owned
+// by the library and handed back to the matching free function.
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: String) {
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeminiEventKind {
+    Trade,
+    BlockTrade,
+    Quote,
+    AuctionOpen,
+    AuctionIndicative,
+    AuctionResult,
+    Unknown,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeminiSide {
+    Unknown,
+    Bid,
+    Ask,
+}
+
+/// Why a quote changed, `Initial` marks the book snapshot after connecting
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeminiReason {
+    None,
+    Initial,
+    Place,
+    Cancel,
+    Trade,
+    Other,
+}
+
+/// One event of a frame. Numbers that do not apply to the kind are NaN or 0.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GeminiEvent {
+    pub kind: GeminiEventKind,
+    /// The maker's side for trades, the book side for quotes
+    pub side: GeminiSide,
+    pub reason: GeminiReason,
+    /// Trade or quote price, the indicative or final price of an auction
+    pub price: f64,
+    /// Trade amount, remaining size at a quote's price, auction quantity
+    pub amount: f64,
+    /// Block trade id or auction event id
+    pub id: u64,
+}
+
+/// A parsed frame, free it with gemini_message_free
+#[repr(C)]
+#[derive(Debug)]
+pub struct GeminiMessage {
+    pub event_id: u64,
+    pub socket_sequence: u32,
+    /// -1 when the frame has no timestamp, as the initial book does
+    pub timestampms: i64,
+    pub events: *mut GeminiEvent,
+    pub events_len: usize,
+}
+
+fn number(d: Option<Decimal>) -> f64 {
+    d.and_then(|d| d.to_f64()).unwrap_or(f64::NAN)
+}
+
+fn side(side: MarketSide) -> GeminiSide {
+    match side {
+        MarketSide::Bid => GeminiSide::Bid,
+        MarketSide::Ask => GeminiSide::Ask,
+        MarketSide::Unknown => GeminiSide::Unknown,
+    }
+}
+
+fn event(event: &Event) -> GeminiEvent {
+    let mut out = GeminiEvent {
+        kind: GeminiEventKind::Unknown,
+        side: GeminiSide::Unknown,
+        reason: GeminiReason::None,
+        price: f64::NAN,
+        amount: f64::NAN,
+        id: 0,
+    };
+    match event {
+        Event::Trade(t) => {
+            out.kind = GeminiEventKind::Trade;
+            out.side = side(t.maker_side);
+            out.price = number(Some(t.price));
+            out.amount = number(Some(t.amount));
+        },
+        Event::BlockTrade(t) => {
+            out.kind = GeminiEventKind::BlockTrade;
+            out.price = number(Some(t.price));
+            out.amount = number(Some(t.amount));
+            out.id = t.tid;
+        },
+        Event::Quote(q) => {
+            out.kind = GeminiEventKind::Quote;
+            out.side = side(q.side);
+            out.reason = match q.reason.as_str() {
+                "initial" => GeminiReason::Initial,
+                "place" => GeminiReason::Place,
+                "cancel" => GeminiReason::Cancel,
+                "trade" => GeminiReason::Trade,
+                _ => GeminiReason::Other,
+            };
+            out.price = number(Some(q.price));
+            out.amount = number(Some(q.remaining));
+        },
+        Event::Auction(AuctionEvent::Open(_)) => out.kind = GeminiEventKind::AuctionOpen,
+        Event::Auction(AuctionEvent::Indicative(a)) => {
+            out.kind = GeminiEventKind::AuctionIndicative;
+            out.price = number(a.indicative_price);
+            out.amount = number(a.indicative_quantity);
+            out.id = a.eid;
+        },
+        Event::Auction(AuctionEvent::Result(a)) => {
+            out.kind = GeminiEventKind::AuctionResult;
+            out.price = number(a.auction_price);
+            out.amount = number(a.auction_quantity);
+            out.id = a.eid;
+        },
+        Event::Unknown => {},
+    }
+    out
+}
+
+unsafe fn parse(data: *const u8, len: usize) -> Option<MarketMessage> {
+    if data.is_null() {
+        set_error(String::from("null frame"));
+        return None;
+    }
+    let frame = std::slice::from_raw_parts(data, len);
+    MarketMessage::from_slice(frame).map_err(|e| set_error(e.to_string())).ok()
+}
+
+/// Parses one market data frame. Returns NULL when it is malformed, see
+/// gemini_last_error for why.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gemini_parse(data: *const u8, len: usize) -> *mut GeminiMessage {
+    let Some(message) = parse(data, len) else {
+        return ptr::null_mut();
+    };
+    let events: Box<[GeminiEvent]> = message.events.iter().map(event).collect();
+    let events_len = events.len();
+    Box::into_raw(Box::new(GeminiMessage {
+        event_id: message.event_id,
+        socket_sequence: message.socket_sequence,
+        timestampms: message.timestampms.map(|ms| ms as i64).unwrap_or(-1),
+        events: Box::into_raw(events) as *mut GeminiEvent,
+        events_len,
+    }))
+}
+
+/// # Safety
+/// `message` must come from gemini_parse and not have been freed, or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn gemini_message_free(message: *mut GeminiMessage) {
+    if message.is_null() {
+        return;
+    }
+    let message = Box::from_raw(message);
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(message.events, message.events_len)));
+}
+
+/// Parses one market data frame and returns it as normalized JSON with prices as
+/// strings, nothing lost to floating point. NULL when it is malformed.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gemini_parse_json(data: *const u8, len: usize) -> *mut c_char {
+    let Some(message) = parse(data, len) else {
+        return ptr::null_mut();
+    };
+    match serde_json::to_string(&message).map(CString::new) {
+        Ok(Ok(json)) => json.into_raw(),
+        _ => {
+            set_error(String::from("could not serialize the message"));
+            ptr::null_mut()
+        },
+    }
+}
+
+/// # Safety
+/// `json` must come from gemini_parse_json and not have been freed, or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn gemini_string_free(json: *mut c_char) {
+    if !json.is_null() {
+        drop(CString::from_raw(json));
+    }
+}
+
+/// Why the last parse on this thread failed, NULL if none has. Valid until the next
+/// failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn gemini_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map(|e| e.as_ptr()).unwrap_or(ptr::null()))
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::error::GeminiError;
+
+const PLACEHOLDERS: [&str; 4] = ["{symbol}", "{date}", "{hour}", "{kind}"];
+
+// A file path with placeholders filled in per event: {symbol}, {date} as 2024-06-01 and
+// {hour} as 14, both UTC, and {kind} such as trade or bbo. A path without any is used as
+// it is, so ./data/{symbol}/{date}/events.jsonl gives every symbol a file per day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTemplate {
+    template: String,
+}
+
+impl PathTemplate {
+    pub fn new(path: &Path) -> Result<Self, GeminiError> {
+        let template = path.to_string_lossy().into_owned();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}').map(|end| start + end + 1).unwrap_or(rest.len());
+            let placeholder = &rest[start..end];
+            if !PLACEHOLDERS.contains(&placeholder) {
+                return Err(GeminiError::Config(format!(
+                    "{}: unknown placeholder `{}`, expected one of {}",
+                    template, placeholder, PLACEHOLDERS.join(", "),
+                )));
+            }
+            rest = &rest[end..];
+        }
+        Ok(Self { template })
+    }
+
+    pub fn uses(&self, placeholder: &str) -> bool {
+        self.template.contains(placeholder)
+    }
+
+    pub fn is_static(&self) -> bool {
+        !PLACEHOLDERS.iter().any(|p| self.uses(p))
+    }
+
+    // `ts_ms` picks the date and hour, a venue prefix such as `kraken:` keeps the symbol
+    // in one path component
+    pub fn render(&self, symbol: &str, kind: &str, ts_ms: u64) -> PathBuf {
+        let time = UNIX_EPOCH + Duration::from_millis(ts_ms);
+        // 2024-06-01T14:30:00Z
+        let rfc3339 = humantime::format_rfc3339_seconds(time).to_string();
+        let symbol: String = symbol.chars().map(|c| if matches!(c, ':' | '/' | '\\') { '_' } else { c }).collect();
+        PathBuf::from(self.template
+            .replace("{symbol}", &symbol)
+            .replace("{date}", &rfc3339[..10])
+            .replace("{hour}", &rfc3339[11..13])
+            .replace("{kind}", kind))
+    }
+
+    // The part of a rendered path that changes with time, files of an earlier one are done
+    pub fn period(&self, ts_ms: u64) -> Option<u64> {
+        let hour = 60 * 60 * 1000;
+        match (self.uses("{hour}"), self.uses("{date}")) {
+            (true, _) => Some(ts_ms / hour),
+            (false, true) => Some(ts_ms / (24 * hour)),
+            (false, false) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+// Lines appended to the files a template names, opened as events need them and closed
+// once the date or hour in their name has passed. Directories are created as needed and
+// `header` starts every new file.
+pub struct TemplatedFiles {
+    template: PathTemplate,
+    header: Option<String>,
+    files: HashMap<PathBuf, File>,
+    period: Option<u64>,
+}
+
+impl TemplatedFiles {
+    pub fn new(template: PathTemplate, header: Option<String>) -> Self {
+        Self {
+            template,
+            header,
+            files: HashMap::new(),
+            period: None,
+        }
+    }
+
+    pub fn write(&mut self, symbol: &str, kind: &str, ts_ms: u64, line: &str) -> Result<(), GeminiError> {
+        let period = self.template.period(ts_ms);
+        if period > self.period {
+            self.files.clear();
+            self.period = period;
+        }
+        let path = self.template.render(symbol, kind, ts_ms);
+        let file = match self.files.get_mut(&path) {
+            Some(file) => file,
+            None => {
+                let file = open(&path, self.header.as_deref())?;
+                self.files.entry(path).or_insert(file)
+            },
+        };
+        // One write per line, so a reader never sees half of one
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn open(path: &Path, header: Option<&str>) -> Result<File, GeminiError> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    // A file continued from an earlier run already has its header
+    if let (Some(header), 0) = (header, file.metadata()?.len()) {
+        file.write_all(header.as_bytes())?;
+    }
+    Ok(file)
+}
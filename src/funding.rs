@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use crate::models::FundingAmount;
+use crate::rest::RestClient;
+
+// Gemini's perpetual contracts end in `perp`, e.g. btcgusdperp
+pub fn is_perpetual(symbol: &str) -> bool {
+    symbol.ends_with("perp")
+}
+
+// Fetches the funding of the perpetual `symbol` every `interval` and hands it to `tx`
+// until shutdown or until the receiver is gone. Failed lookups are retried next tick.
+pub async fn poll(
+    rest: RestClient,
+    symbol: String,
+    interval: Duration,
+    tx: mpsc::Sender<(String, FundingAmount)>,
+    shutdown: CancellationToken,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    let mut last = None;
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = ticker.tick() => {},
+        }
+        match rest.funding_amount(&symbol).await {
+            // Funding changes hourly, only pass on what is new
+            Ok(funding) if last.as_ref() == Some(&funding) => {},
+            Ok(funding) => {
+                last = Some(funding.clone());
+                if tx.send((symbol.clone(), funding)).await.is_err() {
+                    return;
+                }
+            },
+            Err(e) => warn!(symbol, error = %e, "could not fetch the funding amount"),
+        }
+    }
+}
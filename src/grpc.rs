@@ -0,0 +1,209 @@
+use std::net::SocketAddr;
+use std::pin::Pin;
+
+use futures_util::stream::{self, Stream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+use tracing::{error, info};
+
+use crate::client::now_ns;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{self, AuctionEvent, BestBidOffer, BlockTrade, MarketSide, Quote, Trade};
+use crate::output::EventContext;
+
+pub mod proto {
+    tonic::include_proto!("gemini.marketdata.v1");
+}
+
+use proto::event::Payload;
+use proto::market_data_server::{MarketData, MarketDataServer};
+
+// Events a subscriber may fall behind before it starts missing some
+const SUBSCRIBER_BACKLOG: usize = 1024;
+
+fn side(side: MarketSide) -> i32 {
+    let side = match side {
+        MarketSide::Bid => proto::Side::Bid,
+        MarketSide::Ask => proto::Side::Ask,
+        MarketSide::Unknown => proto::Side::Unspecified,
+    };
+    side as i32
+}
+
+impl From<&Trade> for proto::Trade {
+    fn from(t: &Trade) -> Self {
+        Self {
+            price: t.price.to_string(),
+            amount: t.amount.to_string(),
+            maker_side: side(t.maker_side),
+        }
+    }
+}
+
+impl From<&Quote> for proto::Quote {
+    fn from(q: &Quote) -> Self {
+        Self {
+            price: q.price.to_string(),
+            remaining: q.remaining.to_string(),
+            side: side(q.side),
+            reason: q.reason.clone(),
+            delta: q.delta.map(|d| d.to_string()),
+        }
+    }
+}
+
+impl From<&BestBidOffer> for proto::BestBidOffer {
+    fn from(bbo: &BestBidOffer) -> Self {
+        Self {
+            best_bid: bbo.best_bid.to_string(),
+            bid_amount_remaining: bbo.bid_amount_remaining.to_string(),
+            best_offer: bbo.best_offer.to_string(),
+            ask_amount_remaining: bbo.ask_amount_remaining.to_string(),
+        }
+    }
+}
+
+impl From<&BlockTrade> for proto::BlockTrade {
+    fn from(t: &BlockTrade) -> Self {
+        Self {
+            tid: t.tid,
+            price: t.price.to_string(),
+            amount: t.amount.to_string(),
+        }
+    }
+}
+
+impl From<&AuctionEvent> for proto::Auction {
+    fn from(auction: &AuctionEvent) -> Self {
+        let text = |v: Option<rust_decimal::Decimal>| v.map(|v| v.to_string());
+        match auction {
+            AuctionEvent::Open(models::AuctionOpen { auction_time_ms, .. }) => Self {
+                phase: String::from("open"),
+                auction_time_ms: Some(*auction_time_ms),
+                ..Self::default()
+            },
+            AuctionEvent::Indicative(a) => Self {
+                phase: String::from("indicative"),
+                price: text(a.indicative_price),
+                quantity: text(a.indicative_quantity),
+                result: a.result.clone(),
+                auction_time_ms: None,
+            },
+            AuctionEvent::Result(a) => Self {
+                phase: String::from("result"),
+                price: text(a.auction_price),
+                quantity: text(a.auction_quantity),
+                result: a.result.clone(),
+                auction_time_ms: None,
+            },
+        }
+    }
+}
+
+// Feeds the gRPC subscribers from the pipeline
+pub struct GrpcHandler {
+    tx: broadcast::Sender<proto::Event>,
+}
+
+impl GrpcHandler {
+    fn publish(&self, symbol: &str, ctx: &EventContext, payload: Payload) -> Result<(), GeminiError> {
+        let event = proto::Event {
+            symbol: symbol.to_string(),
+            event_id: ctx.event_id,
+            socket_sequence: ctx.socket_sequence,
+            timestampms: ctx.timestampms,
+            received_ms: ctx.received_ms,
+            received_at_ns: ctx.received_ns,
+            emitted_at_ns: now_ns(),
+            payload: Some(payload),
+        };
+        // Nobody subscribed is not an error
+        let _ = self.tx.send(event);
+        Ok(())
+    }
+}
+
+impl EventHandler for GrpcHandler {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.publish(symbol, ctx, Payload::Trade(trade.into()))
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        self.publish(symbol, ctx, Payload::Quote(quote.into()))
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.publish(symbol, ctx, Payload::Bbo(bbo.into()))
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.publish(symbol, ctx, Payload::BlockTrade(trade.into()))
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.publish(symbol, ctx, Payload::Auction(auction.into()))
+    }
+
+    fn name(&self) -> &'static str {
+        "grpc"
+    }
+}
+
+struct MarketDataService {
+    tx: broadcast::Sender<proto::Event>,
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<proto::Event, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl MarketData for MarketDataService {
+    type SubscribeStream = EventStream;
+
+    async fn subscribe(&self, request: Request<proto::SubscribeRequest>) -> Result<Response<EventStream>, Status> {
+        let symbol = request.into_inner().symbol.to_lowercase();
+        let rx = self.tx.subscribe();
+        let events = stream::unfold(rx, move |mut rx| {
+            let symbol = symbol.clone();
+            async move {
+                loop {
+                    match rx.recv().await {
+                        Ok(event) if symbol.is_empty() || event.symbol == symbol => return Some((Ok(event), rx)),
+                        Ok(_) => continue,
+                        Err(RecvError::Lagged(n)) => {
+                            return Some((Err(Status::data_loss(format!("subscriber fell behind by {} events", n))), rx));
+                        },
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+// Binds `addr` right away so a taken port fails at startup. Returns the handler to
+// register with the pipeline and the server to spawn.
+pub fn bind(
+    addr: SocketAddr,
+    shutdown: CancellationToken,
+) -> Result<(GrpcHandler, impl std::future::Future<Output = ()>), GeminiError> {
+    let incoming = TcpIncoming::new(addr, true, None)
+        .map_err(|e| GeminiError::Config(format!("grpc: cannot bind {}: {}", addr, e)))?;
+    let (tx, _) = broadcast::channel(SUBSCRIBER_BACKLOG);
+    let service = MarketDataServer::new(MarketDataService { tx: tx.clone() });
+    info!(%addr, "serving grpc market data");
+    let server = async move {
+        let result = tonic::transport::Server::builder()
+            .add_service(service)
+            .serve_with_incoming_shutdown(incoming, shutdown.cancelled_owned())
+            .await;
+        if let Err(e) = result {
+            error!(error = %e, "grpc server failed");
+        }
+    };
+    Ok((GrpcHandler { tx }, server))
+}
@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use tokio::task::JoinHandle;
+
+use crate::analytics::anomaly::AnomalyEvent;
+use crate::analytics::candles::Candle;
+use crate::analytics::cross::CrossDivergence;
+use crate::analytics::depth::DepthSnapshot;
+use crate::analytics::dwell::DwellSnapshot;
+use crate::analytics::indicators::BookIndicators;
+use crate::analytics::ofi::OfiSnapshot;
+use crate::analytics::technical::TechnicalSnapshot;
+use crate::analytics::volatility::VolatilitySnapshot;
+use crate::analytics::vwap::TradeStatsSnapshot;
+use crate::error::GeminiError;
+use crate::models::{AuctionEvent, BestBidOffer, BlockTrade, FundingAmount, Quote, Trade};
+use crate::output::EventContext;
+use crate::queue::{self, QueueOptions, QueueSender};
+use crate::routing::Route;
+
+// Subscriber to everything the pipeline produces. Every callback defaults to doing
+// nothing, so a handler only implements what it cares about.
+pub trait EventHandler: Send {
+    fn on_trade(&mut self, _symbol: &str, _ctx: &EventContext, _trade: &Trade) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    fn on_quote(&mut self, _symbol: &str, _ctx: &EventContext, _quote: &Quote) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // The best bid and offer after a batch of quotes was applied to the book
+    fn on_book_update(&mut self, _symbol: &str, _ctx: &EventContext, _bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    fn on_block_trade(&mut self, _symbol: &str, _ctx: &EventContext, _trade: &BlockTrade) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    fn on_auction(&mut self, _symbol: &str, _ctx: &EventContext, _auction: &AuctionEvent) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    fn on_stats(&mut self, _symbol: &str, _ctx: &EventContext, _stats: &TradeStatsSnapshot) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    fn on_candle(&mut self, _symbol: &str, _ctx: &EventContext, _candle: &Candle) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // Technical indicators over candle closes, follows the `on_candle` it was computed from
+    fn on_technical(&mut self, _symbol: &str, _ctx: &EventContext, _technical: &TechnicalSnapshot) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // Imbalance and microprice of the best bid and offer, follows `on_book_update`
+    fn on_indicators(&mut self, _symbol: &str, _ctx: &EventContext, _indicators: &BookIndicators) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // Aggregated depth and the prices of the configured sizes, after a quote changed them
+    fn on_depth(&mut self, _symbol: &str, _ctx: &EventContext, _depth: &DepthSnapshot) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // Realized volatility after a mid-price sample completed, follows `on_book_update`
+    fn on_volatility(&mut self, _symbol: &str, _ctx: &EventContext, _volatility: &VolatilitySnapshot) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // How long best prices stood before being replaced, follows `on_book_update`
+    fn on_dwell(&mut self, _symbol: &str, _ctx: &EventContext, _dwell: &DwellSnapshot) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // Order flow imbalance over the rolling windows, follows `on_book_update`
+    fn on_ofi(&mut self, _symbol: &str, _ctx: &EventContext, _ofi: &OfiSnapshot) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // A trade far from the rolling mid or a blown out spread, follows the trade or BBO it concerns
+    fn on_anomaly(&mut self, _symbol: &str, _ctx: &EventContext, _anomaly: &AnomalyEvent) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // A synthetic cross moved outside the threshold or back inside, `symbol` is the target
+    fn on_cross(&mut self, _symbol: &str, _ctx: &EventContext, _cross: &CrossDivergence) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // A new funding amount of a perpetual, polled over REST
+    fn on_funding(&mut self, _symbol: &str, _ctx: &EventContext, _funding: &FundingAmount) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // The connection for `symbol` dropped, a reconnect may follow
+    fn on_disconnect(&mut self, _symbol: &str, _reason: &str) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // The local clock moved on, for handlers holding events back until a time has passed
+    fn on_tick(&mut self, _now_ms: u64) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        Ok(())
+    }
+    // Labels the handler's queue in logs and metrics
+    fn name(&self) -> &'static str {
+        "handler"
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum HandlerEvent {
+    Trade(Trade),
+    Quote(Quote),
+    BookUpdate(BestBidOffer),
+    BlockTrade(BlockTrade),
+    Auction(AuctionEvent),
+    Stats(TradeStatsSnapshot),
+    Candle(Candle),
+    Technical(TechnicalSnapshot),
+    Cross(CrossDivergence),
+    Indicators(BookIndicators),
+    Depth(DepthSnapshot),
+    Volatility(VolatilitySnapshot),
+    Dwell(DwellSnapshot),
+    Ofi(OfiSnapshot),
+    Anomaly(AnomalyEvent),
+    Funding(FundingAmount),
+}
+
+// The kind names of the events, as used in the output and its file templates
+pub const EVENT_KINDS: [&str; 16] = [
+    "trade", "quote", "bbo", "block_trade", "auction", "stats", "candle", "technical",
+    "cross", "indicators", "depth", "volatility", "dwell", "ofi", "anomaly", "funding",
+];
+
+// A kind name such as bbo, also in the plural and with hyphens like block-trades
+pub fn parse_kind(s: &str) -> Result<&'static str, String> {
+    let name = s.trim().to_lowercase().replace('-', "_");
+    EVENT_KINDS.iter()
+        .find(|kind| name == **kind || name.strip_suffix('s') == Some(**kind))
+        .copied()
+        .ok_or_else(|| format!("unknown event kind `{}`, expected one of {}", s, EVENT_KINDS.join(", ")))
+}
+
+impl HandlerEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HandlerEvent::Trade(_) => "trade",
+            HandlerEvent::Quote(_) => "quote",
+            HandlerEvent::BookUpdate(_) => "bbo",
+            HandlerEvent::BlockTrade(_) => "block_trade",
+            HandlerEvent::Auction(_) => "auction",
+            HandlerEvent::Stats(_) => "stats",
+            HandlerEvent::Candle(_) => "candle",
+            HandlerEvent::Technical(_) => "technical",
+            HandlerEvent::Cross(_) => "cross",
+            HandlerEvent::Indicators(_) => "indicators",
+            HandlerEvent::Depth(_) => "depth",
+            HandlerEvent::Volatility(_) => "volatility",
+            HandlerEvent::Dwell(_) => "dwell",
+            HandlerEvent::Ofi(_) => "ofi",
+            HandlerEvent::Anomaly(_) => "anomaly",
+            HandlerEvent::Funding(_) => "funding",
+        }
+    }
+}
+
+// One callback on its way to the handler tasks
+#[derive(Debug, Clone)]
+pub enum HandlerMessage {
+    Event {
+        symbol: String,
+        ctx: EventContext,
+        event: HandlerEvent,
+    },
+    Disconnect {
+        symbol: String,
+        reason: String,
+    },
+    Tick {
+        now_ms: u64,
+    },
+}
+
+impl HandlerMessage {
+    // Disconnects and ticks go to every handler whatever kinds it takes
+    fn is_kind_of(&self, kinds: &[&'static str]) -> bool {
+        match self {
+            HandlerMessage::Event { event, .. } => kinds.is_empty() || kinds.contains(&event.kind()),
+            HandlerMessage::Disconnect { .. } | HandlerMessage::Tick { .. } => true,
+        }
+    }
+
+    pub fn deliver(&self, handler: &mut dyn EventHandler) -> Result<(), GeminiError> {
+        let (symbol, ctx, event) = match self {
+            HandlerMessage::Event { symbol, ctx, event } => (symbol.as_str(), ctx, event),
+            HandlerMessage::Disconnect { symbol, reason } => return handler.on_disconnect(symbol, reason),
+            HandlerMessage::Tick { now_ms } => return handler.on_tick(*now_ms),
+        };
+        match event {
+            HandlerEvent::Trade(t) => handler.on_trade(symbol, ctx, t),
+            HandlerEvent::Quote(q) => handler.on_quote(symbol, ctx, q),
+            HandlerEvent::BookUpdate(bbo) => handler.on_book_update(symbol, ctx, bbo),
+            HandlerEvent::BlockTrade(t) => handler.on_block_trade(symbol, ctx, t),
+            HandlerEvent::Auction(a) => handler.on_auction(symbol, ctx, a),
+            HandlerEvent::Stats(s) => handler.on_stats(symbol, ctx, s),
+            HandlerEvent::Candle(c) => handler.on_candle(symbol, ctx, c),
+            HandlerEvent::Technical(t) => handler.on_technical(symbol, ctx, t),
+            HandlerEvent::Cross(c) => handler.on_cross(symbol, ctx, c),
+            HandlerEvent::Indicators(i) => handler.on_indicators(symbol, ctx, i),
+            HandlerEvent::Depth(d) => handler.on_depth(symbol, ctx, d),
+            HandlerEvent::Volatility(v) => handler.on_volatility(symbol, ctx, v),
+            HandlerEvent::Dwell(d) => handler.on_dwell(symbol, ctx, d),
+            HandlerEvent::Ofi(o) => handler.on_ofi(symbol, ctx, o),
+            HandlerEvent::Anomaly(a) => handler.on_anomaly(symbol, ctx, a),
+            HandlerEvent::Funding(f) => handler.on_funding(symbol, ctx, f),
+        }
+    }
+}
+
+// A handler running on its own blocking thread, fed through a bounded queue so a slow
+// database, broker or terminal never stalls the pipeline
+pub struct HandlerTask {
+    tx: Option<QueueSender<Arc<HandlerMessage>>>,
+    task: Option<JoinHandle<Result<(), GeminiError>>>,
+}
+
+impl HandlerTask {
+    pub fn spawn(handler: Box<dyn EventHandler>, options: QueueOptions) -> Self {
+        Self::spawn_filtered(handler, options, Vec::new(), Vec::new())
+    }
+
+    // Only hands the handler events of these kinds, all of them when empty, that one of
+    // `routes` matches, if there are any
+    pub fn spawn_filtered(mut handler: Box<dyn EventHandler>, options: QueueOptions, kinds: Vec<&'static str>, routes: Vec<Route>) -> Self {
+        let (tx, mut rx) = queue::bounded::<Arc<HandlerMessage>>(handler.name(), options);
+        let task = tokio::task::spawn_blocking(move || {
+            while let Some(message) = rx.blocking_recv() {
+                let routed = routes.is_empty() || routes.iter().any(|route| route.matches(&message));
+                if routed && message.is_kind_of(&kinds) {
+                    message.deliver(handler.as_mut())?;
+                }
+            }
+            handler.flush()
+        });
+        Self {
+            tx: Some(tx),
+            task: Some(task),
+        }
+    }
+
+    // Fails with the handler's own error once its task has stopped
+    pub async fn send(&mut self, message: Arc<HandlerMessage>) -> Result<(), GeminiError> {
+        let Some(tx) = self.tx.as_ref() else {
+            return Ok(());
+        };
+        if tx.send(message).await.is_err() {
+            return self.close().await;
+        }
+        Ok(())
+    }
+
+    // Lets the task drain its queue, flush and finish
+    pub async fn close(&mut self) -> Result<(), GeminiError> {
+        self.tx = None;
+        match self.task.take() {
+            Some(task) => task.await.map_err(|e| GeminiError::Sink(format!("handler task failed: {}", e)))?,
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::metrics;
+use crate::models::{BestBidOffer, Trade};
+use crate::output::EventContext;
+
+// Entries kept per symbol and kind however short the window, so a burst stays bounded
+pub const DEFAULT_MAX_ENTRIES: usize = 100_000;
+
+// A trade or BBO with the exchange time it happened at
+#[derive(Serialize, Debug, Clone)]
+pub struct Timed<T> {
+    pub timestampms: u64,
+    #[serde(flatten)]
+    pub value: T,
+}
+
+#[derive(Debug, Default)]
+struct SymbolHistory {
+    trades: VecDeque<Timed<Trade>>,
+    bbo: VecDeque<Timed<BestBidOffer>>,
+}
+
+// Trades and BBO snapshots of the last `window` per symbol, readable from any clone
+#[derive(Debug, Clone)]
+pub struct History {
+    window_ms: u64,
+    max_entries: usize,
+    symbols: Arc<Mutex<HashMap<String, SymbolHistory>>>,
+}
+
+impl History {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window_ms: window.as_millis() as u64,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            symbols: Arc::default(),
+        }
+    }
+
+    // Keep at most `max` trades and as many BBO changes per symbol, the oldest go first
+    pub fn with_max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max.max(1);
+        self
+    }
+
+    // Drops what fell out of the window, then the oldest beyond the cap, which count as evicted
+    fn push<T>(&self, symbol: &str, entries: &mut VecDeque<Timed<T>>, timestampms: u64, value: T) {
+        entries.push_back(Timed { timestampms, value });
+        let cutoff = timestampms.saturating_sub(self.window_ms);
+        while entries.front().is_some_and(|e| e.timestampms <= cutoff) {
+            entries.pop_front();
+        }
+        let evicted = entries.len().saturating_sub(self.max_entries);
+        if evicted > 0 {
+            entries.drain(..evicted);
+            metrics::global().evicted.with_label_values(&[symbol, "history"]).inc_by(evicted as u64);
+        }
+    }
+
+    pub fn record_trade(&self, symbol: &str, timestampms: u64, trade: &Trade) {
+        let mut symbols = self.symbols.lock().unwrap();
+        let history = symbols.entry(symbol.to_string()).or_default();
+        self.push(symbol, &mut history.trades, timestampms, trade.clone());
+    }
+
+    pub fn record_bbo(&self, symbol: &str, timestampms: u64, bbo: &BestBidOffer) {
+        let mut symbols = self.symbols.lock().unwrap();
+        let history = symbols.entry(symbol.to_string()).or_default();
+        self.push(symbol, &mut history.bbo, timestampms, bbo.clone());
+    }
+
+    // Trades of the window, oldest first
+    pub fn recent_trades(&self, symbol: &str) -> Vec<Timed<Trade>> {
+        let symbols = self.symbols.lock().unwrap();
+        symbols.get(symbol).map(|h| h.trades.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    // Every BBO change of the window, oldest first
+    pub fn bbo_history(&self, symbol: &str) -> Vec<Timed<BestBidOffer>> {
+        let symbols = self.symbols.lock().unwrap();
+        symbols.get(symbol).map(|h| h.bbo.iter().cloned().collect()).unwrap_or_default()
+    }
+}
+
+// Only sees the trades that pass the pipeline's trade filter
+impl EventHandler for History {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.record_trade(symbol, ctx.timestampms.unwrap_or(ctx.received_ms), trade);
+        Ok(())
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.record_bbo(symbol, ctx.timestampms.unwrap_or(ctx.received_ms), bbo);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "history"
+    }
+}
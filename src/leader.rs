@@ -0,0 +1,581 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::analytics::anomaly::AnomalyEvent;
+use crate::analytics::candles::Candle;
+use crate::analytics::cross::CrossDivergence;
+use crate::analytics::depth::DepthSnapshot;
+use crate::analytics::dwell::DwellSnapshot;
+use crate::analytics::indicators::BookIndicators;
+use crate::analytics::ofi::OfiSnapshot;
+use crate::analytics::technical::TechnicalSnapshot;
+use crate::analytics::volatility::VolatilitySnapshot;
+use crate::analytics::vwap::TradeStatsSnapshot;
+use crate::error::GeminiError;
+use crate::handler::{EventHandler, HandlerEvent, HandlerMessage};
+use crate::metrics;
+use crate::models::{AuctionEvent, BestBidOffer, BlockTrade, FundingAmount, Quote, Trade};
+use crate::output::EventContext;
+
+// How often the leader renews the lock and publishes how far its sinks got, and how
+// often a standby tries to take it
+const TICK: Duration = Duration::from_millis(200);
+
+// How long after handing an event to a sink it counts as stored. Sinks batch their writes
+// for about a second, a leader that crashes loses what they still held, so the marks trail
+// by this much and a takeover stores those events again rather than leaving a gap.
+const SETTLE_MS: u64 = 2_000;
+
+// Where redundant collectors agree on which of them writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeaderLock {
+    // An exclusive lock on a file, which the OS releases when its holder exits
+    File(PathBuf),
+    // A Redis key with a TTL the leader keeps renewing, so a hung leader loses it too
+    Redis(String),
+}
+
+impl FromStr for LeaderLock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err(String::from("expected a lock file or a redis:// URL")),
+            url if url.starts_with("redis://") || url.starts_with("rediss://") => Ok(LeaderLock::Redis(url.to_string())),
+            path => Ok(LeaderLock::File(PathBuf::from(path))),
+        }
+    }
+}
+
+// Per symbol, the exchange time and event id of the newest event stored
+pub type Marks = HashMap<String, (u64, u64)>;
+
+// Events without an exchange timestamp are the book snapshot of a connection
+fn mark(ctx: &EventContext) -> Option<(u64, u64)> {
+    ctx.timestampms.map(|ms| (ms, ctx.event_id))
+}
+
+fn parse_marks(text: &str) -> Marks {
+    if text.trim().is_empty() {
+        return Marks::new();
+    }
+    serde_json::from_str(text).unwrap_or_else(|e| {
+        warn!(error = %e, "leader: ignoring the unreadable marks of the previous leader");
+        Marks::new()
+    })
+}
+
+struct Shared {
+    leader: AtomicBool,
+    // Receive time a standby holds events for, to store what the leader missed
+    retain_ms: u64,
+    // Gates that have not been dropped, the lock is kept until they are flushed
+    gates: AtomicUsize,
+    // What each gate stored
+    stored: Mutex<Vec<Marks>>,
+    // What the previous leader stored, a takeover continues after it
+    previous: Mutex<Marks>,
+}
+
+// Whether this process leads, shared by the election and the gates of the storage sinks
+#[derive(Clone)]
+pub struct Leadership {
+    shared: Arc<Shared>,
+}
+
+impl Leadership {
+    // Standbys hold events for twice the `ttl` of the lock, plus the time marks trail by
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                leader: AtomicBool::new(false),
+                retain_ms: 2 * ttl.as_millis() as u64 + SETTLE_MS,
+                gates: AtomicUsize::new(0),
+                stored: Mutex::new(Vec::new()),
+                previous: Mutex::new(Marks::new()),
+            }),
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.shared.leader.load(Ordering::Acquire)
+    }
+
+    // Wraps a sink so it only stores while this process leads
+    pub fn gate(&self, inner: Box<dyn EventHandler>) -> Standby {
+        let mut stored = self.shared.stored.lock().unwrap_or_else(|e| e.into_inner());
+        stored.push(Marks::new());
+        self.shared.gates.fetch_add(1, Ordering::AcqRel);
+        Standby {
+            inner,
+            leadership: self.clone(),
+            slot: stored.len() - 1,
+            leading: false,
+            held: VecDeque::new(),
+            unsettled: VecDeque::new(),
+            previous: Marks::new(),
+        }
+    }
+
+    // Per symbol, how far every gate that saw it got
+    pub fn marks(&self) -> Marks {
+        let stored = self.shared.stored.lock().unwrap_or_else(|e| e.into_inner());
+        let mut marks = Marks::new();
+        for (symbol, mark) in stored.iter().flatten() {
+            marks.entry(symbol.clone()).and_modify(|m| *m = (*m).min(*mark)).or_insert(*mark);
+        }
+        marks
+    }
+
+    fn record(&self, slot: usize, symbol: &str, mark: (u64, u64)) {
+        let mut stored = self.shared.stored.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(marks) = stored.get_mut(slot) {
+            let entry = marks.entry(symbol.to_string()).or_insert(mark);
+            *entry = (*entry).max(mark);
+        }
+    }
+
+    fn previous(&self) -> Marks {
+        self.shared.previous.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn take_over(&self, previous: Marks) {
+        *self.shared.previous.lock().unwrap_or_else(|e| e.into_inner()) = previous;
+        self.shared.leader.store(true, Ordering::Release);
+        metrics::global().leader.set(1.);
+    }
+
+    fn step_down(&self) {
+        self.shared.leader.store(false, Ordering::Release);
+        metrics::global().leader.set(0.);
+    }
+
+    // Whether any sink is gated, until the last of them is flushed
+    pub fn gated(&self) -> bool {
+        self.shared.gates.load(Ordering::Acquire) > 0
+    }
+}
+
+// A storage sink of a collector that may be the standby. While the leader stores, it
+// holds the last events instead; once this process takes over, it first stores the held
+// events newer than what the previous leader published, so the handover leaves no gap,
+// and skips the older ones still coming in on a connection that lagged behind.
+// After a crash the events of the last `SETTLE_MS` may be stored twice.
+pub struct Standby {
+    inner: Box<dyn EventHandler>,
+    leadership: Leadership,
+    slot: usize,
+    // Whether the last event was stored
+    leading: bool,
+    held: VecDeque<(String, EventContext, HandlerEvent)>,
+    // Marks of the events stored too recently to count, by receive time
+    unsettled: VecDeque<(u64, String, (u64, u64))>,
+    // What the previous leader stored, per symbol until this collector got past it
+    previous: Marks,
+}
+
+impl Standby {
+    fn handle(
+        &mut self,
+        symbol: &str,
+        ctx: &EventContext,
+        event: impl FnOnce() -> HandlerEvent,
+        store: impl FnOnce(&mut dyn EventHandler) -> Result<(), GeminiError>,
+    ) -> Result<(), GeminiError> {
+        if !self.leadership.is_leader() {
+            self.leading = false;
+            self.unsettled.clear();
+            self.hold(symbol, ctx, event());
+            return Ok(());
+        }
+        if !self.leading {
+            self.catch_up()?;
+        }
+        if !self.is_new(symbol, ctx) {
+            return Ok(());
+        }
+        store(self.inner.as_mut())?;
+        self.stored(symbol, ctx);
+        Ok(())
+    }
+
+    // Until this collector streams past the previous leader, its snapshot events are not
+    // new either
+    fn is_new(&self, symbol: &str, ctx: &EventContext) -> bool {
+        match (mark(ctx), self.previous.get(symbol)) {
+            (Some(mark), Some(last)) => mark > *last,
+            (None, Some(_)) => false,
+            (_, None) => true,
+        }
+    }
+
+    fn stored(&mut self, symbol: &str, ctx: &EventContext) {
+        let Some(mark) = mark(ctx) else {
+            return;
+        };
+        self.previous.remove(symbol);
+        self.unsettled.push_back((ctx.received_ms, symbol.to_string(), mark));
+        while let Some((_, symbol, mark)) = self.unsettled.pop_front_if(|(received_ms, _, _)| *received_ms + SETTLE_MS <= ctx.received_ms) {
+            self.leadership.record(self.slot, &symbol, mark);
+        }
+    }
+
+    fn hold(&mut self, symbol: &str, ctx: &EventContext, event: HandlerEvent) {
+        let retain_ms = self.leadership.shared.retain_ms;
+        while self.held.front().is_some_and(|(_, held, _)| held.received_ms + retain_ms < ctx.received_ms) {
+            self.held.pop_front();
+        }
+        self.held.push_back((symbol.to_string(), *ctx, event));
+    }
+
+    // Stores the held events the previous leader did not
+    fn catch_up(&mut self) -> Result<(), GeminiError> {
+        self.leading = true;
+        self.previous = self.leadership.previous();
+        let mut stored = 0;
+        for (symbol, ctx, event) in std::mem::take(&mut self.held) {
+            if !self.is_new(&symbol, &ctx) {
+                continue;
+            }
+            HandlerMessage::Event { symbol: symbol.clone(), ctx, event }.deliver(self.inner.as_mut())?;
+            self.stored(&symbol, &ctx);
+            stored += 1;
+        }
+        info!(sink = self.inner.name(), stored, "leader: storing, caught up on the held events");
+        Ok(())
+    }
+}
+
+impl Drop for Standby {
+    fn drop(&mut self) {
+        self.leadership.shared.gates.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl EventHandler for Standby {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Trade(trade.clone()), |inner| inner.on_trade(symbol, ctx, trade))
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Quote(quote.clone()), |inner| inner.on_quote(symbol, ctx, quote))
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::BookUpdate(bbo.clone()), |inner| inner.on_book_update(symbol, ctx, bbo))
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::BlockTrade(trade.clone()), |inner| inner.on_block_trade(symbol, ctx, trade))
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Auction(auction.clone()), |inner| inner.on_auction(symbol, ctx, auction))
+    }
+
+    fn on_stats(&mut self, symbol: &str, ctx: &EventContext, stats: &TradeStatsSnapshot) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Stats(stats.clone()), |inner| inner.on_stats(symbol, ctx, stats))
+    }
+
+    fn on_candle(&mut self, symbol: &str, ctx: &EventContext, candle: &Candle) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Candle(candle.clone()), |inner| inner.on_candle(symbol, ctx, candle))
+    }
+
+    fn on_technical(&mut self, symbol: &str, ctx: &EventContext, technical: &TechnicalSnapshot) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Technical(technical.clone()), |inner| inner.on_technical(symbol, ctx, technical))
+    }
+
+    fn on_indicators(&mut self, symbol: &str, ctx: &EventContext, indicators: &BookIndicators) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Indicators(indicators.clone()), |inner| inner.on_indicators(symbol, ctx, indicators))
+    }
+
+    fn on_depth(&mut self, symbol: &str, ctx: &EventContext, depth: &DepthSnapshot) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Depth(depth.clone()), |inner| inner.on_depth(symbol, ctx, depth))
+    }
+
+    fn on_volatility(&mut self, symbol: &str, ctx: &EventContext, volatility: &VolatilitySnapshot) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Volatility(volatility.clone()), |inner| inner.on_volatility(symbol, ctx, volatility))
+    }
+
+    fn on_dwell(&mut self, symbol: &str, ctx: &EventContext, dwell: &DwellSnapshot) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Dwell(dwell.clone()), |inner| inner.on_dwell(symbol, ctx, dwell))
+    }
+
+    fn on_ofi(&mut self, symbol: &str, ctx: &EventContext, ofi: &OfiSnapshot) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Ofi(ofi.clone()), |inner| inner.on_ofi(symbol, ctx, ofi))
+    }
+
+    fn on_anomaly(&mut self, symbol: &str, ctx: &EventContext, anomaly: &AnomalyEvent) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Anomaly(anomaly.clone()), |inner| inner.on_anomaly(symbol, ctx, anomaly))
+    }
+
+    fn on_cross(&mut self, symbol: &str, ctx: &EventContext, cross: &CrossDivergence) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Cross(cross.clone()), |inner| inner.on_cross(symbol, ctx, cross))
+    }
+
+    fn on_funding(&mut self, symbol: &str, ctx: &EventContext, funding: &FundingAmount) -> Result<(), GeminiError> {
+        self.handle(symbol, ctx, || HandlerEvent::Funding(funding.clone()), |inner| inner.on_funding(symbol, ctx, funding))
+    }
+
+    fn on_disconnect(&mut self, symbol: &str, reason: &str) -> Result<(), GeminiError> {
+        match self.leadership.is_leader() {
+            true => self.inner.on_disconnect(symbol, reason),
+            false => Ok(()),
+        }
+    }
+
+    fn on_tick(&mut self, now_ms: u64) -> Result<(), GeminiError> {
+        match self.leadership.is_leader() {
+            true => self.inner.on_tick(now_ms),
+            false => Ok(()),
+        }
+    }
+
+    // Once the sink flushed, everything it got is stored
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        self.inner.flush()?;
+        for (_, symbol, mark) in std::mem::take(&mut self.unsettled) {
+            self.leadership.record(self.slot, &symbol, mark);
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+// The lock behind an election. Its calls block, the election runs on its own thread.
+pub trait Lease: Send {
+    // Takes the lock if nobody holds it, with the marks its last holder published
+    fn acquire(&mut self) -> Result<Option<Marks>, GeminiError>;
+    // Keeps the lock and publishes `marks`, false once another collector has it
+    fn renew(&mut self, marks: &Marks) -> Result<bool, GeminiError>;
+    // Publishes the final marks and lets a standby take over right away
+    fn release(&mut self, marks: &Marks) -> Result<(), GeminiError>;
+}
+
+// `key` names the Redis lock, the marks are kept beside it under `KEY:marks`
+pub fn open(lock: &LeaderLock, key: &str, ttl: Duration) -> Result<Box<dyn Lease>, GeminiError> {
+    match lock {
+        LeaderLock::File(path) => Ok(Box::new(FileLease {
+            path: path.clone(),
+            file: None,
+            published: String::new(),
+        })),
+        #[cfg(feature = "redis")]
+        LeaderLock::Redis(url) => Ok(Box::new(redis_lease::RedisLease::new(url, key, ttl)?)),
+        #[cfg(not(feature = "redis"))]
+        LeaderLock::Redis(_) => {
+            let _ = (key, ttl);
+            Err(GeminiError::Config(String::from("a redis:// leader lock needs the redis feature")))
+        },
+    }
+}
+
+// Holds the file locked while leading and keeps the marks in it
+struct FileLease {
+    path: PathBuf,
+    file: Option<File>,
+    published: String,
+}
+
+impl Lease for FileLease {
+    fn acquire(&mut self) -> Result<Option<Marks>, GeminiError> {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&self.path)?;
+        match file.try_lock() {
+            Ok(()) => {},
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        let previous = parse_marks(&text);
+        self.published = text;
+        self.file = Some(file);
+        Ok(Some(previous))
+    }
+
+    fn renew(&mut self, marks: &Marks) -> Result<bool, GeminiError> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(false);
+        };
+        let text = serde_json::to_string(marks).unwrap_or_default();
+        if text != self.published {
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(text.as_bytes())?;
+            self.published = text;
+        }
+        Ok(true)
+    }
+
+    fn release(&mut self, marks: &Marks) -> Result<(), GeminiError> {
+        let published = self.renew(marks).map(|_| ());
+        if let Some(file) = self.file.take() {
+            file.unlock()?;
+        }
+        published
+    }
+}
+
+#[cfg(feature = "redis")]
+mod redis_lease {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use redis::{Client, Connection, RedisError};
+
+    use super::{parse_marks, Lease, Marks};
+    use crate::error::GeminiError;
+
+    // Extends the lease and publishes the marks, only while the key still names this collector
+    const RENEW: &str = "if redis.call('get', KEYS[1]) == ARGV[1] then \
+        redis.call('pexpire', KEYS[1], ARGV[2]) redis.call('set', KEYS[2], ARGV[3]) return 1 end return 0";
+    const RELEASE: &str = "if redis.call('get', KEYS[1]) == ARGV[1] then \
+        redis.call('set', KEYS[2], ARGV[2]) redis.call('del', KEYS[1]) end return 0";
+
+    pub struct RedisLease {
+        client: Client,
+        connection: Option<Connection>,
+        key: String,
+        marks_key: String,
+        // Value of the key while this collector leads
+        owner: String,
+        ttl_ms: u64,
+    }
+
+    impl RedisLease {
+        pub fn new(url: &str, key: &str, ttl: Duration) -> Result<Self, GeminiError> {
+            let client = Client::open(url).map_err(|e| GeminiError::Config(format!("leader lock {}: {}", url, e)))?;
+            let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+            Ok(Self {
+                client,
+                connection: None,
+                key: key.to_string(),
+                marks_key: format!("{}:marks", key),
+                owner: format!("{}:{}", std::process::id(), started),
+                ttl_ms: (ttl.as_millis() as u64).max(1),
+            })
+        }
+
+        // Runs `f` on the connection, dropping it on an error so the next call reconnects
+        fn with<T>(&mut self, f: impl FnOnce(&mut Connection, &Self) -> Result<T, RedisError>) -> Result<T, GeminiError> {
+            let mut connection = match self.connection.take() {
+                Some(connection) => connection,
+                None => self.client.get_connection().map_err(lock_error)?,
+            };
+            let result = f(&mut connection, self).map_err(lock_error)?;
+            self.connection = Some(connection);
+            Ok(result)
+        }
+    }
+
+    impl Lease for RedisLease {
+        fn acquire(&mut self) -> Result<Option<Marks>, GeminiError> {
+            self.with(|connection, lease| {
+                let taken: Option<String> = redis::cmd("SET")
+                    .arg(&lease.key).arg(&lease.owner).arg("NX").arg("PX").arg(lease.ttl_ms)
+                    .query(connection)?;
+                if taken.is_none() {
+                    return Ok(None);
+                }
+                let marks: Option<String> = redis::cmd("GET").arg(&lease.marks_key).query(connection)?;
+                Ok(Some(parse_marks(&marks.unwrap_or_default())))
+            })
+        }
+
+        fn renew(&mut self, marks: &Marks) -> Result<bool, GeminiError> {
+            let marks = serde_json::to_string(marks).unwrap_or_default();
+            self.with(|connection, lease| {
+                let kept: i64 = redis::cmd("EVAL")
+                    .arg(RENEW).arg(2).arg(&lease.key).arg(&lease.marks_key)
+                    .arg(&lease.owner).arg(lease.ttl_ms).arg(&marks)
+                    .query(connection)?;
+                Ok(kept == 1)
+            })
+        }
+
+        fn release(&mut self, marks: &Marks) -> Result<(), GeminiError> {
+            let marks = serde_json::to_string(marks).unwrap_or_default();
+            self.with(|connection, lease| {
+                redis::cmd("EVAL")
+                    .arg(RELEASE).arg(2).arg(&lease.key).arg(&lease.marks_key)
+                    .arg(&lease.owner).arg(&marks)
+                    .exec(connection)
+            })
+        }
+    }
+
+    fn lock_error(e: RedisError) -> GeminiError {
+        GeminiError::Sink(format!("leader lock: {}", e))
+    }
+}
+
+// Campaigns for the lock until the gates are flushed. A leader then publishes the final
+// marks and releases it, so a restart hands over cleanly.
+pub fn elect(lease: Box<dyn Lease>, leadership: Leadership, ttl: Duration, shutdown: CancellationToken) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || campaign(lease, leadership, ttl, shutdown))
+}
+
+fn campaign(mut lease: Box<dyn Lease>, leadership: Leadership, ttl: Duration, shutdown: CancellationToken) {
+    let tick = (ttl / 3).min(TICK);
+    let mut renewed = Instant::now();
+    // Only the first of a run of failures is logged
+    let mut failing = false;
+    metrics::global().leader.set(0.);
+    loop {
+        if !leadership.gated() {
+            if leadership.is_leader() {
+                match lease.release(&leadership.marks()) {
+                    Ok(()) => info!("leader: released the lock"),
+                    Err(e) => warn!(error = %e, "leader: cannot release the lock"),
+                }
+                leadership.step_down();
+            }
+            return;
+        }
+        let result = match leadership.is_leader() {
+            true => lease.renew(&leadership.marks()).map(|kept| {
+                renewed = Instant::now();
+                if !kept {
+                    warn!("leader: another collector took the lock, standing by");
+                    leadership.step_down();
+                }
+            }),
+            false if shutdown.is_cancelled() => Ok(()),
+            false => lease.acquire().map(|previous| {
+                if let Some(previous) = previous {
+                    info!(symbols = previous.len(), "leader: took the lock, writing to the sinks");
+                    renewed = Instant::now();
+                    leadership.take_over(previous);
+                }
+            }),
+        };
+        match result {
+            Ok(()) => failing = false,
+            Err(e) => {
+                if !failing {
+                    warn!(error = %e, "leader: lock failed");
+                }
+                failing = true;
+                // Past the TTL another collector may hold the lock
+                if leadership.is_leader() && renewed.elapsed() >= ttl {
+                    warn!("leader: could not renew the lock in time, standing by");
+                    leadership.step_down();
+                    let _ = lease.release(&leadership.marks());
+                }
+            },
+        }
+        std::thread::sleep(tick);
+    }
+}
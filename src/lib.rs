@@ -0,0 +1,134 @@
+#[cfg(feature = "runtime")]
+pub mod alerts;
+#[cfg(feature = "runtime")]
+pub mod analytics;
+#[cfg(feature = "runtime")]
+pub mod auth;
+#[cfg(feature = "runtime")]
+pub mod balances;
+#[cfg(feature = "runtime")]
+pub mod bench;
+#[cfg(feature = "runtime")]
+pub mod blotter;
+#[cfg(feature = "runtime")]
+pub mod book;
+#[cfg(feature = "runtime")]
+pub mod book_check;
+#[cfg(feature = "runtime")]
+pub mod candle_download;
+#[cfg(feature = "runtime")]
+pub mod capture;
+#[cfg(feature = "runtime")]
+pub mod check;
+#[cfg(feature = "runtime")]
+pub mod client;
+#[cfg(feature = "runtime")]
+pub mod close;
+#[cfg(feature = "runtime")]
+pub mod config;
+#[cfg(feature = "runtime")]
+pub mod control;
+#[cfg(feature = "runtime")]
+pub mod daemon;
+#[cfg(feature = "runtime")]
+pub mod dedup;
+#[cfg(feature = "runtime")]
+pub mod downsample;
+#[cfg(feature = "runtime")]
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "runtime")]
+pub mod exchange;
+#[cfg(feature = "runtime")]
+pub mod exchange_candles;
+#[cfg(feature = "runtime")]
+pub mod failover;
+#[cfg(feature = "runtime")]
+pub mod files;
+#[cfg(feature = "runtime")]
+pub mod funding;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "runtime")]
+pub mod handler;
+#[cfg(feature = "runtime")]
+pub mod history;
+#[cfg(feature = "runtime")]
+pub mod leader;
+#[cfg(feature = "runtime")]
+pub mod logging;
+#[cfg(feature = "runtime")]
+pub mod market_state;
+#[cfg(feature = "runtime")]
+pub mod metrics;
+#[cfg(feature = "mock-server")]
+pub mod mock;
+pub mod models;
+#[cfg(feature = "notify")]
+pub mod notify;
+#[cfg(feature = "runtime")]
+pub mod order_events;
+#[cfg(feature = "runtime")]
+pub mod output;
+#[cfg(feature = "runtime")]
+pub mod paper;
+#[cfg(feature = "runtime")]
+pub mod pipeline;
+#[cfg(feature = "runtime")]
+pub mod positions;
+#[cfg(feature = "runtime")]
+pub mod precision;
+#[cfg(feature = "runtime")]
+pub mod proxy;
+#[cfg(feature = "runtime")]
+pub mod quarantine;
+#[cfg(feature = "runtime")]
+pub mod queue;
+#[cfg(feature = "runtime")]
+pub mod rest;
+#[cfg(feature = "runtime")]
+pub mod routing;
+#[cfg(feature = "runtime")]
+pub mod schema;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "runtime")]
+pub mod serve;
+#[cfg(feature = "runtime")]
+pub mod shard;
+#[cfg(feature = "runtime")]
+pub mod shared;
+#[cfg(feature = "runtime")]
+pub mod sinks;
+#[cfg(feature = "runtime")]
+pub mod state;
+#[cfg(feature = "runtime")]
+pub mod status;
+#[cfg(feature = "runtime")]
+pub mod strategy;
+#[cfg(feature = "runtime")]
+pub mod summary;
+#[cfg(feature = "runtime")]
+pub mod symbols;
+#[cfg(feature = "runtime")]
+pub mod tape;
+#[cfg(feature = "runtime")]
+pub mod throttle;
+#[cfg(feature = "runtime")]
+pub mod ticks;
+#[cfg(feature = "runtime")]
+pub mod tls;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "runtime")]
+pub mod wire;
+
+#[cfg(feature = "runtime")]
+pub use client::Client;
+#[cfg(feature = "runtime")]
+pub use error::GeminiError;
+#[cfg(feature = "runtime")]
+pub use market_state::MarketState;
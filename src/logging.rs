@@ -0,0 +1,65 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+use crate::error::GeminiError;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+    // Plain lines led by a syslog priority such as <4>, without times, for the journal
+    Journald,
+}
+
+// Operational logs go to stderr so they never mix with the data lines on stdout.
+// `filter` is a level like `debug` or per-module directives like `warn,order_book::client=debug`.
+pub fn init(filter: &str, format: LogFormat, color: bool) -> Result<(), GeminiError> {
+    let filter = EnvFilter::try_new(filter).map_err(|e| GeminiError::Config(format!("log level `{}`: {}", filter, e)))?;
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_target(false);
+    let installed = match format {
+        LogFormat::Text => builder.with_ansi(color).try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).with_current_span(true).with_span_list(false).try_init(),
+        LogFormat::Journald => builder.with_ansi(false).event_format(Journald).try_init(),
+    };
+    installed.map_err(|e| GeminiError::Config(format!("logging: {}", e)))
+}
+
+// The journal stamps every line itself and reads the level from the priority prefix
+struct Journald;
+
+impl<S, N> FormatEvent<S, N> for Journald
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let priority = match *event.metadata().level() {
+            Level::ERROR => 3,
+            Level::WARN => 4,
+            Level::INFO => 6,
+            Level::DEBUG | Level::TRACE => 7,
+        };
+        write!(writer, "<{}>", priority)?;
+        for span in ctx.event_scope().into_iter().flat_map(|scope| scope.from_root()) {
+            let extensions = span.extensions();
+            match extensions.get::<FormattedFields<N>>().filter(|f| !f.is_empty()) {
+                Some(fields) => write!(writer, "{}{{{}}}: ", span.name(), fields)?,
+                None => write!(writer, "{}: ", span.name())?,
+            }
+        }
+        ctx.format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
@@ -1,75 +1,1161 @@
-use url;
-use clap::Parser;
-
-use futures_util::{future, pin_mut, StreamExt};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rust_decimal::Decimal;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use order_book::alerts::{AlertActions, AlertSink};
+use order_book::auth::Signer;
+use order_book::balances;
+use order_book::bench;
+use order_book::blotter::Blotter;
+use order_book::book_check::{BookChecker, Verdict};
+use order_book::candle_download;
+use order_book::capture::{CaptureFiles, CaptureReader, CapturedFrame};
+use order_book::check;
+use order_book::client::{self, ConnectOptions, FeedEvent, ParsedFrame};
+use order_book::control::{ControlCommand, ControlRequest, LocalControl};
+use order_book::daemon::{self, DumpRequests, PidFile, Reloads};
+use order_book::exchange::{self, Exchange};
+use order_book::exchange_candles::{self, CandleSource};
+use order_book::funding;
+use order_book::history::History;
+use order_book::leader::{self, Leadership};
+use order_book::logging;
+use order_book::metrics;
+use order_book::models::{MarketMessage, OrderSide};
+use order_book::files::PathTemplate;
+use order_book::output::{Formatter, OutputFormat, Printer};
+use order_book::order_events;
+use order_book::paper::{self, PaperHandler, PaperOptions, PaperTrader};
+use order_book::pipeline::{Pipeline, TradeFilter};
+use order_book::rest::RestClient;
+use order_book::shard::{self, Shards};
+use order_book::state;
+use order_book::status;
+use order_book::strategy::mm::{MarketMaker, MarketMakerOptions};
+use order_book::strategy::{self, StrategyRunner};
+use order_book::summary::{ReportFormat, SessionSummary};
+use order_book::symbols;
+use order_book::tape::Tape;
+use order_book::throttle::ThrottleOptions;
+use order_book::ticks::Increments;
+use order_book::quarantine::Quarantine;
+use order_book::queue;
+use order_book::GeminiError;
+
+mod cli;
+use cli::{BacktestArgs, BenchArgs, CandlesArgs, CheckArgs, Cli, Command, ExportArgs, ExportFormat, MarketMakeArgs, PaperArgs, TradeAction, TradeArgs};
 
-mod models;
-use models::*;
+fn new_pipeline(cli: &Cli, increments: HashMap<String, Increments>) -> Pipeline {
+    let mut pipeline = Pipeline::new(cli.queue_options())
+        .with_increments(increments)
+        .top_of_book(cli.feed.top_of_book)
+        .max_book_levels(cli.max_book_levels.map(|n| n as usize))
+        .log_latency(cli.latency_interval)
+        .max_clock_skew(cli.max_clock_skew)
+        .with_trade_filter(TradeFilter {
+            min_size: cli.min_trade_size,
+            min_notional: cli.min_notional,
+        })
+        .with_routes(cli.route.clone());
+    if !cli.spread_thresholds.is_empty() {
+        pipeline = pipeline.with_spread_thresholds(cli.spread_thresholds.clone());
+    }
+    if !cli.vwap_windows.is_empty() {
+        pipeline = pipeline.with_trade_stats(cli.vwap_windows.clone(), cli.stats_interval);
+    }
+    if cli.indicators || !cli.imbalance_bands.is_empty() {
+        pipeline = pipeline.with_indicators(cli.imbalance_bands.clone());
+    }
+    if cli.depth_levels.is_some() || !cli.depth_sizes.is_empty() {
+        pipeline = pipeline.with_depth(cli.depth_levels.map(|n| n as usize), cli.depth_sizes.clone());
+    }
+    if !cli.crosses.is_empty() {
+        pipeline = pipeline.with_crosses(cli.crosses.clone(), cli.cross_threshold_bps);
+    }
+    if let Some(interval) = cli.candles {
+        if cli.candle_source == CandleSource::Local {
+            pipeline = pipeline.with_candles(interval);
+        }
+        pipeline = pipeline.with_technical(cli.technical.clone());
+    }
+    if !cli.ofi_windows.is_empty() {
+        pipeline = pipeline.with_order_flow(cli.ofi_windows.clone(), cli.stats_interval);
+    }
+    if !cli.volatility_windows.is_empty() {
+        pipeline = pipeline.with_volatility(cli.volatility_sample, cli.volatility_windows.clone());
+    }
+    if let Some(options) = cli.anomaly_options() {
+        pipeline = pipeline.with_anomalies(options, cli.anomaly_window);
+    }
+    if let Some(window) = cli.stale_quotes {
+        pipeline = pipeline.with_stale_quotes(window);
+    }
+    if let Some(window) = cli.dwell_window {
+        pipeline = pipeline.with_dwell(window, cli.flicker_threshold, cli.stats_interval);
+    }
+    if let (true, None, Ok(rest)) = (cli.gap_fill, &cli.replay, cli.endpoint().and_then(|endpoint| RestClient::new(&endpoint))) {
+        pipeline = pipeline.with_gap_fill(rest, cli.exchange, cli.gap_fill_max);
+    }
+    if let Some(bucket) = cli.downsample {
+        pipeline = pipeline.with_downsample(bucket);
+    }
+    if let (Some(_), None) = (&cli.leader_lock, &cli.replay) {
+        pipeline = pipeline.with_leadership(Leadership::new(cli.leader_ttl));
+    }
+    if cli.throttle.is_some() || !cli.sample.is_empty() {
+        pipeline = pipeline.with_throttle(ThrottleOptions {
+            rate: cli.throttle,
+            samples: cli.sample.clone(),
+        });
+    }
+    pipeline
+}
 
-#[derive(Parser)]
-struct Cli {
-    #[arg(long)]
-    symbol: String,
+async fn add_sinks(cli: &Cli, pipeline: &mut Pipeline, shutdown: &CancellationToken) -> Result<(), GeminiError> {
+    if let Some(addr) = cli.serve {
+        let (handler, server) = order_book::serve::bind(addr, cli.serve_format, shutdown.clone()).await?;
+        tokio::spawn(server);
+        pipeline.add_output(Box::new(handler));
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(addr) = cli.grpc {
+        let (handler, server) = order_book::grpc::bind(addr, shutdown.clone())?;
+        tokio::spawn(server);
+        pipeline.add_output(Box::new(handler));
+    }
+    // One signer for the key, the balances poller and the order events stream share its nonces
+    let signer = (cli.balances.is_some() || cli.blotter.is_some() || cli.positions)
+        .then(|| Signer::new(&cli.credentials))
+        .transpose()?;
+    if let (Some(interval), Some(signer)) = (cli.balances, &signer) {
+        let signer = signer.clone();
+        let rest = RestClient::new(&cli.endpoint()?)?;
+        tokio::spawn(balances::poll(rest, signer, interval, pipeline.market_state(), shutdown.clone()));
+    }
+    if let (true, Some(signer)) = (cli.blotter.is_some() || cli.positions, signer) {
+        let mut blotter = cli.blotter.as_deref().map(|path| Blotter::open(path, cli.blotter_format)).transpose()?;
+        let positions = cli.positions.then(|| pipeline.positions());
+        let (fills_tx, mut fills) = mpsc::channel(64);
+        tokio::spawn(order_events::stream(cli.endpoint()?, signer, cli.reconnect.clone(), fills_tx, shutdown.clone()));
+        // Ends once the order events stream does, on shutdown at the latest
+        pipeline.add_task(tokio::spawn(async move {
+            while let Some(fill) = fills.recv().await {
+                info!(symbol = %fill.symbol, side = %fill.side, price = %fill.price, amount = %fill.amount, order_id = %fill.order_id, "fill");
+                if let Some(positions) = &positions {
+                    positions.on_fill(&fill);
+                }
+                if let Some(Err(e)) = blotter.as_mut().map(|blotter| blotter.write(&fill)) {
+                    error!(error = %e, "blotter: cannot write fill");
+                }
+            }
+            if let Some(Err(e)) = blotter.as_mut().map(Blotter::close) {
+                error!(error = %e, "blotter: cannot finish");
+            }
+        }));
+    }
+    let history = cli.history.map(|window| History::new(window).with_max_entries(cli.history_max_entries));
+    if let Some(history) = &history {
+        pipeline.add_handler(Box::new(history.clone()));
+    }
+    if let Some(port) = cli.status_port {
+        let feed = pipeline.feed_status();
+        feed.expect(&cli.symbol);
+        let addr = SocketAddr::new(cli.status_bind, port);
+        let server = status::bind(addr, feed, pipeline.market_state(), pipeline.spread(), history, pipeline.positions(), cli.stale_after).await?;
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                error!(error = %e, "status server failed");
+            }
+        });
+    }
+    let actions = AlertActions {
+        webhook: cli.alert_webhook.clone(),
+        command: cli.alert_command.clone(),
+        chats: cli.alert_chats.clone(),
+    };
+    // Anomalies go through the alert actions whenever there are any
+    let anomalies = (cli.anomaly_options().is_some() || cli.stale_quotes.is_some())
+        && (actions.webhook.is_some() || actions.command.is_some() || !actions.chats.is_empty());
+    if !cli.alerts.is_empty() || anomalies {
+        let sink = AlertSink::new(cli.alerts.clone(), actions, cli.alert_cooldown).with_anomalies(anomalies);
+        pipeline.add_handler(Box::new(sink));
+    }
+    #[cfg(feature = "notify")]
+    if let Some(threshold) = cli.notify_trades_above {
+        pipeline.add_output(Box::new(order_book::notify::TradeNotifier::new(threshold)));
+    }
+    #[cfg(feature = "scripting")]
+    if let Some(path) = &cli.script {
+        pipeline.add_handler(Box::new(order_book::script::ScriptHandler::load(path)?));
+    }
+    if let Some(destination) = &cli.fix {
+        pipeline.add_output(Box::new(order_book::sinks::fix::FixSink::open(destination, cli.fix_options())?));
+    }
+    if let Some(url) = &cli.influx_url {
+        pipeline.add_sink(Box::new(order_book::sinks::influx::InfluxSink::new(url, cli.influx_options())?));
+    }
+    if let Some(url) = &cli.http_url {
+        pipeline.add_sink(Box::new(order_book::sinks::http::HttpSink::new(url, cli.http_options())?));
+    }
+    #[cfg(feature = "nats")]
+    if let Some(url) = &cli.nats_url {
+        pipeline.add_sink(Box::new(order_book::sinks::nats::NatsSink::connect(url, cli.nats_options()).await?));
+    }
+    #[cfg(feature = "redis")]
+    if let Some(url) = &cli.redis_url {
+        pipeline.add_sink(Box::new(order_book::sinks::redis::RedisSink::new(url, cli.redis_options())?));
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = &cli.sqlite {
+        pipeline.add_sink(Box::new(order_book::sinks::sqlite::SqliteSink::open(path)?));
+    }
+    #[cfg(feature = "kafka")]
+    if let (Some(brokers), Some(topic)) = (&cli.kafka_brokers, &cli.kafka_topic) {
+        pipeline.add_sink(Box::new(order_book::sinks::kafka::KafkaSink::new(brokers, topic)?));
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(dir) = &cli.parquet {
+        pipeline.add_sink(Box::new(order_book::sinks::parquet::ParquetSink::create(dir)?));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(url) = &cli.postgres_url {
+        pipeline.add_sink(Box::new(order_book::sinks::postgres::PostgresSink::new(url)?));
+    }
+    #[cfg(feature = "zmq")]
+    if let Some(endpoint) = &cli.zmq_bind {
+        pipeline.add_sink(Box::new(order_book::sinks::zmq::ZmqSink::bind(endpoint, cli.zmq_format).await?));
+    }
+    #[cfg(feature = "mqtt")]
+    if let Some(url) = &cli.mqtt_url {
+        pipeline.add_sink(Box::new(order_book::sinks::mqtt::MqttSink::connect(url, cli.mqtt_options())?));
+    }
+    if let (Some(lock), Some(leadership)) = (&cli.leader_lock, pipeline.leadership()) {
+        if !leadership.gated() {
+            warn!("--leader-lock without a storage sink, every collector writes its own output");
+        } else {
+            let lease = leader::open(lock, &cli.leader_key, cli.leader_ttl)?;
+            pipeline.add_task(leader::elect(lease, leadership, cli.leader_ttl, shutdown.clone()));
+        }
+    }
+    Ok(())
 }
 
 #[tokio::main]
-async fn main() {
-    let cli = Cli::parse();
-
-    let ws_url = format!("wss://api.gemini.com/v1/marketdata/{}?top_of_book=true", cli.symbol);
-    let url = url::Url::parse(&ws_url).unwrap();
-
-    let (ws_stream, _)  = connect_async(url).await.expect("Failed to connect");
-    println!("WebSocket handshake has been completed!");
-
-    let (write, read) = ws_stream.split();
-    let bbo = Arc::new(Mutex::new(BestBidOffer::new()));
-    let ws_to_stdout = {
-        read.for_each(|message| async {
-            let m = message.unwrap();
-            if m.is_empty() {
-                return;
+async fn main() -> ExitCode {
+    let cli = match Cli::load() {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let color = !cli.no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal();
+    if let Err(e) = logging::init(&cli.log_level, cli.log_format, color) {
+        eprintln!("error: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    if cli.print_schema {
+        let _ = writeln!(std::io::stdout(), "{}", serde_json::to_string_pretty(&order_book::schema::jsonl_schema()).unwrap_or_default());
+        return ExitCode::SUCCESS;
+    }
+
+    if let Some(Command::Trade(args)) = &cli.command {
+        return match trade(&cli, args).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                error!("{}", e);
+                ExitCode::FAILURE
             }
-            let data = m.into_data();
-            let event = Event::new(data.as_slice());
-            for e in event.events {
-                match e {
-                    Event::Trade(t) => {
-                        let dollar_amt = t.amount * t.price;
-                        let msg = format!("{:?} ${}\n", t, dollar_amt);
-                        tokio::io::stdout().write_all(msg.as_bytes()).await.unwrap();
-                    },
-                    Event::Quote(q) => {
-                        match q.side {
-                            MarketSide::Ask => {
-                                {
-                                    let mut bbo = bbo.lock().unwrap();
-                                    bbo.best_offer = q.price;
-                                    bbo.ask_amount_remaining = q.remaining;
-                                }
-                            },
-                            MarketSide::Bid => {
-                                {
-                                    let mut bbo = bbo.lock().unwrap();
-                                    bbo.best_bid = q.price;
-                                    bbo.bid_amount_remaining = q.remaining;
-                                }
-                            },
-                            MarketSide::Unknown => {},
+        };
+    }
+
+    if let Some(Command::Candles(args)) = &cli.command {
+        return match candles(&cli, args).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                error!("{}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if let Some(Command::Check(args)) = &cli.command {
+        return match check(&cli, args).await {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(e) => {
+                error!("{}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let checked = match cli.list_symbols {
+        true => list_symbols(&cli).await.map(|_| ()),
+        false if cli.replay.is_none() => check_symbols(&cli).await,
+        false => Ok(()),
+    };
+    if let Err(e) = checked {
+        error!("{}", e);
+        return ExitCode::FAILURE;
+    }
+    if cli.list_symbols {
+        return ExitCode::SUCCESS;
+    }
+    // Removed again when main returns
+    let _pid_file = match cli.pid_file.as_deref().map(PidFile::create).transpose() {
+        Ok(pid_file) => pid_file,
+        Err(e) => {
+            error!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let metrics_addr = cli.metrics_addr.or(cli.metrics_port.map(|port| SocketAddr::from(([127, 0, 0, 1], port))));
+    if let Some(addr) = metrics_addr {
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(addr).await {
+                error!(error = %e, "metrics server failed");
+            }
+        });
+    }
+
+    let shutdown = CancellationToken::new();
+    {
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            shutdown.cancel();
+        });
+    }
+    if let (true, Some(interval)) = (cli.daemon, daemon::watchdog_interval()) {
+        tokio::spawn(daemon::watchdog(interval, shutdown.clone()));
+    }
+
+    if !wait_for_start(&cli, &shutdown).await {
+        return ExitCode::SUCCESS;
+    }
+    schedule_stop(&cli, shutdown.clone());
+
+    let result = run_with_output(cli, shutdown).await;
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Holds off until `--start-at`, false when interrupted while waiting
+async fn wait_for_start(cli: &Cli, shutdown: &CancellationToken) -> bool {
+    let Some(start) = cli.start_at else {
+        return true;
+    };
+    let Ok(wait) = start.duration_since(SystemTime::now()) else {
+        return true;
+    };
+    info!(
+        wait = %humantime::format_duration(Duration::from_secs(wait.as_secs())),
+        start = %humantime::format_rfc3339_seconds(start),
+        "waiting for the start time"
+    );
+    tokio::select! {
+        _ = shutdown.cancelled() => false,
+        _ = tokio::time::sleep(wait) => true,
+    }
+}
+
+// Ends the run through `shutdown`, so sinks flush and summaries print as on Ctrl-C,
+// once `--duration` has passed or `--until` is reached, whichever comes first
+fn schedule_stop(cli: &Cli, shutdown: CancellationToken) {
+    let until = cli.until.map(|t| t.duration_since(SystemTime::now()).unwrap_or_default());
+    let Some(remaining) = [cli.duration, until].into_iter().flatten().min() else {
+        return;
+    };
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = shutdown.cancelled() => {},
+            _ = tokio::time::sleep(remaining) => {
+                info!("run window is over");
+                shutdown.cancel();
+            },
+        }
+    });
+}
+
+async fn list_symbols(cli: &Cli) -> Result<(), GeminiError> {
+    let mut known = RestClient::new(&cli.endpoint()?)?.symbols().await?;
+    known.sort();
+    for symbol in known {
+        println!("{}", symbol);
+    }
+    Ok(())
+}
+
+// One authenticated order request, the resulting order state goes to stdout
+async fn trade(cli: &Cli, args: &TradeArgs) -> Result<(), GeminiError> {
+    let signer = Signer::new(&cli.credentials)?;
+    let rest = RestClient::new(&cli.endpoint()?)?;
+    let status = match &args.action {
+        TradeAction::Buy(order) => rest.new_order(&signer, &order.new_order(OrderSide::Buy)).await?,
+        TradeAction::Sell(order) => rest.new_order(&signer, &order.new_order(OrderSide::Sell)).await?,
+        TradeAction::Cancel { order_id } => rest.cancel_order(&signer, *order_id).await?,
+        TradeAction::Status { order_id } => rest.order_status(&signer, *order_id).await?,
+    };
+    match cli.output {
+        // Serializing a struct of strings, numbers and bools cannot fail
+        OutputFormat::Jsonl => println!("{}", serde_json::to_string(&status).unwrap_or_default()),
+        _ => println!("{}", status),
+    }
+    Ok(())
+}
+
+async fn candles(cli: &Cli, args: &CandlesArgs) -> Result<(), GeminiError> {
+    let millis = |time: SystemTime| time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+    let rest = RestClient::new(&cli.endpoint()?)?;
+    let candles = candle_download::download(&rest, &args.symbol, args.interval, args.since.map(millis), args.until.map(millis)).await?;
+    let mut out: Box<dyn Write> = match (&args.output_file, args.format) {
+        #[cfg(feature = "parquet")]
+        (Some(path), ExportFormat::Parquet) => return order_book::sinks::parquet::write_candles(path, &candles),
+        #[cfg(feature = "parquet")]
+        (None, ExportFormat::Parquet) => return Err(GeminiError::Config(String::from("parquet candles need a file in --output-file"))),
+        (Some(path), _) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        (None, _) => Box::new(std::io::stdout().lock()),
+    };
+    match args.format {
+        ExportFormat::Jsonl => candle_download::write_jsonl(&mut out, &candles, &cli.precision())?,
+        _ => candle_download::write_csv(&mut out, &candles, &cli.precision())?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// True when every symbol passed
+async fn check(cli: &Cli, args: &CheckArgs) -> Result<bool, GeminiError> {
+    let results = check::run(cli.exchange, &cli.endpoint()?, &cli.symbol, &cli.feed, args.timeout).await;
+    for result in &results {
+        println!("{}", result.to_json());
+    }
+    Ok(results.iter().all(|r| r.ok))
+}
+
+// Catches typos before they turn into a confusing socket error. If the symbol list
+// can't be fetched the connection attempt gets to report the problem instead.
+async fn check_symbols(cli: &Cli) -> Result<(), GeminiError> {
+    match RestClient::new(&cli.endpoint()?)?.symbols().await {
+        Ok(known) => symbols::validate(&cli.gemini_symbols(), &known),
+        Err(e) => {
+            warn!(error = %e, "could not fetch the symbol list, skipping validation");
+            Ok(())
+        },
+    }
+}
+
+// Price and amount increments of every live symbol. Replays run offline and a failed
+// lookup only costs the precision formatting, so neither is an error.
+async fn fetch_increments(cli: &Cli) -> HashMap<String, Increments> {
+    let mut increments = HashMap::new();
+    if cli.replay.is_some() {
+        return increments;
+    }
+    // A bad endpoint, proxy or CA bundle fails the connections with its error soon enough
+    let Ok(rest) = cli.endpoint().and_then(|endpoint| RestClient::new(&endpoint)) else {
+        return increments;
+    };
+    for symbol in &cli.gemini_symbols() {
+        match rest.symbol_details(symbol).await {
+            Ok(details) => {
+                increments.insert(symbol.clone(), Increments::from(&details));
+            },
+            Err(e) => warn!(symbol, error = %e, "could not fetch symbol details, printing raw precision"),
+        }
+    }
+    increments
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = term.recv() => {},
+                }
+            },
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            },
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+// Builds the pipeline, either printing lines to stdout or driving the dashboard
+async fn run_with_output(cli: Cli, shutdown: CancellationToken) -> Result<(), GeminiError> {
+    let color = !cli.no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal();
+    let formatter = Formatter::new(cli.output).tag_symbol(cli.symbol.len() != 1).color(color)
+        .timezone(cli.tz.clone()).precision(cli.precision());
+    let increments = fetch_increments(&cli).await;
+
+    if let Some(Command::Paper(args)) = &cli.command {
+        return run_paper(&cli, args, increments, shutdown).await;
+    }
+    if let Some(Command::MarketMake(args)) = &cli.command {
+        return run_market_maker(&cli, args, increments, shutdown).await;
+    }
+    if let Some(Command::Backtest(args)) = &cli.command {
+        return run_backtest(&cli, args, increments, shutdown).await;
+    }
+    if let Some(Command::Export(args)) = &cli.command {
+        return run_export(&cli, args, increments, shutdown).await;
+    }
+    if let Some(Command::Bench(args)) = &cli.command {
+        return run_bench(&cli, args, formatter, increments, shutdown).await;
+    }
+
+    #[cfg(feature = "tui")]
+    if cli.tui {
+        use order_book::tui::{self, TuiSink, TuiState};
+
+        let mut pipeline = new_pipeline(&cli, increments);
+        let state = TuiState::new(&cli.symbol)
+            .profile(cli.profile)
+            .positions(cli.positions.then(|| pipeline.positions()))
+            .watchlist_file(cli.config.clone());
+        let state = Arc::new(Mutex::new(state));
+        let watchlist = tui::control(&state);
+        add_sinks(&cli, &mut pipeline, &shutdown).await?;
+        pipeline.add_output(Box::new(TuiSink::new(state.clone()).top_of_book(cli.feed.top_of_book)));
+        let ui = {
+            let shutdown = shutdown.clone();
+            tokio::task::spawn_blocking(move || tui::run(state, shutdown))
+        };
+        let result = drive_with(&cli, &mut pipeline, shutdown.clone(), Some(watchlist)).await;
+        shutdown.cancel();
+        if let Ok(Err(e)) = ui.await {
+            error!(error = %e, "terminal failed");
+        }
+        print_summaries(&pipeline.summaries(), cli.report_format, &mut std::io::stderr());
+        return result;
+    }
+
+    // Nothing but the report, on stdout
+    if cli.report.is_some() {
+        let mut pipeline = new_pipeline(&cli, increments);
+        let result = drive(&cli, &mut pipeline, shutdown).await;
+        print_summaries(&pipeline.summaries(), cli.report_format, &mut std::io::stdout());
+        return result;
+    }
+
+    let mut pipeline = new_pipeline(&cli, increments);
+    match &cli.output_file {
+        None if cli.tape => pipeline.add_output(Box::new(Tape::new(formatter, std::io::stdout(), cli.tape_delay)?)),
+        Some(path) => {
+            let template = PathTemplate::new(path)?;
+            pipeline.add_output(Box::new(Printer::to_files(formatter, template).verbose(cli.verbose)));
+        },
+        None => pipeline.add_output(Box::new(Printer::new(formatter, std::io::stdout())?.verbose(cli.verbose))),
+    }
+    add_sinks(&cli, &mut pipeline, &shutdown).await?;
+    let result = drive(&cli, &mut pipeline, shutdown).await;
+    print_summaries(&pipeline.summaries(), cli.report_format, &mut std::io::stderr());
+    result
+}
+
+fn print_summaries(summaries: &[SessionSummary], format: ReportFormat, out: &mut impl Write) {
+    for summary in summaries {
+        let _ = match format {
+            ReportFormat::Human => write!(out, "{}", summary),
+            ReportFormat::Json => writeln!(out, "{}", summary.report().to_json()),
+        };
+    }
+}
+
+// Fills simulated orders from stdin and `--control` clients against the feed
+async fn run_paper(
+    cli: &Cli,
+    args: &PaperArgs,
+    increments: HashMap<String, Increments>,
+    shutdown: CancellationToken,
+) -> Result<(), GeminiError> {
+    let options = PaperOptions {
+        latency: args.latency,
+        maker_fee_bps: args.maker_fee_bps,
+        taker_fee_bps: args.taker_fee_bps,
+    };
+    let trader = Arc::new(Mutex::new(PaperTrader::new(options, &cli.symbol)));
+    let mut pipeline = new_pipeline(cli, increments);
+    pipeline.add_handler(Box::new(PaperHandler::new(trader.clone())));
+    add_sinks(cli, &mut pipeline, &shutdown).await?;
+    if let Some(addr) = args.control {
+        tokio::spawn(paper::listen(trader.clone(), addr, shutdown.clone()).await?);
+    }
+    eprintln!("{}", paper::USAGE);
+    paper::read_stdin(trader.clone());
+    let result = drive(cli, &mut pipeline, shutdown).await;
+    eprint!("{}", trader.lock().unwrap_or_else(|e| e.into_inner()).summary());
+    result
+}
+
+// Quotes around the mid of every symbol, the report replaces the session summaries
+async fn run_market_maker(
+    cli: &Cli,
+    args: &MarketMakeArgs,
+    increments: HashMap<String, Increments>,
+    shutdown: CancellationToken,
+) -> Result<(), GeminiError> {
+    let options = MarketMakerOptions {
+        spread_bps: args.spread_bps,
+        size: args.size,
+        max_position: args.max_position,
+        fee_bps: args.fee_bps,
+    };
+    let runner = StrategyRunner::new(Box::new(MarketMaker::new(options)));
+    let mut pipeline = new_pipeline(cli, increments);
+    pipeline.add_handler(Box::new(runner.clone()));
+    add_sinks(cli, &mut pipeline, &shutdown).await?;
+    let result = drive(cli, &mut pipeline, shutdown).await;
+    eprint!("{}", runner.report());
+    result
+}
+
+// Fills on stdout and the report after them, nothing else is printed
+async fn run_backtest(
+    cli: &Cli,
+    args: &BacktestArgs,
+    increments: HashMap<String, Increments>,
+    shutdown: CancellationToken,
+) -> Result<(), GeminiError> {
+    let runner = StrategyRunner::new(strategy::build(&args.strategy, &args.params)?);
+    let mut pipeline = new_pipeline(cli, increments);
+    pipeline.add_handler(Box::new(runner.clone()));
+    let result = drive(cli, &mut pipeline, shutdown).await;
+    let mut out = std::io::stdout().lock();
+    for fill in runner.fills() {
+        let _ = writeln!(out, "{}", strategy::format_fill(&fill));
+    }
+    let _ = write!(out, "{}", runner.report());
+    result
+}
+
+// The report goes to stdout, the output is formatted and thrown away
+async fn run_bench(
+    cli: &Cli,
+    args: &BenchArgs,
+    formatter: Formatter,
+    increments: HashMap<String, Increments>,
+    shutdown: CancellationToken,
+) -> Result<(), GeminiError> {
+    let mut pipeline = new_pipeline(cli, increments);
+    if !args.no_output {
+        pipeline.add_output(Box::new(Printer::new(formatter.color(false), std::io::sink())?.verbose(cli.verbose)));
+    }
+    let report = bench::run(&args.input, &cli.symbol, &mut pipeline, &shutdown).await?;
+    match cli.report_format {
+        ReportFormat::Human => print!("{}", report),
+        ReportFormat::Json => println!("{}", serde_json::to_string(&report).map_err(|e| GeminiError::Sink(e.to_string()))?),
+    }
+    Ok(())
+}
+
+// Writes the chosen events of the capture with the initial books, and nothing else
+async fn run_export(
+    cli: &Cli,
+    args: &ExportArgs,
+    increments: HashMap<String, Increments>,
+    shutdown: CancellationToken,
+) -> Result<(), GeminiError> {
+    let mut pipeline = new_pipeline(cli, increments);
+    let format = match args.format {
+        ExportFormat::Csv => OutputFormat::Csv,
+        ExportFormat::Jsonl => OutputFormat::Jsonl,
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => {
+            let Some(dir) = &args.output_file else {
+                return Err(GeminiError::Config(String::from("a parquet export needs a directory in --output-file")));
+            };
+            let sink = order_book::sinks::parquet::ParquetSink::create(dir)?;
+            pipeline.add_output_of(Box::new(sink), args.events.clone());
+            return drive(cli, &mut pipeline, shutdown).await;
+        },
+    };
+    let formatter = Formatter::new(format).timezone(cli.tz.clone()).precision(cli.precision());
+    match &args.output_file {
+        Some(path) => {
+            let printer = Printer::to_files(formatter, PathTemplate::new(path)?).verbose(true);
+            pipeline.add_output_of(Box::new(printer), args.events.clone());
+        },
+        None => {
+            let printer = Printer::new(formatter, std::io::stdout())?.verbose(true);
+            pipeline.add_output_of(Box::new(printer), args.events.clone());
+        },
+    }
+    drive(cli, &mut pipeline, shutdown).await
+}
+
+async fn drive(cli: &Cli, pipeline: &mut Pipeline, shutdown: CancellationToken) -> Result<(), GeminiError> {
+    drive_with(cli, pipeline, shutdown, None).await
+}
+
+// Also takes the commands of `local` beside the control socket and stdin, a replay has no use for them
+async fn drive_with(cli: &Cli, pipeline: &mut Pipeline, shutdown: CancellationToken, local: Option<LocalControl>) -> Result<(), GeminiError> {
+    for route in pipeline.unused_routes() {
+        warn!(sink = route.sink, "no such output is enabled, its route is ignored");
+    }
+    if let Some(saved) = cli.state_file.as_deref().map(state::load).transpose()?.flatten() {
+        info!(symbols = saved.symbols.len(), "restoring saved state");
+        pipeline.restore(saved);
+    }
+    let result = match &cli.replay {
+        Some(path) => replay(cli, path, pipeline, shutdown.clone()).await,
+        None => run(cli, pipeline, shutdown.clone(), local).await,
+    };
+    // Stops what runs beside the pipeline, flushing waits for some of it
+    shutdown.cancel();
+    pipeline.flush().await?;
+    if let Some(path) = &cli.state_file {
+        state::save(path, &pipeline.saved_state(client::now_ms()))?;
+    }
+    result
+}
+
+// The live connections, one per symbol, each with its own token so it can be closed alone
+struct Subscriptions {
+    tokens: HashMap<String, CancellationToken>,
+    connections: JoinSet<Result<(), GeminiError>>,
+    // Kept while control commands may still subscribe, dropped on shutdown so the queue drains
+    shards: Option<Shards>,
+    options: ConnectOptions,
+    shutdown: CancellationToken,
+    // Levels per side in snapshots, all of them when unset
+    depth: Option<usize>,
+    // Where `dump` writes its files
+    dump_dir: PathBuf,
+}
+
+impl Subscriptions {
+    fn subscribe(&mut self, symbol: &str) -> bool {
+        let Some(shards) = &self.shards else {
+            return false;
+        };
+        if self.tokens.contains_key(symbol) {
+            return false;
+        }
+        let token = self.shutdown.child_token();
+        self.connections.spawn(client::stream_frames(symbol.to_string(), shards.sender(symbol).clone(), token.clone(), self.options.clone()));
+        self.tokens.insert(symbol.to_string(), token);
+        true
+    }
+
+    fn unsubscribe(&mut self, symbol: &str) -> bool {
+        match self.tokens.remove(symbol) {
+            Some(token) => {
+                token.cancel();
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self.tokens.keys().cloned().collect();
+        symbols.sort();
+        symbols
+    }
+}
+
+// Carries out one control command against the running session and returns the reply line
+async fn control(command: ControlCommand, subscriptions: &mut Subscriptions, pipeline: &mut Pipeline) -> Result<String, GeminiError> {
+    let reply = match command {
+        ControlCommand::Subscribe(symbol) => match subscriptions.subscribe(&symbol) {
+            true => String::from("ok"),
+            false => format!("error: already subscribed to {}", symbol),
+        },
+        ControlCommand::Unsubscribe(symbol) => match subscriptions.unsubscribe(&symbol) {
+            true => {
+                pipeline.handle_disconnect(&symbol, "unsubscribed").await?;
+                pipeline.forget_quotes(&symbol);
+                pipeline.feed_status().remove(&symbol);
+                String::from("ok")
+            },
+            false => format!("error: not subscribed to {}", symbol),
+        },
+        ControlCommand::Snapshot(symbol) => match pipeline.book(&symbol) {
+            Some(book) => {
+                let levels = |levels: Vec<(&Decimal, &Decimal)>| -> Vec<[String; 2]> {
+                    levels.into_iter().map(|(price, size)| [price.to_string(), size.to_string()]).collect()
+                };
+                let depth = subscriptions.depth.unwrap_or(usize::MAX);
+                serde_json::json!({
+                    "symbol": symbol,
+                    "bids": levels(book.bids().take(depth).collect()),
+                    "asks": levels(book.asks().take(depth).collect()),
+                }).to_string()
+            },
+            None => format!("error: no book for {}", symbol),
+        },
+        // Like --depth-levels for the depth events, and the levels of the snapshot replies
+        ControlCommand::Depth(levels) => match pipeline.set_depth_levels((levels > 0).then_some(levels)) {
+            Ok(()) => {
+                subscriptions.depth = (levels > 0).then_some(levels);
+                String::from("ok")
+            },
+            Err(e) => format!("error: {}", e),
+        },
+        ControlCommand::Stats => serde_json::json!({
+            "subscribed": subscriptions.symbols(),
+            "summaries": pipeline.summaries(),
+        }).to_string(),
+        ControlCommand::Dump => match state::dump(&subscriptions.dump_dir, &pipeline.dump(client::now_ms())) {
+            Ok(path) => serde_json::json!({ "path": path }).to_string(),
+            Err(e) => format!("error: {}", e),
+        },
+        ControlCommand::Shutdown => {
+            subscriptions.shutdown.cancel();
+            String::from("ok")
+        },
+    };
+    Ok(reply)
+}
+
+async fn run(cli: &Cli, pipeline: &mut Pipeline, shutdown: CancellationToken, local: Option<LocalControl>) -> Result<(), GeminiError> {
+    let mut recorder = match &cli.record {
+        Some(path) => Some(CaptureFiles::create(path, cli.capture_options()).await?),
+        None => None,
+    };
+
+    let mut quarantine = match &cli.capture_unknown {
+        Some(path) => Some(Quarantine::open(path).await?),
+        None => None,
+    };
+
+    let (tx, mut rx) = queue::bounded::<FeedEvent>("frames", cli.queue_options());
+    let workers = cli.workers.map_or_else(shard::default_workers, |n| n as usize);
+    debug!(workers, "parsing feeds on worker tasks");
+    let mut subscriptions = Subscriptions {
+        tokens: HashMap::new(),
+        connections: JoinSet::new(),
+        shards: Some(Shards::spawn(workers, &tx, cli.queue_options())),
+        options: ConnectOptions {
+            endpoints: cli.endpoints()?,
+            feed: cli.feed.clone(),
+            reconnect: cli.reconnect.clone(),
+            exchange: cli.exchange,
+        },
+        shutdown: shutdown.clone(),
+        depth: None,
+        dump_dir: cli.dump_dir.clone(),
+    };
+    let (control_tx, mut control_rx) = mpsc::channel::<ControlRequest>(16);
+    let mut control_servers: Vec<tokio::task::JoinHandle<()>> = Vec::new();
+    match &cli.control_socket {
+        #[cfg(unix)]
+        Some(path) => {
+            let rest = RestClient::new(&cli.endpoint()?)?;
+            let server = order_book::control::listen(path, control_tx.clone(), rest, shutdown.clone()).await?;
+            control_servers.push(tokio::spawn(server));
+        },
+        #[cfg(not(unix))]
+        Some(_) => return Err(GeminiError::Config(String::from("--control-socket needs a Unix system"))),
+        None => {},
+    }
+    if let Some(local) = local {
+        let rest = RestClient::new(&cli.endpoint()?)?;
+        control_servers.push(tokio::spawn(order_book::control::serve_local(local, control_tx.clone(), rest, shutdown.clone())));
+    }
+    if cli.interactive {
+        let rest = RestClient::new(&cli.endpoint()?)?;
+        control_servers.push(tokio::spawn(order_book::control::read_stdin(control_tx, rest, shutdown.clone())));
+    }
+    if let Some(since) = cli.backfill {
+        backfill(cli, pipeline, since).await?;
+    }
+    for symbol in &cli.symbol {
+        subscriptions.subscribe(symbol);
+    }
+    let mut reloads = cli.daemon.then(Reloads::new).transpose()?;
+    let mut dumps = DumpRequests::new()?;
+    if cli.daemon {
+        daemon::notify(&format!("READY=1\nSTATUS={}", streaming(&subscriptions)));
+    }
+    let (funding_tx, mut funding_rx) = mpsc::channel(16);
+    for symbol in cli.gemini_symbols().into_iter().filter(|s| funding::is_perpetual(s)) {
+        let rest = RestClient::new(&cli.endpoint()?)?;
+        tokio::spawn(funding::poll(rest, symbol, cli.funding_interval, funding_tx.clone(), shutdown.clone()));
+    }
+    drop(funding_tx);
+    // Other venues' bars are not offered, their symbols go without candles
+    let (candle_tx, mut candle_rx) = mpsc::channel(64);
+    if let (CandleSource::Exchange, Some(interval)) = (cli.candle_source, cli.candles) {
+        let symbols = cli.gemini_symbols();
+        if !symbols.is_empty() {
+            tokio::spawn(exchange_candles::stream(subscriptions.options.endpoints.clone(), symbols, interval, cli.reconnect.clone(), candle_tx, shutdown.clone()));
+        }
+    }
+    // A top of book feed only keeps the best level per side
+    let levels = if cli.feed.top_of_book { 1 } else { cli.check_levels };
+    let mut checker = BookChecker::new(RestClient::new(&cli.endpoint()?)?, levels);
+    let check_every = cli.check_book.unwrap_or(Duration::from_secs(60));
+    let mut check_timer = tokio::time::interval_at(tokio::time::Instant::now() + check_every, check_every);
+    // Often enough that held back updates go out close to the end of their interval
+    let mut clock_timer = tokio::time::interval(Duration::from_millis(100));
+    // Nothing can subscribe later, so the queue closes with the last connection
+    if control_servers.is_empty() && !cli.resync && !cli.daemon {
+        subscriptions.shards = None;
+    }
+    // Only the shard workers may send, so the queue closes after the last of them
+    drop(tx);
+
+    let mut result = Ok(());
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let ParsedFrame { frame, message } = match event {
+                    Some(FeedEvent::Parsed(parsed)) => parsed,
+                    Some(FeedEvent::Frame(frame)) => shard::parse(frame),
+                    Some(FeedEvent::Disconnected { symbol, reason }) => {
+                        if let Err(e) = pipeline.handle_disconnect(&symbol, &reason).await {
+                            shutdown.cancel();
+                            result = Err(e);
+                            break;
                         }
-                        {
-                            let msg = format!("{:?}\n", bbo.lock().unwrap());
-                            tokio::io::stdout().write_all(msg.as_bytes()).await.unwrap();
+                        continue;
+                    },
+                    Some(FeedEvent::RoundTrip { symbol, rtt_ms }) => {
+                        pipeline.record_round_trip(&symbol, rtt_ms);
+                        continue;
+                    },
+                    None => break,
+                };
+                // Frames still queued from a connection that was just unsubscribed
+                if !subscriptions.tokens.contains_key(&frame.symbol) {
+                    continue;
+                }
+                if let Some(recorder) = recorder.as_mut() {
+                    let written = recorder.write(&CapturedFrame {
+                        received_ms: frame.received_ms,
+                        symbol: frame.symbol.clone(),
+                        frame: String::from_utf8_lossy(&frame.data).into_owned(),
+                    }).await;
+                    if let Err(e) = written {
+                        shutdown.cancel();
+                        result = Err(e);
+                        break;
+                    }
+                }
+                if let (Some(quarantine), Ok(message)) = (quarantine.as_mut(), &message) {
+                    if let Err(e) = quarantine.check(&frame.symbol, frame.received_ms, &frame.data, message).await {
+                        shutdown.cancel();
+                        result = Err(e);
+                        break;
+                    }
+                }
+                if let Err(e) = pipeline.handle_message(&frame.symbol, message, frame.received_ms).await {
+                    shutdown.cancel();
+                    result = Err(e);
+                    break;
+                }
+            },
+            Some(request) = control_rx.recv() => {
+                match control(request.command, &mut subscriptions, pipeline).await {
+                    Ok(reply) => {
+                        let _ = request.reply.send(reply);
+                    },
+                    Err(e) => {
+                        shutdown.cancel();
+                        result = Err(e);
+                        break;
+                    },
+                }
+            },
+            Some((symbol, funding)) = funding_rx.recv() => {
+                if let Err(e) = pipeline.handle_funding(&symbol, funding, client::now_ms()).await {
+                    shutdown.cancel();
+                    result = Err(e);
+                    break;
+                }
+            },
+            _ = clock_timer.tick() => {
+                if let Err(e) = pipeline.tick(client::now_ms()).await {
+                    shutdown.cancel();
+                    result = Err(e);
+                    break;
+                }
+            },
+            Some((symbol, candle)) = candle_rx.recv() => {
+                if let Err(e) = pipeline.handle_candle(&symbol, candle, client::now_ms()).await {
+                    shutdown.cancel();
+                    result = Err(e);
+                    break;
+                }
+            },
+            _ = async { if let Some(reloads) = reloads.as_mut() { reloads.next().await } }, if reloads.is_some() => {
+                if let Err(e) = reload(&mut subscriptions, pipeline).await {
+                    shutdown.cancel();
+                    result = Err(e);
+                    break;
+                }
+            },
+            _ = dumps.next() => {
+                match state::dump(&subscriptions.dump_dir, &pipeline.dump(client::now_ms())) {
+                    Ok(path) => info!(path = %path.display(), "dumped the current state"),
+                    Err(e) => warn!(error = %e, dir = %subscriptions.dump_dir.display(), "could not dump the current state"),
+                }
+            },
+            _ = check_timer.tick(), if cli.check_book.is_some() => {
+                let gemini = |symbol: &String| exchange::route(cli.exchange, symbol).0 == Exchange::Gemini;
+                for symbol in subscriptions.symbols().into_iter().filter(gemini) {
+                    checker.check(&symbol);
+                }
+            },
+            (symbol, snapshot) = checker.next() => {
+                // Unsubscribed, or no initial book yet, while the snapshot was on its way
+                let Some(book) = subscriptions.tokens.contains_key(&symbol).then(|| pipeline.book(&symbol)).flatten() else {
+                    checker.forget(&symbol);
+                    continue;
+                };
+                match checker.compare(&symbol, book, &snapshot) {
+                    Verdict::Matches => {},
+                    Verdict::Suspect => checker.check(&symbol),
+                    Verdict::Diverged(_) if cli.resync => {
+                        info!(symbol, "resyncing the order book");
+                        subscriptions.unsubscribe(&symbol);
+                        if let Err(e) = pipeline.handle_disconnect(&symbol, "resync").await {
+                            shutdown.cancel();
+                            result = Err(e);
+                            break;
                         }
+                        subscriptions.subscribe(&symbol);
                     },
-                    Event::Unknown => {},
+                    Verdict::Diverged(_) => {},
                 }
-            }
-        })
+            },
+            _ = shutdown.cancelled(), if subscriptions.shards.is_some() => {
+                subscriptions.shards = None;
+            },
+            Some(finished) = subscriptions.connections.join_next() => {
+                // One symbol failing takes the whole session down
+                if let Ok(Err(e)) = finished {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                    shutdown.cancel();
+                }
+                // Only kept for resyncs, so the queue closes with the last connection
+                if subscriptions.connections.is_empty() && control_servers.is_empty() && !cli.daemon {
+                    subscriptions.shards = None;
+                }
+            },
+        }
+    }
+    if shutdown.is_cancelled() && result.is_ok() {
+        info!("shutting down");
+    }
+    if cli.daemon {
+        daemon::notify("STOPPING=1");
+    }
+    // The loop only ends on shutdown while commands are taken, give the socket time to remove its file
+    for server in control_servers {
+        let _ = server.await;
+    }
+    if let Some(recorder) = recorder {
+        let finished = recorder.finish().await;
+        if result.is_ok() {
+            result = finished;
+        }
+    }
+    result
+}
+
+// Rereads the config file on SIGHUP, subscribing to the symbols it adds and dropping
+// the ones it removes. Other changes wait for a restart, a config that no longer loads
+// leaves everything as it is.
+async fn reload(subscriptions: &mut Subscriptions, pipeline: &mut Pipeline) -> Result<(), GeminiError> {
+    let symbols = match Cli::load() {
+        Ok(cli) => cli.symbol,
+        Err(e) => {
+            warn!(error = %e, "could not reload the config, keeping the running one");
+            return Ok(());
+        },
     };
+    let current = subscriptions.symbols();
+    let removed: Vec<&String> = current.iter().filter(|s| !symbols.contains(s)).collect();
+    let added: Vec<&String> = symbols.iter().filter(|s| !current.contains(s)).collect();
+    for symbol in &removed {
+        control(ControlCommand::Unsubscribe(symbol.to_string()), subscriptions, pipeline).await?;
+    }
+    for symbol in &added {
+        subscriptions.subscribe(symbol);
+    }
+    info!(added = ?added, removed = ?removed, "reloaded the config");
+    daemon::notify(&format!("STATUS={}", streaming(subscriptions)));
+    Ok(())
+}
+
+// The service status systemctl shows
+fn streaming(subscriptions: &Subscriptions) -> String {
+    format!("Streaming {}", subscriptions.symbols().join(", "))
+}
 
-    ws_to_stdout.await;
+// Runs the trades of the last `since` through the pipeline before the feeds connect. A
+// symbol whose trades cannot be fetched only misses its backfill.
+async fn backfill(cli: &Cli, pipeline: &mut Pipeline, since: Duration) -> Result<(), GeminiError> {
+    let rest = RestClient::new(&cli.endpoint()?)?;
+    let since_ms = client::now_ms().saturating_sub(since.as_millis() as u64);
+    for symbol in &cli.gemini_symbols() {
+        match rest.trades_since(symbol, since_ms).await {
+            Ok(trades) => {
+                info!(symbol, trades = trades.len(), "backfilling trades");
+                pipeline.handle_backfill(symbol, trades, client::now_ms()).await?;
+            },
+            Err(e) => warn!(symbol, error = %e, "could not fetch trades to backfill"),
+        }
+    }
+    Ok(())
+}
+
+async fn replay(cli: &Cli, path: &Path, pipeline: &mut Pipeline, shutdown: CancellationToken) -> Result<(), GeminiError> {
+    let mut reader = CaptureReader::open(path, cli.speed).await?;
+    let mut quarantine = match &cli.capture_unknown {
+        Some(path) => Some(Quarantine::open(path).await?),
+        None => None,
+    };
+    loop {
+        let frame = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            frame = reader.next() => match frame? {
+                Some(frame) => frame,
+                None => break,
+            },
+        };
+        if !cli.symbol.is_empty() && !cli.symbol.contains(&frame.symbol) {
+            continue;
+        }
+        if let Some(quarantine) = quarantine.as_mut() {
+            if let Ok(message) = MarketMessage::from_slice(frame.frame.as_bytes()) {
+                quarantine.check(&frame.symbol, frame.received_ms, frame.frame.as_bytes(), &message).await?;
+            }
+        }
+        pipeline.handle_frame(&frame.symbol, frame.frame.as_bytes(), frame.received_ms).await?;
+        // On the recorded clock, a gap in the capture shows up as it did live
+        pipeline.check_quotes(frame.received_ms).await?;
+    }
+    Ok(())
 }
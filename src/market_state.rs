@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::watch;
+
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::metrics;
+use crate::models::BestBidOffer;
+use crate::output::EventContext;
+
+type Slot = Arc<watch::Sender<Option<BestBidOffer>>>;
+
+// Latest BBO per symbol, one watch channel each. Any number of clones read the current
+// value or wait for the next change, and none of them ever holds up the publisher. A
+// symbol holds None until its first update.
+#[derive(Debug, Clone, Default)]
+pub struct MarketState {
+    symbols: Arc<RwLock<HashMap<String, Slot>>>,
+}
+
+impl MarketState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<BestBidOffer> {
+        self.symbols.read().unwrap().get(symbol)?.borrow().clone()
+    }
+
+    // Change notifications for `symbol`, also for one that has not been seen yet
+    pub fn subscribe(&self, symbol: &str) -> watch::Receiver<Option<BestBidOffer>> {
+        self.slot(symbol).subscribe()
+    }
+
+    // Symbols with a channel, updated or only subscribed to
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self.symbols.read().unwrap().keys().cloned().collect();
+        symbols.sort();
+        symbols
+    }
+
+    pub fn publish(&self, symbol: &str, bbo: BestBidOffer) {
+        self.slot(symbol).send_replace(Some(bbo));
+    }
+
+    // The handler that keeps this store up to date from a pipeline
+    pub fn publisher(&self) -> MarketStatePublisher {
+        MarketStatePublisher {
+            state: self.clone(),
+            slots: HashMap::new(),
+        }
+    }
+
+    fn slot(&self, symbol: &str) -> Slot {
+        if let Some(slot) = self.symbols.read().unwrap().get(symbol) {
+            return slot.clone();
+        }
+        let mut symbols = self.symbols.write().unwrap();
+        symbols.entry(symbol.to_string()).or_insert_with(|| Arc::new(watch::channel(None).0)).clone()
+    }
+}
+
+// Publishes every BBO into a `MarketState` and mirrors it into the metrics. It keeps the
+// channels it has seen, so an update only takes the store's lock for a new symbol.
+pub struct MarketStatePublisher {
+    state: MarketState,
+    slots: HashMap<String, Slot>,
+}
+
+impl EventHandler for MarketStatePublisher {
+    fn on_book_update(&mut self, symbol: &str, _: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        metrics::global().set_bbo(symbol, bbo);
+        if !self.slots.contains_key(symbol) {
+            self.slots.insert(symbol.to_string(), self.state.slot(symbol));
+        }
+        self.slots[symbol].send_replace(Some(bbo.clone()));
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "bbo"
+    }
+}
@@ -0,0 +1,244 @@
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use prometheus::{
+    Encoder, Gauge, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::analytics::latency::LatencySnapshot;
+use crate::error::GeminiError;
+use crate::models::BestBidOffer;
+
+pub struct Metrics {
+    registry: Registry,
+    pub messages: IntCounterVec,
+    pub trades: IntCounterVec,
+    pub quotes: IntCounterVec,
+    pub events: IntCounterVec,
+    pub reconnects: IntCounterVec,
+    pub closes: IntCounterVec,
+    pub off_tick: IntCounterVec,
+    pub sequence_gaps: IntCounterVec,
+    pub duplicate_events: IntCounterVec,
+    pub event_id_regressions: IntCounterVec,
+    pub book_divergences: IntCounterVec,
+    pub anomalies: IntCounterVec,
+    pub dropped: IntCounterVec,
+    pub evicted: IntCounterVec,
+    pub queue_depth: GaugeVec,
+    pub best_bid: GaugeVec,
+    pub best_offer: GaugeVec,
+    pub spread: GaugeVec,
+    pub cross_divergence: GaugeVec,
+    pub parse_latency: HistogramVec,
+    pub feed_latency: HistogramVec,
+    pub feed_latency_quantiles: GaugeVec,
+    pub round_trip: GaugeVec,
+    pub clock_skew: GaugeVec,
+    pub active_endpoint: GaugeVec,
+    pub endpoint_health: GaugeVec,
+    pub book_levels: GaugeVec,
+    pub quote_age: GaugeVec,
+    pub stale_quotes: GaugeVec,
+    pub leader: Gauge,
+    pub resident_memory: Gauge,
+}
+
+impl Metrics {
+    fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some(String::from("gemini")), None)?;
+        let counter = |name: &str, help: &str| -> Result<IntCounterVec, prometheus::Error> {
+            let c = IntCounterVec::new(Opts::new(name, help), &["symbol"])?;
+            registry.register(Box::new(c.clone()))?;
+            Ok(c)
+        };
+        let gauge = |name: &str, help: &str| -> Result<GaugeVec, prometheus::Error> {
+            let g = GaugeVec::new(Opts::new(name, help), &["symbol"])?;
+            registry.register(Box::new(g.clone()))?;
+            Ok(g)
+        };
+        let messages = counter("messages_received_total", "Market data messages received")?;
+        let trades = counter("trades_received_total", "Trade events received")?;
+        let quotes = counter("quotes_received_total", "Quote change events received")?;
+        let events = IntCounterVec::new(
+            Opts::new("events_received_total", "Market data events by type, and by reason for book changes"),
+            &["symbol", "type", "reason"],
+        )?;
+        registry.register(Box::new(events.clone()))?;
+        let reconnects = counter("reconnects_total", "WebSocket reconnects")?;
+        let closes = IntCounterVec::new(
+            Opts::new("server_closes_total", "Connections the server ended, by the cause its close frame gave"),
+            &["symbol", "cause"],
+        )?;
+        registry.register(Box::new(closes.clone()))?;
+        let off_tick = counter("off_tick_prices_total", "Trade and quote prices off the symbol's price increment")?;
+        let sequence_gaps = counter("sequence_gaps_total", "Messages whose socket_sequence skipped ahead")?;
+        let duplicate_events = counter("duplicate_events_total", "Messages dropped because their eventId was seen before")?;
+        let event_id_regressions = counter("event_id_regressions_total", "Messages whose eventId was below an earlier one")?;
+        let book_divergences = counter("book_divergences_total", "Order books that disagreed with the REST snapshot twice in a row")?;
+        let anomalies = IntCounterVec::new(
+            Opts::new("anomalies_total", "Trades far from the rolling mid, spread blowouts and stale book sides"),
+            &["symbol", "kind"],
+        )?;
+        registry.register(Box::new(anomalies.clone()))?;
+        let dropped = IntCounterVec::new(
+            Opts::new("queue_dropped_total", "Items evicted from a full drop-oldest queue"),
+            &["queue"],
+        )?;
+        registry.register(Box::new(dropped.clone()))?;
+        let evicted = IntCounterVec::new(
+            Opts::new("evicted_total", "Book levels and history entries dropped to stay within their caps"),
+            &["symbol", "kind"],
+        )?;
+        registry.register(Box::new(evicted.clone()))?;
+        let queue_depth = GaugeVec::new(
+            Opts::new("queue_depth", "Items waiting in a queue as of its last receive"),
+            &["queue"],
+        )?;
+        registry.register(Box::new(queue_depth.clone()))?;
+        let best_bid = gauge("best_bid", "Current best bid price")?;
+        let best_offer = gauge("best_offer", "Current best offer price")?;
+        let spread = gauge("spread", "Current best offer minus best bid")?;
+        let cross_divergence = GaugeVec::new(
+            Opts::new("cross_divergence_bps", "Actual minus implied cross mid in basis points"),
+            &["cross"],
+        )?;
+        registry.register(Box::new(cross_divergence.clone()))?;
+        let parse_latency = HistogramVec::new(
+            HistogramOpts::new("parse_latency_seconds", "Time spent parsing a frame")
+                .buckets(prometheus::exponential_buckets(1e-6, 2., 16)?),
+            &["symbol"],
+        )?;
+        registry.register(Box::new(parse_latency.clone()))?;
+        let feed_latency = HistogramVec::new(
+            HistogramOpts::new("feed_latency_seconds", "Local receive time minus exchange timestampms")
+                .buckets(prometheus::exponential_buckets(1e-3, 2., 14)?),
+            &["symbol"],
+        )?;
+        registry.register(Box::new(feed_latency.clone()))?;
+        let feed_latency_quantiles = GaugeVec::new(
+            Opts::new("feed_latency_quantile_seconds", "Rolling feed latency percentiles"),
+            &["symbol", "quantile"],
+        )?;
+        registry.register(Box::new(feed_latency_quantiles.clone()))?;
+        let round_trip = gauge("websocket_round_trip_seconds", "Last WebSocket ping round trip")?;
+        let clock_skew = gauge("clock_skew_seconds", "Estimated local clock minus exchange clock")?;
+        let active_endpoint = GaugeVec::new(
+            Opts::new("active_endpoint", "1 for the endpoint a symbol's connection uses, 0 for the others"),
+            &["symbol", "endpoint"],
+        )?;
+        registry.register(Box::new(active_endpoint.clone()))?;
+        let endpoint_health = GaugeVec::new(
+            Opts::new("endpoint_health", "Health score of an endpoint from 0 to 1, below 0.5 it is failed over"),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(endpoint_health.clone()))?;
+        let book_levels = gauge("book_levels", "Price levels held in the order book, both sides")?;
+        let quote_age = GaugeVec::new(
+            Opts::new("quote_age_seconds", "Time since a side of the book last changed, as of the last check"),
+            &["symbol", "side"],
+        )?;
+        registry.register(Box::new(quote_age.clone()))?;
+        let stale_quotes = GaugeVec::new(
+            Opts::new("stale_quotes", "1 while a side of the book has not changed for longer than --stale-quotes"),
+            &["symbol", "side"],
+        )?;
+        registry.register(Box::new(stale_quotes.clone()))?;
+        let leader = Gauge::new("leader", "1 while this collector holds the --leader-lock and writes to the storage sinks")?;
+        registry.register(Box::new(leader.clone()))?;
+        let resident_memory = Gauge::new("resident_memory_bytes", "Resident memory of the process, read at each scrape")?;
+        registry.register(Box::new(resident_memory.clone()))?;
+
+        Ok(Self {
+            registry,
+            messages,
+            trades,
+            quotes,
+            events,
+            reconnects,
+            closes,
+            off_tick,
+            sequence_gaps,
+            duplicate_events,
+            event_id_regressions,
+            book_divergences,
+            anomalies,
+            dropped,
+            evicted,
+            queue_depth,
+            best_bid,
+            best_offer,
+            spread,
+            cross_divergence,
+            parse_latency,
+            feed_latency,
+            feed_latency_quantiles,
+            round_trip,
+            clock_skew,
+            active_endpoint,
+            endpoint_health,
+            book_levels,
+            quote_age,
+            stale_quotes,
+            leader,
+            resident_memory,
+        })
+    }
+
+    pub fn set_bbo(&self, symbol: &str, bbo: &BestBidOffer) {
+        let bid = bbo.best_bid.to_f64().unwrap_or(0.);
+        let offer = bbo.best_offer.to_f64().unwrap_or(0.);
+        self.best_bid.with_label_values(&[symbol]).set(bid);
+        self.best_offer.with_label_values(&[symbol]).set(offer);
+        if !bbo.best_bid.is_zero() && !bbo.best_offer.is_zero() {
+            let spread = (bbo.best_offer - bbo.best_bid).to_f64().unwrap_or(0.);
+            self.spread.with_label_values(&[symbol]).set(spread);
+        }
+    }
+
+    pub fn set_latency(&self, symbol: &str, latency: &LatencySnapshot) {
+        let quantiles = [("0.5", latency.p50_ms), ("0.95", latency.p95_ms), ("0.99", latency.p99_ms)];
+        for (quantile, ms) in quantiles {
+            self.feed_latency_quantiles.with_label_values(&[symbol, quantile]).set(ms as f64 / 1000.);
+        }
+    }
+
+    pub fn render(&self) -> String {
+        if let Some(bytes) = resident_bytes() {
+            self.resident_memory.set(bytes as f64);
+        }
+        let mut buf = Vec::new();
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buf);
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+// VmRSS of /proc/self/status, None where there is no procfs
+fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+pub fn global() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    // Names and labels are static, registration can only fail on a programming error
+    METRICS.get_or_init(|| Metrics::new().expect("metrics registration"))
+}
+
+async fn metrics_handler() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], global().render())
+}
+
+pub async fn serve(addr: SocketAddr) -> Result<(), GeminiError> {
+    let app = Router::new().route("/metrics", get(metrics_handler));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+    Ok(())
+}
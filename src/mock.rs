@@ -0,0 +1,446 @@
+use std::collections::{BTreeSet, HashMap};
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::capture::{CaptureReader, CapturedFrame};
+use crate::client::now_ms;
+use crate::error::GeminiError;
+
+// One thing the mock does on a connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    // A text frame, sent as is
+    Frame(String),
+    Sleep(Duration),
+    // Drops the TCP connection without a close frame
+    Disconnect,
+}
+
+// Trouble injected into the scripts, counted in frames of one connection
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    // Drop each of the first `disconnects` connections of a feed after this many frames
+    pub disconnect_after: Option<usize>,
+    pub disconnects: u32,
+    // Skip a v1 socket_sequence number before every n-th frame
+    pub gap_every: Option<NonZeroUsize>,
+    // Cut every n-th frame short so it is no longer valid JSON
+    pub malformed_every: Option<NonZeroUsize>,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    // v1 frames of a capture, with their recorded gaps divided by `speed`
+    Capture { frames: Vec<CapturedFrame>, speed: f64 },
+    // A random walk around 100, `frames` updates `interval` apart after the initial book
+    Synthetic { frames: usize, interval: Duration },
+}
+
+// What the mock serves. Every connection plays its script from the start, the way
+// Gemini sends the whole book again after a reconnect.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    source: Source,
+    faults: Faults,
+}
+
+// The feed a connection asked for: v1 and order events by path, v2 by its subscribe message
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Feed {
+    V1 { symbol: String },
+    V2 { symbols: Vec<String> },
+    // The account's order events, fills of a made up order alongside every third trade
+    Orders,
+}
+
+impl Scenario {
+    pub fn synthetic(frames: usize, interval: Duration) -> Self {
+        Self {
+            source: Source::Synthetic { frames, interval },
+            faults: Faults::default(),
+        }
+    }
+
+    // A speed of 0 sends the frames back to back
+    pub async fn from_capture(path: &Path, speed: f64) -> Result<Self, GeminiError> {
+        let mut reader = CaptureReader::open(path, 0.).await?;
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next().await? {
+            frames.push(frame);
+        }
+        Ok(Self {
+            source: Source::Capture { frames, speed },
+            faults: Faults::default(),
+        })
+    }
+
+    pub fn with_faults(mut self, faults: Faults) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    // Script of the `connection`-th connection to `feed`, counting from 0
+    pub fn steps(&self, feed: &Feed, connection: u32) -> Vec<Step> {
+        let frames = match (&self.source, feed) {
+            (Source::Capture { frames, speed }, Feed::V1 { symbol }) => capture_frames(frames, symbol, *speed),
+            // Captures hold v1 frames only, a v2 client gets the synthetic feed
+            (Source::Capture { .. }, Feed::V2 { symbols }) => synthetic_v2(symbols, 1000, Duration::from_millis(100)),
+            (Source::Synthetic { frames, interval }, Feed::V1 { .. }) => synthetic_v1(*frames, *interval),
+            (Source::Synthetic { frames, interval }, Feed::V2 { symbols }) => synthetic_v2(symbols, *frames, *interval),
+            (Source::Capture { .. }, Feed::Orders) => synthetic_orders(1000, Duration::from_millis(100)),
+            (Source::Synthetic { frames, interval }, Feed::Orders) => synthetic_orders(*frames, *interval),
+        };
+        self.apply_faults(frames, matches!(feed, Feed::V1 { .. }), connection)
+    }
+
+    fn apply_faults(&self, frames: Vec<(Duration, Value)>, v1: bool, connection: u32) -> Vec<Step> {
+        let faults = &self.faults;
+        let disconnect_after = faults.disconnect_after.filter(|_| connection < faults.disconnects);
+        let mut steps = Vec::new();
+        let mut sequence = 0;
+        for (n, (delay, mut frame)) in frames.into_iter().enumerate() {
+            if disconnect_after == Some(n) {
+                steps.push(Step::Disconnect);
+                return steps;
+            }
+            if !delay.is_zero() {
+                steps.push(Step::Sleep(delay));
+            }
+            if v1 {
+                if faults.gap_every.is_some_and(|every| n > 0 && n % every.get() == 0) {
+                    sequence += 1;
+                }
+                frame["socket_sequence"] = json!(sequence);
+                sequence += 1;
+            }
+            let mut text = frame.to_string();
+            if faults.malformed_every.is_some_and(|every| (n + 1) % every.get() == 0) {
+                text.truncate(text.len() / 2);
+            }
+            steps.push(Step::Frame(text));
+        }
+        steps
+    }
+}
+
+fn capture_frames(frames: &[CapturedFrame], symbol: &str, speed: f64) -> Vec<(Duration, Value)> {
+    let mut last = None;
+    frames.iter()
+        .filter(|frame| frame.symbol.eq_ignore_ascii_case(symbol))
+        .filter_map(|frame| {
+            let value = serde_json::from_str(&frame.frame).ok()?;
+            let gap = match (last.replace(frame.received_ms), speed > 0.) {
+                (Some(last), true) => Duration::from_millis(frame.received_ms.saturating_sub(last)).div_f64(speed),
+                _ => Duration::ZERO,
+            };
+            Some((gap, value))
+        })
+        .collect()
+}
+
+// Deterministic prices, so a test can predict what it receives
+fn walk(n: usize) -> (u64, u64) {
+    let mid = 10_000 + (n * 7_919 % 41) as u64 - 20;
+    (mid - 1, mid + 1)
+}
+
+fn price(cents: u64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+struct Level {
+    bid: bool,
+    cents: u64,
+    remaining: &'static str,
+}
+
+// Price levels following `walk`, cancelling whatever a new best bid or offer would cross
+#[derive(Default)]
+struct SyntheticBook {
+    bids: BTreeSet<u64>,
+    asks: BTreeSet<u64>,
+}
+
+impl SyntheticBook {
+    fn initial(&mut self) -> Vec<Level> {
+        let (bid, ask) = walk(0);
+        self.bids.extend((0..5).map(|level| bid - level));
+        self.asks.extend((0..5).map(|level| ask + level));
+        let bids = self.bids.iter().rev().map(|&cents| Level { bid: true, cents, remaining: "1" });
+        let asks = self.asks.iter().map(|&cents| Level { bid: false, cents, remaining: "1" });
+        bids.chain(asks).collect()
+    }
+
+    // Levels that cannot cross go in first and crossing ones after the cancels, so
+    // neither side is ever empty or crossed in between
+    fn update(&mut self, n: usize) -> Vec<Level> {
+        let (bid, ask) = walk(n);
+        let bid_first = self.asks.first().is_none_or(|&best| bid < best);
+        let ask_first = self.bids.last().is_none_or(|&best| ask > best);
+        let crossed_bids: Vec<u64> = self.bids.range(ask..).copied().collect();
+        let crossed_asks: Vec<u64> = self.asks.range(..=bid).copied().collect();
+        self.bids.retain(|&cents| cents < ask);
+        self.asks.retain(|&cents| cents > bid);
+        self.bids.insert(bid);
+        self.asks.insert(ask);
+
+        let placed_bid = Level { bid: true, cents: bid, remaining: "2" };
+        let placed_ask = Level { bid: false, cents: ask, remaining: "2" };
+        let (mut first, mut last) = (Vec::new(), Vec::new());
+        match bid_first {
+            true => first.push(placed_bid),
+            false => last.push(placed_bid),
+        }
+        match ask_first {
+            true => first.push(placed_ask),
+            false => last.push(placed_ask),
+        }
+        let cancels = crossed_bids.into_iter().map(|cents| Level { bid: true, cents, remaining: "0" })
+            .chain(crossed_asks.into_iter().map(|cents| Level { bid: false, cents, remaining: "0" }));
+        first.into_iter().chain(cancels).chain(last).collect()
+    }
+}
+
+fn synthetic_v1(frames: usize, interval: Duration) -> Vec<(Duration, Value)> {
+    let change = |level: Level, reason: &str| json!({
+        "type": "change",
+        "side": if level.bid { "bid" } else { "ask" },
+        "price": price(level.cents),
+        "remaining": level.remaining,
+        "delta": level.remaining,
+        "reason": if level.remaining == "0" { "cancel" } else { reason },
+    });
+    let mut book = SyntheticBook::default();
+    let initial: Vec<Value> = book.initial().into_iter().map(|level| change(level, "initial")).collect();
+    let mut out = vec![(Duration::ZERO, json!({"type": "update", "eventId": 1, "events": initial}))];
+    let start = now_ms();
+    for n in 1..=frames {
+        let timestampms = start + n as u64 * interval.as_millis() as u64;
+        let mut events: Vec<Value> = book.update(n).into_iter().map(|level| change(level, "place")).collect();
+        if n % 3 == 0 {
+            events.push(json!({"type": "trade", "tid": n, "price": price(walk(n).1), "amount": "0.5", "makerSide": "ask"}));
+        }
+        out.push((interval, json!({
+            "type": "update",
+            "eventId": n + 1,
+            "timestamp": timestampms / 1000,
+            "timestampms": timestampms,
+            "events": events,
+        })));
+    }
+    out
+}
+
+// v2 `l2_updates` hold [side, price, remaining] triples
+fn synthetic_v2(symbols: &[String], frames: usize, interval: Duration) -> Vec<(Duration, Value)> {
+    let change = |level: Level| json!([if level.bid { "buy" } else { "sell" }, price(level.cents), level.remaining]);
+    let mut books: Vec<SyntheticBook> = symbols.iter().map(|_| SyntheticBook::default()).collect();
+    let mut out = Vec::new();
+    for (symbol, book) in symbols.iter().zip(&mut books) {
+        let changes: Vec<Value> = book.initial().into_iter().map(change).collect();
+        out.push((Duration::ZERO, json!({"type": "l2_updates", "symbol": symbol, "changes": changes, "trades": []})));
+    }
+    let start = now_ms();
+    for n in 1..=frames {
+        let symbol = &symbols[n % symbols.len()];
+        let changes: Vec<Value> = books[n % symbols.len()].update(n).into_iter().map(change).collect();
+        out.push((interval, json!({"type": "l2_updates", "symbol": symbol, "changes": changes})));
+        if n % 3 == 0 {
+            out.push((Duration::ZERO, json!({
+                "type": "trade",
+                "symbol": symbol,
+                "event_id": n,
+                "timestamp": start + n as u64 * interval.as_millis() as u64,
+                "price": price(walk(n).1),
+                "quantity": "0.5",
+                "side": "buy",
+                "tid": n,
+            })));
+        }
+    }
+    out
+}
+
+// A fill of its own order every third trade of the v1 script, buying at the offer and
+// selling at the bid in turns, with a 0.1% fee
+fn synthetic_orders(frames: usize, interval: Duration) -> Vec<(Duration, Value)> {
+    let mut out = vec![(Duration::ZERO, json!({
+        "type": "subscription_ack",
+        "accountId": 1,
+        "subscriptionId": "mock",
+        "symbolFilter": [],
+        "apiSessionFilter": [],
+        "eventTypeFilter": [],
+    }))];
+    let start = now_ms();
+    let mut waited = Duration::ZERO;
+    for n in 1..=frames {
+        waited += interval;
+        if n % 9 != 0 {
+            continue;
+        }
+        let (bid, ask) = walk(n);
+        let (side, cents, liquidity) = match n % 18 {
+            0 => ("sell", bid, "Maker"),
+            _ => ("buy", ask, "Taker"),
+        };
+        // 0.1% of 0.5 at `cents`
+        let fee = cents * 5;
+        out.push((std::mem::take(&mut waited), json!([{
+            "type": "fill",
+            "order_id": n.to_string(),
+            "symbol": "btcusd",
+            "side": side,
+            "order_type": "exchange limit",
+            "timestampms": start + n as u64 * interval.as_millis() as u64,
+            "is_live": false,
+            "is_cancelled": false,
+            "original_amount": "0.5",
+            "executed_amount": "0.5",
+            "remaining_amount": "0",
+            "price": price(cents),
+            "fill": {
+                "trade_id": format!("{}", 1_000_000 + n),
+                "liquidity": liquidity,
+                "price": price(cents),
+                "amount": "0.5",
+                "fee": format!("0.{:06}", fee),
+                "fee_currency": "USD",
+            },
+        }])));
+    }
+    out
+}
+
+// Connections so far per feed, so reconnects get the next script
+type Connections = Arc<Mutex<HashMap<Feed, u32>>>;
+
+// Binds `addr`, port 0 picks a free one. Returns the bound address and the accept loop
+// to spawn, which runs until `shutdown`.
+pub async fn bind(
+    addr: SocketAddr,
+    scenario: Scenario,
+    shutdown: CancellationToken,
+) -> Result<(SocketAddr, impl std::future::Future<Output = ()>), GeminiError> {
+    let listener = TcpListener::bind(addr).await?;
+    let local = listener.local_addr()?;
+    Ok((local, accept_loop(listener, Arc::new(scenario), shutdown)))
+}
+
+async fn accept_loop(listener: TcpListener, scenario: Arc<Scenario>, shutdown: CancellationToken) {
+    let connections = Connections::default();
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = shutdown.cancelled() => return,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!(error = %e, "mock: accept failed");
+                    continue;
+                },
+            },
+        };
+        let (scenario, connections, shutdown) = (scenario.clone(), connections.clone(), shutdown.clone());
+        tokio::spawn(async move {
+            if let Err(e) = client(stream, &scenario, &connections, shutdown).await {
+                info!(%peer, error = %e, "mock: client dropped");
+            }
+        });
+    }
+}
+
+// The handshake callback's error type is tungstenite's
+#[allow(clippy::result_large_err)]
+async fn client(stream: TcpStream, scenario: &Scenario, connections: &Connections, shutdown: CancellationToken) -> Result<(), GeminiError> {
+    let mut path = String::new();
+    let ws = tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+        path = request.uri().path().to_string();
+        if path.ends_with("/v1/order/events") && !request.headers().contains_key("X-GEMINI-APIKEY") {
+            let mut error = ErrorResponse::new(Some(String::from("missing API key")));
+            *error.status_mut() = StatusCode::UNAUTHORIZED;
+            return Err(error);
+        }
+        match path.contains("/v1/marketdata/") || path.ends_with("/v2/marketdata") || path.ends_with("/v1/order/events") {
+            true => Ok(response),
+            false => {
+                let mut error = ErrorResponse::new(Some(format!("no feed at {}", request.uri().path())));
+                *error.status_mut() = StatusCode::NOT_FOUND;
+                Err(error)
+            },
+        }
+    }).await?;
+    let (mut write, mut read) = ws.split();
+
+    let feed = match path.rsplit_once("/v1/marketdata/") {
+        Some((_, symbol)) => Feed::V1 { symbol: symbol.to_string() },
+        None if path.ends_with("/v1/order/events") => Feed::Orders,
+        None => {
+            // v2 waits for the subscription, e.g.
+            // {"type":"subscribe","subscriptions":[{"name":"l2","symbols":["BTCUSD"]}]}
+            let subscribe = loop {
+                match read.next().await {
+                    Some(Ok(Message::Text(text))) => break text,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                    None => return Ok(()),
+                }
+            };
+            let subscribe: Value = serde_json::from_str(&subscribe)
+                .map_err(|e| GeminiError::Protocol(format!("bad subscribe message: {}", e)))?;
+            let symbols: Vec<String> = subscribe["subscriptions"].as_array().into_iter().flatten()
+                .filter(|subscription| subscription["name"] == "l2")
+                .flat_map(|subscription| subscription["symbols"].as_array().cloned().unwrap_or_default())
+                .filter_map(|symbol| symbol.as_str().map(str::to_string))
+                .collect();
+            if symbols.is_empty() {
+                return Err(GeminiError::Protocol(String::from("no l2 symbols subscribed")));
+            }
+            Feed::V2 { symbols }
+        },
+    };
+    let connection = {
+        let mut connections = connections.lock().unwrap_or_else(|e| e.into_inner());
+        let count = connections.entry(feed.clone()).or_default();
+        *count += 1;
+        *count - 1
+    };
+
+    for step in scenario.steps(&feed, connection) {
+        match step {
+            Step::Frame(text) => write.send(Message::Text(text)).await?,
+            Step::Sleep(delay) => tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(delay) => {},
+            },
+            // Dropping both halves closes the socket without a close frame
+            Step::Disconnect => return Ok(()),
+        }
+    }
+    // Like the exchange the connection stays open once the script is done
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => {
+                write.send(Message::Close(None)).await?;
+                return Ok(());
+            },
+            message = read.next() => match message {
+                Some(Ok(message)) if message.is_close() => return Ok(()),
+                Some(Ok(_)) => {},
+                Some(Err(e)) => return Err(e.into()),
+                None => return Ok(()),
+            },
+        }
+    }
+}
@@ -1,142 +1,683 @@
-use serde::{Serialize, Deserialize};
-use serde_json::{Value, Result};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Debug)]
+use rust_decimal::Decimal;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum MarketSide {
     Bid,
     Ask,
+    #[default]
+    #[serde(other)]
     Unknown, // Should never happen according to API docs
 }
 
 impl MarketSide {
-    pub fn from_string(message: &str) -> MarketSide {
-        return match message {
-            "ask" => MarketSide::Ask,
-            "bid" => MarketSide::Bid,
-            _ => MarketSide::Unknown,
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarketSide::Bid => "bid",
+            MarketSide::Ask => "ask",
+            MarketSide::Unknown => "unknown",
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub enum MessageType {
-    Trade,
-    Change,
-    Unknown, // Should never happen according to API docs
-}
+// Largest price or quantity taken from the feed. Real values are orders of magnitude
+// below, and the bound keeps products and sums of them from overflowing a Decimal.
+pub const MAX_MARKET_VALUE: i64 = 1_000_000_000_000;
+
+// Decimal strings of the market data feed, rejecting anything beyond MAX_MARKET_VALUE
+mod bounded {
+    use rust_decimal::Decimal;
+    use serde::{de, Deserializer};
 
-impl MessageType {
-    pub fn from_string(message: &str) -> Self {
-        return match message {
-            "trade" => Self::Trade,
-            "change" => Self::Change,
-            _ => Self::Unknown
+    pub use rust_decimal::serde::str::serialize;
+
+    fn check<E: de::Error>(value: Decimal) -> Result<Decimal, E> {
+        match value.abs() <= Decimal::from(super::MAX_MARKET_VALUE) {
+            true => Ok(value),
+            false => Err(E::custom(format!("{} is out of range", value))),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+        check(rust_decimal::serde::str::deserialize(deserializer)?)
+    }
+
+    pub mod option {
+        use rust_decimal::Decimal;
+        use serde::Deserializer;
+
+        pub use rust_decimal::serde::str_option::serialize;
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Decimal>, D::Error> {
+            rust_decimal::serde::str_option::deserialize(deserializer)?.map(super::check).transpose()
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Quote {
-    pub price: f64,
+    #[serde(with = "bounded")]
+    pub price: Decimal,
+    #[serde(default)]
     pub reason: String,
-    pub remaining: f64,
+    #[serde(default, with = "bounded")]
+    pub remaining: Decimal,
+    #[serde(default)]
     pub side: MarketSide,
-    pub delta: Option<f64>,
+    #[serde(default, with = "bounded::option")]
+    pub delta: Option<Decimal>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Trade {
-    pub price: f64,
-    pub amount: f64,
+    #[serde(with = "bounded")]
+    pub price: Decimal,
+    #[serde(with = "bounded")]
+    pub amount: Decimal,
+    #[serde(default, rename = "makerSide")]
     pub maker_side: MarketSide,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Trade {
+    // The side that crossed the spread, the opposite of the resting maker's
+    pub fn aggressor(&self) -> Option<OrderSide> {
+        match self.maker_side {
+            MarketSide::Ask => Some(OrderSide::Buy),
+            MarketSide::Bid => Some(OrderSide::Sell),
+            MarketSide::Unknown => None,
+        }
+    }
+}
+
+// Negotiated off-book trade reported to the feed, not part of the continuous market
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockTrade {
+    pub tid: u64,
+    #[serde(with = "bounded")]
+    pub price: Decimal,
+    #[serde(with = "bounded")]
+    pub amount: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuctionOpen {
+    pub auction_open_ms: u64,
+    pub auction_time_ms: u64,
+    pub first_indicative_ms: Option<u64>,
+    pub last_cancel_time_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuctionIndicative {
+    pub eid: u64,
+    pub result: String,
+    pub time_ms: u64,
+    #[serde(default, with = "bounded::option")]
+    pub highest_bid_price: Option<Decimal>,
+    #[serde(default, with = "bounded::option")]
+    pub lowest_ask_price: Option<Decimal>,
+    #[serde(default, with = "bounded::option")]
+    pub collar_price: Option<Decimal>,
+    #[serde(default, with = "bounded::option")]
+    pub indicative_price: Option<Decimal>,
+    #[serde(default, with = "bounded::option")]
+    pub indicative_quantity: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuctionResult {
+    pub eid: u64,
+    pub result: String,
+    pub time_ms: u64,
+    #[serde(default, with = "bounded::option")]
+    pub highest_bid_price: Option<Decimal>,
+    #[serde(default, with = "bounded::option")]
+    pub lowest_ask_price: Option<Decimal>,
+    #[serde(default, with = "bounded::option")]
+    pub collar_price: Option<Decimal>,
+    #[serde(default, with = "bounded::option")]
+    pub auction_price: Option<Decimal>,
+    #[serde(default, with = "bounded::option")]
+    pub auction_quantity: Option<Decimal>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum AuctionEvent {
+    Open(AuctionOpen),
+    Indicative(AuctionIndicative),
+    Result(AuctionResult),
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(into = "RawEvent")]
 pub enum Event {
     Trade(Trade),
+    BlockTrade(BlockTrade),
     Quote(Quote),
+    Auction(AuctionEvent),
+    Unknown,
+}
+
+// Gemini's flat `type` tags, folded into the grouped `Event` variants above
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RawEvent {
+    Trade(Trade),
+    BlockTrade(BlockTrade),
+    Change(Quote),
+    AuctionOpen(AuctionOpen),
+    AuctionIndicative(AuctionIndicative),
+    AuctionResult(AuctionResult),
+    #[serde(other)]
+    Unknown,
+}
+
+// The `type` values of `RawEvent`, read without allocating
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum EventType {
+    Trade,
+    BlockTrade,
+    Change,
+    AuctionOpen,
+    AuctionIndicative,
+    AuctionResult,
+    #[serde(other)]
+    Unknown,
+}
+
+enum EventKey {
+    Type,
+    Other(String),
+}
+
+impl<'de> Deserialize<'de> for EventKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl de::Visitor<'_> for KeyVisitor {
+            type Value = EventKey;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a field name")
+            }
+
+            fn visit_str<E: de::Error>(self, key: &str) -> Result<EventKey, E> {
+                Ok(match key {
+                    "type" => EventKey::Type,
+                    other => EventKey::Other(other.to_string()),
+                })
+            }
+        }
+
+        deserializer.deserialize_identifier(KeyVisitor)
+    }
+}
+
+struct EventVisitor;
+
+impl<'de> de::Visitor<'de> for EventVisitor {
+    type Value = Event;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a market data event")
+    }
+
+    // Parsing through `RawEvent` buffers every event before looking at its `type`, which
+    // is most of the cost of a frame. Gemini sends `type` first, so the rest of the event
+    // can go straight into its struct. Anything else still takes the buffered path.
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Event, A::Error> {
+        let first = match map.next_key::<EventKey>()? {
+            Some(EventKey::Type) => {
+                let kind: EventType = map.next_value()?;
+                let rest = de::value::MapAccessDeserializer::new(map);
+                return Ok(match kind {
+                    EventType::Trade => Event::Trade(Trade::deserialize(rest)?),
+                    EventType::BlockTrade => Event::BlockTrade(BlockTrade::deserialize(rest)?),
+                    EventType::Change => Event::Quote(Quote::deserialize(rest)?),
+                    EventType::AuctionOpen => Event::Auction(AuctionEvent::Open(AuctionOpen::deserialize(rest)?)),
+                    EventType::AuctionIndicative => Event::Auction(AuctionEvent::Indicative(AuctionIndicative::deserialize(rest)?)),
+                    EventType::AuctionResult => Event::Auction(AuctionEvent::Result(AuctionResult::deserialize(rest)?)),
+                    EventType::Unknown => {
+                        de::IgnoredAny::deserialize(rest)?;
+                        Event::Unknown
+                    },
+                });
+            },
+            Some(EventKey::Other(key)) => key,
+            None => return Err(de::Error::missing_field("type")),
+        };
+        let mut fields = serde_json::Map::new();
+        fields.insert(first, map.next_value()?);
+        while let Some((key, value)) = map.next_entry()? {
+            fields.insert(key, value);
+        }
+        RawEvent::deserialize(serde_json::Value::Object(fields)).map(Event::from).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Event {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(EventVisitor)
+    }
+}
+
+impl From<RawEvent> for Event {
+    fn from(raw: RawEvent) -> Self {
+        match raw {
+            RawEvent::Trade(t) => Event::Trade(t),
+            RawEvent::BlockTrade(t) => Event::BlockTrade(t),
+            RawEvent::Change(q) => Event::Quote(q),
+            RawEvent::AuctionOpen(a) => Event::Auction(AuctionEvent::Open(a)),
+            RawEvent::AuctionIndicative(a) => Event::Auction(AuctionEvent::Indicative(a)),
+            RawEvent::AuctionResult(a) => Event::Auction(AuctionEvent::Result(a)),
+            RawEvent::Unknown => Event::Unknown,
+        }
+    }
+}
+
+impl From<Event> for RawEvent {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::Trade(t) => RawEvent::Trade(t),
+            Event::BlockTrade(t) => RawEvent::BlockTrade(t),
+            Event::Quote(q) => RawEvent::Change(q),
+            Event::Auction(AuctionEvent::Open(a)) => RawEvent::AuctionOpen(a),
+            Event::Auction(AuctionEvent::Indicative(a)) => RawEvent::AuctionIndicative(a),
+            Event::Auction(AuctionEvent::Result(a)) => RawEvent::AuctionResult(a),
+            Event::Unknown => RawEvent::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for MarketSide {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Trade(t) => write!(f, "trade {} @ {}, maker {}", t.amount, t.price, t.maker_side),
+            Event::BlockTrade(t) => write!(f, "block trade {} @ {} (tid {})", t.amount, t.price, t.tid),
+            Event::Quote(q) => {
+                write!(f, "change {} {} remaining {}", q.side, q.price, q.remaining)?;
+                if let Some(delta) = q.delta {
+                    write!(f, " delta {}", delta)?;
+                }
+                write!(f, " ({})", q.reason)
+            },
+            Event::Auction(AuctionEvent::Open(a)) => write!(f, "auction open, runs at {}", a.auction_time_ms),
+            Event::Auction(AuctionEvent::Indicative(a)) => {
+                write!(f, "auction indicative {}", a.result)?;
+                auction_outcome(f, a.indicative_price, a.indicative_quantity)
+            },
+            Event::Auction(AuctionEvent::Result(a)) => {
+                write!(f, "auction result {}", a.result)?;
+                auction_outcome(f, a.auction_price, a.auction_quantity)
+            },
+            Event::Unknown => f.write_str("unknown event"),
+        }
+    }
+}
+
+fn auction_outcome(f: &mut fmt::Formatter, price: Option<Decimal>, quantity: Option<Decimal>) -> fmt::Result {
+    match (quantity, price) {
+        (Some(quantity), Some(price)) => write!(f, " {} @ {}", quantity, price),
+        (None, Some(price)) => write!(f, " @ {}", price),
+        _ => Ok(()),
+    }
+}
+
+// An event on its own, outside the message it came in: the `type` tag of the feed
+// followed by the symbol and the fields, `{"symbol":"btcusd","type":"trade",...}`
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct TaggedEvent<'a> {
+    pub symbol: &'a str,
+    #[serde(flatten)]
+    pub event: &'a Event,
+}
+
+impl fmt::Display for TaggedEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.symbol, self.event)
+    }
+}
+
+// How events are serialized apart from their message
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EventShape {
+    // One flat object per event, tagged with `type` and `symbol`
+    #[default]
+    Tagged,
+    // `{"Trade":{...}}`, serde's default for enums, for consumers written against it
+    External,
+}
+
+// The externally tagged shape, named after the variants of `Event`
+#[derive(Serialize)]
+enum ExternalEvent<'a> {
+    Trade(&'a Trade),
+    BlockTrade(&'a BlockTrade),
+    Quote(&'a Quote),
+    Auction(&'a AuctionEvent),
     Unknown,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl<'a> From<&'a Event> for ExternalEvent<'a> {
+    fn from(event: &'a Event) -> Self {
+        match event {
+            Event::Trade(t) => ExternalEvent::Trade(t),
+            Event::BlockTrade(t) => ExternalEvent::BlockTrade(t),
+            Event::Quote(q) => ExternalEvent::Quote(q),
+            Event::Auction(a) => ExternalEvent::Auction(a),
+            Event::Unknown => ExternalEvent::Unknown,
+        }
+    }
+}
+
+impl Event {
+    // Gemini's `type` of the event, `unknown` for the ones the models do not cover
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::Trade(_) => "trade",
+            Event::BlockTrade(_) => "block_trade",
+            Event::Quote(_) => "change",
+            Event::Auction(AuctionEvent::Open(_)) => "auction_open",
+            Event::Auction(AuctionEvent::Indicative(_)) => "auction_indicative",
+            Event::Auction(AuctionEvent::Result(_)) => "auction_result",
+            Event::Unknown => "unknown",
+        }
+    }
+
+    pub fn tagged<'a>(&'a self, symbol: &'a str) -> TaggedEvent<'a> {
+        TaggedEvent { symbol, event: self }
+    }
+
+    pub fn to_json(&self, symbol: &str, shape: EventShape) -> Result<String, serde_json::Error> {
+        match shape {
+            EventShape::Tagged => serde_json::to_string(&self.tagged(symbol)),
+            EventShape::External => serde_json::to_string(&ExternalEvent::from(self)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MarketMessage {
+    #[serde(rename = "eventId")]
     pub event_id: u64,
+    #[serde(default)]
     pub events: Vec<Event>,
     pub timestamp: Option<u64>,
     pub timestampms: Option<u64>,
     pub socket_sequence: u32,
+    // Local wall clock in nanoseconds when the frame came off the socket and when the
+    // client handed the message on, never part of the exchange's JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_at_ns: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emitted_at_ns: Option<u64>,
 }
 
-impl Event {
-    pub fn new(message: &[u8]) -> MarketMessage {
-        let m: Value = serde_json::from_slice(message).expect("Something went wrong with deseralization");
-
-        let events: Vec<_> = m["events"].as_array().unwrap().iter()
-        .map(|e| {
-            let price = e["price"].as_str().unwrap().parse::<f64>().unwrap();
-            match MessageType::from_string(e["type"].as_str().unwrap()) {
-                MessageType::Change => {
-                    let q = Quote {
-                        price,
-                        reason: match e["reason"].as_str() {
-                            Some(n) => n.to_string(),
-                            None => String::from(""),
-                        },
-                        remaining: match e["remaining"].as_str() {
-                            Some(n) => n.parse::<f64>().unwrap(),
-                            None => 0.,
-                        },
-                        side: match e["side"].as_str() {
-                            Some(n) => MarketSide::from_string(n),
-                            None => MarketSide::Unknown,
-                        },
-                        delta: match e["delta"].as_str() {
-                            Some(n) => Some(n.parse::<f64>().unwrap()),
-                            None => None,
-                        }
-                    };
-                    Event::Quote(q)
-                },
-                MessageType::Trade => {
-                    let t = Trade {
-                        price,
-                        amount: e["amount"].as_str().unwrap().parse::<f64>().unwrap(),
-                        maker_side: match e["makerSide"].as_str() {
-                            Some(n) => MarketSide::from_string(n),
-                            None => MarketSide::Unknown,
-                        }
-                    };
-                    Event::Trade(t)
-                }
-                MessageType::Unknown => Event::Unknown,
-            }
-        }).collect();
-
-        MarketMessage {
-            event_id: m["eventId"].as_u64().unwrap(),
-            events,
-            timestamp: m["timestamp"].as_u64(),
-            timestampms: m["timestampms"].as_u64(),
-            socket_sequence: m["socket_sequence"].as_u64().unwrap() as u32,
-        }
+impl MarketMessage {
+    #[cfg(not(feature = "simd"))]
+    pub fn from_slice(message: &[u8]) -> Result<Self, ParseError> {
+        serde_json::from_slice(message).map_err(ParseError)
+    }
+
+    // simd-json parses in place, so it works on a copy of the frame
+    #[cfg(feature = "simd")]
+    pub fn from_slice(message: &[u8]) -> Result<Self, ParseError> {
+        simd_json::serde::from_slice(&mut message.to_vec()).map_err(ParseError)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[cfg(not(feature = "simd"))]
+type JsonError = serde_json::Error;
+#[cfg(feature = "simd")]
+type JsonError = simd_json::Error;
+
+#[derive(Error, Debug)]
+#[error("malformed market message: {0}")]
+pub struct ParseError(#[source] JsonError);
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
 pub struct BestBidOffer {
-    pub best_bid: f64,
-    pub best_offer: f64,
-    pub bid_amount_remaining: f64,
-    pub ask_amount_remaining: f64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub best_bid: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub best_offer: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub bid_amount_remaining: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub ask_amount_remaining: Decimal,
 }
 
 impl BestBidOffer {
     pub fn new() -> Self {
-        Self {
-            best_bid: 0.,
-            best_offer: 0.,
-            bid_amount_remaining: 0.,
-            ask_amount_remaining: 0.,
+        Self::default()
+    }
+}
+
+// Trading parameters of one symbol from `/v1/symbols/details/{symbol}`
+#[derive(Deserialize, Debug, Clone)]
+pub struct SymbolDetails {
+    pub symbol: String,
+    pub base_currency: String,
+    pub quote_currency: String,
+    // Smallest amount increment
+    #[serde(deserialize_with = "decimal_number")]
+    pub tick_size: Decimal,
+    // Smallest price increment
+    #[serde(deserialize_with = "decimal_number")]
+    pub quote_increment: Decimal,
+    #[serde(default, deserialize_with = "decimal_number")]
+    pub min_order_size: Decimal,
+    #[serde(default)]
+    pub status: String,
+}
+
+// One price level of `/v1/book/{symbol}`
+#[derive(Deserialize, Debug, Clone)]
+pub struct BookLevel {
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
+}
+
+// One trade of `/v1/trades/{symbol}`, newest first in the response
+#[derive(Deserialize, Debug, Clone)]
+pub struct TradeRecord {
+    pub timestampms: u64,
+    pub tid: u64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
+    // The taker's side: "buy" or "sell", or "auction" and "block" for those fills
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+impl TradeRecord {
+    // A buy took an offer, so the maker sat on the ask side
+    pub fn maker_side(&self) -> MarketSide {
+        match self.kind.as_str() {
+            "buy" => MarketSide::Ask,
+            "sell" => MarketSide::Bid,
+            _ => MarketSide::Unknown,
         }
     }
 }
+
+// Funding of a perpetual from `/v1/fundingamount/{symbol}`, amounts per contract in the
+// quote currency. Positive amounts are paid by longs to shorts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FundingAmount {
+    pub symbol: String,
+    // When `amount` was charged
+    #[serde(rename = "fundingTimestampMilliSecs")]
+    pub funding_ms: u64,
+    #[serde(rename = "nextFundingTimestamp")]
+    pub next_funding_ms: u64,
+    pub amount: Decimal,
+    // What the next funding would be at the current mark price
+    #[serde(rename = "estimatedFundingAmount", default)]
+    pub estimated_amount: Option<Decimal>,
+}
+
+// Order book snapshot from `/v1/book/{symbol}`, best levels first
+#[derive(Deserialize, Debug, Clone)]
+pub struct BookSnapshot {
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl fmt::Display for OrderSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        })
+    }
+}
+
+// One currency of the account from `/v1/balances`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Balance {
+    pub currency: String,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub available: Decimal,
+    #[serde(rename = "type", default)]
+    pub account_type: String,
+}
+
+// Parameters of `/v1/order/new`. The API only takes limit orders, `options` such as
+// "immediate-or-cancel" or "maker-or-cancel" change how they execute.
+#[derive(Serialize, Debug, Clone)]
+pub struct NewOrder {
+    pub symbol: String,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
+    pub side: OrderSide,
+    #[serde(rename = "type")]
+    pub order_type: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<String>,
+}
+
+// Order state as returned by `/v1/order/new`, `/v1/order/cancel` and `/v1/order/status`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderStatus {
+    pub order_id: String,
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+    pub symbol: String,
+    pub side: OrderSide,
+    #[serde(rename = "type")]
+    pub order_type: String,
+    #[serde(default)]
+    pub timestampms: u64,
+    pub is_live: bool,
+    pub is_cancelled: bool,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub original_amount: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub executed_amount: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub remaining_amount: Decimal,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    pub price: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub avg_execution_price: Decimal,
+    #[serde(default)]
+    pub options: Vec<String>,
+    // Why a cancelled order was cancelled
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match (self.is_live, self.is_cancelled) {
+            (true, _) => "live",
+            (false, true) => "cancelled",
+            (false, false) => "filled",
+        };
+        write!(f, "order {} {} {} {}", self.order_id, self.side, self.original_amount, self.symbol)?;
+        if let Some(price) = self.price {
+            write!(f, " @ {}", price)?;
+        }
+        write!(f, ": {}, executed {} avg {}", state, self.executed_amount, self.avg_execution_price)?;
+        if let Some(reason) = &self.reason {
+            write!(f, " ({})", reason)?;
+        }
+        Ok(())
+    }
+}
+
+// What one execution of an order traded, on a `fill` order event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FillDetails {
+    pub trade_id: String,
+    // Maker or Taker, Auction or Block for fills outside the continuous market
+    pub liquidity: String,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub fee: Decimal,
+    pub fee_currency: String,
+}
+
+// One event of the private `/v1/order/events` feed, such as accepted, fill or cancelled.
+// Only the fields every type carries, `fill` is there on fills.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderEvent {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub order_id: String,
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+    pub symbol: String,
+    pub side: OrderSide,
+    #[serde(default)]
+    pub timestampms: u64,
+    #[serde(default)]
+    pub fill: Option<FillDetails>,
+}
+
+// The details endpoint mixes JSON numbers like 1e-8 and decimal strings
+fn decimal_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+    let text = match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::String(s) => s,
+        other => return Err(serde::de::Error::custom(format!("expected a number, got {}", other))),
+    };
+    Decimal::from_str(&text)
+        .or_else(|_| Decimal::from_scientific(&text))
+        .map(|d| d.normalize())
+        .map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,48 @@
+use notify_rust::Notification;
+use rust_decimal::Decimal;
+use tracing::{info, warn};
+
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{MarketSide, Trade};
+use crate::output::EventContext;
+
+// Shows a desktop notification for every trade worth more than `threshold` in the quote currency
+pub struct TradeNotifier {
+    threshold: Decimal,
+}
+
+impl TradeNotifier {
+    pub fn new(threshold: Decimal) -> Self {
+        Self { threshold }
+    }
+}
+
+impl EventHandler for TradeNotifier {
+    fn on_trade(&mut self, symbol: &str, _ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        let notional = trade.price * trade.amount;
+        if notional <= self.threshold {
+            return Ok(());
+        }
+        // The maker rested on the other side of the aggressor
+        let taker = match trade.maker_side {
+            MarketSide::Ask => "buy",
+            MarketSide::Bid => "sell",
+            MarketSide::Unknown => "trade",
+        };
+        info!(symbol, %notional, "large trade");
+        let summary = format!("Large {} {}", symbol.to_uppercase(), taker);
+        let body = format!("{} @ {} = {} notional", trade.amount, trade.price, notional.round_dp(2));
+        // Showing talks to the notification daemon and may block, so it stays off the runtime
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = Notification::new().summary(&summary).body(&body).appname("order_book").show() {
+                warn!(error = %e, "notify: could not show the notification");
+            }
+        });
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "notify"
+    }
+}
@@ -0,0 +1,175 @@
+use futures_util::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::auth::Signer;
+use crate::client::{self, Endpoint, ReconnectPolicy, WsStream};
+use crate::close::Close;
+use crate::error::GeminiError;
+use crate::models::{OrderEvent, OrderSide};
+
+const ORDER_EVENTS_PATH: &str = "/v1/order/events";
+
+// An execution of one of the account's orders, one row of a blotter
+#[derive(Serialize, Debug, Clone)]
+pub struct Fill {
+    pub timestampms: u64,
+    pub symbol: String,
+    pub side: OrderSide,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub fee: Decimal,
+    pub fee_currency: String,
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub trade_id: String,
+    pub liquidity: String,
+}
+
+impl Fill {
+    fn from_event(event: OrderEvent) -> Option<Self> {
+        let fill = event.fill.filter(|_| event.event_type == "fill")?;
+        Some(Self {
+            timestampms: event.timestampms,
+            symbol: event.symbol.to_lowercase(),
+            side: event.side,
+            price: fill.price,
+            amount: fill.amount,
+            fee: fill.fee,
+            fee_currency: fill.fee_currency,
+            order_id: event.order_id,
+            client_order_id: event.client_order_id,
+            trade_id: fill.trade_id,
+            liquidity: fill.liquidity,
+        })
+    }
+}
+
+// Order events arrive in arrays, heartbeats and the subscription acknowledgement are
+// single objects without fills
+pub fn parse_fills(text: &str) -> Result<Vec<Fill>, GeminiError> {
+    if !text.trim_start().starts_with('[') {
+        return Ok(Vec::new());
+    }
+    let events: Vec<OrderEvent> = serde_json::from_str(text)
+        .map_err(|e| GeminiError::Protocol(format!("malformed order events: {}", e)))?;
+    Ok(events.into_iter().filter_map(Fill::from_event).collect())
+}
+
+// The handshake is signed like a REST request, with the payload in the headers
+async fn connect(endpoint: &Endpoint, signer: &Signer) -> Result<WsStream, GeminiError> {
+    let url = endpoint.ws_base.join(ORDER_EVENTS_PATH.trim_start_matches('/'))?;
+    let signed = signer.sign(ORDER_EVENTS_PATH, &())?;
+    let mut request = url.as_str().into_client_request()?;
+    for (name, value) in [
+        ("X-GEMINI-APIKEY", signed.api_key),
+        ("X-GEMINI-PAYLOAD", signed.payload),
+        ("X-GEMINI-SIGNATURE", signed.signature),
+    ] {
+        let value = HeaderValue::from_str(&value).map_err(|e| GeminiError::Auth(format!("unusable {}: {}", name, e)))?;
+        request.headers_mut().insert(name, value);
+    }
+    client::connect_request(endpoint, request).await.map_err(|e| match e {
+        GeminiError::Connection(e) => match *e {
+            tungstenite::Error::Http(response) if response.status().is_client_error() => {
+                GeminiError::Auth(format!("order events rejected the API key with {}", response.status()))
+            },
+            e => GeminiError::Connection(Box::new(e)),
+        },
+        e => e,
+    })
+}
+
+// Reads fills into `fills` until the server hangs up, returning how it did. None once
+// shutdown fires or nobody takes fills any more.
+async fn session(ws: &mut WsStream, fills: &mpsc::Sender<Fill>, shutdown: &CancellationToken) -> Result<Option<Close>, GeminiError> {
+    loop {
+        let message = tokio::select! {
+            _ = shutdown.cancelled() => {
+                let _ = ws.send(Message::Close(None)).await;
+                return Ok(None);
+            },
+            message = ws.next() => message,
+        };
+        match message {
+            Some(Ok(Message::Text(text))) => {
+                for fill in parse_fills(&text)? {
+                    if fills.send(fill).await.is_err() {
+                        return Ok(None);
+                    }
+                }
+            },
+            Some(Ok(Message::Close(frame))) => return Ok(Some(Close::from_frame(frame.as_ref()))),
+            Some(Ok(_)) => {},
+            Some(Err(e)) => return Err(e.into()),
+            None => return Ok(Some(Close::abnormal())),
+        }
+    }
+}
+
+// Streams the account's fills into `fills` until shutdown, reconnecting per `policy`.
+// A rejected key or a policy violation ends it. Fills while disconnected are not sent
+// again by the exchange.
+pub async fn stream(
+    endpoint: Endpoint,
+    signer: Signer,
+    policy: ReconnectPolicy,
+    fills: mpsc::Sender<Fill>,
+    shutdown: CancellationToken,
+) {
+    let mut attempt = 0;
+    loop {
+        let result = match connect(&endpoint, &signer).await {
+            Ok(mut ws) => {
+                info!("order events connected");
+                attempt = 0;
+                session(&mut ws, &fills, &shutdown).await
+            },
+            Err(e) => Err(e),
+        };
+        let close = match result {
+            Ok(None) => return,
+            Ok(Some(close)) if !close.retries() => {
+                error!(close = %close, "order events closed by server, giving up");
+                return;
+            },
+            Ok(Some(close)) => {
+                warn!(close = %close, "order events closed by server, fills until the reconnect are missed");
+                Some(close)
+            },
+            Err(e @ GeminiError::Auth(_)) => {
+                error!(error = %e, "order events: giving up");
+                return;
+            },
+            Err(e) => {
+                warn!(error = %e, "order events disconnected, fills until the reconnect are missed");
+                None
+            },
+        };
+        let failed = close.as_ref().is_none_or(|close| close.is_failure());
+        if !policy.enabled || (failed && policy.max_attempts.is_some_and(|max| attempt >= max)) {
+            error!("order events: out of reconnect attempts");
+            return;
+        }
+        let delay = match &close {
+            Some(close) => close.delay(&policy, attempt),
+            None => policy.delay(attempt),
+        };
+        if failed {
+            attempt += 1;
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {},
+        }
+    }
+}
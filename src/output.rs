@@ -0,0 +1,736 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::{Duration, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use jiff::tz::TimeZone;
+use jiff::Timestamp;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::analytics::anomaly::{AnomalyEvent, AnomalyKind};
+use crate::analytics::candles::Candle;
+use crate::analytics::cross::CrossDivergence;
+use crate::analytics::depth::DepthSnapshot;
+use crate::analytics::dwell::{DwellSnapshot, SideDwell};
+use crate::analytics::indicators::BookIndicators;
+use crate::analytics::ofi::OfiSnapshot;
+use crate::analytics::technical::TechnicalSnapshot;
+use crate::analytics::volatility::VolatilitySnapshot;
+use crate::analytics::vwap::TradeStatsSnapshot;
+use crate::client::now_ns;
+use crate::error::GeminiError;
+use crate::files::{PathTemplate, TemplatedFiles};
+use crate::handler::EventHandler;
+use crate::models::{AuctionEvent, AuctionIndicative, AuctionResult, BestBidOffer, BlockTrade, FundingAmount, OrderSide, Quote, Trade};
+use crate::precision::Precision;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Jsonl,
+    Csv,
+}
+
+// Per-message metadata attached to every emitted event
+#[derive(Clone, Copy, Debug)]
+pub struct EventContext {
+    pub event_id: u64,
+    pub socket_sequence: u32,
+    pub timestampms: Option<u64>,
+    pub received_ms: u64,
+    // The same local receive time to the nanosecond, where the client took it
+    pub received_ns: u64,
+    // Fetched over REST on startup rather than received live
+    pub backfill: bool,
+    // Fetched over REST after a reconnect, printed while the feed was down
+    pub recovered: bool,
+}
+
+// Normalized shape shared by JSONL output and every JSON-speaking sink
+#[derive(Serialize)]
+pub struct Record<'a, T: Serialize> {
+    kind: &'static str,
+    symbol: &'a str,
+    event_id: u64,
+    socket_sequence: u32,
+    timestampms: Option<u64>,
+    received_ms: u64,
+    received_at_ns: u64,
+    // When the line was written, less `received_at_ns` it is the time spent in here
+    emitted_at_ns: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    backfill: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    recovered: bool,
+    #[serde(flatten)]
+    data: &'a T,
+}
+
+impl<'a, T: Serialize> Record<'a, T> {
+    pub fn new(kind: &'static str, symbol: &'a str, ctx: &EventContext, data: &'a T) -> Self {
+        Self {
+            kind,
+            symbol,
+            event_id: ctx.event_id,
+            socket_sequence: ctx.socket_sequence,
+            timestampms: ctx.timestampms,
+            received_ms: ctx.received_ms,
+            received_at_ns: ctx.received_ns,
+            emitted_at_ns: now_ns(),
+            backfill: ctx.backfill,
+            recovered: ctx.recovered,
+            data,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // Serializing plain structs of strings and integers cannot fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+pub const CSV_HEADER: &str = "kind,symbol,event_id,socket_sequence,timestampms,received_ms,\
+price,amount,side,reason,delta,best_bid,bid_amount_remaining,best_offer,ask_amount_remaining,\
+received_at_ns,emitted_at_ns";
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+// One per symbol on the consolidated tape, in the order symbols first print
+const SYMBOL_COLORS: [&str; 6] = ["\x1b[36m", "\x1b[33m", "\x1b[35m", "\x1b[34m", "\x1b[92m", "\x1b[91m"];
+
+// `--tz` zones: IANA names such as America/New_York, UTC, or local for the system zone
+pub fn parse_timezone(name: &str) -> Result<TimeZone, String> {
+    match name {
+        "local" => TimeZone::try_system().map_err(|e| format!("cannot determine the local time zone: {}", e)),
+        "UTC" | "utc" => Ok(TimeZone::UTC),
+        _ => TimeZone::get(name).map_err(|_| format!("unknown time zone `{}`", name)),
+    }
+}
+
+pub struct Formatter {
+    format: OutputFormat,
+    tag_symbol: bool,
+    color: bool,
+    timezone: Option<TimeZone>,
+    precision: Precision,
+}
+
+impl Formatter {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            tag_symbol: false,
+            color: false,
+            timezone: None,
+            precision: Precision::default(),
+        }
+    }
+
+    // Decimal places of prices, amounts and notionals in every format
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    // Write times as RFC 3339 in `timezone` in human and CSV output, JSONL keeps epoch millis
+    pub fn timezone(mut self, timezone: Option<TimeZone>) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    // Epoch millis as they are without a time zone
+    fn time(&self, ms: u64) -> String {
+        let Some(timezone) = &self.timezone else {
+            return ms.to_string();
+        };
+        match Timestamp::from_millisecond(ms as i64) {
+            Ok(ts) => ts.to_zoned(timezone.clone()).strftime("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string(),
+            Err(_) => ms.to_string(),
+        }
+    }
+
+    // Color human BBO prices green or red by the direction they moved in
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn tick(&self, price: Decimal, previous: Option<Decimal>) -> String {
+        let price = self.precision.price(price);
+        let previous = previous.map(|p| self.precision.price(p));
+        match (self.color, previous) {
+            (true, Some(previous)) if price > previous => format!("{}{}{}", GREEN, price, RESET),
+            (true, Some(previous)) if price < previous => format!("{}{}{}", RED, price, RESET),
+            _ => price.to_string(),
+        }
+    }
+
+    // Prefix human output with the symbol, for sessions with several symbols
+    pub fn tag_symbol(mut self, tag: bool) -> Self {
+        self.tag_symbol = tag;
+        self
+    }
+
+    // With a time zone every line starts with the exchange's time of its message
+    fn human_prefix(&self, symbol: &str, ctx: &EventContext) -> String {
+        let time = match self.timezone {
+            Some(_) => format!("{} ", self.time(ctx.timestampms.unwrap_or(ctx.received_ms))),
+            None => String::new(),
+        };
+        match self.tag_symbol {
+            true => format!("{}[{}] ", time, symbol),
+            false => time,
+        }
+    }
+
+    pub fn header(&self) -> Option<String> {
+        match (self.format, &self.timezone) {
+            (OutputFormat::Csv, None) => Some(format!("{}\n", CSV_HEADER)),
+            (OutputFormat::Csv, Some(_)) => Some(format!("{}\n", CSV_HEADER.replace("timestampms,received_ms", "timestamp,received"))),
+            _ => None,
+        }
+    }
+
+    pub fn trade(&self, symbol: &str, ctx: &EventContext, t: &Trade) -> String {
+        let p = &self.precision;
+        match self.format {
+            OutputFormat::Human => {
+                let origin = match (ctx.backfill, ctx.recovered) {
+                    (true, _) => " (backfill)",
+                    (_, true) => " (recovered)",
+                    _ => "",
+                };
+                format!(
+                    "{}Trade {{ price: {}, amount: {}, maker_side: {:?} }} ${}{}\n",
+                    self.human_prefix(symbol, ctx), p.price(t.price), p.amount(t.amount), t.maker_side,
+                    p.notional_text(t.amount * t.price), origin,
+                )
+            },
+            OutputFormat::Jsonl => self.json_line("trade", symbol, ctx, &Trade {
+                price: p.price(t.price),
+                amount: p.amount(t.amount),
+                ..t.clone()
+            }),
+            OutputFormat::Csv => self.csv_line("trade", symbol, ctx, [
+                p.price(t.price).to_string(), p.amount(t.amount).to_string(), t.maker_side.as_str().to_string(),
+                String::new(), String::new(), String::new(), String::new(), String::new(), String::new(),
+            ]),
+        }
+    }
+
+    // A trade on the consolidated tape, `index` picks the color of its symbol
+    pub fn tape_trade(&self, symbol: &str, index: usize, ctx: &EventContext, t: &Trade) -> String {
+        let side = match t.aggressor() {
+            Some(OrderSide::Buy) => "BUY",
+            Some(OrderSide::Sell) => "SELL",
+            None => "",
+        };
+        match self.format {
+            OutputFormat::Human => self.tape_line(symbol, index, ctx, side, t.price, t.amount),
+            _ => self.trade(symbol, ctx, t),
+        }
+    }
+
+    pub fn tape_block_trade(&self, symbol: &str, index: usize, ctx: &EventContext, t: &BlockTrade) -> String {
+        match self.format {
+            OutputFormat::Human => self.tape_line(symbol, index, ctx, "BLOCK", t.price, t.amount),
+            _ => self.block_trade(symbol, ctx, t),
+        }
+    }
+
+    // Every print is timed, by the exchange's clock
+    fn tape_line(&self, symbol: &str, index: usize, ctx: &EventContext, side: &str, price: Decimal, amount: Decimal) -> String {
+        let time = self.time(ctx.timestampms.unwrap_or(ctx.received_ms));
+        let symbol = match self.color {
+            true => format!("{}{:<10}{}", SYMBOL_COLORS[index % SYMBOL_COLORS.len()], symbol, RESET),
+            false => format!("{:<10}", symbol),
+        };
+        let p = &self.precision;
+        format!("{} {} {:<5} {} @ {} ${}\n", time, symbol, side, p.amount(amount), p.price(price), p.notional_text(amount * price))
+    }
+
+    pub fn block_trade(&self, symbol: &str, ctx: &EventContext, t: &BlockTrade) -> String {
+        let p = &self.precision;
+        match self.format {
+            OutputFormat::Human => format!(
+                "{}BLOCK BlockTrade {{ tid: {}, price: {}, amount: {} }} ${}\n",
+                self.human_prefix(symbol, ctx), t.tid, p.price(t.price), p.amount(t.amount), p.notional_text(t.amount * t.price),
+            ),
+            OutputFormat::Jsonl => self.json_line("block_trade", symbol, ctx, &BlockTrade {
+                price: p.price(t.price),
+                amount: p.amount(t.amount),
+                ..t.clone()
+            }),
+            OutputFormat::Csv => self.csv_line("block_trade", symbol, ctx, [
+                p.price(t.price).to_string(), p.amount(t.amount).to_string(), String::new(),
+                String::new(), String::new(), String::new(), String::new(), String::new(), String::new(),
+            ]),
+        }
+    }
+
+    // Individual quote changes are folded into the BBO line in human mode
+    pub fn quote(&self, symbol: &str, ctx: &EventContext, q: &Quote) -> Option<String> {
+        let p = &self.precision;
+        match self.format {
+            OutputFormat::Human => None,
+            OutputFormat::Jsonl => Some(self.json_line("quote", symbol, ctx, &Quote {
+                price: p.price(q.price),
+                remaining: p.amount(q.remaining),
+                delta: q.delta.map(|d| p.amount(d)),
+                ..q.clone()
+            })),
+            OutputFormat::Csv => Some(self.csv_line("quote", symbol, ctx, [
+                p.price(q.price).to_string(), p.amount(q.remaining).to_string(), q.side.as_str().to_string(),
+                q.reason.clone(), q.delta.map(|d| p.amount(d).to_string()).unwrap_or_default(),
+                String::new(), String::new(), String::new(), String::new(),
+            ])),
+        }
+    }
+
+    // `previous` is the last BBO printed for the symbol, it decides the price colors
+    pub fn bbo(&self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer, previous: Option<&BestBidOffer>) -> String {
+        let p = &self.precision;
+        match self.format {
+            OutputFormat::Human => format!(
+                "{}BestBidOffer {{ best_bid: {}, best_offer: {}, bid_amount_remaining: {}, ask_amount_remaining: {} }}\n",
+                self.human_prefix(symbol, ctx),
+                self.tick(bbo.best_bid, previous.map(|p| p.best_bid)),
+                self.tick(bbo.best_offer, previous.map(|p| p.best_offer)),
+                p.amount(bbo.bid_amount_remaining),
+                p.amount(bbo.ask_amount_remaining),
+            ),
+            OutputFormat::Jsonl => self.json_line("bbo", symbol, ctx, &BestBidOffer {
+                best_bid: p.price(bbo.best_bid),
+                best_offer: p.price(bbo.best_offer),
+                bid_amount_remaining: p.amount(bbo.bid_amount_remaining),
+                ask_amount_remaining: p.amount(bbo.ask_amount_remaining),
+            }),
+            OutputFormat::Csv => self.csv_line("bbo", symbol, ctx, [
+                String::new(), String::new(), String::new(), String::new(), String::new(),
+                p.price(bbo.best_bid).to_string(), p.amount(bbo.bid_amount_remaining).to_string(),
+                p.price(bbo.best_offer).to_string(), p.amount(bbo.ask_amount_remaining).to_string(),
+            ]),
+        }
+    }
+
+    pub fn auction(&self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> String {
+        let p = &self.precision;
+        let price = |v: Option<Decimal>| v.map(|v| p.price(v).to_string()).unwrap_or_else(|| String::from("-"));
+        let quantity = |v: Option<Decimal>| v.map(|v| p.amount(v).to_string()).unwrap_or_else(|| String::from("-"));
+        match self.format {
+            OutputFormat::Human => {
+                let text = match auction {
+                    AuctionEvent::Open(a) => format!(
+                        "Auction open, runs at {} (first indicative {}, last cancel {})",
+                        self.time(a.auction_time_ms),
+                        a.first_indicative_ms.map(|t| self.time(t)).unwrap_or_else(|| String::from("-")),
+                        a.last_cancel_time_ms.map(|t| self.time(t)).unwrap_or_else(|| String::from("-")),
+                    ),
+                    AuctionEvent::Indicative(a) => format!(
+                        "Auction indicative {}: price {} qty {} collar {} (bid {} / ask {})",
+                        a.result, price(a.indicative_price), quantity(a.indicative_quantity),
+                        price(a.collar_price), price(a.highest_bid_price), price(a.lowest_ask_price),
+                    ),
+                    AuctionEvent::Result(a) => format!(
+                        "Auction result {}: price {} qty {} collar {} (bid {} / ask {})",
+                        a.result, price(a.auction_price), quantity(a.auction_quantity),
+                        price(a.collar_price), price(a.highest_bid_price), price(a.lowest_ask_price),
+                    ),
+                };
+                format!("{}{}\n", self.human_prefix(symbol, ctx), text)
+            },
+            OutputFormat::Jsonl => self.json_line("auction", symbol, ctx, &self.fixed_auction(auction)),
+            OutputFormat::Csv => {
+                let (phase, price, quantity) = match auction {
+                    AuctionEvent::Open(_) => ("open", None, None),
+                    AuctionEvent::Indicative(a) => ("indicative", a.indicative_price, a.indicative_quantity),
+                    AuctionEvent::Result(a) => ("result", a.auction_price, a.auction_quantity),
+                };
+                self.csv_line("auction", symbol, ctx, [
+                    price.map(|v| p.price(v).to_string()).unwrap_or_default(),
+                    quantity.map(|v| p.amount(v).to_string()).unwrap_or_default(),
+                    String::new(), phase.to_string(), String::new(),
+                    String::new(), String::new(), String::new(), String::new(),
+                ])
+            },
+        }
+    }
+
+    fn fixed_auction(&self, auction: &AuctionEvent) -> AuctionEvent {
+        let p = &self.precision;
+        let price = |v: Option<Decimal>| v.map(|v| p.price(v));
+        let quantity = |v: Option<Decimal>| v.map(|v| p.amount(v));
+        match auction {
+            AuctionEvent::Open(a) => AuctionEvent::Open(a.clone()),
+            AuctionEvent::Indicative(a) => AuctionEvent::Indicative(AuctionIndicative {
+                highest_bid_price: price(a.highest_bid_price),
+                lowest_ask_price: price(a.lowest_ask_price),
+                collar_price: price(a.collar_price),
+                indicative_price: price(a.indicative_price),
+                indicative_quantity: quantity(a.indicative_quantity),
+                ..a.clone()
+            }),
+            AuctionEvent::Result(a) => AuctionEvent::Result(AuctionResult {
+                highest_bid_price: price(a.highest_bid_price),
+                lowest_ask_price: price(a.lowest_ask_price),
+                collar_price: price(a.collar_price),
+                auction_price: price(a.auction_price),
+                auction_quantity: quantity(a.auction_quantity),
+                ..a.clone()
+            }),
+        }
+    }
+
+    // Rolling trade statistics have no fixed CSV columns, so CSV output skips them
+    pub fn stats(&self, symbol: &str, ctx: &EventContext, stats: &TradeStatsSnapshot) -> Option<String> {
+        match self.format {
+            OutputFormat::Human => {
+                let p = &self.precision;
+                let windows: Vec<String> = stats.windows.iter().map(|w| format!(
+                    "{}: {} trades, volume {} (buy {} / sell {}), notional ${}, vwap {}{}",
+                    w.window, w.trades, p.amount(w.volume), p.amount(w.buy_volume), p.amount(w.sell_volume),
+                    p.notional_text(if p.fixes_notional() { w.notional } else { w.notional.round_dp(2) }),
+                    w.vwap.map(|v| p.price(v).to_string()).unwrap_or_else(|| String::from("-")),
+                    if w.tainted { " (tainted)" } else { "" },
+                )).collect();
+                Some(format!(
+                    "{}Stats {} | delta {}\n",
+                    self.human_prefix(symbol, ctx), windows.join(" | "), p.amount(stats.cumulative_delta),
+                ))
+            },
+            OutputFormat::Jsonl => Some(self.json_line("stats", symbol, ctx, stats)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    pub fn candle(&self, symbol: &str, ctx: &EventContext, c: &Candle) -> Option<String> {
+        match self.format {
+            OutputFormat::Human => Some(format!(
+                "{}Candle {} {} O {} H {} L {} C {} V {} ({}){}\n",
+                self.human_prefix(symbol, ctx), c.interval, self.time(c.start_ms),
+                self.precision.price(c.open), self.precision.price(c.high), self.precision.price(c.low),
+                self.precision.price(c.close), self.precision.amount(c.volume),
+                if c.exchange { String::from("exchange") } else { format!("{} trades", c.trades) },
+                if c.tainted { " (tainted)" } else { "" },
+            )),
+            OutputFormat::Jsonl => Some(self.json_line("candle", symbol, ctx, c)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    // Nothing to print while every indicator is still warming up
+    pub fn technical(&self, symbol: &str, ctx: &EventContext, t: &TechnicalSnapshot) -> Option<String> {
+        if t.values.is_empty() {
+            return None;
+        }
+        match self.format {
+            OutputFormat::Human => {
+                let values: Vec<String> = t.values.iter().map(|(name, value)| format!("{} {}", name, value)).collect();
+                Some(format!("{}TA {} {} {}\n", self.human_prefix(symbol, ctx), t.interval, self.time(t.start_ms), values.join(" ")))
+            },
+            OutputFormat::Jsonl => Some(self.json_line("technical", symbol, ctx, t)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    pub fn indicators(&self, symbol: &str, ctx: &EventContext, i: &BookIndicators) -> Option<String> {
+        match self.format {
+            OutputFormat::Human => {
+                let band = i.band.map(|b| format!(" (band {})", b)).unwrap_or_default();
+                let tainted = if i.tainted { " (tainted)" } else { "" };
+                Some(format!("{}Imbalance {} microprice {}{}{}\n", self.human_prefix(symbol, ctx), i.imbalance, i.microprice, band, tainted))
+            },
+            OutputFormat::Jsonl => Some(self.json_line("indicators", symbol, ctx, i)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    pub fn depth(&self, symbol: &str, ctx: &EventContext, d: &DepthSnapshot) -> Option<String> {
+        let p = &self.precision;
+        let price = |v: Option<Decimal>| v.map(|v| p.price(v).to_string()).unwrap_or_else(|| String::from("-"));
+        match self.format {
+            OutputFormat::Human => {
+                let mut parts = Vec::new();
+                if let (Some(bid), Some(ask)) = (&d.bid, &d.ask) {
+                    parts.push(format!(
+                        "bid {} @ {} over {} | ask {} @ {} over {} | mid {}",
+                        p.amount(bid.amount), p.price(bid.price), bid.levels,
+                        p.amount(ask.amount), p.price(ask.price), ask.levels, price(d.weighted_mid),
+                    ));
+                }
+                for size in &d.sizes {
+                    let spread = size.spread_bps.map(|s| format!(" ({} bps)", s)).unwrap_or_default();
+                    parts.push(format!("{}: buy {} sell {}{}", p.amount(size.amount), price(size.buy), price(size.sell), spread));
+                }
+                let tainted = if d.tainted { " (tainted)" } else { "" };
+                Some(format!("{}Depth {}{}\n", self.human_prefix(symbol, ctx), parts.join(" | "), tainted))
+            },
+            OutputFormat::Jsonl => Some(self.json_line("depth", symbol, ctx, d)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    pub fn volatility(&self, symbol: &str, ctx: &EventContext, v: &VolatilitySnapshot) -> Option<String> {
+        match self.format {
+            OutputFormat::Human => {
+                let windows: Vec<String> = v.windows.iter().map(|w| format!(
+                    "{}: {:.4}% ({:.1}% annualized), return {:+.1} bps",
+                    w.window, w.realized_vol * 100., w.annualized_vol * 100., w.return_bps,
+                )).collect();
+                Some(format!("{}Volatility {}\n", self.human_prefix(symbol, ctx), windows.join(" | ")))
+            },
+            OutputFormat::Jsonl => Some(self.json_line("volatility", symbol, ctx, v)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    pub fn dwell(&self, symbol: &str, ctx: &EventContext, d: &DwellSnapshot) -> Option<String> {
+        let side = |s: &SideDwell| format!(
+            "{} levels, mean {}ms p50 {}ms p90 {}ms p99 {}ms, {} flickers ({:.1}%)",
+            s.levels, s.mean_ms, s.p50_ms, s.p90_ms, s.p99_ms, s.flickers, s.flicker_rate * 100.,
+        );
+        match self.format {
+            OutputFormat::Human => Some(format!(
+                "{}Dwell {}: bid {} | ask {}\n",
+                self.human_prefix(symbol, ctx), d.window, side(&d.bid), side(&d.ask),
+            )),
+            OutputFormat::Jsonl => Some(self.json_line("dwell", symbol, ctx, d)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    pub fn ofi(&self, symbol: &str, ctx: &EventContext, o: &OfiSnapshot) -> Option<String> {
+        match self.format {
+            OutputFormat::Human => {
+                let windows: Vec<String> = o.windows.iter().map(|w| format!(
+                    "{}: {:+} over {} changes{}",
+                    w.window, w.ofi, w.changes, if w.tainted { " (tainted)" } else { "" },
+                )).collect();
+                Some(format!("{}OFI {}\n", self.human_prefix(symbol, ctx), windows.join(" | ")))
+            },
+            OutputFormat::Jsonl => Some(self.json_line("ofi", symbol, ctx, o)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    pub fn anomaly(&self, symbol: &str, ctx: &EventContext, a: &AnomalyEvent) -> Option<String> {
+        match self.format {
+            OutputFormat::Human => {
+                let text = match a.anomaly {
+                    AnomalyKind::TradeAwayFromMid => format!(
+                        "trade {} at {} is {:+}% from the rolling mid {}",
+                        a.amount.unwrap_or_default(), a.value, a.deviation, a.reference,
+                    ),
+                    AnomalyKind::SpreadBlowout => format!(
+                        "spread {} is {}x the rolling average {}",
+                        a.value, a.deviation, a.reference,
+                    ),
+                    AnomalyKind::StaleQuotes => format!(
+                        "{} at {} unchanged for {}s, more than {}s",
+                        a.side.unwrap_or_default().as_str(), a.reference, a.value, a.threshold,
+                    ),
+                };
+                Some(format!("{}ANOMALY {}\n", self.human_prefix(symbol, ctx), text))
+            },
+            OutputFormat::Jsonl => Some(self.json_line("anomaly", symbol, ctx, a)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    pub fn cross(&self, symbol: &str, ctx: &EventContext, c: &CrossDivergence) -> Option<String> {
+        match self.format {
+            OutputFormat::Human => {
+                let state = match c.diverged {
+                    true => "diverged",
+                    false => "back in line",
+                };
+                Some(format!(
+                    "{}Cross {} {}: actual {} implied {} ({} bps)\n",
+                    self.human_prefix(symbol, ctx), c.cross, state, c.actual, c.implied, c.divergence_bps,
+                ))
+            },
+            OutputFormat::Jsonl => Some(self.json_line("cross", symbol, ctx, c)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    pub fn funding(&self, symbol: &str, ctx: &EventContext, f: &FundingAmount) -> Option<String> {
+        match self.format {
+            OutputFormat::Human => {
+                let estimated = f.estimated_amount.map(|e| format!(" (est. next {})", e)).unwrap_or_default();
+                let next = match self.timezone {
+                    Some(_) => self.time(f.next_funding_ms),
+                    None => humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_millis(f.next_funding_ms)).to_string(),
+                };
+                Some(format!("{}Funding {}{}, next at {}\n", self.human_prefix(symbol, ctx), f.amount, estimated, next))
+            },
+            OutputFormat::Jsonl => Some(self.json_line("funding", symbol, ctx, f)),
+            OutputFormat::Csv => None,
+        }
+    }
+
+    fn json_line<T: Serialize>(&self, kind: &'static str, symbol: &str, ctx: &EventContext, data: &T) -> String {
+        let mut line = Record::new(kind, symbol, ctx, data).to_json();
+        line.push('\n');
+        line
+    }
+
+    fn csv_line(&self, kind: &str, symbol: &str, ctx: &EventContext, fields: [String; 9]) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            kind,
+            symbol,
+            ctx.event_id,
+            ctx.socket_sequence,
+            ctx.timestampms.map(|t| self.time(t)).unwrap_or_default(),
+            self.time(ctx.received_ms),
+            fields.join(","),
+            ctx.received_ns,
+            now_ns(),
+        )
+    }
+}
+
+enum Destination<W> {
+    Stream(W),
+    Files(TemplatedFiles),
+}
+
+// The built-in line output: formats every event and writes it to `out`, or to the files
+// of a path template
+pub struct Printer<W> {
+    formatter: Formatter,
+    out: Destination<W>,
+    verbose: bool,
+    last_bbo: HashMap<String, BestBidOffer>,
+}
+
+impl<W: Write + Send> Printer<W> {
+    // Writes the format's header, if it has one, right away
+    pub fn new(formatter: Formatter, mut out: W) -> Result<Self, GeminiError> {
+        if let Some(header) = formatter.header() {
+            out.write_all(header.as_bytes())?;
+        }
+        Ok(Self {
+            formatter,
+            out: Destination::Stream(out),
+            verbose: false,
+            last_bbo: HashMap::new(),
+        })
+    }
+
+    // Print every quote of the initial book snapshot too
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    fn write(&mut self, symbol: &str, kind: &str, ctx: &EventContext, line: Option<String>) -> Result<(), GeminiError> {
+        let Some(line) = line else {
+            return Ok(());
+        };
+        match &mut self.out {
+            Destination::Stream(out) => out.write_all(line.as_bytes())?,
+            Destination::Files(files) => files.write(symbol, kind, ctx.timestampms.unwrap_or(ctx.received_ms), &line)?,
+        }
+        Ok(())
+    }
+}
+
+impl Printer<std::io::Sink> {
+    // Every file gets the format's header when it is created
+    pub fn to_files(formatter: Formatter, template: PathTemplate) -> Self {
+        let header = formatter.header();
+        Self {
+            formatter,
+            out: Destination::Files(TemplatedFiles::new(template, header)),
+            verbose: false,
+            last_bbo: HashMap::new(),
+        }
+    }
+}
+
+impl<W: Write + Send> EventHandler for Printer<W> {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.write(symbol, "trade", ctx, Some(self.formatter.trade(symbol, ctx, trade)))
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        if quote.reason == "initial" && !self.verbose {
+            return Ok(());
+        }
+        self.write(symbol, "quote", ctx, self.formatter.quote(symbol, ctx, quote))
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        let previous = self.last_bbo.insert(symbol.to_string(), bbo.clone());
+        self.write(symbol, "bbo", ctx, Some(self.formatter.bbo(symbol, ctx, bbo, previous.as_ref())))
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.write(symbol, "block_trade", ctx, Some(self.formatter.block_trade(symbol, ctx, trade)))
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.write(symbol, "auction", ctx, Some(self.formatter.auction(symbol, ctx, auction)))
+    }
+
+    fn on_stats(&mut self, symbol: &str, ctx: &EventContext, stats: &TradeStatsSnapshot) -> Result<(), GeminiError> {
+        self.write(symbol, "stats", ctx, self.formatter.stats(symbol, ctx, stats))
+    }
+
+    fn on_candle(&mut self, symbol: &str, ctx: &EventContext, candle: &Candle) -> Result<(), GeminiError> {
+        self.write(symbol, "candle", ctx, self.formatter.candle(symbol, ctx, candle))
+    }
+
+    fn on_technical(&mut self, symbol: &str, ctx: &EventContext, technical: &TechnicalSnapshot) -> Result<(), GeminiError> {
+        self.write(symbol, "technical", ctx, self.formatter.technical(symbol, ctx, technical))
+    }
+
+    fn on_indicators(&mut self, symbol: &str, ctx: &EventContext, indicators: &BookIndicators) -> Result<(), GeminiError> {
+        self.write(symbol, "indicators", ctx, self.formatter.indicators(symbol, ctx, indicators))
+    }
+
+    fn on_depth(&mut self, symbol: &str, ctx: &EventContext, depth: &DepthSnapshot) -> Result<(), GeminiError> {
+        self.write(symbol, "depth", ctx, self.formatter.depth(symbol, ctx, depth))
+    }
+
+    fn on_volatility(&mut self, symbol: &str, ctx: &EventContext, volatility: &VolatilitySnapshot) -> Result<(), GeminiError> {
+        self.write(symbol, "volatility", ctx, self.formatter.volatility(symbol, ctx, volatility))
+    }
+
+    fn on_dwell(&mut self, symbol: &str, ctx: &EventContext, dwell: &DwellSnapshot) -> Result<(), GeminiError> {
+        self.write(symbol, "dwell", ctx, self.formatter.dwell(symbol, ctx, dwell))
+    }
+
+    fn on_ofi(&mut self, symbol: &str, ctx: &EventContext, ofi: &OfiSnapshot) -> Result<(), GeminiError> {
+        self.write(symbol, "ofi", ctx, self.formatter.ofi(symbol, ctx, ofi))
+    }
+
+    fn on_anomaly(&mut self, symbol: &str, ctx: &EventContext, anomaly: &AnomalyEvent) -> Result<(), GeminiError> {
+        self.write(symbol, "anomaly", ctx, self.formatter.anomaly(symbol, ctx, anomaly))
+    }
+
+    fn on_cross(&mut self, symbol: &str, ctx: &EventContext, cross: &CrossDivergence) -> Result<(), GeminiError> {
+        self.write(symbol, "cross", ctx, self.formatter.cross(symbol, ctx, cross))
+    }
+
+    fn on_funding(&mut self, symbol: &str, ctx: &EventContext, funding: &FundingAmount) -> Result<(), GeminiError> {
+        self.write(symbol, "funding", ctx, self.formatter.funding(symbol, ctx, funding))
+    }
+
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        if let Destination::Stream(out) = &mut self.out {
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "printer"
+    }
+}
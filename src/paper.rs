@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::client::now_ms;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{BestBidOffer, OrderSide, Trade};
+use crate::output::EventContext;
+
+pub const USAGE: &str = "commands: buy|sell AMOUNT SYMBOL [PRICE], cancel ID, orders, positions";
+
+// A market order when `limit` is missing
+#[derive(Debug, Clone)]
+pub struct OrderRequest {
+    pub side: OrderSide,
+    pub symbol: String,
+    pub amount: Decimal,
+    pub limit: Option<Decimal>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PaperCommand {
+    Submit(OrderRequest),
+    Cancel(u64),
+    Orders,
+    Positions,
+}
+
+impl FromStr for PaperCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let decimal = |word: &str| match Decimal::from_str(word) {
+            Ok(value) if value > Decimal::ZERO => Ok(value),
+            _ => Err(format!("`{}` is not a positive number", word)),
+        };
+        match words.as_slice() {
+            [side @ ("buy" | "sell"), amount, symbol, rest @ ..] if rest.len() <= 1 => Ok(PaperCommand::Submit(OrderRequest {
+                side: match *side {
+                    "buy" => OrderSide::Buy,
+                    _ => OrderSide::Sell,
+                },
+                symbol: symbol.to_lowercase(),
+                amount: decimal(amount)?,
+                limit: rest.first().map(|price| decimal(price)).transpose()?,
+            })),
+            ["cancel", id] => id.parse().map(PaperCommand::Cancel).map_err(|_| format!("`{}` is not an order id", id)),
+            ["orders"] => Ok(PaperCommand::Orders),
+            ["positions"] => Ok(PaperCommand::Positions),
+            _ => Err(format!("unknown command `{}`, {}", s.trim(), USAGE)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PaperOptions {
+    // Time between submitting an order and it reaching the simulated exchange
+    pub latency: Duration,
+    pub maker_fee_bps: Decimal,
+    pub taker_fee_bps: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Liquidity {
+    Maker,
+    Taker,
+}
+
+struct Order {
+    id: u64,
+    request: OrderRequest,
+    active_at_ms: u64,
+    remaining: Decimal,
+    // A limit order that did not cross on arrival only fills as maker afterwards
+    resting: bool,
+}
+
+#[derive(Default)]
+struct Position {
+    amount: Decimal,
+    // Quote currency spent and received, fees included
+    cash: Decimal,
+    fees: Decimal,
+}
+
+pub struct Fill {
+    order_id: u64,
+    side: OrderSide,
+    symbol: String,
+    price: Decimal,
+    amount: Decimal,
+    liquidity: Liquidity,
+    fee: Decimal,
+    done: bool,
+}
+
+impl fmt::Display for Fill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let liquidity = match self.liquidity {
+            Liquidity::Maker => "maker",
+            Liquidity::Taker => "taker",
+        };
+        let state = if self.done { "filled" } else { "partial" };
+        write!(
+            f,
+            "FILL #{} {} {} {} @ {} ({}, fee {}, {})",
+            self.order_id, self.side, self.amount, self.symbol, self.price, liquidity, self.fee.normalize(), state,
+        )
+    }
+}
+
+// Simulated account: open orders, positions and the latest book of every symbol.
+// Market orders take the touch in full, limit orders that rest fill at their price
+// once a trade prints at or through it or the opposite side moves onto it.
+pub struct PaperTrader {
+    options: PaperOptions,
+    symbols: Vec<String>,
+    next_id: u64,
+    open: Vec<Order>,
+    books: HashMap<String, BestBidOffer>,
+    positions: HashMap<String, Position>,
+}
+
+impl PaperTrader {
+    pub fn new(options: PaperOptions, symbols: &[String]) -> Self {
+        Self {
+            options,
+            symbols: symbols.to_vec(),
+            next_id: 1,
+            open: Vec::new(),
+            books: HashMap::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    // Runs one command line, returning the reply and any fills it caused right away
+    pub fn execute(&mut self, line: &str, now: u64) -> (String, Vec<Fill>) {
+        let command = match PaperCommand::from_str(line) {
+            Ok(command) => command,
+            Err(e) => return (format!("error: {}", e), Vec::new()),
+        };
+        match command {
+            PaperCommand::Submit(request) => self.submit(request, now),
+            PaperCommand::Cancel(id) => (self.cancel(id), Vec::new()),
+            PaperCommand::Orders => (self.orders(), Vec::new()),
+            PaperCommand::Positions => (self.positions(), Vec::new()),
+        }
+    }
+
+    fn submit(&mut self, request: OrderRequest, now: u64) -> (String, Vec<Fill>) {
+        // An empty list means a replay of whatever the capture holds
+        if !self.symbols.is_empty() && !self.symbols.contains(&request.symbol) {
+            return (format!("error: {} is not subscribed", request.symbol), Vec::new());
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let symbol = request.symbol.clone();
+        self.open.push(Order {
+            id,
+            remaining: request.amount,
+            request,
+            active_at_ms: now + self.options.latency.as_millis() as u64,
+            resting: false,
+        });
+        (format!("accepted #{}", id), self.match_orders(&symbol, now, None))
+    }
+
+    fn cancel(&mut self, id: u64) -> String {
+        match self.open.iter().position(|o| o.id == id) {
+            Some(index) => {
+                let order = self.open.remove(index);
+                format!("cancelled #{} with {} left", id, order.remaining)
+            },
+            None => format!("error: no open order #{}", id),
+        }
+    }
+
+    fn orders(&self) -> String {
+        if self.open.is_empty() {
+            return String::from("no open orders");
+        }
+        let lines: Vec<String> = self.open.iter()
+            .map(|o| {
+                let price = o.request.limit.map_or(String::from("market"), |p| p.to_string());
+                format!("#{} {} {} {} @ {}", o.id, o.request.side, o.remaining, o.request.symbol, price)
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    fn positions(&self) -> String {
+        if self.positions.is_empty() {
+            return String::from("no positions");
+        }
+        let mut symbols: Vec<&String> = self.positions.keys().collect();
+        symbols.sort();
+        let lines: Vec<String> = symbols.into_iter()
+            .map(|symbol| {
+                let position = &self.positions[symbol];
+                let pnl = match self.books.get(symbol).and_then(mid) {
+                    Some(mid) => (position.cash + position.amount * mid).round_dp(8).normalize().to_string(),
+                    None => String::from("-"),
+                };
+                format!("{} position {} cash {} fees {} pnl {}", symbol, position.amount, position.cash.normalize(), position.fees.normalize(), pnl)
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    pub fn on_book_update(&mut self, symbol: &str, bbo: &BestBidOffer, now: u64) -> Vec<Fill> {
+        self.books.insert(symbol.to_string(), bbo.clone());
+        self.match_orders(symbol, now, None)
+    }
+
+    pub fn on_trade(&mut self, symbol: &str, trade: &Trade, now: u64) -> Vec<Fill> {
+        self.match_orders(symbol, now, Some(trade))
+    }
+
+    fn match_orders(&mut self, symbol: &str, now: u64, trade: Option<&Trade>) -> Vec<Fill> {
+        let Some(bbo) = self.books.get(symbol) else {
+            return Vec::new();
+        };
+        let mut fills = Vec::new();
+        for order in self.open.iter_mut().filter(|o| o.request.symbol == symbol && o.active_at_ms <= now) {
+            let side = order.request.side;
+            let touch = match side {
+                OrderSide::Buy => bbo.best_offer,
+                OrderSide::Sell => bbo.best_bid,
+            };
+            let crosses = |price: Decimal, limit: Decimal| match side {
+                OrderSide::Buy => price <= limit,
+                OrderSide::Sell => price >= limit,
+            };
+            let fill = match order.request.limit {
+                None if touch.is_zero() => None,
+                None => Some((touch, order.remaining, Liquidity::Taker)),
+                Some(limit) if !order.resting => {
+                    order.resting = true;
+                    match !touch.is_zero() && crosses(touch, limit) {
+                        true => Some((touch, order.remaining, Liquidity::Taker)),
+                        false => None,
+                    }
+                },
+                Some(limit) => match trade {
+                    Some(trade) if crosses(trade.price, limit) => Some((limit, order.remaining.min(trade.amount), Liquidity::Maker)),
+                    None if !touch.is_zero() && crosses(touch, limit) => Some((limit, order.remaining, Liquidity::Maker)),
+                    _ => None,
+                },
+            };
+            let Some((price, amount, liquidity)) = fill else {
+                continue;
+            };
+            let fee_bps = match liquidity {
+                Liquidity::Maker => self.options.maker_fee_bps,
+                Liquidity::Taker => self.options.taker_fee_bps,
+            };
+            let fee = price * amount * fee_bps / Decimal::from(10_000);
+            let signed = match side {
+                OrderSide::Buy => amount,
+                OrderSide::Sell => -amount,
+            };
+            let position = self.positions.entry(symbol.to_string()).or_default();
+            position.amount += signed;
+            position.cash -= signed * price + fee;
+            position.fees += fee;
+            order.remaining -= amount;
+            fills.push(Fill {
+                order_id: order.id,
+                side,
+                symbol: symbol.to_string(),
+                price,
+                amount,
+                liquidity,
+                fee,
+                done: order.remaining.is_zero(),
+            });
+        }
+        self.open.retain(|o| !o.remaining.is_zero());
+        fills
+    }
+
+    // Closing report for stderr
+    pub fn summary(&self) -> String {
+        format!("Paper trading summary\n{}\n{}\n", self.positions(), self.orders())
+    }
+}
+
+fn mid(bbo: &BestBidOffer) -> Option<Decimal> {
+    match bbo.best_bid.is_zero() || bbo.best_offer.is_zero() {
+        true => None,
+        false => Some((bbo.best_bid + bbo.best_offer) / Decimal::TWO),
+    }
+}
+
+fn print_fills(fills: Vec<Fill>) {
+    for fill in fills {
+        println!("{}", fill);
+    }
+}
+
+// Feeds the market into the simulated account and prints fills as they happen
+pub struct PaperHandler {
+    trader: Arc<Mutex<PaperTrader>>,
+}
+
+impl PaperHandler {
+    pub fn new(trader: Arc<Mutex<PaperTrader>>) -> Self {
+        Self { trader }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PaperTrader> {
+        // A panic elsewhere leaves the account as consistent as any single fill
+        self.trader.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl EventHandler for PaperHandler {
+    fn on_trade(&mut self, symbol: &str, _ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        print_fills(self.lock().on_trade(symbol, trade, now_ms()));
+        Ok(())
+    }
+
+    fn on_book_update(&mut self, symbol: &str, _ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        print_fills(self.lock().on_book_update(symbol, bbo, now_ms()));
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "paper"
+    }
+}
+
+fn run_line(trader: &Mutex<PaperTrader>, line: &str) -> (String, Vec<Fill>) {
+    trader.lock().unwrap_or_else(|e| e.into_inner()).execute(line, now_ms())
+}
+
+// Answers commands typed on stdin. A plain thread rather than `tokio::io::stdin`,
+// whose pending read would keep the runtime from shutting down.
+pub fn read_stdin(trader: Arc<Mutex<PaperTrader>>) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    warn!(error = %e, "paper: stdin failed");
+                    return;
+                },
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (reply, fills) = run_line(&trader, &line);
+            println!("{}", reply);
+            print_fills(fills);
+        }
+    });
+}
+
+// Answers one command per line until the input closes or the session ends
+async fn read_commands<R, W>(
+    trader: Arc<Mutex<PaperTrader>>,
+    input: R,
+    mut output: W,
+    shutdown: CancellationToken,
+) -> Result<(), GeminiError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = BufReader::new(input).lines();
+    loop {
+        let line = tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            line = lines.next_line() => match line? {
+                Some(line) => line,
+                None => return Ok(()),
+            },
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (reply, fills) = run_line(&trader, &line);
+        output.write_all(format!("{}\n", reply).as_bytes()).await?;
+        output.flush().await?;
+        print_fills(fills);
+    }
+}
+
+// Binds `addr` right away so a taken port fails at startup. Every TCP client gets
+// the same line protocol as stdin.
+pub async fn listen(
+    trader: Arc<Mutex<PaperTrader>>,
+    addr: SocketAddr,
+    shutdown: CancellationToken,
+) -> Result<impl std::future::Future<Output = ()>, GeminiError> {
+    let listener = TcpListener::bind(addr).await?;
+    info!(%addr, "accepting paper trading commands");
+    Ok(async move {
+        loop {
+            let (stream, peer) = tokio::select! {
+                _ = shutdown.cancelled() => return,
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!(error = %e, "paper: accept failed");
+                        continue;
+                    },
+                },
+            };
+            let (read, write) = stream.into_split();
+            let trader = trader.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                if let Err(e) = read_commands(trader, read, write, shutdown).await {
+                    info!(%peer, error = %e, "paper: client dropped");
+                }
+            });
+        }
+    })
+}
@@ -0,0 +1,1269 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::analytics::anomaly::{AnomalyDetector, AnomalyEvent, AnomalyOptions};
+use crate::analytics::candles::{Candle, CandleAggregator};
+use crate::analytics::cross::{CrossMonitor, CrossRule};
+use crate::analytics::depth::{DepthOptions, DepthSnapshot};
+use crate::analytics::dwell::{DwellSnapshot, DwellStats};
+use crate::analytics::indicators::{self, BookIndicators};
+use crate::analytics::technical::{IndicatorSpec, TechnicalIndicators};
+use crate::analytics::volatility::VolatilityEstimator;
+use crate::analytics::latency::LatencyTracker;
+use crate::analytics::ofi::{OfiSnapshot, OrderFlow};
+use crate::analytics::spread::{SpreadTracker, DEFAULT_THRESHOLDS_BPS};
+use crate::analytics::stale::QuoteClock;
+use crate::analytics::vwap::{TradeStats, TradeStatsSnapshot};
+use crate::analytics::Interruption;
+use crate::book::{OrderBook, TopOfBook, TopOfBookClock};
+use crate::client::FeedOptions;
+use crate::dedup::{EventIds, EventOrder};
+use crate::downsample::Downsampled;
+use crate::error::GeminiError;
+use crate::exchange::{self, Exchange};
+use crate::handler::{EventHandler, HandlerEvent, HandlerMessage, HandlerTask};
+use crate::leader::Leadership;
+use crate::market_state::MarketState;
+use crate::metrics;
+use crate::models::*;
+use crate::output::EventContext;
+use crate::positions::Positions;
+use crate::queue::QueueOptions;
+use crate::rest::RestClient;
+use crate::routing::Route;
+use crate::state::{DumpedSymbol, SavedState, SavedSymbol, StateDump};
+use crate::status::FeedStatus;
+use crate::summary::SessionSummary;
+use crate::throttle::{ThrottleOptions, Throttled};
+use crate::ticks::Increments;
+
+const LATENCY_WINDOW: Duration = Duration::from_secs(60);
+// How often latency percentiles are published when they are not logged
+const LATENCY_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_MAX_CLOCK_SKEW: Duration = Duration::from_secs(1);
+// How often the quote ages are published and checked against the stale window
+const QUOTE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+struct SymbolState {
+    book: OrderBook,
+    summary: SessionSummary,
+    trade_stats: Option<TradeStats>,
+    last_stats_ms: u64,
+    candles: Option<CandleAggregator>,
+    technical: Option<TechnicalIndicators>,
+    volatility: Option<VolatilityEstimator>,
+    top_clock: TopOfBookClock,
+    // When each side of the book last changed, in receive time
+    quotes: QuoteClock,
+    dwell: Option<DwellStats>,
+    last_dwell_ms: u64,
+    // The top as of the previous change, which order flow is measured from
+    top: TopOfBook,
+    order_flow: Option<OrderFlow>,
+    last_ofi_ms: u64,
+    anomaly: Option<AnomalyDetector>,
+    latency: LatencyTracker,
+    last_latency_ms: Option<u64>,
+    // Last ping round trip of the connection, none for replays
+    round_trip_ms: Option<u64>,
+    // The estimated clock skew is beyond the limit, warned about once until it is back
+    clock_skewed: bool,
+    imbalance_band: Option<usize>,
+    last_bbo: Option<BestBidOffer>,
+    last_depth: Option<DepthSnapshot>,
+    last_sequence: Option<u32>,
+    last_event_id: Option<u64>,
+    // Ids of this run only, a restored last_event_id does not make new ones duplicates
+    event_ids: EventIds,
+    last_timestampms: Option<u64>,
+    // Last message time of the run a saved state came from, until the first new message
+    restored_ms: Option<u64>,
+    interruption: Option<Interruption>,
+    // Messages were lost since the last book snapshot
+    book_tainted: bool,
+}
+
+impl SymbolState {
+    fn new(symbol: &str, analytics: &AnalyticsConfig) -> Self {
+        Self {
+            book: OrderBook::new(),
+            summary: SessionSummary::new(symbol),
+            trade_stats: analytics.stats.as_ref().map(|s| TradeStats::new(&s.windows)),
+            last_stats_ms: 0,
+            candles: analytics.candles.map(CandleAggregator::new),
+            technical: (!analytics.technical.is_empty()).then(|| TechnicalIndicators::new(&analytics.technical)),
+            volatility: analytics.volatility.as_ref().map(|v| VolatilityEstimator::new(v.sample, &v.windows)),
+            top_clock: TopOfBookClock::new(),
+            quotes: QuoteClock::new(),
+            dwell: analytics.dwell.as_ref().map(|d| DwellStats::new(d.window, d.flicker)),
+            anomaly: analytics.anomaly.map(|(options, window)| AnomalyDetector::new(options, window)),
+            last_dwell_ms: 0,
+            top: TopOfBook::new(),
+            order_flow: analytics.ofi.as_ref().map(|o| OrderFlow::new(&o.windows)),
+            last_ofi_ms: 0,
+            latency: LatencyTracker::new(LATENCY_WINDOW),
+            last_latency_ms: None,
+            round_trip_ms: None,
+            clock_skewed: false,
+            imbalance_band: None,
+            last_bbo: None,
+            last_depth: None,
+            last_sequence: None,
+            last_event_id: None,
+            event_ids: EventIds::new(),
+            last_timestampms: None,
+            restored_ms: None,
+            interruption: None,
+            book_tainted: false,
+        }
+    }
+
+    // Aggregates overlapping the interruption get marked as tainted
+    fn interrupt(&mut self, interruption: Interruption) {
+        self.interruption = Some(interruption);
+        if let Some(candles) = self.candles.as_mut() {
+            candles.interrupt(interruption);
+        }
+        if let Some(stats) = self.trade_stats.as_mut() {
+            stats.interrupt(interruption);
+        }
+        if let Some(flow) = self.order_flow.as_mut() {
+            flow.interrupt(interruption);
+        }
+    }
+
+    // The first message after a disconnect or restore ends the interruption
+    fn resume(&mut self, ts: u64) {
+        if let Some(interruption) = self.interruption.filter(|i| i.to_ms.is_none()) {
+            self.interrupt(Interruption { to_ms: Some(ts), ..interruption });
+        }
+    }
+}
+
+// Which analytics every symbol runs, set through the `with_*` builders
+#[derive(Default)]
+struct AnalyticsConfig {
+    stats: Option<StatsConfig>,
+    candles: Option<Duration>,
+    // Computed from the candles, empty for none
+    technical: Vec<IndicatorSpec>,
+    volatility: Option<VolatilityConfig>,
+    dwell: Option<DwellConfig>,
+    // Order flow imbalance windows and how often they are emitted
+    ofi: Option<StatsConfig>,
+    // Checks and the window of the rolling mid and spread they compare with
+    anomaly: Option<(AnomalyOptions, Duration)>,
+    depth: Option<DepthOptions>,
+}
+
+pub struct VolatilityConfig {
+    pub sample: Duration,
+    pub windows: Vec<Duration>,
+}
+
+pub struct DwellConfig {
+    pub window: Duration,
+    // Levels replaced sooner than this count as flickers
+    pub flicker: Duration,
+    pub interval: Duration,
+}
+
+pub struct StatsConfig {
+    pub windows: Vec<Duration>,
+    pub interval: Duration,
+}
+
+// Trades below either minimum still feed the analytics but are not handed on
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TradeFilter {
+    pub min_size: Option<Decimal>,
+    // Price times amount, in the quote currency
+    pub min_notional: Option<Decimal>,
+}
+
+impl TradeFilter {
+    fn passes(&self, price: Decimal, amount: Decimal) -> bool {
+        self.min_size.is_none_or(|min| amount >= min)
+            && self.min_notional.is_none_or(|min| price * amount >= min)
+    }
+}
+
+// Shared processing for live and replayed frames: parse, maintain the book and the
+// analytics, and hand every event to the registered handlers
+pub struct Pipeline {
+    symbols: HashMap<String, SymbolState>,
+    order: Vec<String>,
+    handlers: Vec<HandlerTask>,
+    queue: QueueOptions,
+    market_state: MarketState,
+    spread: SpreadTracker,
+    positions: Positions,
+    feed_status: FeedStatus,
+    analytics: AnalyticsConfig,
+    top_of_book: bool,
+    // Levels kept per side of a full depth book, none for all
+    max_book_levels: Option<usize>,
+    latency_log: Option<Duration>,
+    max_clock_skew: Duration,
+    // Flag a side of a book that has not changed for this long, none to only publish the ages
+    stale_quotes: Option<Duration>,
+    last_quote_check_ms: u64,
+    increments: HashMap<String, Increments>,
+    cross: Option<CrossMonitor>,
+    trade_filter: TradeFilter,
+    // Imbalance band edges, empty to emit indicators on every book update
+    indicator_bands: Option<Vec<Decimal>>,
+    throttle: ThrottleOptions,
+    // Bucket of the BBO snapshots sinks store, none to store every tick
+    downsample: Option<Duration>,
+    // Shared with the other collectors of a --leader-lock, sinks only store while leading
+    leadership: Option<Leadership>,
+    gap_fill: Option<GapFill>,
+    routes: Vec<Route>,
+    // Names of the outputs added so far, the sinks routes refer to
+    outputs: Vec<&'static str>,
+    // Work beside the handlers that flushing waits for, such as the blotter
+    tasks: Vec<JoinHandle<()>>,
+}
+
+struct GapFill {
+    rest: RestClient,
+    exchange: Exchange,
+    max: Duration,
+}
+
+impl Pipeline {
+    // `queue` sets the capacity and backpressure policy of every handler's queue.
+    // Registers the BBO and spread trackers behind `summaries`, so it needs a running runtime.
+    pub fn new(queue: QueueOptions) -> Self {
+        let market_state = MarketState::new();
+        let spread = SpreadTracker::new(DEFAULT_THRESHOLDS_BPS.into_iter().map(Decimal::from).collect());
+        Self {
+            symbols: HashMap::new(),
+            order: Vec::new(),
+            handlers: vec![
+                HandlerTask::spawn(Box::new(market_state.publisher()), queue),
+                HandlerTask::spawn(Box::new(spread.clone()), queue),
+            ],
+            queue,
+            positions: Positions::new(market_state.clone()),
+            market_state,
+            spread,
+            feed_status: FeedStatus::new(),
+            analytics: AnalyticsConfig::default(),
+            top_of_book: FeedOptions::default().top_of_book,
+            max_book_levels: None,
+            latency_log: None,
+            max_clock_skew: DEFAULT_MAX_CLOCK_SKEW,
+            stale_quotes: None,
+            last_quote_check_ms: 0,
+            increments: HashMap::new(),
+            cross: None,
+            trade_filter: TradeFilter::default(),
+            indicator_bands: None,
+            throttle: ThrottleOptions::default(),
+            downsample: None,
+            leadership: None,
+            gap_fill: None,
+            routes: Vec::new(),
+            outputs: Vec::new(),
+            tasks: Vec::new(),
+        }
+    }
+
+    // Whether the feed only carries the top level per side or full depth, full depth unless
+    // told otherwise like the default feed
+    pub fn top_of_book(mut self, top_of_book: bool) -> Self {
+        self.top_of_book = top_of_book;
+        self
+    }
+
+    // Keep only the best `levels` per side of a full depth book, dropping the furthest
+    // from the top, so a book that fans out over a long run stays bounded
+    pub fn max_book_levels(mut self, levels: Option<usize>) -> Self {
+        self.max_book_levels = levels;
+        self
+    }
+
+    // Log rolling feed latency percentiles every `interval` of receive time
+    pub fn log_latency(mut self, interval: Option<Duration>) -> Self {
+        self.latency_log = interval;
+        self
+    }
+
+    // Warn when the local clock seems further than `max` off the exchange's
+    pub fn max_clock_skew(mut self, max: Duration) -> Self {
+        self.max_clock_skew = max;
+        self
+    }
+
+    // Report a side of a book that went `window` without a change as a stale quotes anomaly
+    pub fn with_stale_quotes(mut self, window: Duration) -> Self {
+        self.stale_quotes = Some(window);
+        self
+    }
+
+    // Print prices and amounts at each symbol's exchange precision and flag off-tick prices
+    pub fn with_increments(mut self, increments: HashMap<String, Increments>) -> Self {
+        self.increments = increments;
+        self
+    }
+
+    // Watch synthetic crosses and report when one diverges more than `threshold_bps`
+    pub fn with_crosses(mut self, rules: Vec<CrossRule>, threshold_bps: Decimal) -> Self {
+        self.cross = Some(CrossMonitor::new(rules, threshold_bps));
+        self
+    }
+
+    // Report the share of time the spread was wider than each of these basis points
+    pub fn with_spread_thresholds(self, thresholds_bps: Vec<Decimal>) -> Self {
+        self.spread.set_thresholds(thresholds_bps);
+        self
+    }
+
+    // Only hand trades and block trades at or above these minimums to the handlers
+    pub fn with_trade_filter(mut self, filter: TradeFilter) -> Self {
+        self.trade_filter = filter;
+        self
+    }
+
+    // Emit imbalance and microprice after book updates, only when the imbalance moves
+    // to another band if any band edges are given
+    pub fn with_indicators(mut self, mut bands: Vec<Decimal>) -> Self {
+        bands.sort();
+        self.indicator_bands = Some(bands);
+        self
+    }
+
+    // Emit the size and average price of the best `levels` per side with the mid weighted
+    // by them, and the prices buying and selling each of `sizes` would fill at, whenever
+    // a quote changes any of them
+    pub fn with_depth(mut self, levels: Option<usize>, sizes: Vec<Decimal>) -> Self {
+        self.analytics.depth = Some(DepthOptions { levels, sizes });
+        self
+    }
+
+    // Aggregate trades into OHLCV bars of the given interval
+    pub fn with_candles(mut self, interval: Duration) -> Self {
+        self.analytics.candles = Some(interval);
+        self
+    }
+
+    // Emit technical indicators over the closes of the candles, from `with_candles` or
+    // passed to `handle_candle`
+    pub fn with_technical(mut self, specs: Vec<IndicatorSpec>) -> Self {
+        self.analytics.technical = specs;
+        self
+    }
+
+    // Emit realized volatility of mid returns sampled every `sample` over each window
+    pub fn with_volatility(mut self, sample: Duration, windows: Vec<Duration>) -> Self {
+        self.analytics.volatility = Some(VolatilityConfig { sample, windows });
+        self
+    }
+
+    // Time how long best prices stand and emit their distribution over `window` every
+    // `interval` of exchange time
+    pub fn with_dwell(mut self, window: Duration, flicker: Duration, interval: Duration) -> Self {
+        self.analytics.dwell = Some(DwellConfig { window, flicker, interval });
+        self
+    }
+
+    // Sum the order flow imbalance of book changes over each window and emit it every
+    // `interval` of exchange time
+    pub fn with_order_flow(mut self, windows: Vec<Duration>, interval: Duration) -> Self {
+        self.analytics.ofi = Some(StatsConfig { windows, interval });
+        self
+    }
+
+    // Flag trades far from the mid and blown out spreads, both compared with rolling
+    // averages over `window`
+    pub fn with_anomalies(mut self, options: AnomalyOptions, window: Duration) -> Self {
+        self.analytics.anomaly = Some((options, window));
+        self
+    }
+
+    // Track rolling trade statistics and emit them every `interval` of exchange time
+    pub fn with_trade_stats(mut self, windows: Vec<Duration>, interval: Duration) -> Self {
+        self.analytics.stats = Some(StatsConfig { windows, interval });
+        self
+    }
+
+    // After an outage of at most `max`, fetch the trades missed meanwhile over REST and
+    // hand them on flagged as recovered, before the first message of the new connection
+    pub fn with_gap_fill(mut self, rest: RestClient, exchange: Exchange, max: Duration) -> Self {
+        self.gap_fill = Some(GapFill { rest, exchange, max });
+        self
+    }
+
+    // Coalesce the book updates handed to outputs added with `add_output`
+    pub fn with_throttle(mut self, throttle: ThrottleOptions) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    // Have the outputs added with `add_sink` store the last BBO per `bucket` and the
+    // aggregates instead of every trade and book change
+    pub fn with_downsample(mut self, bucket: Duration) -> Self {
+        self.downsample = Some(bucket);
+        self
+    }
+
+    // Have the outputs added with `add_sink` store only while this collector leads, and
+    // hold the latest events meanwhile to catch up on when it takes over
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = Some(leadership);
+        self
+    }
+
+    // Which events the outputs added afterwards get, by their name
+    pub fn with_routes(mut self, routes: Vec<Route>) -> Self {
+        self.routes = routes;
+        self
+    }
+
+    // Routes naming a sink no output was added for, typically a typo or a sink left out
+    pub fn unused_routes(&self) -> Vec<&Route> {
+        self.routes.iter().filter(|route| !self.outputs.contains(&route.sink.as_str())).collect()
+    }
+
+    // Runs the handler on its own task, fed from this pipeline
+    pub fn add_handler(&mut self, handler: Box<dyn EventHandler>) {
+        self.handlers.push(HandlerTask::spawn(handler, self.queue));
+    }
+
+    // Like `add_handler`, for handlers that only pass events on, which get book updates
+    // at the throttled rate
+    pub fn add_output(&mut self, handler: Box<dyn EventHandler>) {
+        self.add_output_of(handler, Vec::new());
+    }
+
+    // Like `add_output`, for storage sinks, which get downsampled data when asked for
+    pub fn add_sink(&mut self, handler: Box<dyn EventHandler>) {
+        let handler: Box<dyn EventHandler> = match self.downsample {
+            Some(bucket) => Box::new(Downsampled::new(handler, bucket)),
+            None => handler,
+        };
+        let handler: Box<dyn EventHandler> = match &self.leadership {
+            Some(leadership) => Box::new(leadership.gate(handler)),
+            None => handler,
+        };
+        self.add_output(handler);
+    }
+
+    // Like `add_output`, handing on only events of these kinds, all of them when empty
+    // and only those its routes let through
+    pub fn add_output_of(&mut self, handler: Box<dyn EventHandler>, kinds: Vec<&'static str>) {
+        let name = handler.name();
+        let routes = self.routes.iter().filter(|route| route.sink == name).cloned().collect();
+        self.outputs.push(name);
+        let handler: Box<dyn EventHandler> = match self.throttle.is_empty() {
+            true => handler,
+            false => Box::new(Throttled::new(handler, &self.throttle)),
+        };
+        self.handlers.push(HandlerTask::spawn_filtered(handler, self.queue, kinds, routes));
+    }
+
+    // A task fed from outside the pipeline whose output must be finished along with the
+    // handlers'. It has to end on shutdown by itself, `flush` waits for it.
+    pub fn add_task(&mut self, task: JoinHandle<()>) {
+        self.tasks.push(task);
+    }
+
+    fn state(&mut self, symbol: &str) -> &mut SymbolState {
+        if !self.symbols.contains_key(symbol) {
+            self.order.push(symbol.to_string());
+        }
+        let analytics = &self.analytics;
+        self.symbols.entry(symbol.to_string()).or_insert_with(|| SymbolState::new(symbol, analytics))
+    }
+
+    pub async fn handle_frame(&mut self, symbol: &str, data: &[u8], received_ms: u64) -> Result<(), GeminiError> {
+        let parse_start = Instant::now();
+        let parsed = MarketMessage::from_slice(data).map_err(|e| e.to_string());
+        metrics::global().parse_latency.with_label_values(&[symbol]).observe(parse_start.elapsed().as_secs_f64());
+        self.handle_message(symbol, parsed, received_ms).await
+    }
+
+    // A frame already parsed, such as on a shard worker
+    pub async fn handle_message(&mut self, symbol: &str, parsed: Result<MarketMessage, String>, received_ms: u64) -> Result<(), GeminiError> {
+        let metrics = metrics::global();
+        metrics.messages.with_label_values(&[symbol]).inc();
+        self.state(symbol).summary.record_message(received_ms);
+        self.feed_status.record(symbol);
+        let event = match parsed {
+            Ok(event) => event,
+            Err(e) => {
+                warn!(symbol, error = %e, "skipping unparsable message");
+                return Ok(());
+            }
+        };
+        self.check_sequence(symbol, event.socket_sequence, event.timestampms.unwrap_or(received_ms));
+        // The first message after (re)connecting carries the whole book as `initial` changes
+        let initial = !event.events.is_empty() && event.events.iter()
+            .all(|e| matches!(e, Event::Quote(q) if q.reason == "initial"));
+        if !self.check_event_id(symbol, event.event_id, initial) {
+            return Ok(());
+        }
+        let state = self.state(symbol);
+        let outage = state.interruption.filter(|i| i.to_ms.is_none()).map(|i| i.from_ms);
+        state.resume(event.timestampms.unwrap_or(received_ms));
+        if initial {
+            state.book_tainted = false;
+        }
+        // Before anything of this message, the candles have not moved past the outage yet
+        if let Some(from_ms) = outage {
+            self.fill_gap(symbol, from_ms, event.timestampms.unwrap_or(received_ms), received_ms).await?;
+        }
+        self.track_position(symbol, event.event_id, event.timestampms.unwrap_or(received_ms));
+        let ctx = EventContext {
+            event_id: event.event_id,
+            socket_sequence: event.socket_sequence,
+            timestampms: event.timestampms,
+            received_ms,
+            // Replayed frames only kept the millisecond
+            received_ns: event.received_at_ns.unwrap_or(received_ms * 1_000_000),
+            backfill: false,
+            recovered: false,
+        };
+        if let Some(timestampms) = ctx.timestampms {
+            let delay = received_ms.saturating_sub(timestampms) as f64 / 1000.;
+            metrics.feed_latency.with_label_values(&[symbol]).observe(delay);
+            self.state(symbol).latency.record(received_ms, timestampms);
+        }
+        self.report_latency(symbol, received_ms);
+        let ts = ctx.timestampms.unwrap_or(received_ms);
+        let completed = match self.state(symbol).candles.as_mut() {
+            Some(candles) => candles.advance(ts),
+            None => Vec::new(),
+        };
+        self.emit_candles(symbol, &ctx, completed).await?;
+
+        if initial {
+            self.state(symbol).book.clear();
+        }
+        let increments = self.increments.get(symbol).copied();
+        for e in event.events {
+            let reason = match &e {
+                Event::Quote(q) => Some(q.reason.as_str()),
+                _ => None,
+            };
+            metrics.events.with_label_values(&[symbol, e.kind(), reason.unwrap_or_default()]).inc();
+            self.state(symbol).summary.record_event(e.kind(), reason);
+            match e {
+                Event::Trade(mut t) => {
+                    metrics.trades.with_label_values(&[symbol]).inc();
+                    if let Some(increments) = increments {
+                        increments.trade(&mut t);
+                        check_tick(symbol, "trade", t.price, &increments);
+                    }
+                    let state = self.state(symbol);
+                    state.summary.record_trade(ts, &t);
+                    let anomaly = state.anomaly.as_mut().and_then(|a| a.trade(ts, t.price, t.amount));
+                    let stats = self.record_stats(symbol, &ctx, &t);
+                    let completed = match self.state(symbol).candles.as_mut() {
+                        Some(candles) => candles.record(ts, &t),
+                        None => Vec::new(),
+                    };
+                    if self.trade_filter.passes(t.price, t.amount) {
+                        self.dispatch(symbol, &ctx, HandlerEvent::Trade(t)).await?;
+                    }
+                    if let Some(anomaly) = anomaly {
+                        self.emit_anomaly(symbol, &ctx, anomaly).await?;
+                    }
+                    if let Some(stats) = stats {
+                        self.dispatch(symbol, &ctx, HandlerEvent::Stats(stats)).await?;
+                    }
+                    self.emit_candles(symbol, &ctx, completed).await?;
+                },
+                Event::Quote(mut q) => {
+                    if let Some(increments) = increments {
+                        increments.quote(&mut q);
+                        check_tick(symbol, "quote", q.price, &increments);
+                    }
+                    metrics.quotes.with_label_values(&[symbol]).inc();
+                    let (top_of_book, max_levels) = (self.top_of_book, self.max_book_levels);
+                    let state = self.state(symbol);
+                    match top_of_book {
+                        true => state.book.replace_top(&q),
+                        false => state.book.apply(&q),
+                    }
+                    if let Some(levels) = max_levels {
+                        let evicted = state.book.truncate(levels);
+                        if evicted > 0 {
+                            metrics.evicted.with_label_values(&[symbol, "book_levels"]).inc_by(evicted as u64);
+                        }
+                    }
+                    if state.quotes.update(q.side, received_ms) {
+                        info!(symbol, side = q.side.as_str(), "quotes changing again");
+                        metrics.stale_quotes.with_label_values(&[symbol, q.side.as_str()]).set(0.);
+                    }
+                    let (bids, asks) = state.book.depth();
+                    metrics.book_levels.with_label_values(&[symbol]).set((bids + asks) as f64);
+                    // Every change counts, several in one message can each move the top
+                    if let (Some(flow), false) = (state.order_flow.as_mut(), initial) {
+                        if let Some(e) = state.top.update(&state.book) {
+                            flow.record(ts, e);
+                        }
+                    }
+                    self.dispatch(symbol, &ctx, HandlerEvent::Quote(q)).await?;
+                    // The snapshot is published once, after the whole batch is applied
+                    if !initial {
+                        self.publish_bbo(symbol, &ctx).await?;
+                        self.publish_depth(symbol, &ctx).await?;
+                    }
+                },
+                Event::BlockTrade(mut t) => {
+                    if let Some(increments) = increments {
+                        increments.block_trade(&mut t);
+                    }
+                    if self.trade_filter.passes(t.price, t.amount) {
+                        self.dispatch(symbol, &ctx, HandlerEvent::BlockTrade(t)).await?;
+                    }
+                },
+                Event::Auction(a) => {
+                    self.dispatch(symbol, &ctx, HandlerEvent::Auction(a)).await?;
+                },
+                Event::Unknown => {},
+            }
+        }
+        if initial {
+            // A fresh book is no flow, it only sets the top to measure from
+            let state = self.state(symbol);
+            state.top.reset();
+            state.top.update(&state.book);
+            self.publish_bbo(symbol, &ctx).await?;
+            self.publish_depth(symbol, &ctx).await?;
+        }
+        Ok(())
+    }
+
+    // Trades fetched over REST before streaming, oldest first. They go through the same
+    // analytics and handlers as live trades, with `backfill` set on their context.
+    pub async fn handle_backfill(&mut self, symbol: &str, trades: Vec<TradeRecord>, received_ms: u64) -> Result<(), GeminiError> {
+        self.handle_records(symbol, trades, received_ms, false).await
+    }
+
+    // Trades the exchange printed between the last message before an outage and the first
+    // one after it, both exclusive to the millisecond, passed on flagged as recovered
+    async fn fill_gap(&mut self, symbol: &str, from_ms: u64, to_ms: u64, received_ms: u64) -> Result<(), GeminiError> {
+        let Some(fill) = self.gap_fill.as_ref() else {
+            return Ok(());
+        };
+        // Nothing to start from before the first message, and no REST trades elsewhere
+        if from_ms == 0 || exchange::route(fill.exchange, symbol).0 != Exchange::Gemini {
+            return Ok(());
+        }
+        let outage = Duration::from_millis(to_ms.saturating_sub(from_ms));
+        if outage > fill.max {
+            warn!(symbol, outage = ?outage, "outage too long to fill in, its trades stay missing");
+            return Ok(());
+        }
+        let trades = match fill.rest.trades_since(symbol, from_ms).await {
+            Ok(trades) => trades,
+            Err(e) => {
+                warn!(symbol, error = %e, "could not fetch the trades missed while disconnected");
+                return Ok(());
+            },
+        };
+        let missed: Vec<TradeRecord> = trades.into_iter()
+            .filter(|t| t.timestampms > from_ms && t.timestampms < to_ms)
+            .collect();
+        info!(symbol, trades = missed.len(), outage = ?outage, "recovered the trades missed while disconnected");
+        self.state(symbol).summary.recovered_trades += missed.len() as u64;
+        self.handle_records(symbol, missed, received_ms, true).await
+    }
+
+    async fn handle_records(&mut self, symbol: &str, trades: Vec<TradeRecord>, received_ms: u64, recovered: bool) -> Result<(), GeminiError> {
+        for record in trades {
+            let ctx = EventContext {
+                event_id: record.tid,
+                socket_sequence: 0,
+                timestampms: Some(record.timestampms),
+                received_ms,
+                received_ns: received_ms * 1_000_000,
+                backfill: !recovered,
+                recovered,
+            };
+            let ts = record.timestampms;
+            if record.kind == "block" {
+                let trade = BlockTrade { tid: record.tid, price: record.price, amount: record.amount };
+                if self.trade_filter.passes(trade.price, trade.amount) {
+                    self.dispatch(symbol, &ctx, HandlerEvent::BlockTrade(trade)).await?;
+                }
+                continue;
+            }
+            let trade = Trade { price: record.price, amount: record.amount, maker_side: record.maker_side() };
+            let completed = match self.state(symbol).candles.as_mut() {
+                Some(candles) => {
+                    let mut completed = candles.advance(ts);
+                    completed.extend(candles.record(ts, &trade));
+                    completed
+                },
+                None => Vec::new(),
+            };
+            self.state(symbol).summary.record_trade(ts, &trade);
+            let stats = self.record_stats(symbol, &ctx, &trade);
+            if self.trade_filter.passes(trade.price, trade.amount) {
+                self.dispatch(symbol, &ctx, HandlerEvent::Trade(trade)).await?;
+            }
+            if let Some(stats) = stats {
+                self.dispatch(symbol, &ctx, HandlerEvent::Stats(stats)).await?;
+            }
+            self.emit_candles(symbol, &ctx, completed).await?;
+        }
+        Ok(())
+    }
+
+    async fn publish_bbo(&mut self, symbol: &str, ctx: &EventContext) -> Result<(), GeminiError> {
+        let state = self.state(symbol);
+        let bbo = state.book.bbo();
+        // Quotes away from the top leave the BBO as it was, nothing to publish then
+        if state.last_bbo.as_ref() == Some(&bbo) {
+            return Ok(());
+        }
+        state.last_bbo = Some(bbo.clone());
+        state.summary.record_bbo(&bbo);
+        let crosses = match self.cross.as_mut() {
+            Some(cross) => cross.update(symbol, &bbo),
+            None => Vec::new(),
+        };
+        let indicators = self.indicators(symbol, &bbo);
+        let volatility = match (&mut self.state(symbol).volatility, bbo.best_bid.is_zero() || bbo.best_offer.is_zero()) {
+            (Some(estimator), false) => {
+                let mid = (bbo.best_bid + bbo.best_offer) / Decimal::TWO;
+                estimator.record(ctx.timestampms.unwrap_or(ctx.received_ms), mid)
+            },
+            _ => None,
+        };
+        let dwell = self.record_dwell(symbol, ctx, &bbo);
+        let ts = ctx.timestampms.unwrap_or(ctx.received_ms);
+        let ofi = self.order_flow(symbol, ts);
+        let anomaly = self.state(symbol).anomaly.as_mut().and_then(|a| a.bbo(ts, &bbo));
+        self.dispatch(symbol, ctx, HandlerEvent::BookUpdate(bbo)).await?;
+        if let Some(anomaly) = anomaly {
+            self.emit_anomaly(symbol, ctx, anomaly).await?;
+        }
+        if let Some(dwell) = dwell {
+            self.dispatch(symbol, ctx, HandlerEvent::Dwell(dwell)).await?;
+        }
+        if let Some(ofi) = ofi {
+            self.dispatch(symbol, ctx, HandlerEvent::Ofi(ofi)).await?;
+        }
+        if let Some(indicators) = indicators {
+            self.dispatch(symbol, ctx, HandlerEvent::Indicators(indicators)).await?;
+        }
+        if let Some(volatility) = volatility {
+            self.dispatch(symbol, ctx, HandlerEvent::Volatility(volatility)).await?;
+        }
+        for cross in crosses {
+            let divergence = cross.divergence_bps.to_f64().unwrap_or(0.);
+            metrics::global().cross_divergence.with_label_values(&[&cross.cross]).set(divergence);
+            if cross.changed {
+                let target = cross.cross.split('=').next().unwrap_or(symbol).to_string();
+                self.dispatch(&target, ctx, HandlerEvent::Cross(cross)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Quotes deeper than the levels and sizes reach leave the depth as it was
+    async fn publish_depth(&mut self, symbol: &str, ctx: &EventContext) -> Result<(), GeminiError> {
+        let Some(options) = self.analytics.depth.as_ref() else {
+            return Ok(());
+        };
+        let Some(state) = self.symbols.get_mut(symbol) else {
+            return Ok(());
+        };
+        let Some(depth) = DepthSnapshot::from_book(&state.book, options) else {
+            return Ok(());
+        };
+        let depth = DepthSnapshot { tainted: state.book_tainted, ..depth };
+        if state.last_depth.as_ref() == Some(&depth) {
+            return Ok(());
+        }
+        state.last_depth = Some(depth.clone());
+        self.dispatch(symbol, ctx, HandlerEvent::Depth(depth)).await
+    }
+
+    async fn emit_anomaly(&mut self, symbol: &str, ctx: &EventContext, anomaly: AnomalyEvent) -> Result<(), GeminiError> {
+        metrics::global().anomalies.with_label_values(&[symbol, anomaly.anomaly.as_str()]).inc();
+        warn!(symbol, anomaly = anomaly.anomaly.as_str(), value = %anomaly.value, reference = %anomaly.reference, "anomaly");
+        self.dispatch(symbol, ctx, HandlerEvent::Anomaly(anomaly)).await
+    }
+
+    // Timestamps the new BBO and returns the dwell statistics when they are due
+    fn record_dwell(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Option<DwellSnapshot> {
+        let interval = self.analytics.dwell.as_ref()?.interval.as_millis() as u64;
+        let ts = ctx.timestampms.unwrap_or(ctx.received_ms);
+        let state = self.state(symbol);
+        let stats = state.dwell.as_mut()?;
+        for dwell in state.top_clock.update(ts, bbo) {
+            stats.record(&dwell);
+        }
+        if ts.saturating_sub(state.last_dwell_ms) < interval {
+            return None;
+        }
+        state.last_dwell_ms = ts;
+        Some(stats.snapshot(ts))
+    }
+
+    // The order flow imbalance windows when they are due
+    fn order_flow(&mut self, symbol: &str, ts: u64) -> Option<OfiSnapshot> {
+        let interval = self.analytics.ofi.as_ref()?.interval.as_millis() as u64;
+        let state = self.state(symbol);
+        let flow = state.order_flow.as_mut()?;
+        if ts.saturating_sub(state.last_ofi_ms) < interval {
+            return None;
+        }
+        state.last_ofi_ms = ts;
+        Some(flow.snapshot(ts))
+    }
+
+    fn indicators(&mut self, symbol: &str, bbo: &BestBidOffer) -> Option<BookIndicators> {
+        let tainted = self.state(symbol).book_tainted;
+        let bands = self.indicator_bands.as_ref()?;
+        let mut indicators = BookIndicators { tainted, ..BookIndicators::from_bbo(bbo)? };
+        if bands.is_empty() {
+            return Some(indicators);
+        }
+        let band = indicators::imbalance_band(bands, indicators.imbalance);
+        indicators.band = Some(band);
+        match self.state(symbol).imbalance_band.replace(band) {
+            Some(previous) if previous == band => None,
+            _ => Some(indicators),
+        }
+    }
+
+    async fn dispatch(&mut self, symbol: &str, ctx: &EventContext, event: HandlerEvent) -> Result<(), GeminiError> {
+        self.send(HandlerMessage::Event {
+            symbol: symbol.to_string(),
+            ctx: *ctx,
+            event,
+        }).await
+    }
+
+    // Funding of a perpetual, polled over REST since the market data feed carries none
+    pub async fn handle_funding(&mut self, symbol: &str, funding: FundingAmount, received_ms: u64) -> Result<(), GeminiError> {
+        let ctx = EventContext {
+            event_id: 0,
+            socket_sequence: 0,
+            timestampms: Some(funding.funding_ms),
+            received_ms,
+            received_ns: received_ms * 1_000_000,
+            backfill: false,
+            recovered: false,
+        };
+        self.dispatch(symbol, &ctx, HandlerEvent::Funding(funding)).await
+    }
+
+    // A bar computed by the exchange, in place of aggregating the trades
+    pub async fn handle_candle(&mut self, symbol: &str, candle: Candle, received_ms: u64) -> Result<(), GeminiError> {
+        let ctx = EventContext {
+            event_id: 0,
+            socket_sequence: 0,
+            timestampms: None,
+            received_ms,
+            received_ns: received_ms * 1_000_000,
+            backfill: false,
+            recovered: false,
+        };
+        self.emit_candles(symbol, &ctx, vec![candle]).await
+    }
+
+    // Tells every handler the connection for `symbol` dropped
+    pub async fn handle_disconnect(&mut self, symbol: &str, reason: &str) -> Result<(), GeminiError> {
+        let state = self.state(symbol);
+        state.last_sequence = None;
+        state.summary.disconnects += 1;
+        let from_ms = state.last_timestampms.unwrap_or_default();
+        state.interrupt(Interruption { from_ms, to_ms: None });
+        state.top_clock.reset();
+        state.top.reset();
+        if let Some(anomaly) = state.anomaly.as_mut() {
+            anomaly.reset();
+        }
+        self.send(HandlerMessage::Disconnect {
+            symbol: symbol.to_string(),
+            reason: reason.to_string(),
+        }).await
+    }
+
+    // Everything the local clock drives rather than the messages: stale quotes, the bars
+    // of quiet symbols, and what the handlers held back past their interval
+    pub async fn tick(&mut self, now_ms: u64) -> Result<(), GeminiError> {
+        self.check_quotes(now_ms).await?;
+        self.close_candles(now_ms).await?;
+        self.send(HandlerMessage::Tick { now_ms }).await
+    }
+
+    async fn send(&mut self, message: HandlerMessage) -> Result<(), GeminiError> {
+        let message = Arc::new(message);
+        for handler in self.handlers.iter_mut() {
+            handler.send(message.clone()).await?;
+        }
+        Ok(())
+    }
+
+    // socket_sequence counts the messages of one connection from 0, anything skipped was lost
+    fn check_sequence(&mut self, symbol: &str, sequence: u32, ts: u64) {
+        let state = self.state(symbol);
+        let last = state.last_sequence.replace(sequence);
+        let Some(expected) = last.map(|last| last.wrapping_add(1)) else {
+            return;
+        };
+        if sequence == expected {
+            return;
+        }
+        let from_ms = state.last_timestampms.unwrap_or(ts);
+        state.interrupt(Interruption { from_ms, to_ms: Some(ts) });
+        // A replayed capture spans reconnects without disconnect events, a restart at 0 is one
+        if sequence != 0 {
+            state.book_tainted = true;
+            state.summary.sequence_gaps += 1;
+            metrics::global().sequence_gaps.with_label_values(&[symbol]).inc();
+            warn!(symbol, expected, received = sequence, "sequence gap");
+        }
+    }
+
+    // Whether to process a message, false for one seen before. A repeated book snapshot
+    // still replaces the book, there is nothing in it to record twice.
+    fn check_event_id(&mut self, symbol: &str, event_id: u64, initial: bool) -> bool {
+        let state = self.state(symbol);
+        match state.event_ids.check(event_id) {
+            EventOrder::Next => true,
+            EventOrder::Duplicate if initial => true,
+            EventOrder::Duplicate => {
+                state.summary.duplicates += 1;
+                metrics::global().duplicate_events.with_label_values(&[symbol]).inc();
+                debug!(symbol, event_id, "dropping duplicate message");
+                false
+            },
+            EventOrder::OutOfOrder { last } => {
+                metrics::global().event_id_regressions.with_label_values(&[symbol]).inc();
+                warn!(symbol, event_id, last, "event id went backwards");
+                true
+            },
+        }
+    }
+
+    // The first message after a restore shows how long nothing was received
+    fn track_position(&mut self, symbol: &str, event_id: u64, ts: u64) {
+        let state = self.state(symbol);
+        let last_event_id = state.last_event_id.replace(event_id);
+        state.last_timestampms = Some(ts);
+        if let Some(last) = state.restored_ms.take() {
+            let gap = humantime::format_duration(Duration::from_millis(ts.saturating_sub(last)));
+            warn!(symbol, %gap, last_event_id, event_id, "resuming from saved state, messages in between were missed");
+        }
+    }
+
+    fn report_latency(&mut self, symbol: &str, now_ms: u64) {
+        let log = self.latency_log.is_some();
+        let interval = self.latency_log.unwrap_or(LATENCY_UPDATE_INTERVAL).as_millis() as u64;
+        let state = self.state(symbol);
+        let last = *state.last_latency_ms.get_or_insert(now_ms);
+        if now_ms.saturating_sub(last) < interval {
+            return;
+        }
+        state.last_latency_ms = Some(now_ms);
+        let Some(latency) = state.latency.snapshot(now_ms) else {
+            return;
+        };
+        metrics::global().set_latency(symbol, &latency);
+        self.check_clock_skew(symbol, latency.min_ms);
+        if log {
+            info!(
+                symbol,
+                p50_ms = latency.p50_ms,
+                p95_ms = latency.p95_ms,
+                p99_ms = latency.p99_ms,
+                max_ms = latency.max_ms,
+                messages = latency.samples,
+                "feed latency"
+            );
+        }
+    }
+
+    // The fastest message of the window took at least half a round trip to get here, what
+    // is left of its delay beyond that is put down to the clocks. Without a round trip the
+    // whole delay counts, so the estimate errs on the side of the local clock being ahead.
+    fn check_clock_skew(&mut self, symbol: &str, min_delay_ms: i64) {
+        let max_ms = self.max_clock_skew.as_millis() as u64;
+        let state = self.state(symbol);
+        let skew_ms = min_delay_ms - state.round_trip_ms.map_or(0, |rtt| rtt as i64 / 2);
+        metrics::global().clock_skew.with_label_values(&[symbol]).set(skew_ms as f64 / 1000.);
+        let skewed = skew_ms.unsigned_abs() > max_ms;
+        if skewed == state.clock_skewed {
+            return;
+        }
+        state.clock_skewed = skewed;
+        match skewed {
+            true => warn!(symbol, skew_ms, max_ms, round_trip_ms = state.round_trip_ms, "local clock is off the exchange clock, latencies and candle times are unreliable"),
+            false => info!(symbol, skew_ms, "local clock is back in line with the exchange clock"),
+        }
+    }
+
+    // Round trip of a ping on the connection of `symbol`
+    pub fn record_round_trip(&mut self, symbol: &str, rtt_ms: u64) {
+        metrics::global().round_trip.with_label_values(&[symbol]).set(rtt_ms as f64 / 1000.);
+        self.state(symbol).round_trip_ms = Some(rtt_ms);
+    }
+
+    // Publishes how long each side of every book has gone without a change and reports the
+    // sides past the stale window. Call it often, it only runs every QUOTE_CHECK_INTERVAL.
+    pub async fn check_quotes(&mut self, now_ms: u64) -> Result<(), GeminiError> {
+        if now_ms.saturating_sub(self.last_quote_check_ms) < QUOTE_CHECK_INTERVAL.as_millis() as u64 {
+            return Ok(());
+        }
+        self.last_quote_check_ms = now_ms;
+        let metrics = metrics::global();
+        let ctx = local_context(now_ms);
+        for symbol in self.order.clone() {
+            let window = self.stale_quotes;
+            let state = self.state(&symbol);
+            for (side, age_ms) in state.quotes.ages(now_ms) {
+                if let Some(age_ms) = age_ms {
+                    metrics.quote_age.with_label_values(&[&symbol, side.as_str()]).set(age_ms as f64 / 1000.);
+                }
+            }
+            let Some(window) = window else {
+                continue;
+            };
+            let book = &state.book;
+            let stale = state.quotes.check(now_ms, window, |side| match side {
+                MarketSide::Bid => book.best_bid().map(|(price, _)| price).unwrap_or_default(),
+                _ => book.best_ask().map(|(price, _)| price).unwrap_or_default(),
+            });
+            for anomaly in stale {
+                let side = anomaly.side.unwrap_or_default().as_str();
+                metrics.stale_quotes.with_label_values(&[&symbol, side]).set(1.);
+                self.emit_anomaly(&symbol, &ctx, anomaly).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Closes the bars the local clock is past the end of, by more than the clock skew allowed,
+    // so the last bar of a quiet symbol does not wait for its next message
+    pub async fn close_candles(&mut self, now_ms: u64) -> Result<(), GeminiError> {
+        let ts = now_ms.saturating_sub(self.max_clock_skew.as_millis() as u64);
+        let ctx = local_context(now_ms);
+        for symbol in self.order.clone() {
+            let completed = match self.state(&symbol).candles.as_mut() {
+                Some(candles) => candles.advance(ts),
+                None => continue,
+            };
+            self.emit_candles(&symbol, &ctx, completed).await?;
+        }
+        Ok(())
+    }
+
+    // An unsubscribed symbol is no longer checked for stale quotes
+    pub fn forget_quotes(&mut self, symbol: &str) {
+        self.state(symbol).quotes = QuoteClock::new();
+        let metrics = metrics::global();
+        for side in [MarketSide::Bid, MarketSide::Ask] {
+            let _ = metrics.quote_age.remove_label_values(&[symbol, side.as_str()]);
+            let _ = metrics.stale_quotes.remove_label_values(&[symbol, side.as_str()]);
+        }
+    }
+
+    // Change the levels the depth events aggregate while running, as `with_depth` would have
+    // set them, keeping its sizes. None leaves only the sizes, or no depth events without them.
+    pub fn set_depth_levels(&mut self, levels: Option<usize>) -> Result<(), GeminiError> {
+        if self.top_of_book {
+            return Err(GeminiError::Config(String::from("depth needs the full book, the feed only carries the top of book")));
+        }
+        if let (Some(levels), Some(max)) = (levels, self.max_book_levels) {
+            if levels > max {
+                return Err(GeminiError::Config(format!("depth {} aggregates more levels than the {} kept per side", levels, max)));
+            }
+        }
+        let sizes = self.analytics.depth.take().map(|options| options.sizes).unwrap_or_default();
+        self.analytics.depth = (levels.is_some() || !sizes.is_empty()).then_some(DepthOptions { levels, sizes });
+        // The next quote publishes the depth at the new levels even if nothing else changed
+        for state in self.symbols.values_mut() {
+            state.last_depth = None;
+        }
+        Ok(())
+    }
+
+    async fn emit_candles(&mut self, symbol: &str, ctx: &EventContext, candles: Vec<Candle>) -> Result<(), GeminiError> {
+        for candle in candles {
+            let technical = self.state(symbol).technical.as_mut().map(|t| t.update(&candle));
+            self.dispatch(symbol, ctx, HandlerEvent::Candle(candle)).await?;
+            if let Some(technical) = technical {
+                self.dispatch(symbol, ctx, HandlerEvent::Technical(technical)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Records the trade and returns a snapshot when one is due
+    fn record_stats(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Option<TradeStatsSnapshot> {
+        let interval = self.analytics.stats.as_ref()?.interval.as_millis() as u64;
+        let ts = ctx.timestampms.unwrap_or(ctx.received_ms);
+        let state = self.state(symbol);
+        let stats = state.trade_stats.as_mut()?;
+        stats.record(ts, trade);
+        if ts.saturating_sub(state.last_stats_ms) < interval {
+            return None;
+        }
+        state.last_stats_ms = ts;
+        Some(stats.snapshot(ts))
+    }
+
+    // Latest best bid and offer of every symbol, shared with the pipeline
+    pub fn market_state(&self) -> MarketState {
+        self.market_state.clone()
+    }
+
+    // Time-weighted spread statistics of every symbol
+    pub fn spread(&self) -> SpreadTracker {
+        self.spread.clone()
+    }
+
+    // The account's positions marked at this pipeline's BBOs, the fills come from outside
+    pub fn positions(&self) -> Positions {
+        self.positions.clone()
+    }
+
+    // The book as currently maintained for `symbol`, if any frame for it arrived
+    pub fn book(&self, symbol: &str) -> Option<&OrderBook> {
+        self.symbols.get(symbol).map(|state| &state.book)
+    }
+
+    pub fn leadership(&self) -> Option<Leadership> {
+        self.leadership.clone()
+    }
+
+    // Last frame time per symbol, for the health and status endpoints
+    pub fn feed_status(&self) -> FeedStatus {
+        self.feed_status.clone()
+    }
+
+    // One summary per symbol, in the order symbols were first seen. The BBO and spread
+    // statistics are only final once `flush` has drained the handlers.
+    pub fn summaries(&self) -> Vec<SessionSummary> {
+        self.order.iter().filter_map(|symbol| self.symbols.get(symbol)).map(|state| {
+            let mut summary = state.summary.clone();
+            summary.bbo = self.market_state.get(&summary.symbol).unwrap_or_default();
+            summary.spread = self.spread.get(&summary.symbol);
+            summary
+        }).collect()
+    }
+
+    // Position, open candle and summary of every symbol, for `--state-file`. Call after
+    // `flush` so the summaries carry the final BBO.
+    pub fn saved_state(&self, now_ms: u64) -> SavedState {
+        let symbols = self.summaries().into_iter().filter_map(|summary| {
+            let state = self.symbols.get(&summary.symbol)?;
+            let candles = state.candles.as_ref();
+            Some(SavedSymbol {
+                symbol: summary.symbol.clone(),
+                last_event_id: state.last_event_id,
+                last_sequence: state.last_sequence,
+                last_timestampms: state.last_timestampms,
+                candle: candles.and_then(|c| c.current().cloned()),
+                last_close: candles.and_then(|c| c.last_close()),
+                summary,
+            })
+        }).collect();
+        SavedState { saved_ms: now_ms, symbols }
+    }
+
+    // Book, indicators and statistics of every symbol as of now, for `state::dump`. The
+    // rolling windows are measured up to each symbol's last message.
+    pub fn dump(&mut self, now_ms: u64) -> StateDump {
+        let summaries = self.summaries();
+        let symbols = summaries.into_iter().filter_map(|summary| {
+            let state = self.symbols.get_mut(&summary.symbol)?;
+            let levels = |levels: Vec<(&Decimal, &Decimal)>| -> Vec<[String; 2]> {
+                levels.into_iter().map(|(price, size)| [price.to_string(), size.to_string()]).collect()
+            };
+            let ts = state.last_timestampms.unwrap_or(now_ms);
+            let bbo = state.book.bbo();
+            Some(DumpedSymbol {
+                symbol: summary.symbol.clone(),
+                bids: levels(state.book.bids().collect()),
+                asks: levels(state.book.asks().collect()),
+                indicators: BookIndicators::from_bbo(&bbo).map(|i| BookIndicators { tainted: state.book_tainted, ..i }),
+                bbo,
+                depth: state.last_depth.clone(),
+                stats: state.trade_stats.as_mut().map(|s| s.snapshot(ts)),
+                order_flow: state.order_flow.as_mut().map(|o| o.snapshot(ts)),
+                candle: state.candles.as_ref().and_then(|c| c.current().cloned()),
+                last_event_id: state.last_event_id,
+                last_sequence: state.last_sequence,
+                last_timestampms: state.last_timestampms,
+                summary,
+            })
+        }).collect();
+        StateDump { dumped_ms: now_ms, symbols }
+    }
+
+    // Continues the aggregations of an earlier run. Call before the first frame.
+    pub fn restore(&mut self, saved: SavedState) {
+        for saved in saved.symbols {
+            let state = self.state(&saved.symbol);
+            state.summary = saved.summary;
+            state.last_sequence = saved.last_sequence;
+            state.last_event_id = saved.last_event_id;
+            state.last_timestampms = saved.last_timestampms;
+            state.restored_ms = saved.last_timestampms;
+            if let Some(candles) = state.candles.as_mut() {
+                candles.restore(saved.candle, saved.last_close);
+            }
+            // Nothing was received while the process was down
+            if let Some(from_ms) = saved.last_timestampms {
+                state.interrupt(Interruption { from_ms, to_ms: None });
+            }
+        }
+    }
+
+    // Drains and flushes every handler task, the first handler error wins
+    pub async fn flush(&mut self) -> Result<(), GeminiError> {
+        let mut result = Ok(());
+        for mut handler in self.handlers.drain(..) {
+            let closed = handler.close().await;
+            if result.is_ok() {
+                result = closed;
+            }
+        }
+        for task in self.tasks.drain(..) {
+            if let Err(e) = task.await {
+                warn!(error = %e, "background task failed");
+            }
+        }
+        result
+    }
+}
+
+// Context of events the local clock brings about rather than a message
+fn local_context(now_ms: u64) -> EventContext {
+    EventContext {
+        event_id: 0,
+        socket_sequence: 0,
+        timestampms: None,
+        received_ms: now_ms,
+        received_ns: now_ms * 1_000_000,
+        backfill: false,
+        recovered: false,
+    }
+}
+
+// A price between ticks means the exchange or our parsing is off, never drop it silently
+fn check_tick(symbol: &str, kind: &str, price: Decimal, increments: &Increments) {
+    if increments.price_on_tick(price) {
+        return;
+    }
+    metrics::global().off_tick.with_label_values(&[symbol]).inc();
+    warn!(symbol, kind, price = %price, tick = %increments.price, "price is not a multiple of the tick");
+}
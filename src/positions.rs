@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::market_state::MarketState;
+use crate::models::OrderSide;
+use crate::order_events::Fill;
+
+#[derive(Debug, Clone, Default)]
+struct Position {
+    // Positive long, negative short
+    amount: Decimal,
+    // Average price the open amount was entered at
+    entry: Decimal,
+    realized: Decimal,
+    // In each fill's fee currency, the quote currency for Gemini's spot pairs
+    fees: Decimal,
+    fills: u64,
+}
+
+impl Position {
+    // Fills against the position realize its PnL at the average entry, what is left
+    // over once it is flat opens the other way at the fill's price
+    fn apply(&mut self, side: OrderSide, price: Decimal, amount: Decimal, fee: Decimal) {
+        let signed = match side {
+            OrderSide::Buy => amount,
+            OrderSide::Sell => -amount,
+        };
+        self.fills += 1;
+        self.fees += fee;
+        if amount.is_zero() {
+            return;
+        }
+        if self.amount.is_zero() || self.amount.is_sign_positive() == signed.is_sign_positive() {
+            let total = self.amount + signed;
+            self.entry = (self.entry * self.amount.abs() + price * amount) / total.abs();
+            self.amount = total;
+            return;
+        }
+        let closed = amount.min(self.amount.abs());
+        let direction = if self.amount.is_sign_positive() { Decimal::ONE } else { Decimal::NEGATIVE_ONE };
+        self.realized += (price - self.entry) * closed * direction;
+        self.amount += signed;
+        if self.amount.is_zero() {
+            self.entry = Decimal::ZERO;
+        } else if self.amount.is_sign_positive() != direction.is_sign_positive() {
+            self.entry = price;
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PositionSnapshot {
+    pub symbol: String,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
+    // Unset while flat
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub average_entry: Option<Decimal>,
+    // The mid the position is marked at, unset before the symbol's first BBO
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub mark: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub realized_pnl: Decimal,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub unrealized_pnl: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub fees: Decimal,
+    pub fills: u64,
+}
+
+// The account's position per symbol, built from its fills since startup and marked at
+// the live mids. Clones share the positions, so the fills can come in on one task while
+// the dashboard and the status server read them.
+#[derive(Debug, Clone)]
+pub struct Positions {
+    positions: Arc<Mutex<BTreeMap<String, Position>>>,
+    marks: MarketState,
+}
+
+impl Positions {
+    pub fn new(marks: MarketState) -> Self {
+        Self {
+            positions: Arc::default(),
+            marks,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, Position>> {
+        self.positions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn on_fill(&self, fill: &Fill) {
+        self.lock().entry(fill.symbol.clone()).or_default().apply(fill.side, fill.price, fill.amount, fill.fee);
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<PositionSnapshot> {
+        let position = self.lock().get(symbol)?.clone();
+        Some(self.snapshot(symbol, &position))
+    }
+
+    // Every symbol with a fill, by name
+    pub fn snapshots(&self) -> Vec<PositionSnapshot> {
+        let positions = self.lock().clone();
+        positions.iter().map(|(symbol, position)| self.snapshot(symbol, position)).collect()
+    }
+
+    fn snapshot(&self, symbol: &str, position: &Position) -> PositionSnapshot {
+        let mark = self.marks.get(symbol)
+            .filter(|bbo| !bbo.best_bid.is_zero() && !bbo.best_offer.is_zero())
+            .map(|bbo| (bbo.best_bid + bbo.best_offer) / Decimal::TWO);
+        let flat = position.amount.is_zero();
+        PositionSnapshot {
+            symbol: symbol.to_string(),
+            amount: position.amount.normalize(),
+            average_entry: (!flat).then_some(position.entry.round_dp(8).normalize()),
+            mark,
+            realized_pnl: position.realized.round_dp(8).normalize(),
+            unrealized_pnl: mark.map(|mark| ((mark - position.entry) * position.amount).round_dp(8).normalize()),
+            fees: position.fees,
+            fills: position.fills,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::str::FromStr;
+
+use rust_decimal::{Decimal, RoundingStrategy};
+
+// Most places a Decimal holds
+const MAX_PLACES: u32 = 28;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumericField {
+    // Prices, and what is derived from them such as the VWAP or candle prices
+    Price,
+    // Trade sizes, remaining quantities, deltas and volumes
+    Amount,
+    // Price times amount
+    Notional,
+}
+
+impl FromStr for NumericField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "price" => Ok(Self::Price),
+            "amount" | "size" => Ok(Self::Amount),
+            "notional" => Ok(Self::Notional),
+            other => Err(format!("unknown field `{}`, expected price, amount or notional", other)),
+        }
+    }
+}
+
+// `FIELD=PLACES` such as price=2 or amount=8
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecimalsRule {
+    pub field: NumericField,
+    pub places: u32,
+}
+
+impl FromStr for DecimalsRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((field, places)) = s.split_once('=') else {
+            return Err(format!("expected `FIELD=PLACES`, got `{}`", s));
+        };
+        let places = places.trim().parse().ok()
+            .filter(|p| *p <= MAX_PLACES)
+            .ok_or_else(|| format!("decimal places must be 0 to {}, got `{}`", MAX_PLACES, places.trim()))?;
+        Ok(Self { field: field.parse()?, places })
+    }
+}
+
+// How numbers are written by the outputs. A field with a rule always has exactly that
+// many places, rounded half away from zero, one without keeps the places it came with.
+// Decimals never print in scientific notation either way. Notionals without a rule drop
+// the trailing zeros multiplying price and amount leaves.
+#[derive(Clone, Debug, Default)]
+pub struct Precision {
+    price: Option<u32>,
+    amount: Option<u32>,
+    notional: Option<u32>,
+    thousands: bool,
+}
+
+impl Precision {
+    // Later rules for a field win
+    pub fn new(rules: &[DecimalsRule]) -> Self {
+        let mut precision = Self::default();
+        for rule in rules {
+            let places = Some(rule.places);
+            match rule.field {
+                NumericField::Price => precision.price = places,
+                NumericField::Amount => precision.amount = places,
+                NumericField::Notional => precision.notional = places,
+            }
+        }
+        precision
+    }
+
+    // Group the integer digits of notionals by thousands in human output
+    pub fn thousands(mut self, thousands: bool) -> Self {
+        self.thousands = thousands;
+        self
+    }
+
+    pub fn price(&self, value: Decimal) -> Decimal {
+        fixed(value, self.price)
+    }
+
+    pub fn amount(&self, value: Decimal) -> Decimal {
+        fixed(value, self.amount)
+    }
+
+    pub fn notional(&self, value: Decimal) -> Decimal {
+        match self.notional {
+            Some(_) => fixed(value, self.notional),
+            None => value.normalize(),
+        }
+    }
+
+    // Whether notionals have a rule, outputs rounding them their own way otherwise
+    pub fn fixes_notional(&self) -> bool {
+        self.notional.is_some()
+    }
+
+    // The notional for people to read, with separators if asked for
+    pub fn notional_text(&self, value: Decimal) -> String {
+        let text = self.notional(value).to_string();
+        match self.thousands {
+            true => group_thousands(&text),
+            false => text,
+        }
+    }
+}
+
+fn fixed(value: Decimal, places: Option<u32>) -> Decimal {
+    let Some(places) = places else {
+        return value;
+    };
+    let mut value = value.round_dp_with_strategy(places, RoundingStrategy::MidpointAwayFromZero);
+    // Pads with zeros up to `places`, after rounding there is nothing left to cut
+    value.rescale(places);
+    value
+}
+
+fn group_thousands(text: &str) -> String {
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text),
+    };
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits, None),
+    };
+    let mut grouped = String::from(sign);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+    grouped
+}
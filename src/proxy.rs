@@ -0,0 +1,111 @@
+use base64::Engine;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+use url::Url;
+
+use crate::error::GeminiError;
+
+// Longest CONNECT response head we wait for before giving up on the proxy
+const MAX_RESPONSE_HEAD: usize = 8 * 1024;
+
+// An egress proxy: socks5:// and socks5h:// go through SOCKS5, http:// and a bare
+// host:port through HTTP CONNECT. Credentials may be given in the URL as user:password@.
+pub fn parse(proxy: &str) -> Result<Url, GeminiError> {
+    let url = match proxy.contains("://") {
+        true => Url::parse(proxy)?,
+        false => Url::parse(&format!("http://{}", proxy))?,
+    };
+    match url.scheme() {
+        "socks5" | "socks5h" | "http" if url.host_str().is_some() => Ok(url),
+        "socks5" | "socks5h" | "http" => Err(GeminiError::Config(format!("proxy {} has no host", proxy))),
+        other => Err(GeminiError::Config(format!("proxy scheme must be socks5, socks5h or http, got {}", other))),
+    }
+}
+
+// The proxy HTTPS_PROXY or ALL_PROXY name, either case, as curl and reqwest read them,
+// unless NO_PROXY exempts `host`. A value we cannot tunnel through is an error, not a
+// direct connection behind the back of a REST client that would still use it.
+pub fn from_env(host: &str) -> Result<Option<Url>, GeminiError> {
+    let found = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok().map(|value| (name, value)))
+        .find(|(_, value)| !value.is_empty());
+    let Some((name, value)) = found else {
+        return Ok(None);
+    };
+    let exempt = ["NO_PROXY", "no_proxy"].iter().filter_map(|name| std::env::var(name).ok()).any(|list| is_exempt(&list, host));
+    if exempt {
+        return Ok(None);
+    }
+    parse(&value).map(Some).map_err(|e| match e {
+        GeminiError::Config(message) => GeminiError::Config(format!("{}: {}", name, message)),
+        e => GeminiError::Config(format!("{}: {}", name, e)),
+    })
+}
+
+// NO_PROXY as curl reads it: `*`, or host names that also cover their subdomains, with or
+// without a leading dot. Ports and IP ranges in the list are not supported.
+fn is_exempt(no_proxy: &str, host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    no_proxy.split(',').map(|entry| entry.trim().trim_start_matches('.').to_lowercase()).any(|entry| {
+        entry == "*" || (!entry.is_empty() && (host == entry || host.ends_with(&format!(".{}", entry))))
+    })
+}
+
+// Opens a TCP stream to `host:port` tunnelled through `proxy`, ready for TLS or plain HTTP
+pub async fn tunnel(proxy: &Url, host: &str, port: u16) -> Result<TcpStream, GeminiError> {
+    let proxy_host = proxy.host_str().unwrap_or_default();
+    let proxy_port = proxy.port_or_known_default().unwrap_or(1080);
+    let user = percent_decode(proxy.username());
+    let password = proxy.password().map(percent_decode).unwrap_or_default();
+    if proxy.scheme() == "http" {
+        let stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+        let credentials = (!user.is_empty()).then(|| format!("{}:{}", user, password));
+        return connect_tunnel(stream, host, port, credentials.as_deref()).await;
+    }
+    let result = match user.is_empty() {
+        true => Socks5Stream::connect((proxy_host, proxy_port), (host, port)).await,
+        false => Socks5Stream::connect_with_password((proxy_host, proxy_port), (host, port), &user, &password).await,
+    };
+    result
+        .map(Socks5Stream::into_inner)
+        .map_err(|e| GeminiError::Protocol(format!("socks5 proxy {}:{}: {}", proxy_host, proxy_port, e)))
+}
+
+async fn connect_tunnel(mut stream: TcpStream, host: &str, port: u16, credentials: Option<&str>) -> Result<TcpStream, GeminiError> {
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if let Some(credentials) = credentials {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", encoded));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read byte by byte so nothing after the response head, the start of TLS, is consumed
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_RESPONSE_HEAD {
+            return Err(GeminiError::Protocol(String::from("proxy CONNECT response too long")));
+        }
+        let mut byte = [0u8];
+        if stream.read(&mut byte).await? == 0 {
+            return Err(GeminiError::Protocol(String::from("proxy closed the connection during CONNECT")));
+        }
+        head.push(byte[0]);
+    }
+    let status_line = String::from_utf8_lossy(&head);
+    let status_line = status_line.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(stream),
+        _ => Err(GeminiError::Protocol(format!("proxy refused CONNECT: {}", status_line))),
+    }
+}
+
+// User names and passwords in a URL are percent-encoded
+fn percent_decode(s: &str) -> String {
+    url::form_urlencoded::parse(format!("v={}", s.replace('+', "%2B")).as_bytes())
+        .next()
+        .map(|(_, v)| v.into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use crate::error::GeminiError;
+use crate::models::{Event, MarketMessage};
+
+// One line of the quarantine file: a capture line with the message's ids and the types
+// the models did not know, so the file also replays
+#[derive(Serialize, Debug)]
+struct QuarantinedFrame<'a> {
+    received_ms: u64,
+    symbol: &'a str,
+    event_id: u64,
+    socket_sequence: u32,
+    unknown_types: Vec<String>,
+    frame: &'a str,
+}
+
+// Appends the frames whose events parsed to `Event::Unknown` to a file, so additions to
+// the exchange's protocol show up instead of being dropped
+pub struct Quarantine {
+    file: File,
+    // Types warned about already
+    seen: HashSet<String>,
+}
+
+impl Quarantine {
+    pub async fn open(path: &Path) -> Result<Self, GeminiError> {
+        let file = OpenOptions::new().create(true).append(true).open(path).await?;
+        Ok(Self { file, seen: HashSet::new() })
+    }
+
+    // Writes `data` out when `message`, parsed from it, holds an unknown event
+    pub async fn check(&mut self, symbol: &str, received_ms: u64, data: &[u8], message: &MarketMessage) -> Result<(), GeminiError> {
+        if !message.events.iter().any(|e| matches!(e, Event::Unknown)) {
+            return Ok(());
+        }
+        let unknown_types = unknown_types(data);
+        for kind in &unknown_types {
+            if self.seen.insert(kind.clone()) {
+                warn!(symbol, kind, "quarantined an event of a type the models do not know");
+            }
+        }
+        let frame = String::from_utf8_lossy(data);
+        let line = QuarantinedFrame {
+            received_ms,
+            symbol,
+            event_id: message.event_id,
+            socket_sequence: message.socket_sequence,
+            unknown_types,
+            frame: &frame,
+        };
+        // Serializing a struct of strings and numbers cannot fail
+        let mut line = serde_json::to_string(&line).unwrap_or_default();
+        line.push('\n');
+        self.file.write_all(line.as_bytes()).await?;
+        self.file.flush().await?;
+        Ok(())
+    }
+}
+
+// The `type` of each event of the frame that parses to `Event::Unknown`, in order
+fn unknown_types(data: &[u8]) -> Vec<String> {
+    #[derive(Deserialize)]
+    struct Events {
+        #[serde(default)]
+        events: Vec<Value>,
+    }
+    let Ok(frame) = serde_json::from_slice::<Events>(data) else {
+        return Vec::new();
+    };
+    frame.events.into_iter()
+        .filter(|e| matches!(Event::deserialize(e), Ok(Event::Unknown)))
+        .map(|e| e.get("type").and_then(Value::as_str).unwrap_or("missing").to_string())
+        .collect()
+}
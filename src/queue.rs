@@ -0,0 +1,135 @@
+use clap::ValueEnum;
+use prometheus::Gauge;
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tracing::warn;
+
+use crate::metrics;
+
+// What a full queue does when the producer has another item
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackpressurePolicy {
+    // Wait for the consumer, slowing the producer down
+    #[default]
+    Block,
+    // Evict the oldest queued item so the producer never waits
+    DropOldest,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct QueueOptions {
+    pub capacity: usize,
+    pub policy: BackpressurePolicy,
+}
+
+impl Default for QueueOptions {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            policy: BackpressurePolicy::Block,
+        }
+    }
+}
+
+// Bounded single-consumer queue between two stages. `name` labels dropped items in the metrics.
+pub fn bounded<T: Clone>(name: &str, options: QueueOptions) -> (QueueSender<T>, QueueReceiver<T>) {
+    let capacity = options.capacity.max(1);
+    let (tx, rx) = match options.policy {
+        BackpressurePolicy::Block => {
+            let (tx, rx) = mpsc::channel(capacity);
+            (QueueSender::Block(tx), Inner::Block(rx))
+        },
+        // A lagging broadcast receiver skips the oldest items, which is exactly drop-oldest
+        BackpressurePolicy::DropOldest => {
+            let (tx, rx) = broadcast::channel(capacity);
+            (QueueSender::DropOldest(tx), Inner::DropOldest(rx))
+        },
+    };
+    let depth = metrics::global().queue_depth.with_label_values(&[name]);
+    (tx, QueueReceiver { name: name.to_string(), inner: rx, depth })
+}
+
+#[derive(Debug)]
+pub enum QueueSender<T> {
+    Block(mpsc::Sender<T>),
+    DropOldest(broadcast::Sender<T>),
+}
+
+impl<T> Clone for QueueSender<T> {
+    fn clone(&self) -> Self {
+        match self {
+            QueueSender::Block(tx) => QueueSender::Block(tx.clone()),
+            QueueSender::DropOldest(tx) => QueueSender::DropOldest(tx.clone()),
+        }
+    }
+}
+
+impl<T> QueueSender<T> {
+    // Gives the item back when the consumer is gone
+    pub async fn send(&self, item: T) -> Result<(), T> {
+        match self {
+            QueueSender::Block(tx) => tx.send(item).await.map_err(|e| e.0),
+            QueueSender::DropOldest(tx) => tx.send(item).map(|_| ()).map_err(|e| e.0),
+        }
+    }
+}
+
+enum Inner<T> {
+    Block(mpsc::Receiver<T>),
+    DropOldest(broadcast::Receiver<T>),
+}
+
+pub struct QueueReceiver<T> {
+    name: String,
+    inner: Inner<T>,
+    depth: Gauge,
+}
+
+impl<T: Clone> QueueReceiver<T> {
+    // None once every sender is gone and the queue is drained
+    pub async fn recv(&mut self) -> Option<T> {
+        let item = match &mut self.inner {
+            Inner::Block(rx) => rx.recv().await,
+            Inner::DropOldest(rx) => loop {
+                match rx.recv().await {
+                    Ok(item) => break Some(item),
+                    Err(RecvError::Lagged(n)) => dropped(&self.name, n),
+                    Err(RecvError::Closed) => break None,
+                }
+            },
+        };
+        self.record_depth();
+        item
+    }
+
+    // Same as `recv`, for consumers running on a blocking thread
+    pub fn blocking_recv(&mut self) -> Option<T> {
+        let item = match &mut self.inner {
+            Inner::Block(rx) => rx.blocking_recv(),
+            Inner::DropOldest(rx) => loop {
+                match rx.blocking_recv() {
+                    Ok(item) => break Some(item),
+                    Err(RecvError::Lagged(n)) => dropped(&self.name, n),
+                    Err(RecvError::Closed) => break None,
+                }
+            },
+        };
+        self.record_depth();
+        item
+    }
+
+    fn record_depth(&self) {
+        let len = match &self.inner {
+            Inner::Block(rx) => rx.len(),
+            Inner::DropOldest(rx) => rx.len(),
+        };
+        self.depth.set(len as f64);
+    }
+}
+
+fn dropped(name: &str, count: u64) {
+    metrics::global().dropped.with_label_values(&[name]).inc_by(count);
+    warn!(queue = name, dropped = count, "queue full, dropped the oldest items");
+}
@@ -0,0 +1,143 @@
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::auth::Signer;
+use crate::client::Endpoint;
+use crate::error::GeminiError;
+use crate::exchange_candles::CandleRow;
+use crate::models::{Balance, BookSnapshot, FundingAmount, NewOrder, OrderStatus, SymbolDetails, TradeRecord};
+
+// Body of a failed private request
+#[derive(Deserialize)]
+struct ApiError {
+    reason: String,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Serialize)]
+struct OrderId {
+    order_id: u64,
+}
+
+// Most trades `/v1/trades` returns per request
+const TRADES_PAGE: usize = 500;
+
+// REST API of the same environment as the market data feed
+#[derive(Debug, Clone)]
+pub struct RestClient {
+    http: reqwest::Client,
+    base: Url,
+}
+
+impl RestClient {
+    // Without a proxy on the endpoint reqwest still honors HTTPS_PROXY and friends. Fails
+    // rather than fall back to a client that would skip the proxy or the CA bundle.
+    pub fn new(endpoint: &Endpoint) -> Result<Self, GeminiError> {
+        let mut builder = endpoint.tls.configure(reqwest::Client::builder())?;
+        if let Some(proxy) = &endpoint.proxy {
+            let proxy = reqwest::Proxy::all(proxy.as_str()).map_err(|e| GeminiError::Config(format!("proxy {}: {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        }
+        Ok(Self {
+            http: builder.build().map_err(|e| GeminiError::Config(format!("rest client: {}", e)))?,
+            base: endpoint.rest_base.clone(),
+        })
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, GeminiError> {
+        let url = self.base.join(path)?;
+        let response = self.http.get(url).send().await?.error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    // Signed call to a private endpoint, `request` is the path such as "/v1/order/new"
+    async fn post<T: DeserializeOwned, P: Serialize>(&self, signer: &Signer, request: &str, params: &P) -> Result<T, GeminiError> {
+        let signed = signer.sign(request, params)?;
+        let url = self.base.join(request.trim_start_matches('/'))?;
+        let response = self.http.post(url)
+            .header("Content-Type", "text/plain")
+            .header("Cache-Control", "no-cache")
+            .header("X-GEMINI-APIKEY", signed.api_key)
+            .header("X-GEMINI-PAYLOAD", signed.payload)
+            .header("X-GEMINI-SIGNATURE", signed.signature)
+            .body("")
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let reason = match response.json::<ApiError>().await {
+                Ok(e) if e.message.is_empty() => e.reason,
+                Ok(e) => format!("{}: {}", e.reason, e.message),
+                Err(_) => status.to_string(),
+            };
+            return Err(match status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => GeminiError::Auth(reason),
+                _ => GeminiError::Api(reason),
+            });
+        }
+        Ok(response.json().await?)
+    }
+
+    // Every tradable symbol, lowercase
+    pub async fn symbols(&self) -> Result<Vec<String>, GeminiError> {
+        self.get("v1/symbols").await
+    }
+
+    pub async fn symbol_details(&self, symbol: &str) -> Result<SymbolDetails, GeminiError> {
+        self.get(&format!("v1/symbols/details/{}", symbol)).await
+    }
+
+    // The best `levels` price levels per side
+    pub async fn book(&self, symbol: &str, levels: usize) -> Result<BookSnapshot, GeminiError> {
+        self.get(&format!("v1/book/{}?limit_bids={}&limit_asks={}", symbol, levels, levels)).await
+    }
+
+    // Last and estimated next funding of a perpetual
+    pub async fn funding_amount(&self, symbol: &str) -> Result<FundingAmount, GeminiError> {
+        self.get(&format!("v1/fundingamount/{}", symbol)).await
+    }
+
+    // Bars of `time_frame` such as 1m, newest first, as far back as the exchange keeps
+    // them. The endpoint takes no time range.
+    pub async fn candles(&self, symbol: &str, time_frame: &str) -> Result<Vec<CandleRow>, GeminiError> {
+        self.get(&format!("v2/candles/{}/{}", symbol, time_frame)).await
+    }
+
+    // Trades after `since_ms`, oldest first, paging forward until the present
+    pub async fn trades_since(&self, symbol: &str, since_ms: u64) -> Result<Vec<TradeRecord>, GeminiError> {
+        let mut trades: Vec<TradeRecord> = Vec::new();
+        let mut since = since_ms;
+        loop {
+            let mut page: Vec<TradeRecord> = self.get(&format!("v1/trades/{}?since={}&limit_trades={}", symbol, since, TRADES_PAGE)).await?;
+            let full = page.len() >= TRADES_PAGE;
+            page.sort_by_key(|t| t.tid);
+            // Pages overlap at the millisecond they are cut at
+            let last_tid = trades.last().map(|t| t.tid).unwrap_or(0);
+            let before = trades.len();
+            trades.extend(page.into_iter().filter(|t| t.tid > last_tid));
+            match trades.last() {
+                Some(last) if full && trades.len() > before => since = last.timestampms,
+                _ => return Ok(trades),
+            }
+        }
+    }
+
+    pub async fn new_order(&self, signer: &Signer, order: &NewOrder) -> Result<OrderStatus, GeminiError> {
+        self.post(signer, "/v1/order/new", order).await
+    }
+
+    pub async fn cancel_order(&self, signer: &Signer, order_id: u64) -> Result<OrderStatus, GeminiError> {
+        self.post(signer, "/v1/order/cancel", &OrderId { order_id }).await
+    }
+
+    pub async fn balances(&self, signer: &Signer) -> Result<Vec<Balance>, GeminiError> {
+        self.post(signer, "/v1/balances", &()).await
+    }
+
+    pub async fn order_status(&self, signer: &Signer, order_id: u64) -> Result<OrderStatus, GeminiError> {
+        self.post(signer, "/v1/order/status", &OrderId { order_id }).await
+    }
+}
@@ -0,0 +1,85 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::handler::{self, HandlerMessage};
+
+// Which events an output gets, by the name of its handler such as kafka or postgres.
+// An output with routes gets what any of them match, one without gets everything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub sink: String,
+    // Event kinds, all of them when empty
+    pub events: Vec<&'static str>,
+    // Symbols, all of them when empty
+    pub symbols: Vec<String>,
+}
+
+impl Route {
+    // Disconnects of a symbol go wherever its events do, ticks go everywhere
+    pub fn matches(&self, message: &HandlerMessage) -> bool {
+        let (symbol, kind) = match message {
+            HandlerMessage::Event { symbol, event, .. } => (symbol, Some(event.kind())),
+            HandlerMessage::Disconnect { symbol, .. } => (symbol, None),
+            HandlerMessage::Tick { .. } => return true,
+        };
+        (self.symbols.is_empty() || self.symbols.contains(symbol))
+            && kind.is_none_or(|kind| self.events.is_empty() || self.events.contains(&kind))
+    }
+}
+
+// The printer writes to stdout or --output-file
+fn sink_name(name: &str) -> String {
+    match name.trim() {
+        "stdout" => String::from("printer"),
+        name => name.to_lowercase(),
+    }
+}
+
+fn parse_kinds<'a>(kinds: impl IntoIterator<Item = &'a str>) -> Result<Vec<&'static str>, String> {
+    kinds.into_iter()
+        .filter(|kind| kind.trim() != "*")
+        .map(handler::parse_kind)
+        .collect()
+}
+
+// `SINK=KINDS[@SYMBOLS]` with comma separated lists such as kafka=trades, stdout=bbo or
+// postgres=*@btcusd,ethusd, where * stands for every kind
+impl FromStr for Route {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((sink, rest)) = s.split_once('=') else {
+            return Err(format!("expected `SINK=KINDS[@SYMBOLS]`, got `{}`", s));
+        };
+        let (kinds, symbols) = rest.split_once('@').unwrap_or((rest, ""));
+        Ok(Self {
+            sink: sink_name(sink),
+            events: parse_kinds(kinds.split(','))?,
+            symbols: symbols.split(',').map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect(),
+        })
+    }
+}
+
+// A `[[routes]]` table of the config
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    pub sink: String,
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+impl TryFrom<RouteConfig> for Route {
+    type Error = String;
+
+    fn try_from(config: RouteConfig) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sink: sink_name(&config.sink),
+            events: parse_kinds(config.events.iter().map(String::as_str))?,
+            symbols: config.symbols.iter().map(|s| s.to_lowercase()).collect(),
+        })
+    }
+}
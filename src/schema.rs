@@ -0,0 +1,259 @@
+use serde_json::{json, Map, Value};
+
+use crate::handler::EVENT_KINDS;
+
+// Version of the JSONL records. Bumped whenever a field is added, removed, renamed or
+// changes its type, so consumers can pin the schema they validated against.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// JSON Schema (draft 2020-12) of the lines of `--output jsonl`, which the JSON-speaking
+// sinks share: the fields every record has, and per `kind` the fields of its event.
+// Kept by hand next to the serde attributes of the event types, `tests/schema.rs`
+// checks the two agree.
+pub fn jsonl_schema() -> Value {
+    let kinds: Vec<Value> = EVENT_KINDS.iter().map(|kind| json!({ "$ref": format!("#/$defs/{}", kind) })).collect();
+    let mut defs = Map::new();
+    defs.insert(String::from("decimal"), json!({
+        "description": "Decimal number as a string, to keep its precision",
+        "type": "string",
+        "pattern": "^-?[0-9]+(\\.[0-9]+)?$",
+    }));
+    defs.insert(String::from("decimal_or_null"), json!({
+        "type": ["string", "null"],
+        "pattern": "^-?[0-9]+(\\.[0-9]+)?$",
+    }));
+    defs.insert(String::from("side"), json!({ "enum": ["bid", "ask", "unknown"] }));
+    defs.insert(String::from("record"), record());
+    for kind in EVENT_KINDS {
+        let (properties, required) = event(kind);
+        let mut properties = properties;
+        properties.insert(String::from("kind"), json!({ "const": kind }));
+        defs.insert(kind.to_string(), json!({
+            "allOf": [{ "$ref": "#/$defs/record" }],
+            "properties": properties,
+            "required": required,
+            "unevaluatedProperties": false,
+        }));
+    }
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "order_book JSONL event",
+        "description": format!("One line of JSONL output, schema version {}", SCHEMA_VERSION),
+        "version": SCHEMA_VERSION,
+        "oneOf": kinds,
+        "$defs": defs,
+    })
+}
+
+// The fields of `Record` around every event
+fn record() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "kind": { "enum": EVENT_KINDS },
+            "symbol": { "type": "string" },
+            "event_id": { "type": "integer", "minimum": 0 },
+            "socket_sequence": { "type": "integer", "minimum": 0 },
+            "timestampms": { "description": "Exchange time of the message", "type": ["integer", "null"] },
+            "received_ms": { "type": "integer" },
+            "received_at_ns": { "type": "integer" },
+            "emitted_at_ns": { "type": "integer" },
+            "backfill": { "description": "Only present, and true, for trades fetched before streaming", "const": true },
+            "recovered": { "description": "Only present, and true, for trades fetched to fill an outage", "const": true },
+        },
+        "required": ["kind", "symbol", "event_id", "socket_sequence", "timestampms", "received_ms", "received_at_ns", "emitted_at_ns"],
+    })
+}
+
+const DECIMAL: &str = "#/$defs/decimal";
+const DECIMAL_OR_NULL: &str = "#/$defs/decimal_or_null";
+const SIDE: &str = "#/$defs/side";
+
+fn r(target: &str) -> Value {
+    json!({ "$ref": target })
+}
+
+fn object(properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn flag(description: &str) -> Value {
+    json!({ "description": description, "const": true })
+}
+
+// The event's own fields of a `kind`, and which of them are always present
+fn event(kind: &str) -> (Map<String, Value>, Vec<&'static str>) {
+    let (properties, required): (Value, Vec<&'static str>) = match kind {
+        "trade" => (json!({
+            "price": r(DECIMAL),
+            "amount": r(DECIMAL),
+            "makerSide": r(SIDE),
+        }), vec!["price", "amount", "makerSide"]),
+        "quote" => (json!({
+            "price": r(DECIMAL),
+            "reason": { "type": "string" },
+            "remaining": r(DECIMAL),
+            "side": r(SIDE),
+            "delta": r(DECIMAL_OR_NULL),
+        }), vec!["price", "reason", "remaining", "side", "delta"]),
+        "bbo" => (json!({
+            "best_bid": r(DECIMAL),
+            "best_offer": r(DECIMAL),
+            "bid_amount_remaining": r(DECIMAL),
+            "ask_amount_remaining": r(DECIMAL),
+        }), vec!["best_bid", "best_offer", "bid_amount_remaining", "ask_amount_remaining"]),
+        "block_trade" => (json!({
+            "tid": { "type": "integer" },
+            "price": r(DECIMAL),
+            "amount": r(DECIMAL),
+        }), vec!["tid", "price", "amount"]),
+        // Fields of the other phases are absent, not null
+        "auction" => (json!({
+            "phase": { "enum": ["open", "indicative", "result"] },
+            "auction_open_ms": { "type": "integer" },
+            "auction_time_ms": { "type": "integer" },
+            "first_indicative_ms": { "type": ["integer", "null"] },
+            "last_cancel_time_ms": { "type": ["integer", "null"] },
+            "eid": { "type": "integer" },
+            "result": { "type": "string" },
+            "time_ms": { "type": "integer" },
+            "highest_bid_price": r(DECIMAL_OR_NULL),
+            "lowest_ask_price": r(DECIMAL_OR_NULL),
+            "collar_price": r(DECIMAL_OR_NULL),
+            "indicative_price": r(DECIMAL_OR_NULL),
+            "indicative_quantity": r(DECIMAL_OR_NULL),
+            "auction_price": r(DECIMAL_OR_NULL),
+            "auction_quantity": r(DECIMAL_OR_NULL),
+        }), vec!["phase"]),
+        "stats" => (json!({
+            "windows": array(object(json!({
+                "window": { "type": "string" },
+                "trades": { "type": "integer" },
+                "volume": r(DECIMAL),
+                "notional": r(DECIMAL),
+                "vwap": r(DECIMAL_OR_NULL),
+                "buy_volume": r(DECIMAL),
+                "sell_volume": r(DECIMAL),
+                "tainted": flag("Present when the feed was interrupted within the window"),
+            }), &["window", "trades", "volume", "notional", "vwap", "buy_volume", "sell_volume"])),
+            "cumulative_delta": r(DECIMAL),
+        }), vec!["windows", "cumulative_delta"]),
+        "candle" => (json!({
+            "start_ms": { "type": "integer" },
+            "interval": { "type": "string" },
+            "open": r(DECIMAL),
+            "high": r(DECIMAL),
+            "low": r(DECIMAL),
+            "close": r(DECIMAL),
+            "volume": r(DECIMAL),
+            "trades": { "type": "integer" },
+            "tainted": flag("Present when the feed was interrupted during the bar"),
+            "exchange": flag("Present for bars computed by the exchange"),
+        }), vec!["start_ms", "interval", "open", "high", "low", "close", "volume", "trades"]),
+        "technical" => (json!({
+            "interval": { "type": "string" },
+            "start_ms": { "type": "integer" },
+            "close": r(DECIMAL),
+            "values": { "type": "object", "additionalProperties": r(DECIMAL) },
+        }), vec!["interval", "start_ms", "close", "values"]),
+        "cross" => (json!({
+            "cross": { "type": "string" },
+            "actual": r(DECIMAL),
+            "implied": r(DECIMAL),
+            "divergence_bps": r(DECIMAL),
+            "diverged": { "type": "boolean" },
+        }), vec!["cross", "actual", "implied", "divergence_bps", "diverged"]),
+        "indicators" => (json!({
+            "imbalance": r(DECIMAL),
+            "microprice": r(DECIMAL),
+            "band": { "type": "integer", "minimum": 0 },
+            "tainted": flag("Present when messages were lost since the book was rebuilt"),
+        }), vec!["imbalance", "microprice"]),
+        "depth" => {
+            let side = object(json!({
+                "levels": { "type": "integer" },
+                "amount": r(DECIMAL),
+                "price": r(DECIMAL),
+            }), &["levels", "amount", "price"]);
+            (json!({
+                "bid": side,
+                "ask": side,
+                "weighted_mid": r(DECIMAL),
+                "sizes": array(object(json!({
+                    "amount": r(DECIMAL),
+                    "buy": r(DECIMAL_OR_NULL),
+                    "sell": r(DECIMAL_OR_NULL),
+                    "mid": r(DECIMAL_OR_NULL),
+                    "spread_bps": r(DECIMAL_OR_NULL),
+                }), &["amount", "buy", "sell", "mid", "spread_bps"])),
+                "tainted": flag("Present when messages were lost since the book was rebuilt"),
+            }), vec![])
+        },
+        "volatility" => (json!({
+            "sample": { "type": "string" },
+            "windows": array(object(json!({
+                "window": { "type": "string" },
+                "samples": { "type": "integer" },
+                "return_bps": { "type": "number" },
+                "realized_vol": { "type": "number" },
+                "annualized_vol": { "type": "number" },
+            }), &["window", "samples", "return_bps", "realized_vol", "annualized_vol"])),
+        }), vec!["sample", "windows"]),
+        "dwell" => {
+            let side = object(json!({
+                "levels": { "type": "integer" },
+                "mean_ms": { "type": "integer" },
+                "p50_ms": { "type": "integer" },
+                "p90_ms": { "type": "integer" },
+                "p99_ms": { "type": "integer" },
+                "max_ms": { "type": "integer" },
+                "flickers": { "type": "integer" },
+                "flicker_rate": { "type": "number" },
+            }), &["levels", "mean_ms", "p50_ms", "p90_ms", "p99_ms", "max_ms", "flickers", "flicker_rate"]);
+            (json!({
+                "window": { "type": "string" },
+                "flicker_threshold": { "type": "string" },
+                "bid": side,
+                "ask": side,
+            }), vec!["window", "flicker_threshold", "bid", "ask"])
+        },
+        "ofi" => (json!({
+            "windows": array(object(json!({
+                "window": { "type": "string" },
+                "ofi": r(DECIMAL),
+                "changes": { "type": "integer" },
+                "tainted": flag("Present when the feed was interrupted within the window"),
+            }), &["window", "ofi", "changes"])),
+        }), vec!["windows"]),
+        "anomaly" => (json!({
+            "anomaly": { "enum": ["trade_away_from_mid", "spread_blowout", "stale_quotes"] },
+            "value": r(DECIMAL),
+            "reference": r(DECIMAL),
+            "deviation": r(DECIMAL),
+            "threshold": r(DECIMAL),
+            "amount": r(DECIMAL),
+            "side": r(SIDE),
+        }), vec!["anomaly", "value", "reference", "deviation", "threshold"]),
+        // The funding fields keep the exchange's names
+        "funding" => (json!({
+            "fundingTimestampMilliSecs": { "type": "integer" },
+            "nextFundingTimestamp": { "type": "integer" },
+            "amount": r(DECIMAL),
+            "estimatedFundingAmount": r(DECIMAL_OR_NULL),
+        }), vec!["fundingTimestampMilliSecs", "nextFundingTimestamp", "amount", "estimatedFundingAmount"]),
+        _ => (json!({}), vec![]),
+    };
+    match properties {
+        Value::Object(properties) => (properties, required),
+        _ => (Map::new(), required),
+    }
+}
@@ -0,0 +1,152 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use tracing::warn;
+
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{BestBidOffer, MarketSide, Quote, Trade};
+use crate::output::EventContext;
+
+// Functions a script may define, each called with the symbol and the event as a map
+const CALLBACKS: [&str; 3] = ["on_trade", "on_quote", "on_bbo"];
+
+// What the script asked for during one callback
+enum Effect {
+    Emit(String),
+    Alert(String),
+}
+
+// Runs a Rhai script's `on_trade(symbol, trade)`, `on_quote(symbol, quote)` and
+// `on_bbo(symbol, bbo)` for every event. Inside them `this` is a map that lives as
+// long as the run, for the script's own state. `emit(value)` prints a line, a string
+// as it is and anything else as JSON, `alert(text)` logs a warning.
+pub struct ScriptHandler {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    state: Dynamic,
+    defined: Vec<&'static str>,
+    effects: Arc<Mutex<Vec<Effect>>>,
+}
+
+impl ScriptHandler {
+    pub fn load(path: &Path) -> Result<Self, GeminiError> {
+        let effects: Arc<Mutex<Vec<Effect>>> = Arc::default();
+        let mut engine = Engine::new();
+        let emitted = effects.clone();
+        engine.register_fn("emit", move |value: Dynamic| {
+            let line = match value.clone().into_string() {
+                Ok(text) => text,
+                Err(_) => serde_json::to_string(&value).unwrap_or_else(|_| value.to_string()),
+            };
+            emitted.lock().unwrap().push(Effect::Emit(line));
+        });
+        let alerted = effects.clone();
+        engine.register_fn("alert", move |text: &str| {
+            alerted.lock().unwrap().push(Effect::Alert(text.to_string()));
+        });
+
+        let script_error = |e: Box<rhai::EvalAltResult>| GeminiError::Config(format!("{}: {}", path.display(), e));
+        let ast = engine.compile_file(path.to_path_buf()).map_err(script_error)?;
+        // Top level statements run once, before the first event
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast).map_err(script_error)?;
+        let defined: Vec<&'static str> = CALLBACKS.into_iter()
+            .filter(|name| ast.iter_functions().any(|f| f.name == *name && f.params.len() == 2))
+            .collect();
+        if defined.is_empty() {
+            return Err(GeminiError::Config(format!("{}: defines none of {}", path.display(), CALLBACKS.join(", "))));
+        }
+        Ok(Self {
+            engine,
+            ast,
+            scope,
+            state: Dynamic::from_map(Map::new()),
+            defined,
+            effects,
+        })
+    }
+
+    fn call(&mut self, callback: &'static str, symbol: &str, event: Map) {
+        if !self.defined.contains(&callback) {
+            return;
+        }
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.state);
+        let result = self.engine.call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, callback, (symbol.to_string(), event));
+        if let Err(e) = result {
+            warn!(symbol, callback, error = %e, "script failed");
+        }
+        let effects = std::mem::take(&mut *self.effects.lock().unwrap());
+        let mut out = std::io::stdout().lock();
+        for effect in effects {
+            match effect {
+                Effect::Emit(line) => {
+                    let _ = writeln!(out, "{}", line);
+                },
+                Effect::Alert(message) => warn!(symbol, message, "script alert"),
+            }
+        }
+    }
+}
+
+fn number(d: Decimal) -> Dynamic {
+    Dynamic::from_float(d.to_f64().unwrap_or(0.))
+}
+
+fn side(side: MarketSide) -> Dynamic {
+    let side = match side {
+        MarketSide::Bid => "bid",
+        MarketSide::Ask => "ask",
+        MarketSide::Unknown => "unknown",
+    };
+    side.into()
+}
+
+fn event_map(ctx: &EventContext) -> Map {
+    let mut map = Map::new();
+    map.insert("event_id".into(), Dynamic::from_int(ctx.event_id as i64));
+    map.insert("timestampms".into(), Dynamic::from_int(ctx.timestampms.unwrap_or(ctx.received_ms) as i64));
+    map.insert("backfill".into(), Dynamic::from_bool(ctx.backfill));
+    map.insert("recovered".into(), Dynamic::from_bool(ctx.recovered));
+    map
+}
+
+impl EventHandler for ScriptHandler {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        let mut map = event_map(ctx);
+        map.insert("price".into(), number(trade.price));
+        map.insert("amount".into(), number(trade.amount));
+        map.insert("maker_side".into(), side(trade.maker_side));
+        self.call("on_trade", symbol, map);
+        Ok(())
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        let mut map = event_map(ctx);
+        map.insert("price".into(), number(quote.price));
+        map.insert("remaining".into(), number(quote.remaining));
+        map.insert("side".into(), side(quote.side));
+        map.insert("reason".into(), quote.reason.clone().into());
+        self.call("on_quote", symbol, map);
+        Ok(())
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        let mut map = event_map(ctx);
+        map.insert("bid".into(), number(bbo.best_bid));
+        map.insert("bid_size".into(), number(bbo.bid_amount_remaining));
+        map.insert("ask".into(), number(bbo.best_offer));
+        map.insert("ask_size".into(), number(bbo.ask_amount_remaining));
+        self.call("on_bbo", symbol, map);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "script"
+    }
+}
@@ -0,0 +1,196 @@
+use std::borrow::Cow;
+use std::net::SocketAddr;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::analytics::anomaly::AnomalyEvent;
+use crate::analytics::candles::Candle;
+use crate::analytics::cross::CrossDivergence;
+use crate::analytics::depth::DepthSnapshot;
+use crate::analytics::dwell::DwellSnapshot;
+use crate::analytics::indicators::BookIndicators;
+use crate::analytics::ofi::OfiSnapshot;
+use crate::analytics::technical::TechnicalSnapshot;
+use crate::analytics::volatility::VolatilitySnapshot;
+use crate::analytics::vwap::TradeStatsSnapshot;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{AuctionEvent, BestBidOffer, BlockTrade, FundingAmount, Quote, Trade};
+use crate::output::{EventContext, Formatter, OutputFormat};
+use crate::wire::{WireData, WireEvent, WireFormat};
+
+// Lines a client may fall behind before it starts missing events
+const CLIENT_BACKLOG: usize = 1024;
+
+// Re-broadcasts the normalized events to every client of a local WebSocket server, as
+// JSONL text messages or binary wire format messages
+pub struct BroadcastHandler {
+    formatter: Formatter,
+    format: WireFormat,
+    tx: broadcast::Sender<Message>,
+}
+
+impl BroadcastHandler {
+    fn publish(&self, line: Option<String>) -> Result<(), GeminiError> {
+        if let (WireFormat::Json, Some(mut line)) = (self.format, line) {
+            line.truncate(line.trim_end().len());
+            // Nobody connected is not an error
+            let _ = self.tx.send(Message::Text(line));
+        }
+        Ok(())
+    }
+
+    fn publish_market(&self, symbol: &str, ctx: &EventContext, data: WireData, line: impl FnOnce() -> String) -> Result<(), GeminiError> {
+        match self.format {
+            WireFormat::Json => self.publish(Some(line())),
+            WireFormat::Bincode => {
+                let _ = self.tx.send(Message::Binary(WireEvent::new(symbol, ctx, data).to_message()));
+                Ok(())
+            },
+        }
+    }
+}
+
+impl EventHandler for BroadcastHandler {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.publish_market(symbol, ctx, WireData::Trade(Cow::Borrowed(trade)), || self.formatter.trade(symbol, ctx, trade))
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        match self.format {
+            WireFormat::Json => self.publish(self.formatter.quote(symbol, ctx, quote)),
+            WireFormat::Bincode => self.publish_market(symbol, ctx, WireData::Quote(Cow::Borrowed(quote)), String::new),
+        }
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.publish_market(symbol, ctx, WireData::Bbo(Cow::Borrowed(bbo)), || self.formatter.bbo(symbol, ctx, bbo, None))
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.publish_market(symbol, ctx, WireData::BlockTrade(Cow::Borrowed(trade)), || self.formatter.block_trade(symbol, ctx, trade))
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.publish_market(symbol, ctx, WireData::from(auction), || self.formatter.auction(symbol, ctx, auction))
+    }
+
+    fn on_stats(&mut self, symbol: &str, ctx: &EventContext, stats: &TradeStatsSnapshot) -> Result<(), GeminiError> {
+        self.publish(self.formatter.stats(symbol, ctx, stats))
+    }
+
+    fn on_candle(&mut self, symbol: &str, ctx: &EventContext, candle: &Candle) -> Result<(), GeminiError> {
+        self.publish(self.formatter.candle(symbol, ctx, candle))
+    }
+
+    fn on_technical(&mut self, symbol: &str, ctx: &EventContext, technical: &TechnicalSnapshot) -> Result<(), GeminiError> {
+        self.publish(self.formatter.technical(symbol, ctx, technical))
+    }
+
+    fn on_indicators(&mut self, symbol: &str, ctx: &EventContext, indicators: &BookIndicators) -> Result<(), GeminiError> {
+        self.publish(self.formatter.indicators(symbol, ctx, indicators))
+    }
+
+    fn on_depth(&mut self, symbol: &str, ctx: &EventContext, depth: &DepthSnapshot) -> Result<(), GeminiError> {
+        self.publish(self.formatter.depth(symbol, ctx, depth))
+    }
+
+    fn on_volatility(&mut self, symbol: &str, ctx: &EventContext, volatility: &VolatilitySnapshot) -> Result<(), GeminiError> {
+        self.publish(self.formatter.volatility(symbol, ctx, volatility))
+    }
+
+    fn on_dwell(&mut self, symbol: &str, ctx: &EventContext, dwell: &DwellSnapshot) -> Result<(), GeminiError> {
+        self.publish(self.formatter.dwell(symbol, ctx, dwell))
+    }
+
+    fn on_ofi(&mut self, symbol: &str, ctx: &EventContext, ofi: &OfiSnapshot) -> Result<(), GeminiError> {
+        self.publish(self.formatter.ofi(symbol, ctx, ofi))
+    }
+
+    fn on_anomaly(&mut self, symbol: &str, ctx: &EventContext, anomaly: &AnomalyEvent) -> Result<(), GeminiError> {
+        self.publish(self.formatter.anomaly(symbol, ctx, anomaly))
+    }
+
+    fn on_cross(&mut self, symbol: &str, ctx: &EventContext, cross: &CrossDivergence) -> Result<(), GeminiError> {
+        self.publish(self.formatter.cross(symbol, ctx, cross))
+    }
+
+    fn on_funding(&mut self, symbol: &str, ctx: &EventContext, funding: &FundingAmount) -> Result<(), GeminiError> {
+        self.publish(self.formatter.funding(symbol, ctx, funding))
+    }
+
+    fn name(&self) -> &'static str {
+        "serve"
+    }
+}
+
+// Binds `addr` right away so a taken port fails at startup. Returns the handler to
+// register with the pipeline and the accept loop to spawn.
+pub async fn bind(
+    addr: SocketAddr,
+    format: WireFormat,
+    shutdown: CancellationToken,
+) -> Result<(BroadcastHandler, impl std::future::Future<Output = ()>), GeminiError> {
+    let listener = TcpListener::bind(addr).await?;
+    let (tx, _) = broadcast::channel(CLIENT_BACKLOG);
+    let handler = BroadcastHandler {
+        formatter: Formatter::new(OutputFormat::Jsonl),
+        format,
+        tx: tx.clone(),
+    };
+    info!(%addr, "serving normalized events over websocket");
+    Ok((handler, accept_loop(listener, tx, shutdown)))
+}
+
+async fn accept_loop(listener: TcpListener, tx: broadcast::Sender<Message>, shutdown: CancellationToken) {
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = shutdown.cancelled() => return,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!(error = %e, "serve: accept failed");
+                    continue;
+                },
+            },
+        };
+        let rx = tx.subscribe();
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client(stream, rx, shutdown).await {
+                info!(%peer, error = %e, "serve: client dropped");
+            }
+        });
+    }
+}
+
+async fn client(stream: TcpStream, mut rx: broadcast::Receiver<Message>, shutdown: CancellationToken) -> Result<(), GeminiError> {
+    let ws = tokio_tungstenite::accept_async(stream).await?;
+    let (mut write, mut read) = ws.split();
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => {
+                write.send(Message::Close(None)).await?;
+                return Ok(());
+            },
+            message = rx.recv() => match message {
+                Ok(message) => write.send(message).await?,
+                Err(RecvError::Lagged(n)) => warn!(skipped = n, "serve: slow client skipped events"),
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            // Clients only listen, anything they send apart from a close is ignored
+            message = read.next() => match message {
+                Some(Ok(message)) if message.is_close() => return Ok(()),
+                Some(Ok(_)) => {},
+                Some(Err(e)) => return Err(e.into()),
+                None => return Ok(()),
+            },
+        }
+    }
+}
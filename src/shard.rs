@@ -0,0 +1,68 @@
+use std::time::Instant;
+
+use crate::client::{FeedEvent, Frame, ParsedFrame};
+use crate::metrics;
+use crate::models::MarketMessage;
+use crate::queue::{self, QueueOptions, QueueReceiver, QueueSender};
+
+// Spreads the connections over a fixed set of worker tasks, each parsing the frames of
+// its symbols, so only finished messages reach the pipeline. A symbol always lands on
+// the same worker, which keeps its frames and disconnects in order. Dropping it closes
+// the workers once their connections are gone too.
+pub struct Shards {
+    senders: Vec<QueueSender<FeedEvent>>,
+}
+
+impl Shards {
+    // Must be called within a tokio runtime. `out` is where every worker hands its events on.
+    pub fn spawn(workers: usize, out: &QueueSender<FeedEvent>, queue: QueueOptions) -> Self {
+        let senders = (0..workers.max(1)).map(|i| {
+            let (tx, rx) = queue::bounded(&format!("shard-{}", i), queue);
+            tokio::spawn(work(rx, out.clone()));
+            tx
+        }).collect();
+        Self { senders }
+    }
+
+    // What the connection of `symbol` sends its events to
+    pub fn sender(&self, symbol: &str) -> &QueueSender<FeedEvent> {
+        &self.senders[shard(symbol, self.senders.len())]
+    }
+
+    pub fn workers(&self) -> usize {
+        self.senders.len()
+    }
+}
+
+// One per core, the runtime's worker threads, when it cannot be told
+pub fn default_workers() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+// FNV-1a, stable across runs unlike the std hasher's random keys
+fn shard(symbol: &str, workers: usize) -> usize {
+    let hash = symbol.bytes().fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+    (hash % workers as u64) as usize
+}
+
+async fn work(mut rx: QueueReceiver<FeedEvent>, out: QueueSender<FeedEvent>) {
+    while let Some(event) = rx.recv().await {
+        let event = match event {
+            FeedEvent::Frame(frame) => FeedEvent::Parsed(parse(frame)),
+            event => event,
+        };
+        if out.send(event).await.is_err() {
+            return;
+        }
+    }
+}
+
+pub fn parse(frame: Frame) -> ParsedFrame {
+    let parse_start = Instant::now();
+    let mut message = MarketMessage::from_slice(&frame.data).map_err(|e| e.to_string());
+    if let Ok(message) = &mut message {
+        message.received_at_ns = Some(frame.received_ns);
+    }
+    metrics::global().parse_latency.with_label_values(&[&frame.symbol]).observe(parse_start.elapsed().as_secs_f64());
+    ParsedFrame { frame, message }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use futures_util::stream::{self, Stream};
+use futures_util::StreamExt;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::book::OrderBook;
+use crate::client::{Client, ConnectOptions};
+use crate::error::GeminiError;
+use crate::models::{Event, MarketMessage, MarketSide, Quote};
+
+// Messages a subscriber may fall behind before it starts missing some
+const SUBSCRIBER_BACKLOG: usize = 1024;
+
+// Errors are not `Clone`, subscribers get them as text
+type Item = Result<MarketMessage, String>;
+
+// What a late subscriber needs to start from: the book so far and the message it stands at
+#[derive(Default)]
+struct Upstream {
+    book: OrderBook,
+    last: Option<MarketMessage>,
+}
+
+impl Upstream {
+    fn apply(&mut self, message: &MarketMessage, top_of_book: bool) {
+        if is_initial(message) {
+            self.book.clear();
+        }
+        for event in &message.events {
+            match event {
+                Event::Quote(q) if top_of_book => self.book.replace_top(q),
+                Event::Quote(q) => self.book.apply(q),
+                _ => {},
+            }
+        }
+        self.last = Some(MarketMessage { events: Vec::new(), ..message.clone() });
+    }
+
+    // The book as the initial message of a fresh connection, none before the real one
+    fn snapshot(&self) -> Option<MarketMessage> {
+        let last = self.last.as_ref()?;
+        let level = |side: MarketSide, (price, size): (&_, &_)| Event::Quote(Quote {
+            price: *price,
+            reason: String::from("initial"),
+            remaining: *size,
+            side,
+            delta: Some(*size),
+        });
+        let events = self.book.bids().map(|l| level(MarketSide::Bid, l))
+            .chain(self.book.asks().map(|l| level(MarketSide::Ask, l)))
+            .collect();
+        Some(MarketMessage { events, socket_sequence: 0, ..last.clone() })
+    }
+}
+
+struct Connection {
+    tx: broadcast::Sender<Item>,
+    upstream: Arc<Mutex<Upstream>>,
+    subscribers: usize,
+    shutdown: CancellationToken,
+}
+
+// One upstream connection per symbol however many parts of an application subscribe to
+// it. The connection opens with the first subscription and closes when the last one is
+// dropped. Clones share the connections.
+#[derive(Clone)]
+pub struct SharedFeeds {
+    options: ConnectOptions,
+    connections: Arc<Mutex<HashMap<String, Connection>>>,
+}
+
+impl SharedFeeds {
+    pub fn new(options: ConnectOptions) -> Self {
+        Self {
+            options,
+            connections: Arc::default(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Connection>> {
+        self.connections.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Must be called within a tokio runtime, the first subscription to a symbol spawns
+    // its connection
+    pub fn subscribe(&self, symbol: &str) -> Subscription {
+        let symbol = symbol.to_lowercase();
+        let mut connections = self.lock();
+        let connection = connections.entry(symbol.clone()).or_insert_with(|| self.connect(&symbol));
+        connection.subscribers += 1;
+        // Holding the upstream lock, no message can slip between the snapshot and the receiver
+        let upstream = connection.upstream.lock().unwrap_or_else(|e| e.into_inner());
+        let rx = connection.tx.subscribe();
+        let snapshot = upstream.snapshot();
+        drop(upstream);
+        debug!(symbol, subscribers = connection.subscribers, "subscribed to shared feed");
+        Subscription {
+            symbol,
+            feeds: self.clone(),
+            upstream: connection.upstream.clone(),
+            rx,
+            snapshot,
+            sequence: None,
+        }
+    }
+
+    // Subscriptions currently sharing the connection of `symbol`
+    pub fn subscribers(&self, symbol: &str) -> usize {
+        self.lock().get(&symbol.to_lowercase()).map_or(0, |c| c.subscribers)
+    }
+
+    // Upstream connections currently open or reconnecting
+    pub fn connections(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn connect(&self, symbol: &str) -> Connection {
+        let (tx, _) = broadcast::channel(SUBSCRIBER_BACKLOG);
+        let upstream = Arc::new(Mutex::new(Upstream::default()));
+        let shutdown = CancellationToken::new();
+        let top_of_book = self.options.feed.top_of_book;
+        let messages = Client::new(symbol).options(self.options.clone()).stream();
+        let task = {
+            let (tx, upstream, shutdown) = (tx.clone(), upstream.clone(), shutdown.clone());
+            let (feeds, symbol) = (self.clone(), symbol.to_string());
+            async move {
+                let mut messages = std::pin::pin!(messages);
+                loop {
+                    let item = tokio::select! {
+                        _ = shutdown.cancelled() => return,
+                        item = messages.next() => item,
+                    };
+                    let Some(item) = item else {
+                        break;
+                    };
+                    let mut upstream = upstream.lock().unwrap_or_else(|e| e.into_inner());
+                    if let Ok(message) = &item {
+                        upstream.apply(message, top_of_book);
+                    }
+                    // Nobody subscribed right now is not an error
+                    let _ = tx.send(item.map_err(|e| e.to_string()));
+                }
+                // The reconnect policy gave up, the next subscription starts over
+                feeds.remove(&symbol, &upstream);
+            }
+        };
+        tokio::spawn(task);
+        Connection {
+            tx,
+            upstream,
+            subscribers: 0,
+            shutdown,
+        }
+    }
+
+    fn remove(&self, symbol: &str, upstream: &Arc<Mutex<Upstream>>) {
+        let mut connections = self.lock();
+        if connections.get(symbol).is_some_and(|c| Arc::ptr_eq(&c.upstream, upstream)) {
+            connections.remove(symbol);
+        }
+    }
+
+    fn unsubscribe(&self, symbol: &str, upstream: &Arc<Mutex<Upstream>>) {
+        let mut connections = self.lock();
+        // A connection that ended since is gone or replaced, nothing to release
+        let Some(connection) = connections.get_mut(symbol).filter(|c| Arc::ptr_eq(&c.upstream, upstream)) else {
+            return;
+        };
+        connection.subscribers -= 1;
+        if connection.subscribers == 0 {
+            connection.shutdown.cancel();
+            connections.remove(symbol);
+            debug!(symbol, "closed shared feed");
+        }
+    }
+}
+
+// One consumer's view of a shared connection. It starts with the book as an initial
+// message when joining a connection already running, and carries its own socket
+// sequence, so it reads like a connection of its own.
+pub struct Subscription {
+    symbol: String,
+    feeds: SharedFeeds,
+    upstream: Arc<Mutex<Upstream>>,
+    rx: broadcast::Receiver<Item>,
+    snapshot: Option<MarketMessage>,
+    sequence: Option<u32>,
+}
+
+impl Subscription {
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    // None once the connection has ended for good
+    pub async fn recv(&mut self) -> Option<Result<MarketMessage, GeminiError>> {
+        let mut message = match self.snapshot.take() {
+            Some(snapshot) => snapshot,
+            None => match self.rx.recv().await {
+                Ok(Ok(message)) => message,
+                Ok(Err(e)) => return Some(Err(GeminiError::Protocol(e))),
+                Err(RecvError::Lagged(n)) => {
+                    // What follows no longer builds on the book, wait for the next connection's
+                    self.sequence = None;
+                    return Some(Err(GeminiError::Protocol(format!("{} subscriber fell behind by {} messages", self.symbol, n))));
+                },
+                Err(RecvError::Closed) => return None,
+            },
+        };
+        let sequence = match (is_initial(&message), self.sequence) {
+            (false, Some(previous)) => previous.wrapping_add(1),
+            _ => 0,
+        };
+        self.sequence = Some(sequence);
+        message.socket_sequence = sequence;
+        Some(Ok(message))
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = Result<MarketMessage, GeminiError>> + Send + 'static {
+        stream::unfold(self, |mut subscription| async move {
+            let item = subscription.recv().await?;
+            Some((item, subscription))
+        })
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.feeds.unsubscribe(&self.symbol, &self.upstream);
+    }
+}
+
+// The first message of a connection carries the whole book
+fn is_initial(message: &MarketMessage) -> bool {
+    !message.events.is_empty() && message.events.iter()
+        .all(|e| matches!(e, Event::Quote(q) if q.reason == "initial"))
+}
@@ -0,0 +1,476 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Write as _};
+use std::fs::File;
+use std::io::{self, BufWriter, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use rust_decimal::Decimal;
+use tracing::{info, warn};
+
+use crate::client::ReconnectPolicy;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{BestBidOffer, MarketSide, Quote, Trade};
+use crate::output::EventContext;
+
+const SOH: char = '\x01';
+// How often the session thread looks at the socket, and so the heartbeat resolution
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// How long to wait for the counterparty to answer our Logout
+const LOGOUT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct FixOptions {
+    pub sender_comp_id: String,
+    pub target_comp_id: String,
+    // HeartBtInt of the session, idle sessions send a Heartbeat this often
+    pub heartbeat: Duration,
+}
+
+// MDUpdateAction (279)
+#[derive(Debug, Clone, Copy)]
+enum UpdateAction {
+    New = 0,
+    Change = 1,
+    Delete = 2,
+}
+
+// Tag=value pairs of a message body, each terminated by SOH
+#[derive(Default)]
+struct Fields(String);
+
+impl Fields {
+    fn add(&mut self, tag: u32, value: impl Display) -> &mut Self {
+        let _ = write!(self.0, "{}={}{}", tag, value, SOH);
+        self
+    }
+
+    // MDEntryDate (272) and MDEntryTime (273)
+    fn entry_time(&mut self, ms: u64) -> &mut Self {
+        let (date, time) = utc(ms);
+        self.add(272, date).add(273, time)
+    }
+}
+
+// "YYYYMMDD" and "HH:MM:SS.sss" in UTC
+fn utc(ms: u64) -> (String, String) {
+    let rfc3339 = humantime::format_rfc3339_millis(UNIX_EPOCH + Duration::from_millis(ms)).to_string();
+    (rfc3339[..10].replace('-', ""), rfc3339[11..23].to_string())
+}
+
+fn now_ms() -> u64 {
+    UNIX_EPOCH.elapsed().unwrap_or_default().as_millis() as u64
+}
+
+// Frames bodies with the standard header and trailer, numbering them as it goes
+struct Encoder {
+    sender: String,
+    target: String,
+    seq: u64,
+}
+
+impl Encoder {
+    fn new(options: &FixOptions) -> Self {
+        Self {
+            sender: options.sender_comp_id.clone(),
+            target: options.target_comp_id.clone(),
+            seq: 0,
+        }
+    }
+
+    fn encode(&mut self, msg_type: &str, fields: &Fields) -> Vec<u8> {
+        self.seq += 1;
+        let (date, time) = utc(now_ms());
+        let mut body = Fields::default();
+        body.add(35, msg_type).add(49, &self.sender).add(56, &self.target).add(34, self.seq).add(52, format!("{}-{}", date, time));
+        body.0.push_str(&fields.0);
+        let mut message = format!("8=FIX.4.4{}9={}{}{}", SOH, body.0.len(), SOH, body.0);
+        let checksum = message.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        let _ = write!(message, "10={:03}{}", checksum, SOH);
+        message.into_bytes()
+    }
+}
+
+// One side of the session, shared between the sink and the thread reading the socket
+struct Session {
+    stream: TcpStream,
+    encoder: Encoder,
+    heartbeat: Duration,
+    last_sent: Instant,
+    alive: bool,
+    logout_sent: Option<Instant>,
+}
+
+impl Session {
+    fn send(&mut self, msg_type: &str, fields: &Fields) -> io::Result<()> {
+        let message = self.encoder.encode(msg_type, fields);
+        self.stream.write_all(&message)?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+}
+
+fn lock(session: &Mutex<Session>) -> MutexGuard<'_, Session> {
+    session.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Splits the next complete message off the front of `buffer`
+fn next_message(buffer: &mut Vec<u8>) -> Option<Vec<(u32, String)>> {
+    let trailer = buffer.windows(4).position(|w| w == b"\x0110=")?;
+    let end = trailer + 1 + buffer[trailer + 1..].iter().position(|&b| b == b'\x01')?;
+    let message: Vec<u8> = buffer.drain(..=end).collect();
+    Some(message.split(|&b| b == b'\x01')
+        .filter_map(|field| {
+            let field = String::from_utf8_lossy(field);
+            let (tag, value) = field.split_once('=')?;
+            Some((tag.parse().ok()?, value.to_string()))
+        })
+        .collect())
+}
+
+fn field(message: &[(u32, String)], tag: u32) -> &str {
+    message.iter().find(|(t, _)| *t == tag).map(|(_, v)| v.as_str()).unwrap_or_default()
+}
+
+// Answers the counterparty's administrative messages and keeps the session alive with
+// heartbeats, until either side logs out or the connection drops
+fn run_session(mut stream: TcpStream, session: Arc<Mutex<Session>>) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    'session: loop {
+        match stream.read(&mut chunk) {
+            Ok(0) => {
+                if lock(&session).logout_sent.is_none() {
+                    warn!("fix: counterparty closed the connection");
+                }
+                break;
+            },
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {},
+            Err(e) => {
+                warn!(error = %e, "fix: read failed");
+                break;
+            },
+        }
+        let mut session = lock(&session);
+        while let Some(message) = next_message(&mut buffer) {
+            let sent = match field(&message, 35) {
+                "A" => {
+                    info!("fix: logged on");
+                    Ok(())
+                },
+                // TestRequest
+                "1" => session.send("0", Fields::default().add(112, field(&message, 112))),
+                // Nothing is kept for resending, a SequenceReset moves the counterparty past the gap
+                "2" => {
+                    let next = session.encoder.seq + 2;
+                    session.send("4", Fields::default().add(36, next))
+                },
+                "3" => {
+                    warn!(seq = field(&message, 45), reason = field(&message, 58), "fix: message rejected");
+                    Ok(())
+                },
+                "5" => {
+                    if session.logout_sent.is_none() {
+                        warn!(reason = field(&message, 58), "fix: logged out by counterparty");
+                        let _ = session.send("5", &Fields::default());
+                    }
+                    break 'session;
+                },
+                _ => Ok(()),
+            };
+            if let Err(e) = sent {
+                warn!(error = %e, "fix: send failed");
+                break 'session;
+            }
+        }
+        match session.logout_sent {
+            Some(sent) if sent.elapsed() >= LOGOUT_TIMEOUT => break,
+            Some(_) => {},
+            None if session.last_sent.elapsed() >= session.heartbeat => {
+                if let Err(e) = session.send("0", &Fields::default()) {
+                    warn!(error = %e, "fix: heartbeat failed");
+                    break;
+                }
+            },
+            None => {},
+        }
+    }
+    let mut session = lock(&session);
+    session.alive = false;
+    let _ = session.stream.shutdown(Shutdown::Both);
+}
+
+// Initiator side of a FIX session over TCP. Every Logon resets the sequence numbers, so
+// a reconnect starts a fresh session followed by snapshots of every book.
+struct Connection {
+    addr: String,
+    options: FixOptions,
+    session: Option<(Arc<Mutex<Session>>, JoinHandle<()>)>,
+    attempt: u32,
+    retry_at: Instant,
+}
+
+impl Connection {
+    fn connect(&mut self) -> io::Result<()> {
+        let stream = TcpStream::connect(&self.addr)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let reader = stream.try_clone()?;
+        let mut session = Session {
+            stream,
+            encoder: Encoder::new(&self.options),
+            heartbeat: self.options.heartbeat,
+            last_sent: Instant::now(),
+            alive: true,
+            logout_sent: None,
+        };
+        // EncryptMethod none, HeartBtInt and ResetSeqNumFlag
+        session.send("A", Fields::default().add(98, 0).add(108, self.options.heartbeat.as_secs().max(1)).add(141, "Y"))?;
+        let session = Arc::new(Mutex::new(session));
+        let thread = {
+            let session = session.clone();
+            std::thread::spawn(move || run_session(reader, session))
+        };
+        self.session = Some((session, thread));
+        Ok(())
+    }
+
+    // True when a new session was just logged on
+    fn reconnect(&mut self) -> bool {
+        if self.session.is_some() || Instant::now() < self.retry_at {
+            return false;
+        }
+        match self.connect() {
+            Ok(()) => {
+                info!(addr = %self.addr, "fix: reconnected");
+                self.attempt = 0;
+                true
+            },
+            Err(e) => {
+                if self.attempt == 0 {
+                    warn!(addr = %self.addr, error = %e, "fix: cannot connect, retrying");
+                }
+                self.attempt += 1;
+                self.retry_at = Instant::now() + ReconnectPolicy::default().delay(self.attempt);
+                false
+            },
+        }
+    }
+
+    // Messages are dropped while the session is down
+    fn send(&mut self, msg_type: &str, fields: &Fields) {
+        let Some((session, _)) = &self.session else {
+            return;
+        };
+        let mut session = lock(session);
+        let sent = match session.alive {
+            true => session.send(msg_type, fields),
+            false => Err(io::Error::from(ErrorKind::NotConnected)),
+        };
+        if let Err(e) = sent {
+            drop(session);
+            warn!(error = %e, "fix: session lost, reconnecting");
+            self.disconnect();
+        }
+    }
+
+    fn disconnect(&mut self) {
+        if let Some((session, thread)) = self.session.take() {
+            let _ = lock(&session).stream.shutdown(Shutdown::Both);
+            let _ = thread.join();
+        }
+    }
+
+    fn logout(&mut self) {
+        let Some((session, thread)) = self.session.take() else {
+            return;
+        };
+        {
+            let mut session = lock(&session);
+            if session.alive && session.send("5", &Fields::default()).is_ok() {
+                session.logout_sent = Some(Instant::now());
+            } else {
+                let _ = session.stream.shutdown(Shutdown::Both);
+            }
+        }
+        // The session thread ends on the counterparty's Logout or after LOGOUT_TIMEOUT
+        let _ = thread.join();
+    }
+}
+
+enum Output {
+    // One message per line
+    File { writer: BufWriter<File>, encoder: Encoder },
+    Tcp(Connection),
+}
+
+#[derive(Default)]
+struct Book {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+    // Collecting the `initial` quotes after a (re)connect, published as one snapshot
+    loading: bool,
+}
+
+impl Book {
+    // MarketDataSnapshotFullRefresh (35=W) body, bids best first, then offers best first
+    fn snapshot(&self, symbol: &str) -> Fields {
+        let mut fields = Fields::default();
+        fields.add(55, symbol).add(268, self.bids.len() + self.asks.len());
+        for (price, size) in self.bids.iter().rev() {
+            fields.add(269, 0).add(270, price).add(271, size);
+        }
+        for (price, size) in &self.asks {
+            fields.add(269, 1).add(270, price).add(271, size);
+        }
+        fields
+    }
+}
+
+// Translates trades and book changes into FIX 4.4 market data for legacy consumers:
+// a MarketDataSnapshotFullRefresh (35=W) of the whole book after every (re)connect to
+// Gemini, and a MarketDataIncrementalRefresh (35=X) per trade and changed price level.
+// Messages go to a TCP session as initiator, or to a file one per line.
+pub struct FixSink {
+    output: Output,
+    books: HashMap<String, Book>,
+}
+
+impl FixSink {
+    // `destination` is tcp://HOST:PORT or a file path
+    pub fn open(destination: &str, options: FixOptions) -> Result<Self, GeminiError> {
+        let output = match destination.strip_prefix("tcp://") {
+            Some(addr) => {
+                let mut connection = Connection {
+                    addr: addr.trim_end_matches('/').to_string(),
+                    options,
+                    session: None,
+                    attempt: 0,
+                    retry_at: Instant::now(),
+                };
+                connection.connect().map_err(|e| GeminiError::Sink(format!("fix: cannot connect to {}: {}", connection.addr, e)))?;
+                Output::Tcp(connection)
+            },
+            None => Output::File {
+                writer: BufWriter::new(File::create(Path::new(destination))?),
+                encoder: Encoder::new(&options),
+            },
+        };
+        Ok(Self {
+            output,
+            books: HashMap::new(),
+        })
+    }
+
+    fn send(&mut self, msg_type: &str, fields: &Fields) -> Result<(), GeminiError> {
+        match &mut self.output {
+            Output::File { writer, encoder } => {
+                writer.write_all(&encoder.encode(msg_type, fields))?;
+                writer.write_all(b"\n")?;
+            },
+            Output::Tcp(connection) => {
+                if connection.reconnect() {
+                    for (symbol, book) in self.books.iter().filter(|(_, book)| !book.loading) {
+                        connection.send("W", &book.snapshot(symbol));
+                    }
+                }
+                connection.send(msg_type, fields);
+            },
+        }
+        Ok(())
+    }
+
+    // Publishes the snapshot once the `initial` quotes of `symbol` are all in
+    fn finish_snapshot(&mut self, symbol: &str) -> Result<(), GeminiError> {
+        let Some(book) = self.books.get_mut(symbol).filter(|book| book.loading) else {
+            return Ok(());
+        };
+        book.loading = false;
+        let snapshot = book.snapshot(symbol);
+        self.send("W", &snapshot)
+    }
+}
+
+impl EventHandler for FixSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.finish_snapshot(symbol)?;
+        let mut fields = Fields::default();
+        fields.add(268, 1)
+            .add(279, UpdateAction::New as u8).add(269, 2).add(55, symbol)
+            .add(270, trade.price).add(271, trade.amount)
+            .entry_time(ctx.timestampms.unwrap_or(ctx.received_ms));
+        self.send("X", &fields)
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        let initial = quote.reason == "initial";
+        if !initial {
+            self.finish_snapshot(symbol)?;
+        }
+        let book = self.books.entry(symbol.to_string()).or_default();
+        if initial && !book.loading {
+            *book = Book { loading: true, ..Book::default() };
+        }
+        let (levels, entry_type) = match quote.side {
+            MarketSide::Bid => (&mut book.bids, 0),
+            MarketSide::Ask => (&mut book.asks, 1),
+            MarketSide::Unknown => return Ok(()),
+        };
+        let action = match quote.remaining.is_zero() {
+            true => {
+                levels.remove(&quote.price);
+                UpdateAction::Delete
+            },
+            false => match levels.insert(quote.price, quote.remaining) {
+                Some(_) => UpdateAction::Change,
+                None => UpdateAction::New,
+            },
+        };
+        if initial {
+            return Ok(());
+        }
+        let mut fields = Fields::default();
+        fields.add(268, 1).add(279, action as u8).add(269, entry_type).add(55, symbol).add(270, quote.price);
+        if !matches!(action, UpdateAction::Delete) {
+            fields.add(271, quote.remaining);
+        }
+        fields.entry_time(ctx.timestampms.unwrap_or(ctx.received_ms));
+        self.send("X", &fields)
+    }
+
+    fn on_book_update(&mut self, symbol: &str, _: &EventContext, _: &BestBidOffer) -> Result<(), GeminiError> {
+        self.finish_snapshot(symbol)
+    }
+
+    // An empty snapshot, so consumers do not keep quoting from a book that went stale
+    fn on_disconnect(&mut self, symbol: &str, _reason: &str) -> Result<(), GeminiError> {
+        let Some(book) = self.books.get_mut(symbol) else {
+            return Ok(());
+        };
+        *book = Book::default();
+        let snapshot = book.snapshot(symbol);
+        self.send("W", &snapshot)
+    }
+
+    // Only called once the handler's queue is closed
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        let loading: Vec<String> = self.books.iter().filter(|(_, book)| book.loading).map(|(symbol, _)| symbol.clone()).collect();
+        for symbol in loading {
+            self.finish_snapshot(&symbol)?;
+        }
+        match &mut self.output {
+            Output::File { writer, .. } => writer.flush()?,
+            Output::Tcp(connection) => connection.logout(),
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "fix"
+    }
+}
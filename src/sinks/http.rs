@@ -0,0 +1,154 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::runtime::Handle;
+use tracing::warn;
+
+use crate::client::ReconnectPolicy;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{AuctionEvent, BestBidOffer, BlockTrade, Quote, Trade};
+use crate::output::{EventContext, Record};
+
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    // Events per request, a batch also goes out once `batch_interval` passed
+    pub batch_size: usize,
+    pub batch_interval: Duration,
+    // Signs every request, see `HttpSink`
+    pub secret: Option<String>,
+    // Attempts after the first for a batch that failed to deliver
+    pub retries: u32,
+}
+
+// POSTs normalized events in batches as a JSON array. With a secret each request
+// carries `X-Timestamp` in milliseconds and `X-Signature: sha256=<hex>`, the
+// HMAC-SHA256 of `<timestamp>.<body>`, so the receiver can check who sent it.
+// Connection errors, 429 and 5xx responses are retried with exponential backoff,
+// which holds up the sink's queue while it waits.
+pub struct HttpSink {
+    http: reqwest::Client,
+    runtime: Handle,
+    url: String,
+    options: HttpOptions,
+    backoff: ReconnectPolicy,
+    batch: Vec<String>,
+    last_flush: Instant,
+}
+
+impl HttpSink {
+    // Must be created inside the runtime, the requests are driven from the handler thread
+    pub fn new(url: &str, options: HttpOptions) -> Result<Self, GeminiError> {
+        let url = reqwest::Url::parse(url).map_err(|e| GeminiError::Sink(format!("http: bad URL `{}`: {}", url, e)))?;
+        Ok(Self {
+            http: reqwest::Client::new(),
+            runtime: Handle::current(),
+            url: url.to_string(),
+            backoff: ReconnectPolicy {
+                enabled: true,
+                initial_delay: Duration::from_millis(500),
+                max_delay: Duration::from_secs(30),
+                max_attempts: Some(options.retries),
+            },
+            batch: Vec::with_capacity(options.batch_size),
+            options,
+            last_flush: Instant::now(),
+        })
+    }
+
+    fn push<T: Serialize>(&mut self, kind: &'static str, symbol: &str, ctx: &EventContext, data: &T) -> Result<(), GeminiError> {
+        self.batch.push(Record::new(kind, symbol, ctx, data).to_json());
+        if self.batch.len() >= self.options.batch_size || self.last_flush.elapsed() >= self.options.batch_interval {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn request(&self, body: &str) -> Result<reqwest::RequestBuilder, GeminiError> {
+        let mut request = self.http.post(&self.url)
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+        if let Some(secret) = &self.options.secret {
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis().to_string();
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+                .map_err(|e| GeminiError::Sink(format!("http: secret: {}", e)))?;
+            mac.update(timestamp.as_bytes());
+            mac.update(b".");
+            mac.update(body.as_bytes());
+            request = request
+                .header("X-Timestamp", timestamp)
+                .header("X-Signature", format!("sha256={}", hex::encode(mac.finalize().into_bytes())));
+        }
+        Ok(request)
+    }
+}
+
+impl EventHandler for HttpSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.push("trade", symbol, ctx, trade)
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        self.push("quote", symbol, ctx, quote)
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.push("bbo", symbol, ctx, bbo)
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.push("block_trade", symbol, ctx, trade)
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.push("auction", symbol, ctx, auction)
+    }
+
+    // A batch that still fails after the retries is dropped, a refused request
+    // (401, 403 or 404) stops the sink
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        self.last_flush = Instant::now();
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let events = self.batch.len();
+        let body = format!("[{}]", self.batch.join(","));
+        self.batch.clear();
+        let mut attempt = 0;
+        loop {
+            // Signed again on every attempt, so the timestamp stays fresh
+            let request = self.request(&body)?;
+            let response = self.runtime.block_on(async {
+                let response = request.send().await?;
+                let status = response.status();
+                Ok::<_, reqwest::Error>((status, response.text().await.unwrap_or_default()))
+            });
+            let error = match response {
+                Ok((status, _)) if status.is_success() => return Ok(()),
+                Ok((status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND), body)) =>
+                    return Err(GeminiError::Sink(format!("http: {}: {}", status, body.trim()))),
+                Ok((status, body)) if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() => {
+                    warn!(%status, body = body.trim(), dropped = events, "http: batch rejected");
+                    return Ok(());
+                },
+                Ok((status, body)) => format!("{}: {}", status, body.trim()),
+                Err(e) => e.to_string(),
+            };
+            if attempt >= self.options.retries {
+                warn!(error, attempts = attempt + 1, dropped = events, "http: giving up on batch");
+                return Ok(());
+            }
+            let delay = self.backoff.delay(attempt);
+            warn!(error, delay = ?delay, attempt = attempt + 1, "http: retrying batch");
+            std::thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "http"
+    }
+}
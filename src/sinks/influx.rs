@@ -0,0 +1,260 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use tokio::runtime::Handle;
+use tracing::warn;
+
+use crate::analytics::indicators::BookIndicators;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{BestBidOffer, Trade};
+use crate::output::EventContext;
+
+const BATCH_SIZE: usize = 5000;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeasurementKind {
+    Trades,
+    Bbo,
+    Indicators,
+}
+
+impl FromStr for MeasurementKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trades" => Ok(MeasurementKind::Trades),
+            "bbo" => Ok(MeasurementKind::Bbo),
+            "indicators" => Ok(MeasurementKind::Indicators),
+            other => Err(format!("unknown measurement `{}`, expected trades, bbo or indicators", other)),
+        }
+    }
+}
+
+// `KIND=NAME`, renames the measurement one kind of event is written to
+#[derive(Debug, Clone)]
+pub struct MeasurementName {
+    pub kind: MeasurementKind,
+    pub name: String,
+}
+
+impl FromStr for MeasurementName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, name) = s.split_once('=').ok_or_else(|| format!("expected KIND=NAME, got `{}`", s))?;
+        if name.is_empty() {
+            return Err(format!("empty measurement name in `{}`", s));
+        }
+        Ok(Self {
+            kind: kind.trim().parse()?,
+            name: name.trim().to_string(),
+        })
+    }
+}
+
+// `KEY=VALUE`, a tag added to every point next to `symbol` and `side`
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for Tag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((key, value)) if !key.is_empty() && !value.is_empty() => Ok(Self {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(format!("expected KEY=VALUE, got `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", escape(&self.key, ",= "), escape(&self.value, ",= "))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InfluxOptions {
+    // Sent as `Authorization: Token <token>`
+    pub token: Option<String>,
+    pub tags: Vec<Tag>,
+    pub measurements: Vec<MeasurementName>,
+}
+
+struct Measurements {
+    trades: String,
+    bbo: String,
+    indicators: String,
+}
+
+impl Measurements {
+    fn new(names: &[MeasurementName]) -> Self {
+        let mut measurements = Self {
+            trades: String::from("trades"),
+            bbo: String::from("bbo"),
+            indicators: String::from("indicators"),
+        };
+        for name in names {
+            let slot = match name.kind {
+                MeasurementKind::Trades => &mut measurements.trades,
+                MeasurementKind::Bbo => &mut measurements.bbo,
+                MeasurementKind::Indicators => &mut measurements.indicators,
+            };
+            *slot = escape(&name.name, ", ");
+        }
+        measurements
+    }
+}
+
+// Posts trades, BBO updates and book indicators as line protocol to an InfluxDB write
+// endpoint, e.g. http://localhost:8086/api/v2/write?org=ORG&bucket=BUCKET
+pub struct InfluxSink {
+    http: reqwest::Client,
+    runtime: Handle,
+    url: String,
+    token: Option<String>,
+    tags: String,
+    measurements: Measurements,
+    lines: String,
+    points: usize,
+    last_flush: Instant,
+    last_ns: u64,
+}
+
+impl InfluxSink {
+    // Must be created inside the runtime, the writes are driven from the handler thread
+    pub fn new(url: &str, options: InfluxOptions) -> Result<Self, GeminiError> {
+        let url = reqwest::Url::parse(url).map_err(|e| GeminiError::Sink(format!("influx: bad URL `{}`: {}", url, e)))?;
+        Ok(Self {
+            http: reqwest::Client::new(),
+            runtime: Handle::current(),
+            url: url.to_string(),
+            token: options.token,
+            tags: options.tags.iter().map(|tag| format!(",{}", tag)).collect(),
+            measurements: Measurements::new(&options.measurements),
+            lines: String::new(),
+            points: 0,
+            last_flush: Instant::now(),
+            last_ns: 0,
+        })
+    }
+
+    // Influx keeps one point per series and timestamp, so events sharing a millisecond
+    // are spread over its nanoseconds instead of overwriting each other
+    fn timestamp(&mut self, ctx: &EventContext) -> u64 {
+        let ns = ctx.timestampms.unwrap_or(ctx.received_ms) * 1_000_000;
+        self.last_ns = match ns / 1_000_000 == self.last_ns / 1_000_000 {
+            true => ns.max(self.last_ns + 1),
+            false => ns,
+        };
+        self.last_ns
+    }
+
+    fn point(&mut self, kind: MeasurementKind, symbol: &str, side: Option<&str>, fields: &str, ctx: &EventContext) -> Result<(), GeminiError> {
+        let timestamp = self.timestamp(ctx);
+        self.lines.push_str(match kind {
+            MeasurementKind::Trades => &self.measurements.trades,
+            MeasurementKind::Bbo => &self.measurements.bbo,
+            MeasurementKind::Indicators => &self.measurements.indicators,
+        });
+        self.lines.push_str(",symbol=");
+        self.lines.push_str(&escape(symbol, ",= "));
+        if let Some(side) = side {
+            self.lines.push_str(",side=");
+            self.lines.push_str(side);
+        }
+        self.lines.push_str(&self.tags);
+        self.lines.push_str(&format!(" {} {}\n", fields, timestamp));
+        self.points += 1;
+        if self.points >= BATCH_SIZE || self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl EventHandler for InfluxSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, t: &Trade) -> Result<(), GeminiError> {
+        let fields = format!("price={},amount={}", t.price, t.amount);
+        self.point(MeasurementKind::Trades, symbol, Some(t.maker_side.as_str()), &fields, ctx)
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        let mut fields = format!(
+            "bid={},bid_size={},ask={},ask_size={}",
+            bbo.best_bid, bbo.bid_amount_remaining, bbo.best_offer, bbo.ask_amount_remaining,
+        );
+        if !bbo.best_bid.is_zero() && !bbo.best_offer.is_zero() {
+            fields.push_str(&format!(",mid={}", (bbo.best_bid + bbo.best_offer) / Decimal::TWO));
+        }
+        self.point(MeasurementKind::Bbo, symbol, None, &fields, ctx)
+    }
+
+    fn on_indicators(&mut self, symbol: &str, ctx: &EventContext, indicators: &BookIndicators) -> Result<(), GeminiError> {
+        let mut fields = format!("imbalance={},microprice={}", indicators.imbalance, indicators.microprice);
+        if let Some(band) = indicators.band {
+            fields.push_str(&format!(",band={}i", band));
+        }
+        self.point(MeasurementKind::Indicators, symbol, None, &fields, ctx)
+    }
+
+    // A database that is down or rejects some points costs this batch only, a missing
+    // bucket or a refused token stops the sink
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        self.last_flush = Instant::now();
+        if self.points == 0 {
+            return Ok(());
+        }
+        let mut request = self.http.post(&self.url).body(std::mem::take(&mut self.lines));
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Token {}", token));
+        }
+        let points = std::mem::take(&mut self.points);
+        let response = self.runtime.block_on(async {
+            let response = request.send().await?;
+            let status = response.status();
+            Ok::<_, reqwest::Error>((status, response.text().await.unwrap_or_default()))
+        });
+        match response {
+            Ok((status, _)) if status.is_success() => Ok(()),
+            Ok((status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND), body)) =>
+                Err(GeminiError::Sink(format!("influx: {}: {}", status, body.trim()))),
+            Ok((status, body)) => {
+                warn!(%status, body = body.trim(), dropped = points, "influx: write rejected");
+                Ok(())
+            },
+            Err(e) => {
+                warn!(error = %e, dropped = points, "influx: write failed");
+                Ok(())
+            },
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "influx"
+    }
+}
+
+// Backslash-escapes the characters line protocol gives a meaning in this position
+fn escape(s: &str, special: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if special.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer};
+use rdkafka::ClientContext;
+use tracing::warn;
+
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{AuctionEvent, BestBidOffer, BlockTrade, Quote, Trade};
+use crate::output::{EventContext, Record};
+
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+const QUEUE_FULL_RETRIES: usize = 50;
+
+struct DeliveryReporter;
+
+impl ClientContext for DeliveryReporter {}
+
+impl ProducerContext for DeliveryReporter {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: Self::DeliveryOpaque) {
+        if let Err((e, _)) = result {
+            warn!(error = %e, "kafka: delivery failed");
+        }
+    }
+}
+
+// Publishes each normalized event as JSON keyed by symbol
+pub struct KafkaSink {
+    producer: ThreadedProducer<DeliveryReporter>,
+    topic: String,
+}
+
+impl KafkaSink {
+    pub fn new(brokers: &str, topic: &str) -> Result<Self, GeminiError> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("linger.ms", "5")
+            .create_with_context(DeliveryReporter)
+            .map_err(sink_error)?;
+        Ok(Self {
+            producer,
+            topic: topic.to_string(),
+        })
+    }
+
+    fn publish(&mut self, symbol: &str, payload: String) -> Result<(), GeminiError> {
+        let mut record = BaseRecord::to(&self.topic).key(symbol).payload(&payload);
+        for _ in 0..QUEUE_FULL_RETRIES {
+            match self.producer.send(record) {
+                Ok(()) => return Ok(()),
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), r)) => {
+                    // The background thread drains the queue, give it a moment
+                    record = r;
+                    std::thread::sleep(Duration::from_millis(10));
+                },
+                Err((e, _)) => return Err(sink_error(e)),
+            }
+        }
+        Err(GeminiError::Sink(String::from("kafka: producer queue full")))
+    }
+}
+
+impl EventHandler for KafkaSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.publish(symbol, Record::new("trade", symbol, ctx, trade).to_json())
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        self.publish(symbol, Record::new("quote", symbol, ctx, quote).to_json())
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.publish(symbol, Record::new("bbo", symbol, ctx, bbo).to_json())
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.publish(symbol, Record::new("block_trade", symbol, ctx, trade).to_json())
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.publish(symbol, Record::new("auction", symbol, ctx, auction).to_json())
+    }
+
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        self.producer.flush(FLUSH_TIMEOUT).map_err(sink_error)
+    }
+
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+}
+
+fn sink_error(e: KafkaError) -> GeminiError {
+    GeminiError::Sink(format!("kafka: {}", e))
+}
@@ -0,0 +1,138 @@
+use async_nats::jetstream::{self, context::PublishAckFuture};
+use async_nats::{Client, ConnectOptions, Event};
+use serde::Serialize;
+use tokio::runtime::Handle;
+use tracing::{info, warn};
+
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{AuctionEvent, BestBidOffer, BlockTrade, Quote, Trade};
+use crate::output::{EventContext, Record};
+
+// JetStream acknowledgements awaited together, so publishing does not wait a round trip per event
+const ACK_BATCH: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct NatsOptions {
+    // Subjects are `<prefix>.<symbol>.<kind>`
+    pub subject_prefix: String,
+    // Publish through JetStream into this stream, created over `<prefix>.>` when missing
+    pub stream: Option<String>,
+}
+
+// Publishes each normalized event as JSON to `gemini.marketdata.<symbol>.<kind>`. The
+// client reconnects on its own and buffers what is published in the meantime.
+pub struct NatsSink {
+    runtime: Handle,
+    client: Client,
+    jetstream: Option<jetstream::Context>,
+    pending_acks: Vec<PublishAckFuture>,
+    prefix: String,
+}
+
+impl NatsSink {
+    pub async fn connect(url: &str, options: NatsOptions) -> Result<Self, GeminiError> {
+        let client = ConnectOptions::new()
+            .event_callback(|event| async move {
+                match event {
+                    Event::Connected => info!("nats: connected"),
+                    Event::Disconnected => warn!("nats: disconnected, reconnecting"),
+                    Event::Closed => {},
+                    other => warn!(event = %other, "nats: client event"),
+                }
+            })
+            .connect(url)
+            .await
+            .map_err(sink_error)?;
+        let jetstream = match options.stream {
+            Some(stream) => {
+                let context = jetstream::new(client.clone());
+                context.get_or_create_stream(jetstream::stream::Config {
+                    name: stream,
+                    subjects: vec![format!("{}.>", options.subject_prefix)],
+                    ..Default::default()
+                }).await.map_err(sink_error)?;
+                Some(context)
+            },
+            None => None,
+        };
+        Ok(Self {
+            runtime: Handle::current(),
+            client,
+            jetstream,
+            pending_acks: Vec::new(),
+            prefix: options.subject_prefix,
+        })
+    }
+
+    fn publish<T: Serialize>(&mut self, kind: &'static str, symbol: &str, ctx: &EventContext, data: &T) -> Result<(), GeminiError> {
+        let subject = format!("{}.{}.{}", self.prefix, symbol, kind);
+        let payload = Record::new(kind, symbol, ctx, data).to_json();
+        match &self.jetstream {
+            Some(context) => {
+                let ack = self.runtime.block_on(context.publish(subject, payload.into())).map_err(sink_error)?;
+                self.pending_acks.push(ack);
+                if self.pending_acks.len() >= ACK_BATCH {
+                    self.await_acks();
+                }
+            },
+            None => self.runtime.block_on(self.client.publish(subject, payload.into())).map_err(sink_error)?,
+        }
+        Ok(())
+    }
+
+    // Messages the server did not confirm are reported, the feed keeps going
+    fn await_acks(&mut self) {
+        let acks = std::mem::take(&mut self.pending_acks);
+        let failed = self.runtime.block_on(async {
+            let mut failed = 0;
+            for ack in acks {
+                if let Err(e) = ack.await {
+                    if failed == 0 {
+                        warn!(error = %e, "nats: jetstream publish failed");
+                    }
+                    failed += 1;
+                }
+            }
+            failed
+        });
+        if failed > 1 {
+            warn!(failed, "nats: jetstream publishes failed");
+        }
+    }
+}
+
+impl EventHandler for NatsSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.publish("trade", symbol, ctx, trade)
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        self.publish("quote", symbol, ctx, quote)
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.publish("bbo", symbol, ctx, bbo)
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.publish("block_trade", symbol, ctx, trade)
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.publish("auction", symbol, ctx, auction)
+    }
+
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        self.await_acks();
+        self.runtime.block_on(self.client.flush()).map_err(sink_error)
+    }
+
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+}
+
+fn sink_error(e: impl std::fmt::Display) -> GeminiError {
+    GeminiError::Sink(format!("nats: {}", e))
+}
@@ -0,0 +1,425 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use arrow_array::builder::{Decimal128Builder, StringBuilder, TimestampMillisecondBuilder, UInt64Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use rust_decimal::Decimal;
+
+use crate::analytics::candles::Candle;
+use crate::client::now_ns;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{Quote, Trade};
+use crate::order_events::Fill;
+use crate::output::EventContext;
+
+// Rows buffered before they are written out as a row group
+const ROW_GROUP_SIZE: usize = 50_000;
+// A row group is also closed this often, so a crash loses at most this much
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+// Prices and sizes are stored as DECIMAL(38, 10), enough for every Gemini tick and lot
+const DECIMAL_PRECISION: u8 = 38;
+const DECIMAL_SCALE: i8 = 10;
+
+fn decimal() -> DataType {
+    DataType::Decimal128(DECIMAL_PRECISION, DECIMAL_SCALE)
+}
+
+fn timestamp() -> DataType {
+    DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+}
+
+fn context_fields() -> Vec<Field> {
+    vec![
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("event_id", DataType::UInt64, false),
+        Field::new("timestamp", timestamp(), true),
+        Field::new("received", timestamp(), false),
+        Field::new("received_at_ns", DataType::UInt64, false),
+        Field::new("emitted_at_ns", DataType::UInt64, false),
+    ]
+}
+
+fn trade_schema() -> SchemaRef {
+    let mut fields = context_fields();
+    fields.extend([
+        Field::new("price", decimal(), false),
+        Field::new("amount", decimal(), false),
+        Field::new("maker_side", DataType::Utf8, false),
+    ]);
+    Arc::new(Schema::new(fields))
+}
+
+fn quote_schema() -> SchemaRef {
+    let mut fields = context_fields();
+    fields.extend([
+        Field::new("price", decimal(), false),
+        Field::new("remaining", decimal(), false),
+        Field::new("delta", decimal(), true),
+        Field::new("side", DataType::Utf8, false),
+        Field::new("reason", DataType::Utf8, false),
+    ]);
+    Arc::new(Schema::new(fields))
+}
+
+fn fill_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("time", timestamp(), false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("side", DataType::Utf8, false),
+        Field::new("price", decimal(), false),
+        Field::new("quantity", decimal(), false),
+        Field::new("fee", decimal(), false),
+        Field::new("fee_currency", DataType::Utf8, false),
+        Field::new("order_id", DataType::Utf8, false),
+        Field::new("client_order_id", DataType::Utf8, true),
+        Field::new("trade_id", DataType::Utf8, false),
+        Field::new("liquidity", DataType::Utf8, false),
+    ]))
+}
+
+fn candle_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("start", timestamp(), false),
+        Field::new("interval", DataType::Utf8, false),
+        Field::new("open", decimal(), false),
+        Field::new("high", decimal(), false),
+        Field::new("low", decimal(), false),
+        Field::new("close", decimal(), false),
+        Field::new("volume", decimal(), false),
+    ]))
+}
+
+fn decimal_builder() -> Decimal128Builder {
+    Decimal128Builder::new().with_data_type(decimal())
+}
+
+fn timestamp_builder() -> TimestampMillisecondBuilder {
+    TimestampMillisecondBuilder::new().with_timezone("UTC")
+}
+
+// Rescales to the column scale, rounding anything finer than 10 decimals
+fn mantissa(value: Decimal) -> i128 {
+    let mut value = value.round_dp(DECIMAL_SCALE as u32);
+    value.rescale(DECIMAL_SCALE as u32);
+    value.mantissa()
+}
+
+struct Columns {
+    symbol: StringBuilder,
+    event_id: UInt64Builder,
+    timestamp: TimestampMillisecondBuilder,
+    received: TimestampMillisecondBuilder,
+    received_at_ns: UInt64Builder,
+    emitted_at_ns: UInt64Builder,
+}
+
+impl Columns {
+    fn new() -> Self {
+        Self {
+            symbol: StringBuilder::new(),
+            event_id: UInt64Builder::new(),
+            timestamp: timestamp_builder(),
+            received: timestamp_builder(),
+            received_at_ns: UInt64Builder::new(),
+            emitted_at_ns: UInt64Builder::new(),
+        }
+    }
+
+    fn append(&mut self, symbol: &str, ctx: &EventContext) {
+        self.symbol.append_value(symbol);
+        self.event_id.append_value(ctx.event_id);
+        self.timestamp.append_option(ctx.timestampms.map(|t| t as i64));
+        self.received.append_value(ctx.received_ms as i64);
+        self.received_at_ns.append_value(ctx.received_ns);
+        self.emitted_at_ns.append_value(now_ns());
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(self.symbol.finish()),
+            Arc::new(self.event_id.finish()),
+            Arc::new(self.timestamp.finish()),
+            Arc::new(self.received.finish()),
+            Arc::new(self.received_at_ns.finish()),
+            Arc::new(self.emitted_at_ns.finish()),
+        ]
+    }
+}
+
+struct TradeColumns {
+    context: Columns,
+    price: Decimal128Builder,
+    amount: Decimal128Builder,
+    maker_side: StringBuilder,
+}
+
+impl TradeColumns {
+    fn new() -> Self {
+        Self {
+            context: Columns::new(),
+            price: decimal_builder(),
+            amount: decimal_builder(),
+            maker_side: StringBuilder::new(),
+        }
+    }
+
+    fn append(&mut self, symbol: &str, ctx: &EventContext, t: &Trade) {
+        self.context.append(symbol, ctx);
+        self.price.append_value(mantissa(t.price));
+        self.amount.append_value(mantissa(t.amount));
+        self.maker_side.append_value(t.maker_side.as_str());
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        let mut columns = self.context.finish();
+        columns.extend([
+            Arc::new(self.price.finish()) as ArrayRef,
+            Arc::new(self.amount.finish()),
+            Arc::new(self.maker_side.finish()),
+        ]);
+        columns
+    }
+}
+
+struct QuoteColumns {
+    context: Columns,
+    price: Decimal128Builder,
+    remaining: Decimal128Builder,
+    delta: Decimal128Builder,
+    side: StringBuilder,
+    reason: StringBuilder,
+}
+
+impl QuoteColumns {
+    fn new() -> Self {
+        Self {
+            context: Columns::new(),
+            price: decimal_builder(),
+            remaining: decimal_builder(),
+            delta: decimal_builder(),
+            side: StringBuilder::new(),
+            reason: StringBuilder::new(),
+        }
+    }
+
+    fn append(&mut self, symbol: &str, ctx: &EventContext, q: &Quote) {
+        self.context.append(symbol, ctx);
+        self.price.append_value(mantissa(q.price));
+        self.remaining.append_value(mantissa(q.remaining));
+        self.delta.append_option(q.delta.map(mantissa));
+        self.side.append_value(q.side.as_str());
+        self.reason.append_value(&q.reason);
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        let mut columns = self.context.finish();
+        columns.extend([
+            Arc::new(self.price.finish()) as ArrayRef,
+            Arc::new(self.remaining.finish()),
+            Arc::new(self.delta.finish()),
+            Arc::new(self.side.finish()),
+            Arc::new(self.reason.finish()),
+        ]);
+        columns
+    }
+}
+
+// One Parquet file, written a row group at a time
+struct Table {
+    writer: Option<ArrowWriter<File>>,
+    schema: SchemaRef,
+    rows: usize,
+}
+
+impl Table {
+    fn create(path: PathBuf, schema: SchemaRef) -> Result<Self, GeminiError> {
+        let file = File::create(&path)
+            .map_err(|e| GeminiError::Sink(format!("parquet: cannot create {}: {}", path.display(), e)))?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .set_max_row_group_row_count(Some(ROW_GROUP_SIZE))
+            .build();
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties)).map_err(sink_error)?;
+        Ok(Self {
+            writer: Some(writer),
+            schema,
+            rows: 0,
+        })
+    }
+
+    fn write(&mut self, columns: Vec<ArrayRef>) -> Result<(), GeminiError> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        if self.rows > 0 {
+            let batch = RecordBatch::try_new(self.schema.clone(), columns)
+                .map_err(|e| GeminiError::Sink(format!("parquet: {}", e)))?;
+            writer.write(&batch).map_err(sink_error)?;
+            writer.flush().map_err(sink_error)?;
+            self.rows = 0;
+        }
+        Ok(())
+    }
+
+    // Writes the footer, without it the file cannot be read
+    fn close(&mut self) -> Result<(), GeminiError> {
+        match self.writer.take() {
+            Some(writer) => writer.close().map(|_| ()).map_err(sink_error),
+            None => Ok(()),
+        }
+    }
+}
+
+// Writes trades and quotes to `trades-<start>.parquet` and `quotes-<start>.parquet`
+// in a directory, with typed decimal and timestamp columns
+pub struct ParquetSink {
+    trades: Table,
+    trade_columns: TradeColumns,
+    quotes: Table,
+    quote_columns: QuoteColumns,
+    last_flush: Instant,
+}
+
+impl ParquetSink {
+    pub fn create(dir: &Path) -> Result<Self, GeminiError> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| GeminiError::Sink(format!("parquet: cannot create {}: {}", dir.display(), e)))?;
+        let started: String = humantime::format_rfc3339_seconds(SystemTime::now()).to_string()
+            .chars().filter(|c| !matches!(c, '-' | ':')).collect();
+        Ok(Self {
+            trades: Table::create(dir.join(format!("trades-{}.parquet", started)), trade_schema())?,
+            trade_columns: TradeColumns::new(),
+            quotes: Table::create(dir.join(format!("quotes-{}.parquet", started)), quote_schema())?,
+            quote_columns: QuoteColumns::new(),
+            last_flush: Instant::now(),
+        })
+    }
+
+    fn appended(&mut self) -> Result<(), GeminiError> {
+        if self.trades.rows >= ROW_GROUP_SIZE || self.quotes.rows >= ROW_GROUP_SIZE
+            || self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.write_row_groups()?;
+        }
+        Ok(())
+    }
+
+    fn write_row_groups(&mut self) -> Result<(), GeminiError> {
+        self.last_flush = Instant::now();
+        self.trades.write(self.trade_columns.finish())?;
+        self.quotes.write(self.quote_columns.finish())
+    }
+}
+
+impl EventHandler for ParquetSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, t: &Trade) -> Result<(), GeminiError> {
+        self.trade_columns.append(symbol, ctx, t);
+        self.trades.rows += 1;
+        self.appended()
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, q: &Quote) -> Result<(), GeminiError> {
+        self.quote_columns.append(symbol, ctx, q);
+        self.quotes.rows += 1;
+        self.appended()
+    }
+
+    // Only called once the handler's queue is closed, so the files are finished here
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        self.write_row_groups()?;
+        self.trades.close()?;
+        self.quotes.close()
+    }
+
+    fn name(&self) -> &'static str {
+        "parquet"
+    }
+}
+
+// The account's fills in `fills-<start>.parquet` in a directory. Fills are rare, each
+// one is written out as its own row group right away.
+pub struct ParquetBlotter {
+    table: Table,
+}
+
+impl ParquetBlotter {
+    pub fn create(dir: &Path) -> Result<Self, GeminiError> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| GeminiError::Sink(format!("parquet: cannot create {}: {}", dir.display(), e)))?;
+        let started: String = humantime::format_rfc3339_seconds(SystemTime::now()).to_string()
+            .chars().filter(|c| !matches!(c, '-' | ':')).collect();
+        Ok(Self {
+            table: Table::create(dir.join(format!("fills-{}.parquet", started)), fill_schema())?,
+        })
+    }
+
+    pub fn write(&mut self, fill: &Fill) -> Result<(), GeminiError> {
+        let decimal = |value: Decimal| {
+            let mut column = decimal_builder();
+            column.append_value(mantissa(value));
+            Arc::new(column.finish()) as ArrayRef
+        };
+        let text = |value: Option<&str>| {
+            let mut column = StringBuilder::new();
+            column.append_option(value);
+            Arc::new(column.finish()) as ArrayRef
+        };
+        let mut time = timestamp_builder();
+        time.append_value(fill.timestampms as i64);
+        self.table.rows = 1;
+        self.table.write(vec![
+            Arc::new(time.finish()),
+            text(Some(&fill.symbol)),
+            text(Some(&fill.side.to_string())),
+            decimal(fill.price),
+            decimal(fill.amount),
+            decimal(fill.fee),
+            text(Some(&fill.fee_currency)),
+            text(Some(&fill.order_id)),
+            text(fill.client_order_id.as_deref()),
+            text(Some(&fill.trade_id)),
+            text(Some(&fill.liquidity)),
+        ])
+    }
+
+    pub fn close(&mut self) -> Result<(), GeminiError> {
+        self.table.close()
+    }
+}
+
+// Writes downloaded bars of any symbols to one file at `path`, in row groups of ROW_GROUP_SIZE
+pub fn write_candles(path: &Path, candles: &[(String, Candle)]) -> Result<(), GeminiError> {
+    let mut table = Table::create(path.to_path_buf(), candle_schema())?;
+    for chunk in candles.chunks(ROW_GROUP_SIZE) {
+        let mut symbol = StringBuilder::new();
+        let mut start = timestamp_builder();
+        let mut interval = StringBuilder::new();
+        let mut prices: [Decimal128Builder; 5] = std::array::from_fn(|_| decimal_builder());
+        for (name, candle) in chunk {
+            symbol.append_value(name);
+            start.append_value(candle.start_ms as i64);
+            interval.append_value(&candle.interval);
+            for (column, value) in prices.iter_mut().zip([candle.open, candle.high, candle.low, candle.close, candle.volume]) {
+                column.append_value(mantissa(value));
+            }
+        }
+        let mut columns: Vec<ArrayRef> = vec![Arc::new(symbol.finish()), Arc::new(start.finish()), Arc::new(interval.finish())];
+        columns.extend(prices.iter_mut().map(|column| Arc::new(column.finish()) as ArrayRef));
+        table.rows = chunk.len();
+        table.write(columns)?;
+    }
+    table.close()
+}
+
+fn sink_error(e: ParquetError) -> GeminiError {
+    GeminiError::Sink(format!("parquet: {}", e))
+}
@@ -0,0 +1,292 @@
+use std::error::Error;
+use std::io::Write;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use native_tls::TlsConnector;
+use postgres::{Client, Config};
+use postgres_native_tls::MakeTlsConnector;
+use rust_decimal::Decimal;
+use tracing::warn;
+
+use crate::analytics::candles::Candle;
+use crate::client::ReconnectPolicy;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{BestBidOffer, Quote, Trade};
+use crate::output::EventContext;
+
+const BATCH_SIZE: usize = 1000;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+// Reconnects tried for one batch before the sink gives up
+const RETRY_ATTEMPTS: u32 = 5;
+
+// Created on every connect. `timestamp` is the exchange's time when the feed sends
+// one, `received` is always set, either can be made a TimescaleDB hypertable's time
+// column with `SELECT create_hypertable('trades', 'received', migrate_data => true)`.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS trades (
+    symbol TEXT NOT NULL,
+    event_id BIGINT NOT NULL,
+    timestamp TIMESTAMPTZ,
+    received TIMESTAMPTZ NOT NULL,
+    price NUMERIC NOT NULL,
+    amount NUMERIC NOT NULL,
+    maker_side TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS quotes (
+    symbol TEXT NOT NULL,
+    event_id BIGINT NOT NULL,
+    timestamp TIMESTAMPTZ,
+    received TIMESTAMPTZ NOT NULL,
+    price NUMERIC NOT NULL,
+    remaining NUMERIC NOT NULL,
+    delta NUMERIC,
+    side TEXT NOT NULL,
+    reason TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS bbo (
+    symbol TEXT NOT NULL,
+    event_id BIGINT NOT NULL,
+    timestamp TIMESTAMPTZ,
+    received TIMESTAMPTZ NOT NULL,
+    best_bid NUMERIC NOT NULL,
+    bid_amount_remaining NUMERIC NOT NULL,
+    best_offer NUMERIC NOT NULL,
+    ask_amount_remaining NUMERIC NOT NULL
+);
+CREATE TABLE IF NOT EXISTS candles (
+    symbol TEXT NOT NULL,
+    start TIMESTAMPTZ NOT NULL,
+    interval TEXT NOT NULL,
+    open NUMERIC NOT NULL,
+    high NUMERIC NOT NULL,
+    low NUMERIC NOT NULL,
+    close NUMERIC NOT NULL,
+    volume NUMERIC NOT NULL,
+    trades BIGINT NOT NULL,
+    tainted BOOLEAN NOT NULL
+);
+CREATE INDEX IF NOT EXISTS trades_symbol_received ON trades (symbol, received);
+CREATE INDEX IF NOT EXISTS quotes_symbol_received ON quotes (symbol, received);
+CREATE INDEX IF NOT EXISTS bbo_symbol_received ON bbo (symbol, received);
+CREATE INDEX IF NOT EXISTS candles_symbol_start ON candles (symbol, start);
+";
+
+// Rows of one table in COPY's text format, waiting for the next batch
+#[derive(Default)]
+struct CopyBuffer {
+    text: String,
+    rows: usize,
+}
+
+impl CopyBuffer {
+    fn begin(&mut self, symbol: &str, ctx: &EventContext) -> &mut Self {
+        self.text(symbol);
+        self.field(&ctx.event_id.to_string());
+        self.timestamp(ctx.timestampms);
+        self.timestamp(Some(ctx.received_ms));
+        self
+    }
+
+    // Every field is followed by a tab, `end` turns the last one into the newline
+    fn field(&mut self, value: &str) -> &mut Self {
+        self.text.push_str(value);
+        self.text.push('\t');
+        self
+    }
+
+    fn text(&mut self, value: &str) -> &mut Self {
+        for c in value.chars() {
+            match c {
+                '\\' => self.text.push_str("\\\\"),
+                '\t' => self.text.push_str("\\t"),
+                '\n' => self.text.push_str("\\n"),
+                '\r' => self.text.push_str("\\r"),
+                c => self.text.push(c),
+            }
+        }
+        self.text.push('\t');
+        self
+    }
+
+    fn decimal(&mut self, value: Option<Decimal>) -> &mut Self {
+        match value {
+            Some(value) => self.field(&value.to_string()),
+            None => self.field("\\N"),
+        }
+    }
+
+    fn timestamp(&mut self, ms: Option<u64>) -> &mut Self {
+        match ms {
+            Some(ms) => {
+                let time = humantime::format_rfc3339_millis(UNIX_EPOCH + Duration::from_millis(ms));
+                self.field(&time.to_string())
+            },
+            None => self.field("\\N"),
+        }
+    }
+
+    fn end(&mut self) {
+        self.text.pop();
+        self.text.push('\n');
+        self.rows += 1;
+    }
+
+    fn clear(&mut self) {
+        self.text.clear();
+        self.rows = 0;
+    }
+}
+
+// Writes trades, quotes, BBO snapshots and candles to PostgreSQL with one COPY per table and
+// batch, reconnecting when the server goes away
+pub struct PostgresSink {
+    config: Config,
+    tls: MakeTlsConnector,
+    client: Option<Client>,
+    trades: CopyBuffer,
+    quotes: CopyBuffer,
+    bbos: CopyBuffer,
+    candles: CopyBuffer,
+    last_flush: Instant,
+}
+
+impl PostgresSink {
+    // Only parses the URL, the connection is opened by the first batch on the sink's
+    // own thread
+    pub fn new(url: &str) -> Result<Self, GeminiError> {
+        let config = url.parse::<Config>().map_err(|e| GeminiError::Sink(format!("postgres: bad URL: {}", e)))?;
+        let tls = TlsConnector::new().map_err(|e| GeminiError::Sink(format!("postgres: {}", e)))?;
+        Ok(Self {
+            config,
+            tls: MakeTlsConnector::new(tls),
+            client: None,
+            trades: CopyBuffer::default(),
+            quotes: CopyBuffer::default(),
+            bbos: CopyBuffer::default(),
+            candles: CopyBuffer::default(),
+            last_flush: Instant::now(),
+        })
+    }
+
+    fn pending(&self) -> usize {
+        self.trades.rows + self.quotes.rows + self.bbos.rows + self.candles.rows
+    }
+
+    fn appended(&mut self) -> Result<(), GeminiError> {
+        if self.pending() >= BATCH_SIZE || self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    // All tables are copied in one transaction, so a batch retried after a lost
+    // connection is never stored twice
+    fn try_flush(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.client.as_ref().is_none_or(|client| client.is_closed()) {
+            let mut client = self.config.connect(self.tls.clone())?;
+            client.batch_execute(SCHEMA)?;
+            self.client = Some(client);
+        }
+        let client = self.client.as_mut().expect("connected above");
+        let mut tx = client.transaction()?;
+        for (statement, buffer) in [
+            ("COPY trades FROM STDIN", &self.trades),
+            ("COPY quotes FROM STDIN", &self.quotes),
+            ("COPY bbo FROM STDIN", &self.bbos),
+            ("COPY candles FROM STDIN", &self.candles),
+        ] {
+            if buffer.rows > 0 {
+                let mut writer = tx.copy_in(statement)?;
+                writer.write_all(buffer.text.as_bytes())?;
+                writer.finish()?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl EventHandler for PostgresSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, t: &Trade) -> Result<(), GeminiError> {
+        self.trades.begin(symbol, ctx)
+            .decimal(Some(t.price))
+            .decimal(Some(t.amount))
+            .text(t.maker_side.as_str())
+            .end();
+        self.appended()
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, q: &Quote) -> Result<(), GeminiError> {
+        self.quotes.begin(symbol, ctx)
+            .decimal(Some(q.price))
+            .decimal(Some(q.remaining))
+            .decimal(q.delta)
+            .text(q.side.as_str())
+            .text(&q.reason)
+            .end();
+        self.appended()
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.bbos.begin(symbol, ctx)
+            .decimal(Some(bbo.best_bid))
+            .decimal(Some(bbo.bid_amount_remaining))
+            .decimal(Some(bbo.best_offer))
+            .decimal(Some(bbo.ask_amount_remaining))
+            .end();
+        self.appended()
+    }
+
+    fn on_candle(&mut self, symbol: &str, _ctx: &EventContext, c: &Candle) -> Result<(), GeminiError> {
+        self.candles.text(symbol)
+            .timestamp(Some(c.start_ms))
+            .text(&c.interval)
+            .decimal(Some(c.open))
+            .decimal(Some(c.high))
+            .decimal(Some(c.low))
+            .decimal(Some(c.close))
+            .decimal(Some(c.volume))
+            .field(&c.trades.to_string())
+            .field(if c.tainted { "t" } else { "f" })
+            .end();
+        self.appended()
+    }
+
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        self.last_flush = Instant::now();
+        if self.pending() == 0 {
+            return Ok(());
+        }
+        let policy = ReconnectPolicy::default();
+        let mut attempt = 0;
+        loop {
+            match self.try_flush() {
+                Ok(()) => break,
+                // Errors reported by the server itself, like a rejected login or a
+                // mismatched table, will not go away by reconnecting
+                Err(e) if attempt < RETRY_ATTEMPTS && !is_server_error(e.as_ref()) => {
+                    attempt += 1;
+                    let delay = policy.delay(attempt);
+                    warn!(error = %e, delay = ?delay, "postgres: reconnecting");
+                    self.client = None;
+                    std::thread::sleep(delay);
+                },
+                Err(e) => return Err(GeminiError::Sink(format!("postgres: {}", e))),
+            }
+        }
+        self.trades.clear();
+        self.quotes.clear();
+        self.bbos.clear();
+        self.candles.clear();
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+}
+
+fn is_server_error(e: &(dyn Error + Send + Sync + 'static)) -> bool {
+    e.downcast_ref::<postgres::Error>().is_some_and(|e| e.as_db_error().is_some())
+}
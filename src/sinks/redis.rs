@@ -0,0 +1,103 @@
+use redis::{Client, Connection, RedisError};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::client::ReconnectPolicy;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{AuctionEvent, BestBidOffer, BlockTrade, Quote, Trade};
+use crate::output::{EventContext, Record};
+
+// Reconnects tried for one event before the sink gives up
+const RETRY_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Clone)]
+pub struct RedisOptions {
+    // Channels and streams are named `<prefix>:<symbol>:<kind>`
+    pub prefix: String,
+    // Also XADD every event to a stream of the same name, trimmed to about this many entries
+    pub stream_maxlen: Option<u64>,
+}
+
+// Publishes each normalized event as JSON to `gemini:<symbol>:trades`, `...:quotes`
+// and so on, optionally keeping them in capped Redis Streams as well
+pub struct RedisSink {
+    client: Client,
+    connection: Option<Connection>,
+    options: RedisOptions,
+}
+
+impl RedisSink {
+    pub fn new(url: &str, options: RedisOptions) -> Result<Self, GeminiError> {
+        let client = Client::open(url).map_err(sink_error)?;
+        Ok(Self {
+            client,
+            connection: None,
+            options,
+        })
+    }
+
+    fn publish<T: Serialize>(&mut self, kind: &'static str, channel: &str, symbol: &str, ctx: &EventContext, data: &T) -> Result<(), GeminiError> {
+        let key = format!("{}:{}:{}", self.options.prefix, symbol, channel);
+        let payload = Record::new(kind, symbol, ctx, data).to_json();
+        let mut pipe = redis::pipe();
+        pipe.cmd("PUBLISH").arg(&key).arg(&payload).ignore();
+        if let Some(maxlen) = self.options.stream_maxlen {
+            pipe.cmd("XADD").arg(&key).arg("MAXLEN").arg("~").arg(maxlen).arg("*").arg("data").arg(&payload).ignore();
+        }
+
+        let policy = ReconnectPolicy::default();
+        let mut attempt = 0;
+        loop {
+            let result = match self.connection.as_mut() {
+                Some(connection) => pipe.exec(connection),
+                None => self.client.get_connection().and_then(|mut connection| {
+                    let result = pipe.exec(&mut connection);
+                    self.connection = Some(connection);
+                    result
+                }),
+            };
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < RETRY_ATTEMPTS && (e.is_io_error() || e.is_connection_dropped()) => {
+                    attempt += 1;
+                    let delay = policy.delay(attempt);
+                    warn!(error = %e, delay = ?delay, "redis: reconnecting");
+                    self.connection = None;
+                    std::thread::sleep(delay);
+                },
+                Err(e) => return Err(sink_error(e)),
+            }
+        }
+    }
+}
+
+impl EventHandler for RedisSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.publish("trade", "trades", symbol, ctx, trade)
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        self.publish("quote", "quotes", symbol, ctx, quote)
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.publish("bbo", "bbo", symbol, ctx, bbo)
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.publish("block_trade", "block_trades", symbol, ctx, trade)
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.publish("auction", "auctions", symbol, ctx, auction)
+    }
+
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+}
+
+fn sink_error(e: RedisError) -> GeminiError {
+    GeminiError::Sink(format!("redis: {}", e))
+}
@@ -0,0 +1,163 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use rusqlite::{params, Connection};
+
+use crate::analytics::candles::Candle;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{BestBidOffer, Quote, Trade};
+use crate::output::EventContext;
+
+const BATCH_SIZE: usize = 500;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS trades (
+    symbol TEXT NOT NULL,
+    event_id INTEGER NOT NULL,
+    timestampms INTEGER,
+    received_ms INTEGER NOT NULL,
+    price NUMERIC NOT NULL,
+    amount NUMERIC NOT NULL,
+    maker_side TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS quotes (
+    symbol TEXT NOT NULL,
+    event_id INTEGER NOT NULL,
+    timestampms INTEGER,
+    received_ms INTEGER NOT NULL,
+    price NUMERIC NOT NULL,
+    remaining NUMERIC NOT NULL,
+    delta NUMERIC,
+    side TEXT NOT NULL,
+    reason TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS bbo (
+    symbol TEXT NOT NULL,
+    event_id INTEGER NOT NULL,
+    timestampms INTEGER,
+    received_ms INTEGER NOT NULL,
+    best_bid NUMERIC NOT NULL,
+    bid_amount_remaining NUMERIC NOT NULL,
+    best_offer NUMERIC NOT NULL,
+    ask_amount_remaining NUMERIC NOT NULL
+);
+CREATE TABLE IF NOT EXISTS candles (
+    symbol TEXT NOT NULL,
+    start_ms INTEGER NOT NULL,
+    interval TEXT NOT NULL,
+    open NUMERIC NOT NULL,
+    high NUMERIC NOT NULL,
+    low NUMERIC NOT NULL,
+    close NUMERIC NOT NULL,
+    volume NUMERIC NOT NULL,
+    trades INTEGER NOT NULL,
+    tainted INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS trades_symbol_time ON trades (symbol, received_ms);
+CREATE INDEX IF NOT EXISTS quotes_symbol_time ON quotes (symbol, received_ms);
+CREATE INDEX IF NOT EXISTS bbo_symbol_time ON bbo (symbol, received_ms);
+CREATE INDEX IF NOT EXISTS candles_symbol_time ON candles (symbol, start_ms);
+";
+
+enum Row {
+    Trade(String, EventContext, String, String, &'static str),
+    Quote(String, EventContext, String, String, Option<String>, &'static str, String),
+    Bbo(String, EventContext, [String; 4]),
+    Candle(String, Candle),
+}
+
+pub struct SqliteSink {
+    conn: Connection,
+    pending: Vec<Row>,
+    last_flush: Instant,
+}
+
+impl SqliteSink {
+    pub fn open(path: &Path) -> Result<Self, GeminiError> {
+        let conn = Connection::open(path).map_err(sink_error)?;
+        conn.execute_batch(SCHEMA).map_err(sink_error)?;
+        Ok(Self {
+            conn,
+            pending: Vec::with_capacity(BATCH_SIZE),
+            last_flush: Instant::now(),
+        })
+    }
+
+    fn push(&mut self, row: Row) -> Result<(), GeminiError> {
+        self.pending.push(row);
+        if self.pending.len() >= BATCH_SIZE || self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl EventHandler for SqliteSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, t: &Trade) -> Result<(), GeminiError> {
+        self.push(Row::Trade(symbol.to_string(), *ctx, t.price.to_string(), t.amount.to_string(), t.maker_side.as_str()))
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, q: &Quote) -> Result<(), GeminiError> {
+        self.push(Row::Quote(
+            symbol.to_string(), *ctx, q.price.to_string(), q.remaining.to_string(),
+            q.delta.map(|d| d.to_string()), q.side.as_str(), q.reason.clone(),
+        ))
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.push(Row::Bbo(symbol.to_string(), *ctx, [
+            bbo.best_bid.to_string(), bbo.bid_amount_remaining.to_string(),
+            bbo.best_offer.to_string(), bbo.ask_amount_remaining.to_string(),
+        ]))
+    }
+
+    fn on_candle(&mut self, symbol: &str, _ctx: &EventContext, candle: &Candle) -> Result<(), GeminiError> {
+        self.push(Row::Candle(symbol.to_string(), candle.clone()))
+    }
+
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        self.last_flush = Instant::now();
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let tx = self.conn.transaction().map_err(sink_error)?;
+        {
+            let mut trades = tx.prepare_cached(
+                "INSERT INTO trades VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)").map_err(sink_error)?;
+            let mut quotes = tx.prepare_cached(
+                "INSERT INTO quotes VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)").map_err(sink_error)?;
+            let mut bbos = tx.prepare_cached(
+                "INSERT INTO bbo VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)").map_err(sink_error)?;
+            let mut candles = tx.prepare_cached(
+                "INSERT INTO candles VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)").map_err(sink_error)?;
+            for row in self.pending.drain(..) {
+                match row {
+                    Row::Trade(symbol, ctx, price, amount, side) => trades.execute(params![
+                        symbol, ctx.event_id as i64, ctx.timestampms.map(|t| t as i64), ctx.received_ms as i64, price, amount, side,
+                    ]),
+                    Row::Quote(symbol, ctx, price, remaining, delta, side, reason) => quotes.execute(params![
+                        symbol, ctx.event_id as i64, ctx.timestampms.map(|t| t as i64), ctx.received_ms as i64, price, remaining, delta, side, reason,
+                    ]),
+                    Row::Bbo(symbol, ctx, [bid, bid_size, offer, offer_size]) => bbos.execute(params![
+                        symbol, ctx.event_id as i64, ctx.timestampms.map(|t| t as i64), ctx.received_ms as i64, bid, bid_size, offer, offer_size,
+                    ]),
+                    Row::Candle(symbol, c) => candles.execute(params![
+                        symbol, c.start_ms as i64, c.interval, c.open.to_string(), c.high.to_string(), c.low.to_string(),
+                        c.close.to_string(), c.volume.to_string(), c.trades as i64, c.tainted,
+                    ]),
+                }.map_err(sink_error)?;
+            }
+        }
+        tx.commit().map_err(sink_error)
+    }
+
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+}
+
+fn sink_error(e: rusqlite::Error) -> GeminiError {
+    GeminiError::Sink(format!("sqlite: {}", e))
+}
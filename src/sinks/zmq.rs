@@ -0,0 +1,73 @@
+use std::borrow::Cow;
+
+use serde::Serialize;
+use tokio::runtime::Handle;
+use zeromq::{PubSocket, Socket, SocketSend, ZmqError, ZmqMessage};
+
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{AuctionEvent, BestBidOffer, BlockTrade, Quote, Trade};
+use crate::output::{EventContext, Record};
+use crate::wire::{WireData, WireEvent, WireFormat};
+
+// Publishes every normalized event as a two part message on a ZeroMQ PUB socket: the
+// topic `<symbol>.<kind>`, so subscribing to `btcusd` gets everything for one symbol,
+// and the JSON record or binary wire format message. Subscribers that fall behind lose
+// messages, the feed never waits.
+pub struct ZmqSink {
+    runtime: Handle,
+    socket: PubSocket,
+    format: WireFormat,
+}
+
+impl ZmqSink {
+    // `endpoint` like tcp://0.0.0.0:5556 or ipc:///tmp/gemini.sock
+    pub async fn bind(endpoint: &str, format: WireFormat) -> Result<Self, GeminiError> {
+        let mut socket = PubSocket::new();
+        socket.bind(endpoint).await.map_err(sink_error)?;
+        Ok(Self {
+            runtime: Handle::current(),
+            socket,
+            format,
+        })
+    }
+
+    fn publish<T: Serialize>(&mut self, kind: &'static str, symbol: &str, ctx: &EventContext, data: &T, wire: WireData) -> Result<(), GeminiError> {
+        let mut message = ZmqMessage::from(format!("{}.{}", symbol, kind));
+        message.push_back(match self.format {
+            WireFormat::Json => Record::new(kind, symbol, ctx, data).to_json().into(),
+            WireFormat::Bincode => WireEvent::new(symbol, ctx, wire).to_message().into(),
+        });
+        self.runtime.block_on(self.socket.send(message)).map_err(sink_error)
+    }
+}
+
+impl EventHandler for ZmqSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.publish("trade", symbol, ctx, trade, WireData::Trade(Cow::Borrowed(trade)))
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        self.publish("quote", symbol, ctx, quote, WireData::Quote(Cow::Borrowed(quote)))
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.publish("bbo", symbol, ctx, bbo, WireData::Bbo(Cow::Borrowed(bbo)))
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.publish("block_trade", symbol, ctx, trade, WireData::BlockTrade(Cow::Borrowed(trade)))
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.publish("auction", symbol, ctx, auction, WireData::from(auction))
+    }
+
+    fn name(&self) -> &'static str {
+        "zmq"
+    }
+}
+
+fn sink_error(e: ZmqError) -> GeminiError {
+    GeminiError::Sink(format!("zmq: {}", e))
+}
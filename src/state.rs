@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+
+use jiff::Timestamp;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::analytics::candles::Candle;
+use crate::analytics::depth::DepthSnapshot;
+use crate::analytics::indicators::BookIndicators;
+use crate::analytics::ofi::OfiSnapshot;
+use crate::analytics::vwap::TradeStatsSnapshot;
+use crate::error::GeminiError;
+use crate::models::BestBidOffer;
+use crate::summary::SessionSummary;
+
+// What a collector keeps across restarts, written to `--state-file` on shutdown
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SavedState {
+    pub saved_ms: u64,
+    pub symbols: Vec<SavedSymbol>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedSymbol {
+    pub symbol: String,
+    #[serde(default)]
+    pub last_event_id: Option<u64>,
+    #[serde(default)]
+    pub last_sequence: Option<u32>,
+    // Exchange time of the last processed message, the gap on restore is measured from it
+    #[serde(default)]
+    pub last_timestampms: Option<u64>,
+    // The bar still open at shutdown and the close flat bars continue from
+    #[serde(default)]
+    pub candle: Option<Candle>,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    pub last_close: Option<Decimal>,
+    pub summary: SessionSummary,
+}
+
+// A missing file is a first start, not an error
+pub fn load(path: &Path) -> Result<Option<SavedState>, GeminiError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(GeminiError::Config(format!("{}: {}", path.display(), e))),
+    };
+    serde_json::from_str(&text)
+        .map(Some)
+        .map_err(|e| GeminiError::Config(format!("{}: {}", path.display(), e)))
+}
+
+// Written next to the target and renamed over it, so a crash mid-write keeps the old state
+pub fn save(path: &Path, state: &SavedState) -> Result<(), GeminiError> {
+    write_json(path, state)
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), GeminiError> {
+    let json = serde_json::to_vec_pretty(value).map_err(|e| GeminiError::Sink(e.to_string()))?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// What a running collector holds for every symbol at one moment, for looking into it
+// without stopping it. Unlike `SavedState` it is never read back.
+#[derive(Serialize, Debug)]
+pub struct StateDump {
+    pub dumped_ms: u64,
+    pub symbols: Vec<DumpedSymbol>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DumpedSymbol {
+    pub symbol: String,
+    // Full depth as [price, size], best first
+    pub bids: Vec<[String; 2]>,
+    pub asks: Vec<[String; 2]>,
+    pub bbo: BestBidOffer,
+    pub indicators: Option<BookIndicators>,
+    // The last depth event, with --depth-levels or --depth-sizes
+    pub depth: Option<DepthSnapshot>,
+    pub stats: Option<TradeStatsSnapshot>,
+    pub order_flow: Option<OfiSnapshot>,
+    pub candle: Option<Candle>,
+    pub last_event_id: Option<u64>,
+    pub last_sequence: Option<u32>,
+    pub last_timestampms: Option<u64>,
+    pub summary: SessionSummary,
+}
+
+// Writes `dump` to a file in `dir` named after its time, such as
+// state-20240101T120000.000Z.json, complete or not at all
+pub fn dump(dir: &Path, dump: &StateDump) -> Result<PathBuf, GeminiError> {
+    let time = Timestamp::from_millisecond(dump.dumped_ms as i64)
+        .map(|ts| ts.strftime("%Y%m%dT%H%M%S%.3fZ").to_string())
+        .unwrap_or_else(|_| dump.dumped_ms.to_string());
+    let path = dir.join(format!("state-{}.json", time));
+    write_json(&path, dump)?;
+    Ok(path)
+}
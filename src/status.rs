@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use serde::Serialize;
+
+use crate::analytics::spread::{SpreadSnapshot, SpreadTracker};
+use crate::market_state::MarketState;
+use crate::error::GeminiError;
+use crate::history::History;
+use crate::metrics;
+use crate::models::BestBidOffer;
+use crate::positions::{PositionSnapshot, Positions};
+
+// When each symbol last delivered a frame, fed by the pipeline and read by the status server
+#[derive(Debug, Clone)]
+pub struct FeedStatus {
+    started: Instant,
+    last_message: Arc<Mutex<HashMap<String, Instant>>>,
+    symbols: Arc<Mutex<Vec<String>>>,
+}
+
+impl Default for FeedStatus {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            last_message: Arc::default(),
+            symbols: Arc::default(),
+        }
+    }
+}
+
+impl FeedStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Reports `symbols` before their first frame, so one that never connects shows up as stale
+    pub fn expect(&self, symbols: &[String]) {
+        for symbol in symbols {
+            self.add_symbol(symbol);
+        }
+    }
+
+    pub fn record(&self, symbol: &str) {
+        let mut last_message = self.last_message.lock().unwrap();
+        match last_message.get_mut(symbol) {
+            Some(at) => *at = Instant::now(),
+            None => {
+                last_message.insert(symbol.to_string(), Instant::now());
+                drop(last_message);
+                self.add_symbol(symbol);
+            },
+        }
+    }
+
+    // Stops reporting `symbol`, after it was unsubscribed
+    pub fn remove(&self, symbol: &str) {
+        self.symbols.lock().unwrap().retain(|s| s != symbol);
+        self.last_message.lock().unwrap().remove(symbol);
+    }
+
+    fn add_symbol(&self, symbol: &str) {
+        let mut symbols = self.symbols.lock().unwrap();
+        if !symbols.iter().any(|s| s == symbol) {
+            symbols.push(symbol.to_string());
+        }
+    }
+
+    // Time since the symbol's last frame, or since startup for one still waiting for its first
+    fn age(&self, symbol: &str) -> Duration {
+        self.last_message.lock().unwrap().get(symbol).unwrap_or(&self.started).elapsed()
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        self.symbols.lock().unwrap().clone()
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SymbolStatus {
+    pub symbol: String,
+    // Unset until the first frame for the symbol arrived
+    pub last_message_age_ms: Option<u64>,
+    pub messages: u64,
+    pub sequence_gaps: u64,
+    pub reconnects: u64,
+    pub bbo: Option<BestBidOffer>,
+    pub spread: Option<SpreadSnapshot>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StatusReport {
+    pub uptime_secs: u64,
+    pub healthy: bool,
+    pub symbols: Vec<SymbolStatus>,
+    // With --positions, every symbol the account traded since startup
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub positions: Vec<PositionSnapshot>,
+}
+
+#[derive(Clone)]
+struct StatusState {
+    feed: FeedStatus,
+    bbo: MarketState,
+    spread: SpreadTracker,
+    history: Option<History>,
+    positions: Positions,
+    stale_after: Duration,
+}
+
+impl StatusState {
+    // Symbols that have been silent for longer than `stale_after`
+    fn stale(&self) -> Vec<String> {
+        self.feed.symbols().into_iter().filter(|s| self.feed.age(s) > self.stale_after).collect()
+    }
+
+    fn report(&self) -> StatusReport {
+        let metrics = metrics::global();
+        let seen = self.feed.last_message.lock().unwrap().clone();
+        let symbols = self.feed.symbols().into_iter().map(|symbol| SymbolStatus {
+            last_message_age_ms: seen.get(&symbol).map(|at| at.elapsed().as_millis() as u64),
+            messages: metrics.messages.with_label_values(&[&symbol]).get(),
+            sequence_gaps: metrics.sequence_gaps.with_label_values(&[&symbol]).get(),
+            reconnects: metrics.reconnects.with_label_values(&[&symbol]).get(),
+            bbo: self.bbo.get(&symbol),
+            spread: self.spread.get(&symbol),
+            symbol,
+        }).collect();
+        StatusReport {
+            uptime_secs: self.feed.started.elapsed().as_secs(),
+            healthy: self.stale().is_empty(),
+            symbols,
+            positions: self.positions.snapshots(),
+        }
+    }
+}
+
+// 200 while every symbol delivered a frame within `stale_after`, 503 naming the silent ones otherwise
+async fn healthz(State(state): State<StatusState>) -> impl IntoResponse {
+    let stale = state.stale();
+    match stale.is_empty() {
+        true => (StatusCode::OK, String::from("ok\n")),
+        false => (StatusCode::SERVICE_UNAVAILABLE, format!("stale: {}\n", stale.join(","))),
+    }
+}
+
+async fn status(State(state): State<StatusState>) -> impl IntoResponse {
+    // A report of strings and numbers always serializes
+    let body = serde_json::to_string(&state.report()).unwrap_or_default();
+    ([(header::CONTENT_TYPE, "application/json")], body)
+}
+
+// Trades and BBO changes of the history window, 404 without `--history`
+async fn recent_history(State(state): State<StatusState>, Path(symbol): Path<String>) -> impl IntoResponse {
+    let Some(history) = &state.history else {
+        return (StatusCode::NOT_FOUND, [(header::CONTENT_TYPE, "text/plain")], String::from("no history kept, see --history\n"));
+    };
+    let symbol = symbol.to_lowercase();
+    let body = serde_json::json!({
+        "symbol": symbol,
+        "trades": history.recent_trades(&symbol),
+        "bbo": history.bbo_history(&symbol),
+    });
+    (StatusCode::OK, [(header::CONTENT_TYPE, "application/json")], body.to_string())
+}
+
+// Binds `addr` right away so a taken port fails at startup, returns the server to spawn
+pub async fn bind(
+    addr: SocketAddr,
+    feed: FeedStatus,
+    bbo: MarketState,
+    spread: SpreadTracker,
+    history: Option<History>,
+    positions: Positions,
+    stale_after: Duration,
+) -> Result<impl std::future::Future<Output = Result<(), GeminiError>>, GeminiError> {
+    let app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/status", get(status))
+        .route("/history/:symbol", get(recent_history))
+        .with_state(StatusState { feed, bbo, spread, history, positions, stale_after });
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    Ok(async move {
+        axum::serve(listener, app).await?;
+        Ok(())
+    })
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+use crate::error::GeminiError;
+use crate::models::{BestBidOffer, MarketSide, OrderSide, Trade};
+use crate::strategy::{mid, Account, Params, SimFill, Strategy};
+
+#[derive(Debug, Clone)]
+pub struct MarketMakerOptions {
+    // Distance from the bid quote to the ask quote, in basis points of the mid
+    pub spread_bps: Decimal,
+    // Amount quoted on each side
+    pub size: Decimal,
+    // Stop quoting the side that would grow the position beyond this
+    pub max_position: Option<Decimal>,
+    // Paid on every fill, negative for a rebate
+    pub fee_bps: Decimal,
+}
+
+impl Default for MarketMakerOptions {
+    fn default() -> Self {
+        Self {
+            spread_bps: Decimal::TEN,
+            size: Decimal::ONE,
+            max_position: None,
+            fee_bps: Decimal::ZERO,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quote {
+    price: Decimal,
+    remaining: Decimal,
+}
+
+#[derive(Debug, Default)]
+struct Quoting {
+    bid: Option<Quote>,
+    ask: Option<Quote>,
+    account: Account,
+}
+
+// Quotes `spread_bps` around the mid of every symbol, moving both quotes with every BBO
+// change. A quote fills from trades printing at or through it, as if it were first in
+// the queue at its price, so the fills are an upper bound.
+pub struct MarketMaker {
+    options: MarketMakerOptions,
+    symbols: HashMap<String, Quoting>,
+}
+
+impl MarketMaker {
+    pub fn new(options: MarketMakerOptions) -> Self {
+        Self {
+            options,
+            symbols: HashMap::new(),
+        }
+    }
+
+    // spread_bps, size, max_position and fee_bps, as the market-make flags
+    pub fn from_params(params: &mut Params) -> Result<Self, GeminiError> {
+        let defaults = MarketMakerOptions::default();
+        Ok(Self::new(MarketMakerOptions {
+            spread_bps: params.take("spread_bps")?.unwrap_or(defaults.spread_bps),
+            size: params.take("size")?.unwrap_or(defaults.size),
+            max_position: params.take("max_position")?,
+            fee_bps: params.take("fee_bps")?.unwrap_or(defaults.fee_bps),
+        }))
+    }
+
+    fn quoting(&mut self, symbol: &str) -> &mut Quoting {
+        self.symbols.entry(symbol.to_string()).or_default()
+    }
+}
+
+impl Strategy for MarketMaker {
+    fn on_book_update(&mut self, symbol: &str, _ts_ms: u64, bbo: &BestBidOffer) {
+        let options = self.options.clone();
+        let quoting = self.quoting(symbol);
+        let Some(mid) = mid(bbo) else {
+            quoting.bid = None;
+            quoting.ask = None;
+            return;
+        };
+        quoting.account.mark = Some(mid);
+        let half = mid * options.spread_bps / Decimal::from(20_000);
+        let room = |position: Decimal| options.max_position.is_none_or(|max| position < max);
+        let position = quoting.account.position;
+        quoting.bid = room(position).then_some(Quote { price: mid - half, remaining: options.size });
+        quoting.ask = room(-position).then_some(Quote { price: mid + half, remaining: options.size });
+    }
+
+    // A trade whose taker sold fills the bid, one whose taker bought fills the ask
+    fn on_trade(&mut self, symbol: &str, ts_ms: u64, trade: &Trade) -> Vec<SimFill> {
+        let fee_bps = self.options.fee_bps;
+        let quoting = self.quoting(symbol);
+        let mut fills = Vec::new();
+        let sides = [
+            (OrderSide::Buy, MarketSide::Bid, &mut quoting.bid),
+            (OrderSide::Sell, MarketSide::Ask, &mut quoting.ask),
+        ];
+        for (side, maker_side, quote) in sides {
+            let Some(resting) = quote.as_mut() else {
+                continue;
+            };
+            let through = match side {
+                OrderSide::Buy => trade.price <= resting.price,
+                OrderSide::Sell => trade.price >= resting.price,
+            };
+            if !through || !matches!(trade.maker_side, MarketSide::Unknown) && trade.maker_side != maker_side {
+                continue;
+            }
+            let amount = resting.remaining.min(trade.amount);
+            let fee = quoting.account.fill(side, resting.price, amount, fee_bps);
+            fills.push(SimFill {
+                symbol: symbol.to_string(),
+                side,
+                price: resting.price,
+                amount,
+                fee,
+                ts_ms,
+            });
+            resting.remaining -= amount;
+            if resting.remaining.is_zero() {
+                *quote = None;
+            }
+        }
+        fills
+    }
+
+    // The quotes are gone with the connection, the book snapshot after a reconnect sets new ones
+    fn on_disconnect(&mut self, symbol: &str) {
+        let quoting = self.quoting(symbol);
+        quoting.bid = None;
+        quoting.ask = None;
+    }
+
+    fn account(&self, symbol: &str) -> Option<&Account> {
+        self.symbols.get(symbol).map(|q| &q.account)
+    }
+
+    fn describe(&self) -> String {
+        let max = self.options.max_position.map(|m| format!(", position within {}", m)).unwrap_or_default();
+        format!(
+            "Market making simulation, {} bps wide, {} a side{}, fees {} bps",
+            self.options.spread_bps, self.options.size, max, self.options.fee_bps,
+        )
+    }
+}
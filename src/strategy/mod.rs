@@ -0,0 +1,283 @@
+pub mod mm;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use rust_decimal::Decimal;
+use tracing::info;
+
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{BestBidOffer, OrderSide, Trade};
+use crate::output::EventContext;
+
+// Names `build` accepts
+pub const STRATEGIES: [&str; 1] = ["mm"];
+
+// A trading strategy fed the events of the pipeline, live or replayed. It keeps the
+// account of every symbol it trades and returns the fills each trade gave it.
+pub trait Strategy: Send {
+    fn on_book_update(&mut self, _symbol: &str, _ts_ms: u64, _bbo: &BestBidOffer) {}
+
+    fn on_trade(&mut self, symbol: &str, ts_ms: u64, trade: &Trade) -> Vec<SimFill>;
+
+    // Orders resting on the exchange are gone with the connection
+    fn on_disconnect(&mut self, _symbol: &str) {}
+
+    fn account(&self, symbol: &str) -> Option<&Account>;
+
+    // One line on how the strategy is set up, heading its report
+    fn describe(&self) -> String;
+}
+
+// Builds a strategy by name from `key=value` parameters, rejecting ones it does not take
+pub fn build(name: &str, params: &[String]) -> Result<Box<dyn Strategy>, GeminiError> {
+    let mut params = Params::parse(params)?;
+    let strategy: Box<dyn Strategy> = match name {
+        "mm" => Box::new(mm::MarketMaker::from_params(&mut params)?),
+        _ => {
+            return Err(GeminiError::Config(format!(
+                "unknown strategy `{}`, expected one of {}", name, STRATEGIES.join(", "),
+            )));
+        },
+    };
+    params.finish(name)?;
+    Ok(strategy)
+}
+
+// Strategy parameters, taken one by one so the ones left over can be reported
+pub struct Params {
+    values: HashMap<String, String>,
+}
+
+impl Params {
+    pub fn parse(params: &[String]) -> Result<Self, GeminiError> {
+        let mut values = HashMap::new();
+        for param in params {
+            let Some((key, value)) = param.split_once('=') else {
+                return Err(GeminiError::Config(format!("strategy parameter `{}` is not KEY=VALUE", param)));
+            };
+            values.insert(key.trim().to_string(), value.trim().to_string());
+        }
+        Ok(Self { values })
+    }
+
+    pub fn take<T: FromStr>(&mut self, key: &str) -> Result<Option<T>, GeminiError> {
+        let Some(value) = self.values.remove(key) else {
+            return Ok(None);
+        };
+        value.parse()
+            .map(Some)
+            .map_err(|_| GeminiError::Config(format!("invalid value `{}` for strategy parameter {}", value, key)))
+    }
+
+    fn finish(self, name: &str) -> Result<(), GeminiError> {
+        let mut unknown: Vec<String> = self.values.into_keys().collect();
+        unknown.sort();
+        match unknown.is_empty() {
+            true => Ok(()),
+            false => Err(GeminiError::Config(format!("{} does not take {}", name, unknown.join(", ")))),
+        }
+    }
+}
+
+// A simulated execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimFill {
+    pub symbol: String,
+    pub side: OrderSide,
+    pub price: Decimal,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub ts_ms: u64,
+}
+
+impl fmt::Display for SimFill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} @ {} (fee {})", self.side, self.amount, self.symbol, self.price, self.fee.normalize())
+    }
+}
+
+// Position and cash of one symbol. Cash is in the quote currency with fees taken off, so
+// the PnL at any price is cash plus the position valued at it.
+#[derive(Debug, Clone, Default)]
+pub struct Account {
+    pub position: Decimal,
+    pub cash: Decimal,
+    pub fees: Decimal,
+    pub fills: u64,
+    pub bought: Decimal,
+    pub sold: Decimal,
+    // Largest absolute position held at any point
+    pub max_position: Decimal,
+    // Last mid, to mark the position at
+    pub mark: Option<Decimal>,
+}
+
+impl Account {
+    // Books a fill, `fee_bps` of its notional, negative for a rebate
+    pub fn fill(&mut self, side: OrderSide, price: Decimal, amount: Decimal, fee_bps: Decimal) -> Decimal {
+        let fee = price * amount * fee_bps / Decimal::from(10_000);
+        let signed = match side {
+            OrderSide::Buy => amount,
+            OrderSide::Sell => -amount,
+        };
+        match side {
+            OrderSide::Buy => self.bought += amount,
+            OrderSide::Sell => self.sold += amount,
+        }
+        self.position += signed;
+        self.cash -= signed * price + fee;
+        self.fees += fee;
+        self.fills += 1;
+        self.max_position = self.max_position.max(self.position.abs());
+        fee
+    }
+
+    pub fn pnl(&self) -> Option<Decimal> {
+        Some(self.cash + self.position * self.mark?)
+    }
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pnl = self.pnl().map(|p| p.round_dp(8).normalize().to_string()).unwrap_or_else(|| String::from("-"));
+        writeln!(f, "  fills: {} (bought {}, sold {})", self.fills, self.bought, self.sold)?;
+        writeln!(f, "  position: {} (largest {})", self.position, self.max_position)?;
+        writeln!(f, "  cash: {}, fees: {}", self.cash.round_dp(8).normalize(), self.fees.round_dp(8).normalize())?;
+        writeln!(f, "  PnL marked to the mid: {}", pnl)
+    }
+}
+
+pub fn mid(bbo: &BestBidOffer) -> Option<Decimal> {
+    match bbo.best_bid.is_zero() || bbo.best_offer.is_zero() {
+        true => None,
+        false => Some((bbo.best_bid + bbo.best_offer) / Decimal::TWO),
+    }
+}
+
+// PnL extremes of one symbol, sampled after every event
+#[derive(Debug, Clone, Copy, Default)]
+struct Curve {
+    peak: Decimal,
+    trough: Decimal,
+    max_drawdown: Decimal,
+}
+
+impl Curve {
+    fn sample(&mut self, pnl: Decimal) {
+        self.peak = self.peak.max(pnl);
+        self.trough = self.trough.min(pnl);
+        self.max_drawdown = self.max_drawdown.max(self.peak - pnl);
+    }
+}
+
+struct Run {
+    strategy: Box<dyn Strategy>,
+    fills: Vec<SimFill>,
+    curves: HashMap<String, Curve>,
+    // Symbols in the order they were first seen
+    symbols: Vec<String>,
+}
+
+impl Run {
+    fn sample(&mut self, symbol: &str) {
+        if !self.curves.contains_key(symbol) {
+            self.symbols.push(symbol.to_string());
+        }
+        let curve = self.curves.entry(symbol.to_string()).or_default();
+        if let Some(pnl) = self.strategy.account(symbol).and_then(Account::pnl) {
+            curve.sample(pnl);
+        }
+    }
+}
+
+// Runs a strategy as a pipeline handler and keeps its fills and the PnL curve of every
+// symbol for the report. Clones share the run, so one can be handed to the pipeline
+// and the other read once it is done.
+#[derive(Clone)]
+pub struct StrategyRunner {
+    run: Arc<Mutex<Run>>,
+}
+
+impl StrategyRunner {
+    pub fn new(strategy: Box<dyn Strategy>) -> Self {
+        Self {
+            run: Arc::new(Mutex::new(Run {
+                strategy,
+                fills: Vec::new(),
+                curves: HashMap::new(),
+                symbols: Vec::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Run> {
+        self.run.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn fills(&self) -> Vec<SimFill> {
+        self.lock().fills.clone()
+    }
+
+    pub fn report(&self) -> String {
+        let run = self.lock();
+        let mut report = format!("{}\n", run.strategy.describe());
+        let mut total = Decimal::ZERO;
+        for symbol in &run.symbols {
+            let Some(account) = run.strategy.account(symbol) else {
+                continue;
+            };
+            let curve = run.curves[symbol];
+            total += account.pnl().unwrap_or_default();
+            report.push_str(&format!("{}\n{}", symbol, account));
+            report.push_str(&format!(
+                "  PnL high/low: {} / {}, max drawdown: {}\n",
+                curve.peak.round_dp(8).normalize(), curve.trough.round_dp(8).normalize(), curve.max_drawdown.round_dp(8).normalize(),
+            ));
+        }
+        if run.symbols.len() > 1 {
+            report.push_str(&format!("Total PnL: {}\n", total.round_dp(8).normalize()));
+        }
+        report
+    }
+}
+
+impl EventHandler for StrategyRunner {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        let mut run = self.lock();
+        let fills = run.strategy.on_trade(symbol, ctx.timestampms.unwrap_or(ctx.received_ms), trade);
+        for fill in fills {
+            let position = run.strategy.account(symbol).map(|a| a.position).unwrap_or_default();
+            info!(symbol, %fill, %position, "simulated fill");
+            run.fills.push(fill);
+        }
+        run.sample(symbol);
+        Ok(())
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        let mut run = self.lock();
+        run.strategy.on_book_update(symbol, ctx.timestampms.unwrap_or(ctx.received_ms), bbo);
+        run.sample(symbol);
+        Ok(())
+    }
+
+    fn on_disconnect(&mut self, symbol: &str, _reason: &str) -> Result<(), GeminiError> {
+        self.lock().strategy.on_disconnect(symbol);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "strategy"
+    }
+}
+
+// A fill as a line of the backtest output, led by its time
+pub fn format_fill(fill: &SimFill) -> String {
+    let time = humantime::format_rfc3339_millis(UNIX_EPOCH + Duration::from_millis(fill.ts_ms));
+    format!("{} {}", time, fill)
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::analytics::histogram::{SizeHistogram, VolumeProfile};
+use crate::analytics::spread::SpreadSnapshot;
+use crate::models::{BestBidOffer, OrderSide, Trade};
+
+// Biggest trades by amount kept for the report
+const LARGEST_PRINTS: usize = 5;
+
+// Rows of the volume profile in the report, and the width of the longest bar
+const PROFILE_ROWS: usize = 20;
+const BAR_WIDTH: usize = 30;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Print {
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
+    pub timestampms: u64,
+}
+
+// Running statistics for one symbol over the lifetime of the process. Fields added
+// since a state file was written start out empty.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SessionSummary {
+    pub symbol: String,
+    pub messages: u64,
+    // Messages dropped as repeats of ones already processed
+    pub duplicates: u64,
+    pub sequence_gaps: u64,
+    pub disconnects: u64,
+    // Trades missed while disconnected and fetched over REST, counted in `trades` too
+    pub recovered_trades: u64,
+    // Receive times of the first and the last message
+    pub first_ms: Option<u64>,
+    pub last_ms: Option<u64>,
+    // Events by their type, and by reason for book changes, such as `change/cancel`
+    pub event_types: BTreeMap<String, u64>,
+    pub trades: u64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub volume: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub notional: Decimal,
+    // Volume of trades whose taker bought and of those whose taker sold
+    #[serde(with = "rust_decimal::serde::str")]
+    pub buy_volume: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub sell_volume: Decimal,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub open: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub high: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub low: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub close: Option<Decimal>,
+    // Largest first
+    pub largest: Vec<Print>,
+    pub sizes: SizeHistogram,
+    pub profile: VolumeProfile,
+    // Sum of the spreads of every two sided BBO, for the average
+    #[serde(with = "rust_decimal::serde::str")]
+    pub spread_total: Decimal,
+    pub spread_samples: u64,
+    // Time-weighted, filled in with the BBO when the summary is taken
+    pub spread: Option<SpreadSnapshot>,
+    pub bbo: BestBidOffer,
+}
+
+impl SessionSummary {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn record_message(&mut self, received_ms: u64) {
+        self.messages += 1;
+        self.first_ms.get_or_insert(received_ms);
+        self.last_ms = Some(received_ms);
+    }
+
+    pub fn record_event(&mut self, kind: &str, reason: Option<&str>) {
+        let key = match reason {
+            Some(reason) => format!("{}/{}", kind, reason),
+            None => kind.to_string(),
+        };
+        *self.event_types.entry(key).or_default() += 1;
+    }
+
+    pub fn record_trade(&mut self, ts_ms: u64, trade: &Trade) {
+        self.trades += 1;
+        self.volume += trade.amount;
+        self.notional += trade.amount * trade.price;
+        match trade.aggressor() {
+            Some(OrderSide::Buy) => self.buy_volume += trade.amount,
+            Some(OrderSide::Sell) => self.sell_volume += trade.amount,
+            None => {},
+        }
+        self.open.get_or_insert(trade.price);
+        self.high = Some(self.high.map_or(trade.price, |h| h.max(trade.price)));
+        self.low = Some(self.low.map_or(trade.price, |l| l.min(trade.price)));
+        self.close = Some(trade.price);
+        if self.largest.len() < LARGEST_PRINTS || self.largest.last().is_some_and(|p| trade.amount > p.amount) {
+            let at = self.largest.partition_point(|p| p.amount >= trade.amount);
+            self.largest.insert(at, Print { price: trade.price, amount: trade.amount, timestampms: ts_ms });
+            self.largest.truncate(LARGEST_PRINTS);
+        }
+        self.sizes.record(trade.amount);
+        self.profile.record(trade.price, trade.amount);
+    }
+
+    pub fn record_bbo(&mut self, bbo: &BestBidOffer) {
+        if !bbo.best_bid.is_zero() && !bbo.best_offer.is_zero() {
+            self.spread_total += bbo.best_offer - bbo.best_bid;
+            self.spread_samples += 1;
+        }
+    }
+
+    pub fn vwap(&self) -> Option<Decimal> {
+        self.notional.checked_div(self.volume)
+    }
+
+    // Buy minus sell volume over the session
+    pub fn cumulative_delta(&self) -> Decimal {
+        self.buy_volume - self.sell_volume
+    }
+
+    // Over BBO updates, not weighted by how long each spread stood
+    pub fn average_spread(&self) -> Option<Decimal> {
+        self.spread_total.checked_div(Decimal::from(self.spread_samples))
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        Some(Duration::from_millis(self.last_ms?.saturating_sub(self.first_ms?)))
+    }
+
+    pub fn report(&self) -> SessionReport<'_> {
+        SessionReport {
+            summary: self,
+            vwap: self.vwap().map(|v| v.round_dp(8).normalize()),
+            average_spread: self.average_spread(),
+            cumulative_delta: self.cumulative_delta(),
+            duration_ms: self.duration().map(|d| d.as_millis() as u64),
+        }
+    }
+}
+
+// The summary with the figures derived from it, the JSON form of the report
+#[derive(Serialize)]
+pub struct SessionReport<'a> {
+    #[serde(flatten)]
+    summary: &'a SessionSummary,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    vwap: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    average_spread: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str")]
+    cumulative_delta: Decimal,
+    duration_ms: Option<u64>,
+}
+
+impl SessionReport<'_> {
+    pub fn to_json(&self) -> String {
+        // Serializing plain structs of strings and integers cannot fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for SessionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let price = |p: Option<Decimal>| p.map(|p| p.to_string()).unwrap_or_else(|| String::from("-"));
+        let time = |ms: u64| humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_millis(ms));
+        writeln!(f, "Session summary for {}", self.symbol)?;
+        if let (Some(first), Some(last), Some(duration)) = (self.first_ms, self.last_ms, self.duration()) {
+            let duration = humantime::format_duration(Duration::from_secs(duration.as_secs()));
+            writeln!(f, "  span: {} to {} ({})", time(first), time(last), duration)?;
+        }
+        writeln!(f, "  messages processed: {}", self.messages)?;
+        if !self.event_types.is_empty() {
+            let types: Vec<String> = self.event_types.iter().map(|(kind, count)| format!("{} {}", kind, count)).collect();
+            writeln!(f, "  events: {}", types.join(", "))?;
+        }
+        if self.duplicates > 0 {
+            writeln!(f, "  duplicates dropped: {}", self.duplicates)?;
+        }
+        writeln!(f, "  sequence gaps: {}, disconnects: {}", self.sequence_gaps, self.disconnects)?;
+        if self.recovered_trades > 0 {
+            writeln!(f, "  trades recovered after disconnects: {}", self.recovered_trades)?;
+        }
+        writeln!(f, "  trades: {}", self.trades)?;
+        writeln!(f, "  trade volume: {} (${})", self.volume, self.notional)?;
+        writeln!(f, "  buy/sell volume: {} / {} (delta {})", self.buy_volume, self.sell_volume, self.cumulative_delta())?;
+        writeln!(f, "  open/close: {} / {}", price(self.open), price(self.close))?;
+        writeln!(f, "  session high/low: {} / {}", price(self.high), price(self.low))?;
+        writeln!(f, "  VWAP: {}", price(self.vwap().map(|v| v.round_dp(8).normalize())))?;
+        for (i, print) in self.largest.iter().enumerate() {
+            let label = if i == 0 { "largest prints:" } else { "" };
+            writeln!(f, "  {:<16}{} @ {} at {}", label, print.amount, print.price, time(print.timestampms))?;
+        }
+        if !self.sizes.is_empty() {
+            writeln!(f, "  trade sizes:")?;
+            let most = self.sizes.buckets().iter().map(|b| b.trades).max().unwrap_or(1);
+            for bucket in self.sizes.buckets() {
+                let bar = "#".repeat((bucket.trades * BAR_WIDTH as u64).div_ceil(most) as usize);
+                writeln!(f, "    from {:<12} {:>8} {}", bucket.from.normalize(), bucket.trades, bar)?;
+            }
+        }
+        if let (Some(poc), Some((low, high))) = (self.profile.point_of_control(), self.profile.value_area()) {
+            writeln!(f, "  volume profile: point of control {}, value area {} to {}", poc.price, low, high)?;
+            let profile = self.profile.coarsened(PROFILE_ROWS);
+            let most = profile.levels.iter().map(|l| l.volume).max().unwrap_or(Decimal::ONE);
+            for level in profile.levels.iter().rev() {
+                let bar = (level.volume * Decimal::from(BAR_WIDTH)).checked_div(most).unwrap_or_default().ceil();
+                writeln!(f, "    {:<14} {:>16} {}", level.price, level.volume, "#".repeat(bar.to_usize().unwrap_or(0)))?;
+            }
+        }
+        if let Some(spread) = self.average_spread() {
+            writeln!(f, "  average spread: {} over {} BBO updates", spread.round_dp(8).normalize(), self.spread_samples)?;
+        }
+        if let Some(spread) = &self.spread {
+            writeln!(f, "  time-weighted spread: {} ({} bps), median {}", spread.twas, spread.twas_bps, spread.median)?;
+            let above: Vec<String> = spread.thresholds.iter()
+                .map(|t| format!("{}% over {} bps", t.percent_above, t.bps))
+                .collect();
+            if !above.is_empty() {
+                writeln!(f, "  spread wider: {}", above.join(", "))?;
+            }
+        }
+        writeln!(f, "  final BBO: {:?}", self.bbo)
+    }
+}
@@ -0,0 +1,42 @@
+use crate::error::GeminiError;
+
+// Fails on the first symbol the exchange doesn't list, suggesting the closest listed one
+pub fn validate(requested: &[String], known: &[String]) -> Result<(), GeminiError> {
+    for symbol in requested {
+        if known.iter().any(|k| k.eq_ignore_ascii_case(symbol)) {
+            continue;
+        }
+        let message = match suggest(symbol, known) {
+            Some(close) => format!("unknown symbol `{}`, did you mean `{}`?", symbol, close),
+            None => format!("unknown symbol `{}`, see --list-symbols", symbol),
+        };
+        return Err(GeminiError::Config(message));
+    }
+    Ok(())
+}
+
+// The known symbol with the smallest edit distance, if it is close enough to be a typo
+pub fn suggest<'a>(symbol: &str, known: &'a [String]) -> Option<&'a str> {
+    let symbol = symbol.to_lowercase();
+    let max_distance = (symbol.len() / 3).max(1);
+    known.iter()
+        .map(|k| (edit_distance(&symbol, &k.to_lowercase()), k))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, k)| k.as_str())
+}
+
+// Levenshtein distance over chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,100 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::time::Duration;
+
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{BestBidOffer, BlockTrade, Quote, Trade};
+use crate::output::{EventContext, Formatter};
+
+// The trades of every symbol merged into one stream in exchange time order. Each print is
+// held back for `delay`, so one that arrives a little later on another connection can
+// still go before it. Anything on the feed moves the clock on, but only trades and block
+// trades are written.
+pub struct Tape<W> {
+    formatter: Formatter,
+    out: W,
+    delay_ms: u64,
+    // Newest exchange time seen on any symbol
+    clock_ms: u64,
+    // Formatted lines by exchange time, then arrival
+    pending: BTreeMap<(u64, u64), String>,
+    arrivals: u64,
+    // The color of each symbol, in the order they first printed
+    symbols: HashMap<String, usize>,
+}
+
+impl<W: Write + Send> Tape<W> {
+    // Writes the format's header, if it has one, right away
+    pub fn new(formatter: Formatter, mut out: W, delay: Duration) -> Result<Self, GeminiError> {
+        if let Some(header) = formatter.header() {
+            out.write_all(header.as_bytes())?;
+        }
+        Ok(Self {
+            formatter,
+            out,
+            delay_ms: delay.as_millis() as u64,
+            clock_ms: 0,
+            pending: BTreeMap::new(),
+            arrivals: 0,
+            symbols: HashMap::new(),
+        })
+    }
+
+    fn index(&mut self, symbol: &str) -> usize {
+        let next = self.symbols.len();
+        *self.symbols.entry(symbol.to_string()).or_insert(next)
+    }
+
+    fn hold(&mut self, ctx: &EventContext, line: String) -> Result<(), GeminiError> {
+        self.arrivals += 1;
+        self.pending.insert((ctx.timestampms.unwrap_or(ctx.received_ms), self.arrivals), line);
+        self.advance(ctx)
+    }
+
+    // Writes everything that has been held for long enough at the newest time seen
+    fn advance(&mut self, ctx: &EventContext) -> Result<(), GeminiError> {
+        self.clock_ms = self.clock_ms.max(ctx.timestampms.unwrap_or(ctx.received_ms));
+        while let Some(entry) = self.pending.first_entry() {
+            if entry.key().0 + self.delay_ms > self.clock_ms {
+                break;
+            }
+            self.out.write_all(entry.remove().as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> EventHandler for Tape<W> {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        let index = self.index(symbol);
+        let line = self.formatter.tape_trade(symbol, index, ctx, trade);
+        self.hold(ctx, line)
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        let index = self.index(symbol);
+        let line = self.formatter.tape_block_trade(symbol, index, ctx, trade);
+        self.hold(ctx, line)
+    }
+
+    fn on_quote(&mut self, _: &str, ctx: &EventContext, _: &Quote) -> Result<(), GeminiError> {
+        self.advance(ctx)
+    }
+
+    fn on_book_update(&mut self, _: &str, ctx: &EventContext, _: &BestBidOffer) -> Result<(), GeminiError> {
+        self.advance(ctx)
+    }
+
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        for line in std::mem::take(&mut self.pending).into_values() {
+            self.out.write_all(line.as_bytes())?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "tape"
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::analytics::anomaly::AnomalyEvent;
+use crate::analytics::candles::Candle;
+use crate::analytics::cross::CrossDivergence;
+use crate::analytics::depth::DepthSnapshot;
+use crate::analytics::dwell::DwellSnapshot;
+use crate::analytics::indicators::BookIndicators;
+use crate::analytics::ofi::OfiSnapshot;
+use crate::analytics::technical::TechnicalSnapshot;
+use crate::analytics::volatility::VolatilitySnapshot;
+use crate::analytics::vwap::TradeStatsSnapshot;
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{AuctionEvent, BestBidOffer, BlockTrade, FundingAmount, Quote, Trade};
+use crate::output::EventContext;
+
+// The kinds that can be coalesced, everything else always passes through
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampledKind {
+    Bbo,
+    Quote,
+    Indicators,
+    Depth,
+}
+
+const KINDS: [SampledKind; 4] = [SampledKind::Bbo, SampledKind::Quote, SampledKind::Indicators, SampledKind::Depth];
+
+impl SampledKind {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl FromStr for SampledKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bbo" => Ok(SampledKind::Bbo),
+            "quote" => Ok(SampledKind::Quote),
+            "indicators" => Ok(SampledKind::Indicators),
+            "depth" => Ok(SampledKind::Depth),
+            _ => Err(format!("cannot sample `{}`, expected bbo, quote, indicators or depth", s)),
+        }
+    }
+}
+
+// `KIND:INTERVAL` such as bbo:1s
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SampleRule {
+    pub kind: SampledKind,
+    pub interval: Duration,
+}
+
+impl FromStr for SampleRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((kind, interval)) = s.split_once(':') else {
+            return Err(format!("expected `KIND:INTERVAL`, got `{}`", s));
+        };
+        Ok(Self {
+            kind: kind.trim().parse()?,
+            interval: crate::analytics::parse_window(interval.trim())?,
+        })
+    }
+}
+
+// `COUNT/PERIOD` such as 10/s, 100/m or 5/10s, as the interval between two events
+pub fn parse_rate(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("expected `COUNT/PERIOD` such as 10/s, got `{}`", s);
+    let (count, period) = s.split_once('/').ok_or_else(invalid)?;
+    let count: u32 = count.trim().parse().ok().filter(|c| *c > 0).ok_or_else(invalid)?;
+    let period = match period.trim() {
+        "s" => Duration::from_secs(1),
+        "m" => Duration::from_secs(60),
+        "h" => Duration::from_secs(60 * 60),
+        period => crate::analytics::parse_window(period)?,
+    };
+    Ok(period / count)
+}
+
+// At most one update of a kind per symbol every interval. `rate` applies to every
+// sampled kind without a rule of its own.
+#[derive(Debug, Clone, Default)]
+pub struct ThrottleOptions {
+    pub rate: Option<Duration>,
+    pub samples: Vec<SampleRule>,
+}
+
+impl ThrottleOptions {
+    pub fn is_empty(&self) -> bool {
+        self.rate.is_none() && self.samples.is_empty()
+    }
+
+    fn interval(&self, kind: SampledKind) -> Option<Duration> {
+        self.samples.iter().rev().find(|r| r.kind == kind).map(|r| r.interval).or(self.rate)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Sampled {
+    Bbo(BestBidOffer),
+    Quote(Quote),
+    Indicators(BookIndicators),
+    Depth(DepthSnapshot),
+}
+
+impl Sampled {
+    fn kind(&self) -> SampledKind {
+        match self {
+            Sampled::Bbo(_) => SampledKind::Bbo,
+            Sampled::Quote(_) => SampledKind::Quote,
+            Sampled::Indicators(_) => SampledKind::Indicators,
+            Sampled::Depth(_) => SampledKind::Depth,
+        }
+    }
+
+    // A newer update of the same state replaces this one: the same level for quotes,
+    // anything of the kind otherwise
+    fn superseded_by(&self, newer: &Sampled) -> bool {
+        match (self, newer) {
+            (Sampled::Quote(old), Sampled::Quote(new)) => old.side == new.side && old.price == new.price,
+            (old, new) => old.kind() == new.kind(),
+        }
+    }
+
+    fn deliver(&self, handler: &mut dyn EventHandler, symbol: &str, ctx: &EventContext) -> Result<(), GeminiError> {
+        match self {
+            Sampled::Bbo(bbo) => handler.on_book_update(symbol, ctx, bbo),
+            Sampled::Quote(quote) => handler.on_quote(symbol, ctx, quote),
+            Sampled::Indicators(indicators) => handler.on_indicators(symbol, ctx, indicators),
+            Sampled::Depth(depth) => handler.on_depth(symbol, ctx, depth),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Slot {
+    last_ms: Option<u64>,
+    // Held back since the last update went out, the latest of each level for quotes
+    pending: Vec<(EventContext, Sampled)>,
+}
+
+// Wraps a handler and coalesces its book updates: within an interval only the latest
+// state goes out, once the interval is over. Trades and every other event pass through
+// untouched. Intervals run on exchange time, so replays thin out the same way.
+pub struct Throttled {
+    inner: Box<dyn EventHandler>,
+    intervals: [Option<u64>; 4],
+    slots: HashMap<String, [Slot; 4]>,
+}
+
+impl Throttled {
+    pub fn new(inner: Box<dyn EventHandler>, options: &ThrottleOptions) -> Self {
+        Self {
+            inner,
+            intervals: KINDS.map(|kind| options.interval(kind).map(|i| i.as_millis() as u64)),
+            slots: HashMap::new(),
+        }
+    }
+
+    fn offer(&mut self, symbol: &str, ctx: &EventContext, update: Sampled) -> Result<(), GeminiError> {
+        let kind = update.kind().index();
+        let Some(interval) = self.intervals[kind] else {
+            return update.deliver(self.inner.as_mut(), symbol, ctx);
+        };
+        let now = ctx.timestampms.unwrap_or(ctx.received_ms);
+        self.release(symbol, Some(now), Some(kind))?;
+        let slot = &mut self.slots.entry(symbol.to_string()).or_default()[kind];
+        slot.pending.retain(|(_, pending)| !pending.superseded_by(&update));
+        slot.pending.push((*ctx, update));
+        if slot.last_ms.is_none_or(|last| now >= last + interval) {
+            slot.last_ms = Some(now);
+            for (ctx, update) in std::mem::take(&mut slot.pending) {
+                update.deliver(self.inner.as_mut(), symbol, &ctx)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Sends what was held back for `symbol` once its interval is over at `now`, or
+    // everything with no `now`. The kind of an update being offered is left to `offer`,
+    // which has a newer state to send.
+    fn release(&mut self, symbol: &str, now: Option<u64>, offered: Option<usize>) -> Result<(), GeminiError> {
+        let Some(slots) = self.slots.get_mut(symbol) else {
+            return Ok(());
+        };
+        for (kind, (slot, interval)) in slots.iter_mut().zip(self.intervals).enumerate() {
+            if offered == Some(kind) {
+                continue;
+            }
+            let due = match (now, slot.last_ms, interval) {
+                (Some(now), Some(last), Some(interval)) => now >= last + interval,
+                _ => true,
+            };
+            if slot.pending.is_empty() || !due {
+                continue;
+            }
+            slot.last_ms = now.or(slot.last_ms);
+            for (ctx, update) in std::mem::take(&mut slot.pending) {
+                update.deliver(self.inner.as_mut(), symbol, &ctx)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn pass(&mut self, symbol: &str, ctx: &EventContext) -> Result<&mut dyn EventHandler, GeminiError> {
+        self.release(symbol, Some(ctx.timestampms.unwrap_or(ctx.received_ms)), None)?;
+        Ok(self.inner.as_mut())
+    }
+}
+
+impl EventHandler for Throttled {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_trade(symbol, ctx, trade)
+    }
+
+    fn on_quote(&mut self, symbol: &str, ctx: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        self.offer(symbol, ctx, Sampled::Quote(quote.clone()))
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.offer(symbol, ctx, Sampled::Bbo(bbo.clone()))
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_block_trade(symbol, ctx, trade)
+    }
+
+    fn on_auction(&mut self, symbol: &str, ctx: &EventContext, auction: &AuctionEvent) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_auction(symbol, ctx, auction)
+    }
+
+    fn on_stats(&mut self, symbol: &str, ctx: &EventContext, stats: &TradeStatsSnapshot) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_stats(symbol, ctx, stats)
+    }
+
+    fn on_candle(&mut self, symbol: &str, ctx: &EventContext, candle: &Candle) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_candle(symbol, ctx, candle)
+    }
+
+    fn on_technical(&mut self, symbol: &str, ctx: &EventContext, technical: &TechnicalSnapshot) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_technical(symbol, ctx, technical)
+    }
+
+    fn on_indicators(&mut self, symbol: &str, ctx: &EventContext, indicators: &BookIndicators) -> Result<(), GeminiError> {
+        self.offer(symbol, ctx, Sampled::Indicators(indicators.clone()))
+    }
+
+    fn on_depth(&mut self, symbol: &str, ctx: &EventContext, depth: &DepthSnapshot) -> Result<(), GeminiError> {
+        self.offer(symbol, ctx, Sampled::Depth(depth.clone()))
+    }
+
+    fn on_volatility(&mut self, symbol: &str, ctx: &EventContext, volatility: &VolatilitySnapshot) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_volatility(symbol, ctx, volatility)
+    }
+
+    fn on_dwell(&mut self, symbol: &str, ctx: &EventContext, dwell: &DwellSnapshot) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_dwell(symbol, ctx, dwell)
+    }
+
+    fn on_ofi(&mut self, symbol: &str, ctx: &EventContext, ofi: &OfiSnapshot) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_ofi(symbol, ctx, ofi)
+    }
+
+    fn on_anomaly(&mut self, symbol: &str, ctx: &EventContext, anomaly: &AnomalyEvent) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_anomaly(symbol, ctx, anomaly)
+    }
+
+    fn on_cross(&mut self, symbol: &str, ctx: &EventContext, cross: &CrossDivergence) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_cross(symbol, ctx, cross)
+    }
+
+    fn on_funding(&mut self, symbol: &str, ctx: &EventContext, funding: &FundingAmount) -> Result<(), GeminiError> {
+        self.pass(symbol, ctx)?.on_funding(symbol, ctx, funding)
+    }
+
+    // The last state before the connection dropped still goes out
+    fn on_disconnect(&mut self, symbol: &str, reason: &str) -> Result<(), GeminiError> {
+        self.release(symbol, None, None)?;
+        self.inner.on_disconnect(symbol, reason)
+    }
+
+    // The state held back for a symbol gone quiet goes out once its interval is over
+    fn on_tick(&mut self, now_ms: u64) -> Result<(), GeminiError> {
+        let symbols: Vec<String> = self.slots.keys().cloned().collect();
+        for symbol in symbols {
+            self.release(&symbol, Some(now_ms), None)?;
+        }
+        self.inner.on_tick(now_ms)
+    }
+
+    fn flush(&mut self) -> Result<(), GeminiError> {
+        let symbols: Vec<String> = self.slots.keys().cloned().collect();
+        for symbol in symbols {
+            self.release(&symbol, None, None)?;
+        }
+        self.inner.flush()
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
@@ -0,0 +1,56 @@
+use rust_decimal::Decimal;
+
+use crate::models::{BlockTrade, Quote, SymbolDetails, Trade};
+
+// Price and amount increments of one symbol, used to print every value at the
+// exchange's precision and to spot prices that are off the tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Increments {
+    pub price: Decimal,
+    pub amount: Decimal,
+}
+
+impl From<&SymbolDetails> for Increments {
+    fn from(details: &SymbolDetails) -> Self {
+        Self {
+            price: details.quote_increment,
+            amount: details.tick_size,
+        }
+    }
+}
+
+// Pads `value` with trailing zeros to the increment's decimal places. A value with more
+// places than that is left alone rather than rounded.
+fn pad(value: Decimal, increment: Decimal) -> Decimal {
+    let mut padded = value;
+    if value.scale() < increment.scale() {
+        padded.rescale(increment.scale());
+    }
+    padded
+}
+
+fn on_tick(value: Decimal, increment: Decimal) -> bool {
+    increment.is_zero() || (value % increment).is_zero()
+}
+
+impl Increments {
+    pub fn price_on_tick(&self, price: Decimal) -> bool {
+        on_tick(price, self.price)
+    }
+
+    pub fn trade(&self, t: &mut Trade) {
+        t.price = pad(t.price, self.price);
+        t.amount = pad(t.amount, self.amount);
+    }
+
+    pub fn block_trade(&self, t: &mut BlockTrade) {
+        t.price = pad(t.price, self.price);
+        t.amount = pad(t.amount, self.amount);
+    }
+
+    pub fn quote(&self, q: &mut Quote) {
+        q.price = pad(q.price, self.price);
+        q.remaining = pad(q.remaining, self.amount);
+        q.delta = q.delta.map(|d| pad(d, self.amount));
+    }
+}
@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use tokio_tungstenite::Connector;
+
+use crate::error::GeminiError;
+
+// How exchange connections verify the server: extra trusted roots from a PEM bundle,
+// or no verification at all for test setups
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsOptions {
+    // Certificates of the bundle, PEM encoded, trusted next to the system roots
+    ca_certs: Vec<Vec<u8>>,
+    insecure: bool,
+}
+
+impl TlsOptions {
+    pub fn new(ca_file: Option<&Path>, insecure: bool) -> Result<Self, GeminiError> {
+        let ca_certs = match ca_file {
+            Some(path) => {
+                let pem = std::fs::read(path).map_err(|e| GeminiError::Config(format!("{}: {}", path.display(), e)))?;
+                let certs = pem_certificates(&pem);
+                if certs.is_empty() {
+                    return Err(GeminiError::Config(format!("{}: no PEM certificates found", path.display())));
+                }
+                certs
+            },
+            None => Vec::new(),
+        };
+        Ok(Self { ca_certs, insecure })
+    }
+
+    // The connector for WebSocket connections, None leaves the choice to tungstenite
+    #[cfg(feature = "rustls")]
+    pub fn connector(&self) -> Result<Option<Connector>, GeminiError> {
+        use std::sync::Arc;
+
+        use rustls::{ClientConfig, RootCertStore};
+
+        let config = match self.insecure {
+            true => ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(insecure::NoVerification))
+                .with_no_client_auth(),
+            false => {
+                let mut roots = RootCertStore::empty();
+                let native = rustls_native_certs::load_native_certs()
+                    .map_err(|e| GeminiError::Config(format!("system certificates: {}", e)))?;
+                roots.add_parsable_certificates(native);
+                for pem in &self.ca_certs {
+                    for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+                        let cert = cert.map_err(|e| GeminiError::Config(format!("ca certificate: {}", e)))?;
+                        roots.add(cert).map_err(|e| GeminiError::Config(format!("ca certificate: {}", e)))?;
+                    }
+                }
+                ClientConfig::builder().with_root_certificates(roots).with_no_client_auth()
+            },
+        };
+        Ok(Some(Connector::Rustls(Arc::new(config))))
+    }
+
+    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+    pub fn connector(&self) -> Result<Option<Connector>, GeminiError> {
+        if *self == Self::default() {
+            return Ok(None);
+        }
+        let mut builder = native_tls::TlsConnector::builder();
+        builder.danger_accept_invalid_certs(self.insecure);
+        for pem in &self.ca_certs {
+            let cert = native_tls::Certificate::from_pem(pem)
+                .map_err(|e| GeminiError::Config(format!("ca certificate: {}", e)))?;
+            builder.add_root_certificate(cert);
+        }
+        let connector = builder.build().map_err(|e| GeminiError::Config(format!("tls: {}", e)))?;
+        Ok(Some(Connector::NativeTls(connector)))
+    }
+
+    // Without a TLS backend only ws:// endpoints can be reached, there is nothing to verify
+    #[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+    pub fn connector(&self) -> Result<Option<Connector>, GeminiError> {
+        Ok(None)
+    }
+
+    // Applies the same backend and trust to a REST client, and refuses the same bundles
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    pub fn configure(&self, mut builder: reqwest::ClientBuilder) -> Result<reqwest::ClientBuilder, GeminiError> {
+        #[cfg(feature = "rustls")]
+        {
+            builder = builder.use_rustls_tls();
+        }
+        for pem in &self.ca_certs {
+            let cert = reqwest::Certificate::from_pem(pem)
+                .map_err(|e| GeminiError::Config(format!("ca certificate: {}", e)))?;
+            builder = builder.add_root_certificate(cert);
+        }
+        Ok(builder.danger_accept_invalid_certs(self.insecure))
+    }
+
+    #[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+    pub fn configure(&self, builder: reqwest::ClientBuilder) -> Result<reqwest::ClientBuilder, GeminiError> {
+        Ok(builder)
+    }
+}
+
+// Splits a bundle into its certificates, native-tls only reads the first one of a PEM
+fn pem_certificates(pem: &[u8]) -> Vec<Vec<u8>> {
+    const END: &str = "-----END CERTIFICATE-----";
+    let text = String::from_utf8_lossy(pem);
+    let mut certs = Vec::new();
+    let mut rest = text.as_ref();
+    while let (Some(start), Some(end)) = (rest.find("-----BEGIN CERTIFICATE-----"), rest.find(END)) {
+        if end < start {
+            rest = &rest[end + END.len()..];
+            continue;
+        }
+        certs.push(rest.as_bytes()[start..end + END.len()].to_vec());
+        rest = &rest[end + END.len()..];
+    }
+    certs
+}
+
+#[cfg(feature = "rustls")]
+mod insecure {
+    use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use rustls::{DigitallySignedStruct, Error, SignatureScheme};
+
+    // Accepts any certificate, for --insecure only
+    #[derive(Debug)]
+    pub struct NoVerification;
+
+    impl ServerCertVerifier for NoVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            rustls::crypto::ring::default_provider().signature_verification_algorithms.supported_schemes()
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::widgets::Widget;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::analytics::candles::{Candle, CandleAggregator};
+use crate::models::Trade;
+
+// Intervals the chart cycles through, each aggregated all along so switching is instant
+pub const CHART_INTERVALS: [Duration; 5] = [
+    Duration::from_secs(60),
+    Duration::from_secs(5 * 60),
+    Duration::from_secs(15 * 60),
+    Duration::from_secs(60 * 60),
+    Duration::from_secs(4 * 60 * 60),
+];
+
+// Closed bars kept per interval, more than any terminal is wide
+const CHART_LEN: usize = 500;
+
+// Rows at the bottom of the chart given to volume bars
+const VOLUME_ROWS: u16 = 4;
+
+// Room for the price labels left of the bars
+const AXIS_WIDTH: u16 = 12;
+
+const VOLUME_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// Candles of one symbol at one interval, the last one still open
+#[derive(Debug, Clone)]
+pub struct CandleSeries {
+    aggregator: CandleAggregator,
+    closed: VecDeque<Candle>,
+}
+
+impl CandleSeries {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            aggregator: CandleAggregator::new(interval),
+            closed: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, ts_ms: u64, trade: &Trade) {
+        let completed = self.aggregator.record(ts_ms, trade);
+        self.push(completed);
+    }
+
+    // Moves the chart along while no trades come in
+    pub fn advance(&mut self, ts_ms: u64) {
+        let completed = self.aggregator.advance(ts_ms);
+        self.push(completed);
+    }
+
+    fn push(&mut self, completed: Vec<Candle>) {
+        self.closed.extend(completed);
+        while self.closed.len() > CHART_LEN {
+            self.closed.pop_front();
+        }
+    }
+
+    // The last `count` bars, oldest first
+    pub fn last(&self, count: usize) -> Vec<&Candle> {
+        let mut candles: Vec<&Candle> = self.closed.iter().chain(self.aggregator.current()).collect();
+        let skip = candles.len().saturating_sub(count);
+        candles.drain(..skip);
+        candles
+    }
+}
+
+// One column per bar: `│` for the wick, `┃` for the body, green when it closed up.
+// Volume runs along the bottom rows in eighth-block steps.
+pub struct CandleChart<'a> {
+    series: &'a CandleSeries,
+}
+
+impl<'a> CandleChart<'a> {
+    pub fn new(series: &'a CandleSeries) -> Self {
+        Self { series }
+    }
+}
+
+impl Widget for CandleChart<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width <= AXIS_WIDTH || area.height <= VOLUME_ROWS + 1 {
+            return;
+        }
+        let bars_width = area.width - AXIS_WIDTH;
+        let candles = self.series.last(bars_width as usize);
+        if candles.is_empty() {
+            buf.set_string(area.x, area.y, "waiting for trades", Style::default().fg(Color::Gray));
+            return;
+        }
+        let price_rows = area.height - VOLUME_ROWS;
+        let price = |d: Decimal| d.to_f64().unwrap_or(0.);
+        let high_label = candles.iter().map(|c| c.high).max().unwrap_or_default();
+        let low_label = candles.iter().map(|c| c.low).min().unwrap_or_default();
+        let (high, low) = (price(high_label), price(low_label));
+        let max_volume = candles.iter().map(|c| price(c.volume)).fold(0., f64::max);
+        let range = (high - low).max(f64::EPSILON);
+        // Row 0 is the top, the highest price
+        let row_of = |p: f64| (((high - p) / range) * (price_rows - 1) as f64).round() as u16;
+
+        buf.set_string(area.x, area.y, format!("{:>11}", high_label), Style::default());
+        buf.set_string(area.x, area.y + price_rows - 1, format!("{:>11}", low_label), Style::default());
+        buf.set_string(area.x, area.y + price_rows, format!("{:>11}", "volume"), Style::default().fg(Color::Gray));
+
+        for (i, candle) in candles.iter().enumerate() {
+            let x = area.x + AXIS_WIDTH + i as u16;
+            let (open, close) = (price(candle.open), price(candle.close));
+            let color = if close >= open { Color::Green } else { Color::Red };
+            let (body_top, body_bottom) = (row_of(open.max(close)), row_of(open.min(close)));
+            for row in row_of(price(candle.high))..=row_of(price(candle.low)) {
+                let symbol = if (body_top..=body_bottom).contains(&row) { '┃' } else { '│' };
+                if let Some(cell) = buf.cell_mut((x, area.y + row)) {
+                    cell.set_char(symbol).set_fg(color);
+                }
+            }
+
+            // Volume in eighths of a row, at least a sliver for any trading at all
+            let eighths = match max_volume > 0. {
+                true => (price(candle.volume) / max_volume * (VOLUME_ROWS * 8) as f64).round() as u16,
+                false => 0,
+            };
+            let eighths = if candle.trades > 0 { eighths.max(1) } else { eighths };
+            for level in 0..VOLUME_ROWS {
+                let filled = eighths.saturating_sub(level * 8).min(8);
+                if filled == 0 {
+                    break;
+                }
+                let y = area.y + area.height - 1 - level;
+                if let Some(cell) = buf.cell_mut((x, y)) {
+                    cell.set_char(VOLUME_BLOCKS[filled as usize - 1]).set_fg(color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::widgets::Widget;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::book::OrderBook;
+
+// How far the ladder reaches on each side of the mid, in basis points, cycled with +/-
+pub const LADDER_ZOOMS: [u32; 8] = [5, 10, 25, 50, 100, 250, 500, 1000];
+
+// Width of the price and size columns in front of the bars
+const LABEL_WIDTH: u16 = 26;
+
+// Asks above the mid, bids below it, each row one price bucket. The bar of a row
+// shows the cumulative size from the mid out to that bucket, the number its own size.
+pub struct DepthLadder<'a> {
+    book: &'a OrderBook,
+    half_range_bps: u32,
+}
+
+impl<'a> DepthLadder<'a> {
+    pub fn new(book: &'a OrderBook, half_range_bps: u32) -> Self {
+        Self { book, half_range_bps }
+    }
+}
+
+// Sizes per bucket, the closest to the mid first, from levels ordered best first
+fn bucket_sizes<'b>(levels: impl Iterator<Item = (&'b Decimal, &'b Decimal)>, mid: Decimal, bucket: Decimal, rows: usize) -> Vec<Decimal> {
+    let mut sizes = vec![Decimal::ZERO; rows];
+    for (price, size) in levels {
+        let index = ((*price - mid).abs() / bucket).floor().to_usize().unwrap_or(usize::MAX);
+        match sizes.get_mut(index) {
+            Some(bucket_size) => *bucket_size += *size,
+            None => break,
+        }
+    }
+    sizes
+}
+
+impl Widget for DepthLadder<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (bid, ask) = (self.book.best_bid(), self.book.best_ask());
+        let (mid, scale) = match (bid, ask) {
+            (Some((bid, _)), Some((ask, _))) => ((bid + ask) / Decimal::TWO, bid.scale().max(ask.scale())),
+            (Some((price, _)), None) | (None, Some((price, _))) => (price, price.scale()),
+            (None, None) => {
+                buf.set_string(area.x, area.y, "waiting for the book", Style::default().fg(Color::Gray));
+                return;
+            },
+        };
+        let ask_rows = (area.height / 2) as usize;
+        let bid_rows = area.height as usize - ask_rows;
+        if ask_rows == 0 || area.width <= LABEL_WIDTH {
+            return;
+        }
+        let half_range = mid * Decimal::from(self.half_range_bps) / Decimal::from(10_000);
+        let bucket = half_range / Decimal::from(ask_rows);
+        if bucket.is_zero() {
+            return;
+        }
+        let asks = bucket_sizes(self.book.asks(), mid, bucket, ask_rows);
+        let bids = bucket_sizes(self.book.bids(), mid, bucket, bid_rows);
+        let cumulative = |sizes: &[Decimal]| -> Vec<Decimal> {
+            sizes.iter().scan(Decimal::ZERO, |total, size| {
+                *total += *size;
+                Some(*total)
+            }).collect()
+        };
+        let (ask_totals, bid_totals) = (cumulative(&asks), cumulative(&bids));
+        let max_total = ask_totals.iter().chain(&bid_totals).max().copied().unwrap_or_default();
+        let bar_width = (area.width - LABEL_WIDTH) as f64;
+
+        let mut row = |y: u16, price: Decimal, size: Decimal, total: Decimal, color: Color| {
+            let size = if size.is_zero() { String::new() } else { size.normalize().to_string() };
+            buf.set_string(area.x, y, format!("{:>12} {:>12} ", price.round_dp(scale), size), Style::default());
+            let filled = match max_total.is_zero() {
+                true => 0,
+                false => ((total / max_total).to_f64().unwrap_or(0.) * bar_width).round() as u16,
+            };
+            let bar = "█".repeat(filled as usize);
+            buf.set_string(area.x + LABEL_WIDTH, y, bar, Style::default().fg(color));
+        };
+        // The farthest ask on top so prices fall down the ladder, each row labelled with
+        // the far edge of its bucket
+        for (i, (size, total)) in asks.iter().zip(&ask_totals).enumerate() {
+            let price = mid + bucket * Decimal::from(i + 1);
+            row(area.y + (ask_rows - 1 - i) as u16, price, *size, *total, Color::Red);
+        }
+        for (i, (size, total)) in bids.iter().zip(&bid_totals).enumerate() {
+            let price = mid - bucket * Decimal::from(i + 1);
+            row(area.y + (ask_rows + i) as u16, price, *size, *total, Color::Green);
+        }
+    }
+}
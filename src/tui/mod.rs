@@ -0,0 +1,528 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Tabs};
+use ratatui::Frame;
+use rust_decimal::Decimal;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::analytics::anomaly::{AnomalyEvent, AnomalyKind};
+use crate::analytics::histogram::VolumeProfile;
+use crate::analytics::vwap::RollingWindow;
+use crate::book::OrderBook;
+use crate::config;
+use crate::control::{ControlCommand, LocalControl};
+use crate::error::GeminiError;
+use crate::handler::EventHandler;
+use crate::models::{BestBidOffer, BlockTrade, FundingAmount, MarketSide, OrderSide, Quote, Trade};
+use crate::output::EventContext;
+use crate::positions::{PositionSnapshot, Positions};
+
+pub mod chart;
+pub mod ladder;
+pub mod profile;
+
+use chart::{CandleChart, CandleSeries, CHART_INTERVALS};
+use ladder::{DepthLadder, LADDER_ZOOMS};
+use profile::ProfileChart;
+
+const TAPE_LEN: usize = 200;
+const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+// Window of the buy and sell volume shown next to the BBO
+const FLOW_WINDOW: Duration = Duration::from_secs(60);
+
+struct TapeEntry {
+    ctx: EventContext,
+    price: Decimal,
+    amount: Decimal,
+    maker_side: MarketSide,
+    block: bool,
+}
+
+struct SymbolView {
+    bbo: BestBidOffer,
+    // Only known for perpetuals
+    funding: Option<FundingAmount>,
+    tape: VecDeque<TapeEntry>,
+    // One series per entry of CHART_INTERVALS
+    candles: Vec<CandleSeries>,
+    // Rebuilt from the quotes, the pipeline's own book lives on another task
+    book: OrderBook,
+    flow: RollingWindow,
+    // Buy minus sell volume since the dashboard started
+    cumulative_delta: Decimal,
+    profile: VolumeProfile,
+    // Sides reported stale, until their next change
+    stale: Vec<MarketSide>,
+}
+
+impl Default for SymbolView {
+    fn default() -> Self {
+        Self {
+            bbo: BestBidOffer::default(),
+            funding: None,
+            tape: VecDeque::new(),
+            candles: CHART_INTERVALS.iter().map(|interval| CandleSeries::new(*interval)).collect(),
+            book: OrderBook::new(),
+            flow: RollingWindow::new(FLOW_WINDOW),
+            cumulative_delta: Decimal::ZERO,
+            profile: VolumeProfile::default(),
+            stale: Vec::new(),
+        }
+    }
+}
+
+impl SymbolView {
+    fn print(&mut self, entry: TapeEntry) {
+        self.tape.push_front(entry);
+        self.tape.truncate(TAPE_LEN);
+    }
+}
+
+#[derive(Default)]
+pub struct TuiState {
+    symbols: Vec<String>,
+    views: HashMap<String, SymbolView>,
+    selected: usize,
+    // Index into CHART_INTERVALS
+    interval: usize,
+    // Index into LADDER_ZOOMS
+    zoom: usize,
+    // Show the volume profile under the depth ladder
+    profile: bool,
+    // The account's positions, shown under the BBO
+    positions: Option<Positions>,
+    // Subscribe and unsubscribe lines for the main loop, None in a replay
+    commands: Option<mpsc::Sender<String>>,
+    // Unsubscribed symbols, so events still queued for them bring no tab back
+    removed: HashSet<String>,
+    // The symbol being typed after `a`
+    input: Option<String>,
+    // The outcome of the last command or save
+    status: String,
+    // The config file the watchlist is saved to
+    watchlist_file: Option<PathBuf>,
+}
+
+impl TuiState {
+    pub fn new(symbols: &[String]) -> Self {
+        Self {
+            symbols: symbols.to_vec(),
+            zoom: 2,
+            ..Self::default()
+        }
+    }
+
+    pub fn profile(mut self, profile: bool) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn positions(mut self, positions: Option<Positions>) -> Self {
+        self.positions = positions;
+        self
+    }
+
+    pub fn watchlist_file(mut self, path: Option<PathBuf>) -> Self {
+        self.watchlist_file = path;
+        self
+    }
+
+    fn view(&mut self, symbol: &str) -> &mut SymbolView {
+        if !self.symbols.iter().any(|s| s == symbol) && !self.removed.contains(symbol) {
+            self.symbols.push(symbol.to_string());
+        }
+        self.views.entry(symbol.to_string()).or_default()
+    }
+
+    fn send(&mut self, line: String) {
+        let sent = self.commands.as_ref().is_some_and(|commands| commands.try_send(line).is_ok());
+        if !sent {
+            self.status = String::from("the watchlist only changes on a live feed");
+        }
+    }
+
+    // Moves the selected symbol `by` places along the tabs, keeping it selected
+    fn shift(&mut self, by: isize) {
+        let Some(to) = self.selected.checked_add_signed(by).filter(|to| *to < self.symbols.len()) else {
+            return;
+        };
+        self.symbols.swap(self.selected, to);
+        self.selected = to;
+    }
+
+    fn save(&mut self) {
+        self.status = match &self.watchlist_file {
+            Some(path) => match config::save_symbols(path, &self.symbols) {
+                Ok(()) => format!("saved the watchlist to {}", path.display()),
+                Err(e) => format!("error: {}", e),
+            },
+            None => String::from("no --config file to save the watchlist to"),
+        };
+    }
+
+    // Applies the reply of the main loop to a subscribe or unsubscribe of the watchlist
+    fn answered(&mut self, line: &str, reply: String) {
+        if reply == "ok" {
+            match line.parse::<ControlCommand>() {
+                Ok(ControlCommand::Subscribe(symbol)) => {
+                    self.removed.remove(&symbol);
+                    self.view(&symbol);
+                    self.selected = self.symbols.iter().position(|s| *s == symbol).unwrap_or(self.selected);
+                },
+                Ok(ControlCommand::Unsubscribe(symbol)) => {
+                    self.symbols.retain(|s| *s != symbol);
+                    self.views.remove(&symbol);
+                    self.removed.insert(symbol);
+                    self.selected = self.selected.min(self.symbols.len().saturating_sub(1));
+                },
+                _ => {},
+            }
+        }
+        self.status = format!("{}: {}", line.trim(), reply);
+    }
+
+    // Typing a symbol after `a`, Enter subscribes to it
+    fn type_key(&mut self, code: KeyCode) {
+        let Some(input) = self.input.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Enter => {
+                let symbol = input.trim().to_lowercase();
+                self.input = None;
+                if !symbol.is_empty() {
+                    self.send(format!("subscribe {}", symbol));
+                }
+            },
+            KeyCode::Esc => self.input = None,
+            KeyCode::Backspace => {
+                input.pop();
+            },
+            KeyCode::Char(c) if !c.is_whitespace() => input.push(c),
+            _ => {},
+        }
+    }
+}
+
+// Subscribes and unsubscribes on the keys of the dashboard, hand it to the main loop
+// with the other control servers
+pub fn control(state: &Arc<Mutex<TuiState>>) -> LocalControl {
+    let (tx, lines) = mpsc::channel(16);
+    state.lock().unwrap().commands = Some(tx);
+    let state = state.clone();
+    LocalControl {
+        lines,
+        reply: Box::new(move |line, reply| state.lock().unwrap().answered(line, reply)),
+    }
+}
+
+// Feeds the dashboard from the pipeline like any other handler
+pub struct TuiSink {
+    state: Arc<Mutex<TuiState>>,
+    top_of_book: bool,
+}
+
+impl TuiSink {
+    pub fn new(state: Arc<Mutex<TuiState>>) -> Self {
+        Self { state, top_of_book: false }
+    }
+
+    // Quotes of a top of book feed replace their whole side
+    pub fn top_of_book(mut self, top_of_book: bool) -> Self {
+        self.top_of_book = top_of_book;
+        self
+    }
+}
+
+impl EventHandler for TuiSink {
+    fn on_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &Trade) -> Result<(), GeminiError> {
+        let mut state = self.state.lock().unwrap();
+        let view = state.view(symbol);
+        let ts = ctx.timestampms.unwrap_or(ctx.received_ms);
+        for series in view.candles.iter_mut() {
+            series.record(ts, trade);
+        }
+        let aggressor = trade.aggressor();
+        view.flow.push(ts, trade.price, trade.amount, aggressor);
+        match aggressor {
+            Some(OrderSide::Buy) => view.cumulative_delta += trade.amount,
+            Some(OrderSide::Sell) => view.cumulative_delta -= trade.amount,
+            None => {},
+        }
+        view.profile.record(trade.price, trade.amount);
+        view.print(TapeEntry {
+            ctx: *ctx,
+            price: trade.price,
+            amount: trade.amount,
+            maker_side: trade.maker_side,
+            block: false,
+        });
+        Ok(())
+    }
+
+    fn on_block_trade(&mut self, symbol: &str, ctx: &EventContext, trade: &BlockTrade) -> Result<(), GeminiError> {
+        self.state.lock().unwrap().view(symbol).print(TapeEntry {
+            ctx: *ctx,
+            price: trade.price,
+            amount: trade.amount,
+            maker_side: MarketSide::Unknown,
+            block: true,
+        });
+        Ok(())
+    }
+
+    fn on_quote(&mut self, symbol: &str, _: &EventContext, quote: &Quote) -> Result<(), GeminiError> {
+        let mut state = self.state.lock().unwrap();
+        let view = state.view(symbol);
+        view.stale.retain(|side| *side != quote.side);
+        match self.top_of_book {
+            true => view.book.replace_top(quote),
+            false => view.book.apply(quote),
+        }
+        Ok(())
+    }
+
+    fn on_book_update(&mut self, symbol: &str, ctx: &EventContext, bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        let mut state = self.state.lock().unwrap();
+        let view = state.view(symbol);
+        view.bbo = bbo.clone();
+        let ts = ctx.timestampms.unwrap_or(ctx.received_ms);
+        for series in view.candles.iter_mut() {
+            series.advance(ts);
+        }
+        view.flow.evict(ts);
+        Ok(())
+    }
+
+    fn on_funding(&mut self, symbol: &str, _: &EventContext, funding: &FundingAmount) -> Result<(), GeminiError> {
+        self.state.lock().unwrap().view(symbol).funding = Some(funding.clone());
+        Ok(())
+    }
+
+    fn on_anomaly(&mut self, symbol: &str, _: &EventContext, anomaly: &AnomalyEvent) -> Result<(), GeminiError> {
+        if let (AnomalyKind::StaleQuotes, Some(side)) = (anomaly.anomaly, anomaly.side) {
+            self.state.lock().unwrap().view(symbol).stale.push(side);
+        }
+        Ok(())
+    }
+
+    // The initial message after the reconnect brings the whole book again
+    fn on_disconnect(&mut self, symbol: &str, _reason: &str) -> Result<(), GeminiError> {
+        self.state.lock().unwrap().view(symbol).book.clear();
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "tui"
+    }
+}
+
+// Blocking render loop, run it on a blocking thread. Cancels the token when the user quits.
+pub fn run(state: Arc<Mutex<TuiState>>, shutdown: CancellationToken) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = (|| {
+        while !shutdown.is_cancelled() {
+            terminal.draw(|frame| draw(frame, &state.lock().unwrap()))?;
+            if !event::poll(FRAME_INTERVAL)? {
+                continue;
+            }
+            if let TermEvent::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let mut state = state.lock().unwrap();
+                if state.input.is_some() {
+                    state.type_key(key.code);
+                    continue;
+                }
+                let count = state.symbols.len().max(1);
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => shutdown.cancel(),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => shutdown.cancel(),
+                    KeyCode::Right if key.modifiers.contains(KeyModifiers::SHIFT) => state.shift(1),
+                    KeyCode::Left if key.modifiers.contains(KeyModifiers::SHIFT) => state.shift(-1),
+                    KeyCode::Char(']') => state.shift(1),
+                    KeyCode::Char('[') => state.shift(-1),
+                    KeyCode::Right | KeyCode::Tab => state.selected = (state.selected + 1) % count,
+                    KeyCode::Left | KeyCode::BackTab => state.selected = (state.selected + count - 1) % count,
+                    KeyCode::Char('i') => state.interval = (state.interval + 1) % CHART_INTERVALS.len(),
+                    KeyCode::Char('+') | KeyCode::Char('=') => state.zoom = state.zoom.saturating_sub(1),
+                    KeyCode::Char('-') => state.zoom = (state.zoom + 1).min(LADDER_ZOOMS.len() - 1),
+                    KeyCode::Char('a') => state.input = Some(String::new()),
+                    KeyCode::Char('d') | KeyCode::Delete => {
+                        if let Some(symbol) = state.symbols.get(state.selected).cloned() {
+                            state.send(format!("unsubscribe {}", symbol));
+                        }
+                    },
+                    KeyCode::Char('w') => state.save(),
+                    _ => {},
+                }
+            }
+        }
+        Ok(())
+    })();
+    ratatui::restore();
+    result
+}
+
+fn draw(frame: &mut Frame, state: &TuiState) {
+    let [tabs_area, bbo_area, main_area, help_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(7 + state.positions.is_some() as u16),
+        Constraint::Min(6),
+        Constraint::Length(2),
+    ]).areas(frame.area());
+    let [left_area, right_area] = Layout::horizontal([Constraint::Fill(1), Constraint::Length(48)]).areas(main_area);
+    let [ladder_area, profile_area] = match state.profile {
+        true => Layout::vertical([Constraint::Fill(1), Constraint::Fill(1)]).areas(right_area),
+        false => [right_area, Rect::default()],
+    };
+    let [chart_area, tape_area] = Layout::vertical([Constraint::Fill(1), Constraint::Fill(1)]).areas(left_area);
+
+    // Symbols with a stale side stand out in every tab
+    let tabs = Tabs::new(state.symbols.iter().map(|s| {
+        match state.views.get(s).is_some_and(|v| !v.stale.is_empty()) {
+            true => Line::styled(format!("{} !", s.to_uppercase()), Style::default().fg(Color::Yellow)),
+            false => Line::from(s.to_uppercase()),
+        }
+    }))
+        .select(state.selected)
+        .block(Block::bordered().title("Symbols"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_widget(tabs, tabs_area);
+
+    let empty = SymbolView::default();
+    let view = state.symbols.get(state.selected)
+        .and_then(|s| state.views.get(s))
+        .unwrap_or(&empty);
+
+    let bbo = &view.bbo;
+    let spread = match bbo.best_bid.is_zero() || bbo.best_offer.is_zero() {
+        true => String::from("-"),
+        false => (bbo.best_offer - bbo.best_bid).to_string(),
+    };
+    let mut bbo_lines = vec![
+        Line::from(vec![
+            Span::styled("Bid   ", Style::default().fg(Color::Green)),
+            Span::raw(format!("{:>16} x {}", bbo.best_bid, bbo.bid_amount_remaining)),
+        ]),
+        Line::from(vec![
+            Span::styled("Offer ", Style::default().fg(Color::Red)),
+            Span::raw(format!("{:>16} x {}", bbo.best_offer, bbo.ask_amount_remaining)),
+        ]),
+        Line::from(format!("Spread {:>15}", spread)),
+        Line::from(vec![
+            Span::raw(format!("Flow {}  ", humantime::format_duration(FLOW_WINDOW))),
+            Span::styled(format!("buy {}", view.flow.buy_volume()), Style::default().fg(Color::Green)),
+            Span::raw(" / "),
+            Span::styled(format!("sell {}", view.flow.sell_volume()), Style::default().fg(Color::Red)),
+            Span::raw(format!("   delta {}", view.cumulative_delta)),
+        ]),
+    ];
+    if let Some(funding) = &view.funding {
+        let next = clock(funding.next_funding_ms);
+        bbo_lines.push(Line::from(format!("Fund. {:>16}   next {}", funding.amount, &next[..8])));
+    }
+    if let Some(positions) = &state.positions {
+        let position = state.symbols.get(state.selected).and_then(|s| positions.get(s));
+        bbo_lines.push(position_line(position.as_ref()));
+    }
+    let mut bbo_block = Block::bordered().title("Best bid / offer");
+    if !view.stale.is_empty() {
+        let sides: Vec<&str> = view.stale.iter().map(|side| side.as_str()).collect();
+        bbo_block = bbo_block.title(Line::styled(format!(" STALE {} ", sides.join("/")), Style::default().fg(Color::Black).bg(Color::Yellow)));
+    }
+    frame.render_widget(Paragraph::new(bbo_lines).block(bbo_block), bbo_area);
+
+    let interval = CHART_INTERVALS[state.interval];
+    let chart_block = Block::bordered().title(format!("Candles {}", humantime::format_duration(interval)));
+    frame.render_widget(CandleChart::new(&view.candles[state.interval]), chart_block.inner(chart_area));
+    frame.render_widget(chart_block, chart_area);
+
+    let half_range_bps = LADDER_ZOOMS[state.zoom];
+    let ladder_block = Block::bordered().title(format!("Depth \u{00b1}{} bps", half_range_bps));
+    frame.render_widget(DepthLadder::new(&view.book, half_range_bps), ladder_block.inner(ladder_area));
+    frame.render_widget(ladder_block, ladder_area);
+
+    if state.profile {
+        let profile_block = Block::bordered().title("Volume profile");
+        frame.render_widget(ProfileChart::new(&view.profile), profile_block.inner(profile_area));
+        frame.render_widget(profile_block, profile_area);
+    }
+
+    let visible = tape_area.height.saturating_sub(2) as usize;
+    let trades: Vec<ListItem> = view.tape.iter().take(visible).map(|t| {
+        // A resting ask getting hit means the aggressor was buying
+        let color = match t.maker_side {
+            MarketSide::Ask => Color::Green,
+            MarketSide::Bid => Color::Red,
+            MarketSide::Unknown => Color::Gray,
+        };
+        let mut spans = vec![
+            Span::raw(format!("{}  ", clock(t.ctx.timestampms.unwrap_or(t.ctx.received_ms)))),
+            Span::styled(format!("{:>16}", t.price), Style::default().fg(color)),
+            Span::raw(format!(" {:>14}  ${}", t.amount, (t.amount * t.price).round_dp(2))),
+        ];
+        if t.block {
+            spans.push(Span::styled("  BLOCK", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        }
+        ListItem::new(Line::from(spans))
+    }).collect();
+    frame.render_widget(List::new(trades).block(Block::bordered().title("Trades")), tape_area);
+
+    let status = match &state.input {
+        Some(input) => Line::from(vec![
+            Span::styled("Add symbol: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{}\u{2588}   Enter subscribes, Esc cancels", input)),
+        ]),
+        None => Line::styled(state.status.as_str(), Style::default().fg(Color::Gray)),
+    };
+    let help = Line::from("q quit   \u{2190}/\u{2192} switch symbol   [/] move it   a add   d remove   w save watchlist   i chart interval   +/- zoom depth");
+    frame.render_widget(Paragraph::new(vec![help, status]), help_area);
+}
+
+// Flat until the first fill, PnL green or red by its sign
+fn position_line(position: Option<&PositionSnapshot>) -> Line<'static> {
+    let Some(p) = position else {
+        return Line::from(format!("Pos.  {:>16}", 0));
+    };
+    let pnl = |value: Decimal| {
+        let color = match value {
+            v if v > Decimal::ZERO => Color::Green,
+            v if v < Decimal::ZERO => Color::Red,
+            _ => Color::Gray,
+        };
+        Span::styled(format!("{:+}", value.round_dp(2)), Style::default().fg(color))
+    };
+    let entry = p.average_entry.map(|e| e.to_string()).unwrap_or_else(|| String::from("-"));
+    let mut spans = vec![Span::raw(format!("Pos.  {:>16} @ {}   uPnL ", p.amount, entry))];
+    spans.push(match p.unrealized_pnl {
+        Some(unrealized) => pnl(unrealized),
+        None => Span::raw("-"),
+    });
+    spans.push(Span::raw("  rPnL "));
+    spans.push(pnl(p.realized_pnl));
+    spans.push(Span::raw(format!("  fees {}", p.fees)));
+    Line::from(spans)
+}
+
+// HH:MM:SS.mmm in UTC
+fn clock(ms: u64) -> String {
+    let day_ms = ms % 86_400_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        day_ms / 3_600_000,
+        day_ms / 60_000 % 60,
+        day_ms / 1000 % 60,
+        day_ms % 1000,
+    )
+}
@@ -0,0 +1,54 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::Widget;
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::analytics::histogram::VolumeProfile;
+
+// Width of the price and volume columns in front of the bars
+const LABEL_WIDTH: u16 = 26;
+
+// Volume traded at each price over the session, highest price on top, merged into as
+// many levels as there are rows. The point of control is yellow, the value area bright.
+pub struct ProfileChart<'a> {
+    profile: &'a VolumeProfile,
+}
+
+impl<'a> ProfileChart<'a> {
+    pub fn new(profile: &'a VolumeProfile) -> Self {
+        Self { profile }
+    }
+}
+
+impl Widget for ProfileChart<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if self.profile.is_empty() {
+            buf.set_string(area.x, area.y, "waiting for trades", Style::default().fg(Color::Gray));
+            return;
+        }
+        if area.height == 0 || area.width <= LABEL_WIDTH {
+            return;
+        }
+        let profile = self.profile.coarsened(area.height as usize);
+        let poc = profile.point_of_control().map(|l| l.price);
+        let value_area = profile.value_area();
+        let most = profile.levels.iter().filter_map(|l| l.volume.to_f64()).fold(0., f64::max);
+        let bar_width = area.width - LABEL_WIDTH;
+        for (row, level) in profile.levels.iter().rev().enumerate() {
+            let y = area.y + row as u16;
+            let inside = value_area.is_some_and(|(low, high)| (low..=high).contains(&level.price));
+            let style = match (Some(level.price) == poc, inside) {
+                (true, _) => Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                (false, true) => Style::default().fg(Color::White),
+                (false, false) => Style::default().fg(Color::Gray),
+            };
+            buf.set_string(area.x, y, format!("{:>12} {:>12} ", level.price, level.volume.round_dp(4)), style);
+            let filled = match most > 0. {
+                true => (level.volume.to_f64().unwrap_or(0.) / most * bar_width as f64).ceil() as u16,
+                false => 0,
+            };
+            buf.set_string(area.x + LABEL_WIDTH, y, "\u{2588}".repeat(filled.min(bar_width) as usize), style);
+        }
+    }
+}
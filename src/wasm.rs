@@ -0,0 +1,24 @@
+use wasm_bindgen::prelude::*;
+
+use crate::models::{EventShape, MarketMessage};
+
+// Parses one market data frame, as received on a browser WebSocket, and returns it
+// normalized the way the collector's models serialize it, as a JSON string
+#[wasm_bindgen(js_name = parseMarketMessage)]
+pub fn parse_market_message(frame: &str) -> Result<String, JsError> {
+    let message = MarketMessage::from_slice(frame.as_bytes()).map_err(|e| JsError::new(&e.to_string()))?;
+    serde_json::to_string(&message).map_err(|e| JsError::new(&e.to_string()))
+}
+
+// The events of one frame as a JSON array of flat objects tagged with `type` and `symbol`,
+// or with `external` in the `{"Trade":{...}}` shape of earlier consumers
+#[wasm_bindgen(js_name = parseEvents)]
+pub fn parse_events(frame: &str, symbol: &str, external: bool) -> Result<String, JsError> {
+    let message = MarketMessage::from_slice(frame.as_bytes()).map_err(|e| JsError::new(&e.to_string()))?;
+    let shape = if external { EventShape::External } else { EventShape::Tagged };
+    let events = message.events.iter()
+        .map(|event| event.to_json(symbol, shape))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(format!("[{}]", events.join(",")))
+}
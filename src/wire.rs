@@ -0,0 +1,119 @@
+use std::borrow::Cow;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::client::now_ns;
+use crate::error::GeminiError;
+use crate::models::{AuctionEvent, AuctionIndicative, AuctionOpen, AuctionResult, BestBidOffer, BlockTrade, Quote, Trade};
+use crate::output::EventContext;
+
+// Every binary capture file, and every binary message on its own, starts with these
+// four bytes followed by the format version as a little endian u16
+pub const MAGIC: [u8; 4] = *b"GMWS";
+pub const VERSION: u16 = 2;
+// Captures read the same in version 1, only events grew since
+pub const MIN_CAPTURE_VERSION: u16 = 1;
+pub const HEADER_LEN: usize = MAGIC.len() + 2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireFormat {
+    #[default]
+    Json,
+    // bincode 2 with its standard config behind the MAGIC/VERSION header
+    Bincode,
+}
+
+pub fn header() -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[..MAGIC.len()].copy_from_slice(&MAGIC);
+    header[MAGIC.len()..].copy_from_slice(&VERSION.to_le_bytes());
+    header
+}
+
+// Fails for anything that is not a header of a version from `oldest` up to this build's
+pub fn check_header(bytes: &[u8], oldest: u16) -> Result<(), GeminiError> {
+    if bytes.len() < HEADER_LEN || bytes[..MAGIC.len()] != MAGIC {
+        return Err(GeminiError::Protocol(String::from("not binary gemini_websocket data")));
+    }
+    match u16::from_le_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]) {
+        version if (oldest..=VERSION).contains(&version) => Ok(()),
+        other => Err(GeminiError::Protocol(format!("wire format version {} is not supported, this build reads {}", other, VERSION))),
+    }
+}
+
+// The market data of one event. Analytics like candles or stats are only published as
+// text, as they are in CSV output.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum WireData<'a> {
+    Trade(Cow<'a, Trade>),
+    Quote(Cow<'a, Quote>),
+    Bbo(Cow<'a, BestBidOffer>),
+    BlockTrade(Cow<'a, BlockTrade>),
+    AuctionOpen(Cow<'a, AuctionOpen>),
+    AuctionIndicative(Cow<'a, AuctionIndicative>),
+    AuctionResult(Cow<'a, AuctionResult>),
+}
+
+impl<'a> From<&'a AuctionEvent> for WireData<'a> {
+    fn from(auction: &'a AuctionEvent) -> Self {
+        match auction {
+            AuctionEvent::Open(open) => WireData::AuctionOpen(Cow::Borrowed(open)),
+            AuctionEvent::Indicative(indicative) => WireData::AuctionIndicative(Cow::Borrowed(indicative)),
+            AuctionEvent::Result(result) => WireData::AuctionResult(Cow::Borrowed(result)),
+        }
+    }
+}
+
+// Binary counterpart of the JSONL `Record`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WireEvent<'a> {
+    pub symbol: Cow<'a, str>,
+    pub event_id: u64,
+    pub socket_sequence: u32,
+    pub timestampms: Option<u64>,
+    pub received_ms: u64,
+    pub received_at_ns: u64,
+    pub emitted_at_ns: u64,
+    pub data: WireData<'a>,
+}
+
+impl<'a> WireEvent<'a> {
+    pub fn new(symbol: &'a str, ctx: &EventContext, data: WireData<'a>) -> Self {
+        Self {
+            symbol: Cow::Borrowed(symbol),
+            event_id: ctx.event_id,
+            socket_sequence: ctx.socket_sequence,
+            timestampms: ctx.timestampms,
+            received_ms: ctx.received_ms,
+            received_at_ns: ctx.received_ns,
+            emitted_at_ns: now_ns(),
+            data,
+        }
+    }
+
+    // Header and event, the form used for messages
+    pub fn to_message(&self) -> Vec<u8> {
+        let mut message = header().to_vec();
+        // Plain data structures always encode
+        message.extend(encode(self).unwrap_or_default());
+        message
+    }
+
+    pub fn from_message(message: &[u8]) -> Result<WireEvent<'static>, GeminiError> {
+        check_header(message, VERSION)?;
+        decode(&message[HEADER_LEN..])
+    }
+}
+
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, GeminiError> {
+    bincode::serde::encode_to_vec(value, bincode::config::standard())
+        .map_err(|e| GeminiError::Protocol(format!("cannot encode: {}", e)))
+}
+
+pub fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, GeminiError> {
+    bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+        .map(|(value, _)| value)
+        .map_err(|e| GeminiError::Protocol(format!("cannot decode: {}", e)))
+}
@@ -0,0 +1,30 @@
+// Builders shared by the integration tests, each of them uses only some
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use rust_decimal::Decimal;
+
+use order_book::models::{MarketSide, Trade};
+use order_book::output::EventContext;
+
+pub fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+// An event sent by the exchange at `ts` and received right away
+pub fn context(ts: u64) -> EventContext {
+    EventContext {
+        event_id: ts,
+        socket_sequence: 0,
+        timestampms: Some(ts),
+        received_ms: ts,
+        received_ns: ts * 1_000_000,
+        backfill: false,
+        recovered: false,
+    }
+}
+
+pub fn trade() -> Trade {
+    Trade { price: Decimal::from(3642), amount: Decimal::ONE, maker_side: MarketSide::Ask }
+}
@@ -0,0 +1,214 @@
+// Two collectors electing a leader over the same lock file: whatever the handover, every
+// event ends up stored by one of them, and only once when the leader released the lock.
+mod common;
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use common::{context, trade};
+use order_book::error::GeminiError;
+use order_book::handler::EventHandler;
+use order_book::leader::{self, LeaderLock, Lease, Leadership, Marks, Standby};
+use order_book::models::Trade;
+use order_book::output::EventContext;
+
+const TTL: Duration = Duration::from_millis(300);
+const SYMBOL: &str = "btcusd";
+// Exchange time of the first event, the next ones follow every 100ms
+const START_MS: u64 = 1_547_760_000_000;
+
+// The event ids a sink stored
+#[derive(Clone, Default)]
+struct Stored(Arc<Mutex<Vec<u64>>>);
+
+impl EventHandler for Stored {
+    fn on_trade(&mut self, _symbol: &str, ctx: &EventContext, _trade: &Trade) -> Result<(), GeminiError> {
+        self.0.lock().unwrap().push(ctx.event_id);
+        Ok(())
+    }
+}
+
+impl Stored {
+    fn ids(&self) -> Vec<u64> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+// A lock file of its own per test, the tests run in parallel
+struct LockFile(PathBuf);
+
+impl LockFile {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("order_book-{}-{}.lock", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        Self(path)
+    }
+
+    fn lease(&self) -> Box<dyn Lease> {
+        leader::open(&LeaderLock::File(self.0.clone()), "", TTL).unwrap()
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// Renews through the file until told to fail, like a Redis server gone out of reach
+struct Flaky {
+    inner: Box<dyn Lease>,
+    failing: Arc<AtomicBool>,
+}
+
+impl Lease for Flaky {
+    fn acquire(&mut self) -> Result<Option<Marks>, GeminiError> {
+        self.inner.acquire()
+    }
+
+    fn renew(&mut self, marks: &Marks) -> Result<bool, GeminiError> {
+        match self.failing.load(Ordering::Acquire) {
+            true => Err(GeminiError::Sink(String::from("lock unreachable"))),
+            false => self.inner.renew(marks),
+        }
+    }
+
+    fn release(&mut self, marks: &Marks) -> Result<(), GeminiError> {
+        self.inner.release(marks)
+    }
+}
+
+struct Collector {
+    leadership: Leadership,
+    gate: Standby,
+    stored: Stored,
+    election: JoinHandle<()>,
+}
+
+impl Collector {
+    fn start(lease: Box<dyn Lease>) -> Self {
+        let leadership = Leadership::new(TTL);
+        let stored = Stored::default();
+        let gate = leadership.gate(Box::new(stored.clone()));
+        let election = leader::elect(lease, leadership.clone(), TTL, CancellationToken::new());
+        Self { leadership, gate, stored, election }
+    }
+
+    fn feed(&mut self, ids: impl IntoIterator<Item = u64>) {
+        for id in ids {
+            let ctx = EventContext { event_id: id, socket_sequence: id as u32, ..context(START_MS + id * 100) };
+            self.gate.on_trade(SYMBOL, &ctx, &trade()).unwrap();
+        }
+    }
+
+    // Flushes the sink and lets the election release the lock, as on shutdown
+    async fn stop(self) -> Stored {
+        let Collector { leadership, mut gate, stored, election } = self;
+        gate.flush().unwrap();
+        drop(gate);
+        election.await.unwrap();
+        assert!(!leadership.is_leader());
+        stored
+    }
+}
+
+async fn wait_for(what: &str, done: impl Fn() -> bool) {
+    for _ in 0..250 {
+        if done() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("timed out waiting for {}", what);
+}
+
+// Every id from 1 to `last` once
+fn assert_complete(mut ids: Vec<u64>, last: u64) {
+    ids.sort_unstable();
+    let expected: Vec<u64> = (1..=last).collect();
+    assert_eq!(ids, expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn takeover_stores_the_held_events() {
+    let lock = LockFile::new("held");
+    let mut a = Collector::start(lock.lease());
+    wait_for("a to lead", || a.leadership.is_leader()).await;
+    let mut b = Collector::start(lock.lease());
+
+    a.feed(1..=100);
+    // The standby is ahead of the leader when it goes away
+    b.feed(1..=120);
+    assert!(b.stored.ids().is_empty());
+    let a = a.stop().await;
+    wait_for("b to take over", || b.leadership.is_leader()).await;
+    b.feed(121..=150);
+    let b = b.stop().await;
+
+    assert_eq!(b.ids(), (101..=150).collect::<Vec<u64>>());
+    assert_complete([a.ids(), b.ids()].concat(), 150);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn takeover_skips_what_the_leader_stored() {
+    let lock = LockFile::new("lagging");
+    let mut a = Collector::start(lock.lease());
+    wait_for("a to lead", || a.leadership.is_leader()).await;
+    let mut b = Collector::start(lock.lease());
+
+    a.feed(1..=100);
+    // The standby's connection lags behind the leader's
+    b.feed(1..=80);
+    let a = a.stop().await;
+    wait_for("b to take over", || b.leadership.is_leader()).await;
+    b.feed(81..=150);
+    let b = b.stop().await;
+
+    assert_eq!(b.ids(), (101..=150).collect::<Vec<u64>>());
+    assert_complete([a.ids(), b.ids()].concat(), 150);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn marks_trail_until_the_sink_flushed() {
+    let lock = LockFile::new("settle");
+    let mut a = Collector::start(lock.lease());
+    wait_for("a to lead", || a.leadership.is_leader()).await;
+
+    a.feed(1..=100);
+    // Event 100 came in 10s after the first, the last 2s of them may still sit in a batch
+    let mark = |id: u64| (START_MS + id * 100, id);
+    assert_eq!(a.leadership.marks()[SYMBOL], mark(80));
+    a.gate.flush().unwrap();
+    assert_eq!(a.leadership.marks()[SYMBOL], mark(100));
+    a.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn leader_steps_down_after_a_missed_renewal() {
+    let lock = LockFile::new("renewal");
+    let failing = Arc::new(AtomicBool::new(false));
+    let mut a = Collector::start(Box::new(Flaky { inner: lock.lease(), failing: failing.clone() }));
+    wait_for("a to lead", || a.leadership.is_leader()).await;
+    let mut b = Collector::start(lock.lease());
+
+    a.feed(1..=100);
+    b.feed(1..=100);
+    failing.store(true, Ordering::Release);
+    wait_for("a to step down", || !a.leadership.is_leader()).await;
+    wait_for("b to take over", || b.leadership.is_leader()).await;
+    // Both still receive, only the new leader stores
+    a.feed(101..=150);
+    b.feed(101..=150);
+    let a = a.stop().await;
+    let b = b.stop().await;
+
+    // Without a flush the published marks trail, so what the old leader stored in the
+    // last 2s before it stepped down is stored again rather than lost
+    assert_eq!(a.ids(), (1..=100).collect::<Vec<u64>>());
+    assert_eq!(b.ids(), (81..=150).collect::<Vec<u64>>());
+}
@@ -0,0 +1,232 @@
+// Parses the message shapes of Gemini's v1 market data feed from tests/fixtures and
+// checks the values that come out, plus frames the parser has to reject cleanly.
+mod common;
+
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+
+use common::fixture;
+use order_book::capture::CaptureReader;
+use order_book::models::{AuctionEvent, Event, EventShape, MarketMessage, MarketSide};
+use order_book::pipeline::Pipeline;
+use order_book::queue::QueueOptions;
+
+fn parse_fixture(name: &str) -> MarketMessage {
+    let frame = std::fs::read(fixture(name)).unwrap_or_else(|e| panic!("{}: {}", name, e));
+    MarketMessage::from_slice(&frame).unwrap_or_else(|e| panic!("{}: {}", name, e))
+}
+
+// One line per event, so a whole message compares as a list of strings
+fn describe(event: &Event) -> String {
+    match event {
+        Event::Trade(t) => format!("trade {} {} {}", t.price, t.amount, t.maker_side.as_str()),
+        Event::Quote(q) => format!(
+            "change {} {} {} {} {}",
+            q.side.as_str(), q.price, q.remaining, q.delta.map(|d| d.to_string()).unwrap_or_default(), q.reason
+        ),
+        Event::BlockTrade(t) => format!("block_trade {} {} {}", t.tid, t.price, t.amount),
+        Event::Auction(AuctionEvent::Open(a)) => format!(
+            "auction_open {} {} {:?} {:?}",
+            a.auction_open_ms, a.auction_time_ms, a.first_indicative_ms, a.last_cancel_time_ms
+        ),
+        Event::Auction(AuctionEvent::Indicative(a)) => format!(
+            "auction_indicative {} {} {:?} {:?}",
+            a.eid, a.result, a.indicative_price, a.indicative_quantity
+        ),
+        Event::Auction(AuctionEvent::Result(a)) => format!(
+            "auction_result {} {} {:?} {:?}",
+            a.eid, a.result, a.auction_price, a.auction_quantity
+        ),
+        Event::Unknown => String::from("unknown"),
+    }
+}
+
+struct Case {
+    fixture: &'static str,
+    event_id: u64,
+    socket_sequence: u32,
+    timestampms: Option<u64>,
+    events: &'static [&'static str],
+}
+
+const CASES: &[Case] = &[
+    Case {
+        fixture: "initial_book.json",
+        event_id: 5375461993,
+        socket_sequence: 0,
+        timestampms: None,
+        events: &[
+            "change bid 3641.61 0.83372051 0.83372051 initial",
+            "change bid 3641.60 4.8 4.8 initial",
+            "change ask 3641.62 0.00195 0.00195 initial",
+            "change ask 3643.01 12.3 12.3 initial",
+        ],
+    },
+    Case {
+        fixture: "trade.json",
+        event_id: 5375547515,
+        socket_sequence: 15,
+        timestampms: Some(1547760288001),
+        events: &[
+            "trade 3632.54 0.1362819142 ask",
+            "change ask 3632.54 0 -0.1362819142 trade",
+        ],
+    },
+    Case {
+        fixture: "place_cancel.json",
+        event_id: 5375503736,
+        socket_sequence: 1,
+        timestampms: Some(1547759967559),
+        events: &[
+            "change bid 3633.54 1.5 1.5 place",
+            "change ask 3650.00 0 -2 cancel",
+        ],
+    },
+    Case {
+        fixture: "block_trade.json",
+        event_id: 1111597035,
+        socket_sequence: 26,
+        timestampms: Some(1570035281355),
+        events: &["block_trade 1111597035 8215.00 12.5"],
+    },
+    Case {
+        fixture: "auction_open.json",
+        event_id: 371469414,
+        socket_sequence: 1,
+        timestampms: Some(1486501200000),
+        events: &["auction_open 1486591200000 1486674000000 Some(1486673400000) Some(1486673985000)"],
+    },
+    Case {
+        fixture: "auction_indicative.json",
+        event_id: 372291230,
+        socket_sequence: 2,
+        timestampms: Some(1486673400000),
+        events: &["auction_indicative 2541793004 success Some(1011.65) Some(16.70464313)"],
+    },
+    Case {
+        fixture: "auction_result.json",
+        event_id: 372306234,
+        socket_sequence: 3,
+        timestampms: Some(1486674000000),
+        events: &["auction_result 2541793005 success Some(1011.10) Some(7.99425734)"],
+    },
+    Case {
+        fixture: "unknown_event.json",
+        event_id: 42,
+        socket_sequence: 4,
+        timestampms: Some(1547760300000),
+        events: &["unknown", "trade 3633.00 0.01 bid"],
+    },
+];
+
+#[test]
+fn fixtures_parse_to_expected_values() {
+    for case in CASES {
+        let message = parse_fixture(case.fixture);
+        assert_eq!(message.event_id, case.event_id, "{}", case.fixture);
+        assert_eq!(message.socket_sequence, case.socket_sequence, "{}", case.fixture);
+        assert_eq!(message.timestampms, case.timestampms, "{}", case.fixture);
+        let events: Vec<String> = message.events.iter().map(describe).collect();
+        assert_eq!(events, case.events, "{}", case.fixture);
+    }
+}
+
+#[test]
+fn parsed_messages_survive_a_round_trip() {
+    for case in CASES {
+        let message = parse_fixture(case.fixture);
+        let json = serde_json::to_vec(&message).unwrap();
+        let again = MarketMessage::from_slice(&json).unwrap_or_else(|e| panic!("{}: {}", case.fixture, e));
+        let before: Vec<String> = message.events.iter().map(describe).collect();
+        let after: Vec<String> = again.events.iter().map(describe).collect();
+        assert_eq!(before, after, "{}", case.fixture);
+    }
+}
+
+#[test]
+fn events_serialize_tagged_or_external() {
+    let message = parse_fixture("trade.json");
+    let tagged: serde_json::Value = serde_json::from_str(&message.events[0].to_json("btcusd", EventShape::Tagged).unwrap()).unwrap();
+    assert_eq!(tagged["type"], "trade");
+    assert_eq!(tagged["symbol"], "btcusd");
+    assert_eq!(tagged["price"], "3632.54");
+    let external: serde_json::Value = serde_json::from_str(&message.events[1].to_json("btcusd", EventShape::External).unwrap()).unwrap();
+    assert_eq!(external["Quote"]["side"], "ask");
+    assert_eq!(message.events[1].to_string(), "change ask 3632.54 remaining 0 delta -0.1362819142 (trade)");
+}
+
+#[test]
+fn decimals_keep_their_precision() {
+    let message = parse_fixture("trade.json");
+    let Event::Trade(trade) = &message.events[0] else {
+        panic!("expected a trade, got {:?}", message.events[0]);
+    };
+    assert_eq!(trade.amount, Decimal::from_str("0.1362819142").unwrap());
+    assert_eq!(trade.amount.scale(), 10);
+    assert_eq!(trade.maker_side, MarketSide::Ask);
+}
+
+// Frames that must come back as errors, never as a panic or a half-filled message
+const MALFORMED: &[(&str, &str)] = &[
+    ("empty", ""),
+    ("not json", "hello"),
+    ("truncated", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":[{"type":"trade","#),
+    ("array", "[1,2,3]"),
+    ("missing event id", r#"{"type":"update","socket_sequence":0,"events":[]}"#),
+    ("event id as string", r#"{"type":"update","eventId":"1","socket_sequence":0,"events":[]}"#),
+    ("negative sequence", r#"{"type":"update","eventId":1,"socket_sequence":-1,"events":[]}"#),
+    ("events not a list", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":{}}"#),
+    ("event not an object", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":[7]}"#),
+    ("event without type", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":[{"price":"1"}]}"#),
+    ("type not a string", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":[{"type":5}]}"#),
+    ("price as number", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":[{"type":"trade","tid":1,"price":1.5,"amount":"1"}]}"#),
+    ("price not decimal", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":[{"type":"trade","tid":1,"price":"abc","amount":"1"}]}"#),
+    ("trade without amount", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":[{"type":"trade","tid":1,"price":"1"}]}"#),
+    ("price beyond decimal", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":[{"type":"trade","tid":1,"price":"792281625142643375935439503350","amount":"1"}]}"#),
+    ("price out of range", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":[{"type":"trade","tid":1,"price":"79228162514264337593543950335","amount":"1"}]}"#),
+    ("remaining out of range", r#"{"type":"update","eventId":1,"socket_sequence":0,"events":[{"type":"change","side":"bid","price":"1","remaining":"-10000000000000","reason":"place"}]}"#),
+    ("heartbeat", r#"{"type":"heartbeat","socket_sequence":31}"#),
+];
+
+#[test]
+fn malformed_frames_are_errors() {
+    for (name, frame) in MALFORMED {
+        assert!(MarketMessage::from_slice(frame.as_bytes()).is_err(), "{} parsed", name);
+    }
+}
+
+#[test]
+fn heartbeat_fixture_is_rejected() {
+    let frame = std::fs::read(fixture("heartbeat.json")).unwrap();
+    assert!(MarketMessage::from_slice(&frame).is_err());
+}
+
+#[tokio::test]
+async fn capture_replays_through_the_pipeline() {
+    let mut reader = CaptureReader::open(&fixture("btcusd.jsonl"), 0.).await.unwrap();
+    // The capture is a full depth feed, so changes update single levels
+    let mut pipeline = Pipeline::new(QueueOptions::default())
+        .top_of_book(false)
+        .with_candles(std::time::Duration::from_secs(60))
+        .with_trade_stats(vec![std::time::Duration::from_secs(60)], std::time::Duration::from_secs(1))
+        .with_indicators(vec![Decimal::from(10)]);
+    let mut frames = 0;
+    while let Some(frame) = reader.next().await.unwrap() {
+        pipeline.handle_frame(&frame.symbol, frame.frame.as_bytes(), frame.received_ms).await.unwrap();
+        frames += 1;
+    }
+    pipeline.flush().await.unwrap();
+
+    let summaries = pipeline.summaries();
+    assert_eq!(summaries.len(), 1);
+    let summary = &summaries[0];
+    assert_eq!(summary.messages, frames);
+    assert_eq!(summary.trades, 3);
+    assert_eq!(summary.volume, Decimal::from_str("2.25").unwrap());
+    assert_eq!(summary.high, Some(Decimal::from_str("3643.00").unwrap()));
+    assert_eq!(summary.low, Some(Decimal::from_str("3641.50").unwrap()));
+    assert_eq!(summary.bbo.best_bid, Decimal::from_str("3641.00").unwrap());
+    assert_eq!(summary.bbo.best_offer, Decimal::from_str("3643.00").unwrap());
+    assert_eq!(summary.bbo.ask_amount_remaining, Decimal::from(2));
+}
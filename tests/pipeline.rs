@@ -0,0 +1,119 @@
+// Drives the pipeline from the local clock, past the last message of the capture
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+
+use common::{context, fixture, trade};
+use order_book::analytics::candles::Candle;
+use order_book::capture::CaptureReader;
+use order_book::downsample::Downsampled;
+use order_book::error::GeminiError;
+use order_book::handler::{EventHandler, HandlerMessage};
+use order_book::models::BestBidOffer;
+use order_book::output::EventContext;
+use order_book::pipeline::Pipeline;
+use order_book::queue::QueueOptions;
+use order_book::throttle::{ThrottleOptions, Throttled};
+
+// The bars a handler received, kept after the pipeline is done with it
+#[derive(Clone, Default)]
+struct Candles(Arc<Mutex<Vec<Candle>>>);
+
+impl EventHandler for Candles {
+    fn on_candle(&mut self, _symbol: &str, _ctx: &EventContext, candle: &Candle) -> Result<(), GeminiError> {
+        self.0.lock().unwrap().push(candle.clone());
+        Ok(())
+    }
+}
+
+// The exchange time of each BBO a handler received
+#[derive(Clone, Default)]
+struct Bbos(Arc<Mutex<Vec<u64>>>);
+
+impl EventHandler for Bbos {
+    fn on_book_update(&mut self, _symbol: &str, ctx: &EventContext, _bbo: &BestBidOffer) -> Result<(), GeminiError> {
+        self.0.lock().unwrap().push(ctx.timestampms.unwrap());
+        Ok(())
+    }
+}
+
+fn tick(handler: &mut dyn EventHandler, now_ms: u64) {
+    HandlerMessage::Tick { now_ms }.deliver(handler).unwrap();
+}
+
+async fn replay(pipeline: &mut Pipeline) {
+    let mut reader = CaptureReader::open(&fixture("btcusd.jsonl"), 0.).await.unwrap();
+    while let Some(frame) = reader.next().await.unwrap() {
+        pipeline.handle_frame(&frame.symbol, frame.frame.as_bytes(), frame.received_ms).await.unwrap();
+    }
+}
+
+fn open_bar(pipeline: &Pipeline) -> Option<u64> {
+    pipeline.saved_state(0).symbols[0].candle.as_ref().map(|c| c.start_ms)
+}
+
+#[tokio::test]
+async fn clock_closes_the_last_bar() {
+    let candles = Candles::default();
+    let mut pipeline = Pipeline::new(QueueOptions::default())
+        .top_of_book(false)
+        .with_candles(Duration::from_secs(1));
+    pipeline.add_output(Box::new(candles.clone()));
+    replay(&mut pipeline).await;
+    // The last trade of the capture is at 1547760001490, in the bar starting a second in
+    assert_eq!(open_bar(&pipeline), Some(1_547_760_001_000));
+
+    // Within the clock skew allowed after the end of the bar, it stays open
+    pipeline.close_candles(1_547_760_002_999).await.unwrap();
+    assert_eq!(open_bar(&pipeline), Some(1_547_760_001_000));
+    pipeline.close_candles(1_547_760_003_000).await.unwrap();
+    assert_eq!(open_bar(&pipeline), None);
+    // The silence after it still gets flat bars
+    pipeline.close_candles(1_547_760_005_000).await.unwrap();
+    pipeline.flush().await.unwrap();
+    drop(pipeline);
+
+    let candles = candles.0.lock().unwrap();
+    let starts: Vec<u64> = candles.iter().map(|c| c.start_ms).collect();
+    assert_eq!(starts, [1_547_760_000_000, 1_547_760_001_000, 1_547_760_002_000, 1_547_760_003_000]);
+    assert_eq!(candles[0].trades, 2);
+    assert_eq!(candles[1].trades, 1);
+    for flat in &candles[2..] {
+        assert_eq!(flat.trades, 0);
+        assert_eq!(flat.volume, Decimal::ZERO);
+        assert_eq!(flat.open, candles[1].close);
+    }
+}
+
+#[test]
+fn clock_releases_a_held_update() {
+    let bbos = Bbos::default();
+    let options = ThrottleOptions { rate: None, samples: vec!["bbo:1s".parse().unwrap()] };
+    let mut throttled = Throttled::new(Box::new(bbos.clone()), &options);
+    throttled.on_book_update("btcusd", &context(1_000), &BestBidOffer::new()).unwrap();
+    throttled.on_book_update("btcusd", &context(1_200), &BestBidOffer::new()).unwrap();
+    assert_eq!(*bbos.0.lock().unwrap(), [1_000]);
+
+    // No further update of the symbol comes, the clock sends the held one at the end of the interval
+    tick(&mut throttled, 1_999);
+    assert_eq!(*bbos.0.lock().unwrap(), [1_000]);
+    tick(&mut throttled, 2_000);
+    assert_eq!(*bbos.0.lock().unwrap(), [1_000, 1_200]);
+}
+
+#[test]
+fn clock_stores_the_last_bbo_of_a_bucket() {
+    let bbos = Bbos::default();
+    let mut downsampled = Downsampled::new(Box::new(bbos.clone()), Duration::from_secs(1));
+    downsampled.on_book_update("btcusd", &context(1_100), &BestBidOffer::new()).unwrap();
+    downsampled.on_book_update("btcusd", &context(1_700), &BestBidOffer::new()).unwrap();
+    // Left out events of the same bucket keep it open
+    downsampled.on_trade("btcusd", &context(1_800), &trade()).unwrap();
+    tick(&mut downsampled, 1_999);
+    assert!(bbos.0.lock().unwrap().is_empty());
+    tick(&mut downsampled, 2_000);
+    assert_eq!(*bbos.0.lock().unwrap(), [1_700]);
+}
@@ -0,0 +1,142 @@
+// Checks that the JSONL lines the formatter writes match the schema of --print-schema:
+// the capture replayed with every analytic on, plus the events it does not have.
+mod common;
+
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use serde_json::Value;
+
+use common::fixture;
+use order_book::analytics::anomaly::AnomalyOptions;
+use order_book::analytics::cross::CrossDivergence;
+use order_book::capture::CaptureReader;
+use order_book::handler::EVENT_KINDS;
+use order_book::models::FundingAmount;
+use order_book::output::{EventContext, Formatter, OutputFormat, Printer};
+use order_book::pipeline::Pipeline;
+use order_book::queue::QueueOptions;
+use order_book::schema::jsonl_schema;
+
+// What the printer wrote, kept after the pipeline is done with it
+#[derive(Clone, Default)]
+struct Lines(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Lines {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+async fn replay() -> Vec<String> {
+    let lines = Lines::default();
+    let printer = Printer::new(Formatter::new(OutputFormat::Jsonl), lines.clone()).unwrap().verbose(true);
+    let window = vec![Duration::from_secs(1)];
+    let interval = Duration::from_millis(100);
+    let mut pipeline = Pipeline::new(QueueOptions::default())
+        .top_of_book(false)
+        .with_candles(Duration::from_secs(1))
+        .with_technical(vec!["sma:1".parse().unwrap()])
+        .with_trade_stats(window.clone(), interval)
+        .with_indicators(vec![Decimal::from(10)])
+        .with_depth(None, vec![Decimal::from(1)])
+        .with_volatility(interval, window.clone())
+        .with_dwell(Duration::from_secs(1), Duration::from_millis(50), interval)
+        .with_order_flow(window, interval)
+        .with_anomalies(AnomalyOptions { trade_pct: None, spread_multiple: None }, Duration::from_secs(60))
+        .with_stale_quotes(Duration::from_secs(5));
+    pipeline.add_output(Box::new(printer));
+
+    let mut reader = CaptureReader::open(&fixture("btcusd.jsonl"), 0.).await.unwrap();
+    let mut last_ms = 0;
+    while let Some(frame) = reader.next().await.unwrap() {
+        pipeline.handle_frame(&frame.symbol, frame.frame.as_bytes(), frame.received_ms).await.unwrap();
+        last_ms = frame.received_ms;
+    }
+    for name in ["auction_open.json", "auction_indicative.json", "auction_result.json", "block_trade.json"] {
+        let frame = std::fs::read(fixture(name)).unwrap();
+        pipeline.handle_frame("btcusd", &frame, last_ms).await.unwrap();
+    }
+    pipeline.check_quotes(last_ms + 60_000).await.unwrap();
+    let funding = FundingAmount {
+        symbol: String::from("btcgusdperp"),
+        funding_ms: 1_547_760_000_000,
+        next_funding_ms: 1_547_763_600_000,
+        amount: Decimal::new(-125, 4),
+        estimated_amount: None,
+    };
+    pipeline.handle_funding("btcgusdperp", funding, last_ms).await.unwrap();
+    pipeline.flush().await.unwrap();
+    drop(pipeline);
+
+    let text = String::from_utf8(lines.0.lock().unwrap().clone()).unwrap();
+    text.lines().map(String::from).collect()
+}
+
+#[tokio::test]
+async fn jsonl_output_matches_the_schema() {
+    let schema = jsonl_schema();
+    let validator = jsonschema::validator_for(&schema).unwrap();
+
+    let mut lines = replay().await;
+    // Crosses need a second and third symbol, their line is formatted directly
+    let cross = CrossDivergence {
+        cross: String::from("ethbtc"),
+        actual: Decimal::new(2750, 5),
+        implied: Decimal::new(2748, 5),
+        divergence_bps: Decimal::new(728, 2),
+        diverged: false,
+        changed: false,
+    };
+    let ctx = EventContext {
+        event_id: 7,
+        socket_sequence: 3,
+        timestampms: Some(1_547_760_000_000),
+        received_ms: 1_547_760_000_010,
+        received_ns: 1_547_760_000_010_000_000,
+        backfill: false,
+        recovered: false,
+    };
+    lines.extend(Formatter::new(OutputFormat::Jsonl).cross("ethbtc", &ctx, &cross));
+
+    let mut kinds = BTreeSet::new();
+    for line in &lines {
+        let record: Value = serde_json::from_str(line).unwrap();
+        let errors: Vec<String> = validator.iter_errors(&record).map(|e| e.to_string()).collect();
+        assert!(errors.is_empty(), "{}: {:?}", line, errors);
+        kinds.insert(record["kind"].as_str().unwrap().to_string());
+    }
+    let missing: Vec<&&str> = EVENT_KINDS.iter().filter(|kind| !kinds.contains(**kind)).collect();
+    assert!(missing.is_empty(), "no {:?} lines to check", missing);
+}
+
+#[test]
+fn schema_rejects_unknown_fields() {
+    let validator = jsonschema::validator_for(&jsonl_schema()).unwrap();
+    let trade = serde_json::json!({
+        "kind": "trade",
+        "symbol": "btcusd",
+        "event_id": 1,
+        "socket_sequence": 1,
+        "timestampms": 1547760000100u64,
+        "received_ms": 1547760000120u64,
+        "received_at_ns": 1547760000120000000u64,
+        "emitted_at_ns": 1547760000120000000u64,
+        "price": "3642.00",
+        "amount": "0.5",
+        "makerSide": "ask",
+    });
+    assert!(validator.is_valid(&trade));
+    let mut renamed = trade.clone();
+    renamed["maker_side"] = renamed["makerSide"].take();
+    renamed.as_object_mut().unwrap().remove("makerSide");
+    assert!(!validator.is_valid(&renamed));
+}